@@ -9,6 +9,18 @@
 
 #define ERROR 1
 
+#define NOT_PRESENT 2
+
+#define RECEIVE_OK 0
+
+#define RECEIVE_INVALID_UTF8 1
+
+#define RECEIVE_INVALID_PART 2
+
+#define RECEIVE_TYPE_NOT_ALLOWED 3
+
+#define RECEIVE_SESSION_EXPIRED 4
+
 typedef char *PtrString;
 
 typedef void *PtrVoid;
@@ -28,6 +40,20 @@ typedef struct Response {
     union Value value;
 } Response;
 
+typedef struct ReceiveSlot {
+    uint32_t status_code;
+    uint32_t error_code;
+    bool complete;
+} ReceiveSlot;
+
+typedef struct Arena Arena;
+
+typedef struct URFile URFile;
+
+typedef PtrVoid (*AllocFn)(uintptr_t size, uintptr_t align);
+
+typedef void (*FreeFn)(PtrVoid ptr);
+
 typedef struct Response *PtrResponse;
 
 PtrResponse crypto_hd_key_get_key_data(void *crypto_hdkey);
@@ -106,12 +132,122 @@ PtrResponse ur_decoder_new(void);
 
 PtrResponse ur_decoder_receive(void *decoder, void *ur);
 
+PtrResponse ur_decoder_receive_at(void *decoder, PtrString ur, uint64_t now_ms);
+
+PtrResponse ur_decoder_receive_bytes(void *decoder, PtrString frame);
+
+void ur_decoder_receive_bytes_into(void *decoder, const uint8_t *frame, size_t len, struct ReceiveSlot *slot);
+
+PtrResponse ur_decoder_set_inactivity_timeout(void *decoder, uint64_t timeout_ms);
+
+PtrResponse ur_decoder_check_timeout(void *decoder, uint64_t now_ms);
+
+PtrResponse ur_decoder_set_allowed_types(void *decoder, PtrString types);
+
+void ur_decoder_receive_part_into(void *decoder, PtrString ur, struct ReceiveSlot *slot);
+
 PtrResponse ur_decoder_is_complete(void *decoder);
 
 PtrResponse ur_decoder_result(void *decoder);
 
+PtrResponse ur_decoder_checksum(void *decoder);
+
+PtrResponse ur_decoder_sha256(void *decoder);
+
+PtrResponse ur_decoder_short_code(void *decoder);
+
 PtrResponse ur_decoder_resolve(void *decoder, void *target_type);
 
+PtrResponse ur_decoder_resolve_item(void *decoder, PtrString target_type);
+
+PtrResponse ur_decoder_sessions_new(void);
+
+PtrResponse ur_decoder_sessions_receive(void *decoder_sessions, PtrString ur);
+
+PtrResponse ur_decoder_sessions_receive_at(void *decoder_sessions, PtrString ur, uint64_t now_ms);
+
+PtrResponse ur_decoder_sessions_set_inactivity_timeout(void *decoder_sessions, uint64_t timeout_ms);
+
+PtrResponse ur_decoder_sessions_check_timeout(void *decoder_sessions, uint64_t now_ms);
+
+PtrResponse ur_decoder_sessions_set_allowed_types(void *decoder_sessions, PtrString types);
+
+PtrResponse ur_decoder_sessions_is_complete(const void *decoder_sessions, uint32_t checksum, PtrString ur_type);
+
+PtrResponse ur_decoder_sessions_get_len(const void *decoder_sessions);
+
+PtrResponse ur_decoder_sessions_resolve(void *decoder_sessions, uint32_t checksum, PtrString ur_type);
+
+PtrResponse ur_decoder_sessions_remove(void *decoder_sessions, uint32_t checksum, PtrString ur_type);
+
+PtrResponse registry_item_to_cbor(const void *registry_item);
+
+PtrResponse registry_item_get_ur_type(const void *registry_item);
+
+PtrResponse registry_item_get_object(const void *registry_item);
+
+void registry_item_free(void *registry_item);
+
+PtrResponse registry_object_to_cbor(void *object, PtrString ur_type);
+
 void utils_free(void *any_ptr);
 
+void ur_registry_free_string(PtrString str);
+
+PtrResponse ur_registry_arena_new(void);
+
+bool ur_registry_arena_enter(struct Arena *arena);
+
+void ur_registry_arena_leave(void);
+
+void ur_registry_arena_reset(struct Arena *arena);
+
+bool ur_registry_arena_free(struct Arena *arena);
+
+bool ur_registry_set_allocator(AllocFn alloc, FreeFn free);
+
+void ur_registry_free_response(PtrResponse response);
+
+PtrResponse ur_ndef_encode_ur(PtrString ur);
+
+PtrResponse ur_ndef_encode_cbor(PtrString ur_type, PtrString cbor);
+
+PtrResponse ur_ndef_decode(PtrString message);
+
+PtrResponse ur_file_write(PtrString ur_type, PtrString cbor);
+
+PtrResponse ur_file_read(PtrString contents);
+
+PtrResponse ur_file_get_type(const struct URFile *ur_file);
+
+PtrResponse ur_file_get_cbor(const struct URFile *ur_file);
+
+PtrResponse ur_file_resolve(const struct URFile *ur_file);
+
 PtrResponse ur_encoder_next_part(void *ur_encoder);
+
+PtrResponse ur_encoder_next_part_bytes(void *ur_encoder);
+
+size_t ur_encoder_next_part_bytes_into(void *ur_encoder, uint8_t *buffer, size_t capacity);
+
+PtrResponse ur_encoder_current_index(void *ur_encoder);
+
+PtrResponse ur_encoder_fragment_count(void *ur_encoder);
+
+PtrResponse ur_encoder_checksum(void *ur_encoder);
+
+PtrResponse ur_encoder_sha256(void *ur_encoder);
+
+PtrResponse ur_encoder_short_code(void *ur_encoder);
+
+PtrResponse ur_encoder_has_looped(void *ur_encoder);
+
+PtrResponse ur_encoder_set_redundancy(void *ur_encoder, uint32_t percent);
+
+PtrResponse ur_encoder_planned_part_count(void *ur_encoder);
+
+PtrResponse ur_encoder_has_emitted_planned(void *ur_encoder);
+
+PtrResponse ur_encoder_last_part_duration(void *ur_encoder, uint32_t profile);
+
+PtrResponse ur_encoder_next_paced_part(void *ur_encoder, uint32_t profile);
@@ -2,6 +2,7 @@ import 'package:ur_registry_flutter/ffi/ffi_factory.dart';
 
 const success = 0;
 const error = 1;
+const notPresent = 2;
 
 const typeObject = "OBJECT";
 const typeBoolean = "BOOLEAN";
@@ -60,6 +61,10 @@ base class Response extends Struct {
     return statusCode == error;
   }
 
+  bool isNotPresent() {
+    return statusCode == notPresent;
+  }
+
   void checkValueType(String target) {
     if (valueType.toDartString() != target)
       throw Exception(
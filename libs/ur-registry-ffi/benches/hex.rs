@@ -0,0 +1,52 @@
+//! Hex throughput of the FFI getters on sign_data sizes seen in practice,
+//! against the `hex` crate they replaced. Run with `cargo bench`.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+use ur_registry_ffi::hex_codec;
+
+const SIZES: [usize; 3] = [1024, 64 * 1024, 512 * 1024];
+const MIN_RUNTIME: Duration = Duration::from_millis(500);
+
+// Mean time per call, running `f` for at least MIN_RUNTIME
+fn time(mut f: impl FnMut()) -> Duration {
+    let start = Instant::now();
+    let mut runs = 0u32;
+    while start.elapsed() < MIN_RUNTIME {
+        f();
+        runs += 1;
+    }
+    start.elapsed() / runs
+}
+
+fn report(name: &str, size: usize, baseline: Duration, fast: Duration) {
+    let throughput = |d: Duration| size as f64 / d.as_secs_f64() / (1024.0 * 1024.0);
+    println!(
+        "{:<7} {:>7} B  hex {:>9.1} MiB/s  hex_codec {:>9.1} MiB/s  x{:.1}",
+        name,
+        size,
+        throughput(baseline),
+        throughput(fast),
+        baseline.as_secs_f64() / fast.as_secs_f64()
+    );
+}
+
+fn main() {
+    for size in SIZES {
+        let bytes: Vec<u8> = (0..size).map(|i| (i * 31) as u8).collect();
+        let baseline = time(|| drop(black_box(hex::encode(black_box(&bytes)))));
+        let fast = time(|| drop(black_box(hex_codec::encode(black_box(&bytes)))));
+        report("encode", size, baseline, fast);
+
+        let mut buffer = vec![0; size * 2 + 1];
+        let into = time(|| {
+            black_box(hex_codec::write_c_hex(black_box(&bytes), buffer.as_mut_ptr() as *mut _, buffer.len()));
+        });
+        report("into", size, baseline, into);
+
+        let text = hex::encode(&bytes);
+        let baseline = time(|| drop(black_box(hex::decode(black_box(&text)).unwrap())));
+        let fast = time(|| drop(black_box(hex_codec::decode(black_box(&text)).unwrap())));
+        report("decode", size, baseline, fast);
+    }
+}
@@ -0,0 +1,172 @@
+//! Host allocator hooks for the buffers handed across the FFI: responses
+//! and the strings they carry. Firmware with static memory pools registers
+//! its own alloc/free pair so those buffers come from its pools and can be
+//! released by the host.
+//!
+//! The hooks don't cover the library's internal allocations, which still
+//! use Rust's global allocator; hosts replacing it too do so with
+//! `#[global_allocator]` when building the library.
+
+use crate::response::{PtrResponse, Response};
+use crate::types::{PtrString, PtrVoid};
+use std::alloc::{handle_alloc_error, Layout};
+use std::ffi::CString;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::OnceLock;
+
+/// Returns memory of `size` bytes aligned to `align`, or null when out of
+/// memory
+pub type AllocFn = extern "C" fn(size: usize, align: usize) -> PtrVoid;
+pub type FreeFn = extern "C" fn(ptr: PtrVoid);
+
+#[derive(Clone, Copy)]
+struct HostAllocator {
+    alloc: AllocFn,
+    free: FreeFn,
+}
+
+impl HostAllocator {
+    fn allocate(&self, layout: Layout) -> PtrVoid {
+        let ptr = (self.alloc)(layout.size(), layout.align());
+        if ptr.is_null() {
+            handle_alloc_error(layout)
+        }
+        ptr
+    }
+
+    fn alloc_string(&self, string: CString) -> PtrString {
+        let bytes = string.as_bytes_with_nul();
+        let ptr = self.allocate(Layout::for_value(bytes)) as PtrString;
+        unsafe { std::ptr::copy_nonoverlapping(bytes.as_ptr(), ptr as *mut u8, bytes.len()) };
+        ptr
+    }
+
+    fn alloc_response(&self, response: Response) -> PtrResponse {
+        let ptr = self.allocate(Layout::new::<Response>()) as PtrResponse;
+        unsafe { ptr.write(response) };
+        ptr
+    }
+}
+
+static HOST: OnceLock<HostAllocator> = OnceLock::new();
+
+// Which allocator buffers come from, decided by whichever comes first of
+// the first allocation and `ur_registry_set_allocator`. One atomic covers
+// both, so an allocation racing the registration can't pick the default
+// allocator and then see its buffer freed by the host.
+const UNDECIDED: u8 = 0;
+const DEFAULT: u8 = 1;
+const REGISTERING: u8 = 2;
+const REGISTERED: u8 = 3;
+static STATE: AtomicU8 = AtomicU8::new(UNDECIDED);
+
+fn host() -> Option<&'static HostAllocator> {
+    loop {
+        match STATE.compare_exchange(UNDECIDED, DEFAULT, Ordering::AcqRel, Ordering::Acquire) {
+            Ok(_) | Err(DEFAULT) => return None,
+            Err(REGISTERED) => return HOST.get(),
+            // The hooks are being stored; wait the few instructions it takes
+            Err(_) => std::hint::spin_loop(),
+        }
+    }
+}
+
+pub fn alloc_string(string: CString) -> PtrString {
+    match host() {
+        Some(host) => host.alloc_string(string),
+        None => string.into_raw(),
+    }
+}
+
+pub fn free_string(ptr: PtrString) {
+    match HOST.get() {
+        Some(host) => (host.free)(ptr as PtrVoid),
+        None => drop(unsafe { CString::from_raw(ptr) }),
+    }
+}
+
+pub fn alloc_response(response: Response) -> PtrResponse {
+    match host() {
+        Some(host) => host.alloc_response(response),
+        None => Box::into_raw(Box::new(response)),
+    }
+}
+
+pub fn free_response(ptr: PtrResponse) {
+    match HOST.get() {
+        Some(host) => (host.free)(ptr as PtrVoid),
+        None => drop(unsafe { Box::from_raw(ptr) }),
+    }
+}
+
+// ========== FFI Functions ==========
+
+/// Routes responses and returned strings through `alloc` and `free`. Must
+/// be called once, before any other function: returns false, changing
+/// nothing, when hooks are already set or a buffer was already allocated.
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn ur_registry_set_allocator(alloc: AllocFn, free: FreeFn) -> bool {
+    if STATE.compare_exchange(UNDECIDED, REGISTERING, Ordering::AcqRel, Ordering::Acquire).is_err() {
+        return false;
+    }
+    let registered = HOST.set(HostAllocator { alloc, free }).is_ok();
+    STATE.store(REGISTERED, Ordering::Release);
+    registered
+}
+
+/// Frees a response, but not the strings it points to, which are freed
+/// with `ur_registry_free_string`. Null is ignored.
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn ur_registry_free_response(response: PtrResponse) {
+    if !response.is_null() {
+        free_response(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CStr;
+    use std::sync::Mutex;
+
+    // A stand-in pool recording live blocks with their layouts
+    static POOL: Mutex<Vec<(usize, Layout)>> = Mutex::new(Vec::new());
+
+    extern "C" fn pool_alloc(size: usize, align: usize) -> PtrVoid {
+        let layout = Layout::from_size_align(size, align).unwrap();
+        let ptr = unsafe { std::alloc::alloc(layout) };
+        POOL.lock().unwrap().push((ptr as usize, layout));
+        ptr as PtrVoid
+    }
+
+    extern "C" fn pool_free(ptr: PtrVoid) {
+        let mut pool = POOL.lock().unwrap();
+        let index = pool.iter().position(|(p, _)| *p == ptr as usize).unwrap();
+        let (_, layout) = pool.remove(index);
+        unsafe { std::alloc::dealloc(ptr as *mut u8, layout) };
+    }
+
+    #[test]
+    fn test_host_allocator() {
+        let host = HostAllocator { alloc: pool_alloc, free: pool_free };
+        let string = host.alloc_string(CString::new("pooled").unwrap());
+        let response = host.alloc_response(Response::success_uint32(3));
+        assert_eq!(POOL.lock().unwrap().len(), 2);
+        assert_eq!(unsafe { CStr::from_ptr(string) }.to_str().unwrap(), "pooled");
+        assert_eq!(unsafe { &*response }.status_code, 0);
+
+        (host.free)(string as PtrVoid);
+        (host.free)(response as PtrVoid);
+        assert!(POOL.lock().unwrap().is_empty());
+    }
+
+    #[cfg(feature = "ffi")]
+    #[test]
+    fn test_allocator_fixed_after_first_allocation() {
+        free_response(alloc_response(Response::success_null()));
+        assert!(!ur_registry_set_allocator(pool_alloc, pool_free));
+        assert!(HOST.get().is_none());
+    }
+}
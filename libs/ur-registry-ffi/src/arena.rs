@@ -0,0 +1,204 @@
+//! Arenas taking ownership of everything FFI calls return while one is
+//! entered, so callers making bursts of calls (decoding every camera frame)
+//! release the lot with a single reset instead of freeing each response and
+//! string.
+//!
+//! Arenas collect responses and their strings only: objects a response
+//! points to are still freed by the caller. Results an arena owns must not
+//! be freed individually.
+//!
+//! An arena is entered on one thread at a time, and can't be freed while
+//! another thread has it entered.
+
+use crate::alloc;
+use crate::response::{PtrResponse, Response};
+use crate::types::{PtrString, PtrVoid};
+use crate::utils::ur_registry_free_string;
+use std::cell::Cell;
+use std::ptr::null_mut;
+use std::sync::Mutex;
+use std::thread::{self, ThreadId};
+
+#[derive(Default)]
+pub struct Arena {
+    responses: Vec<PtrResponse>,
+    strings: Vec<PtrString>,
+    // Thread that has the arena entered, if any
+    entered_on: Mutex<Option<ThreadId>>,
+}
+
+impl Arena {
+    // Marks the arena entered on this thread; false when another has it
+    fn claim(&self) -> bool {
+        let mut entered_on = self.entered_on.lock().unwrap();
+        match *entered_on {
+            Some(owner) if owner != thread::current().id() => false,
+            _ => {
+                *entered_on = Some(thread::current().id());
+                true
+            }
+        }
+    }
+
+    fn release(&self) {
+        *self.entered_on.lock().unwrap() = None;
+    }
+
+    fn is_entered_elsewhere(&self) -> bool {
+        self.entered_on.lock().unwrap().is_some_and(|owner| owner != thread::current().id())
+    }
+
+    /// Frees everything collected so far; the arena stays usable
+    pub fn reset(&mut self) {
+        for string in self.strings.drain(..) {
+            ur_registry_free_string(string);
+        }
+        for response in self.responses.drain(..) {
+            alloc::free_response(response);
+        }
+    }
+}
+
+impl Drop for Arena {
+    fn drop(&mut self) {
+        self.reset();
+    }
+}
+
+thread_local! {
+    // Arenas are entered per thread: calls elsewhere allocate as usual
+    static CURRENT: Cell<*mut Arena> = const { Cell::new(null_mut()) };
+}
+
+fn with_current(f: impl FnOnce(&mut Arena)) {
+    CURRENT.with(|current| {
+        let arena = current.get();
+        if !arena.is_null() {
+            f(unsafe { &mut *arena })
+        }
+    })
+}
+
+pub fn adopt_response(response: PtrResponse) {
+    with_current(|arena| arena.responses.push(response))
+}
+
+pub fn adopt_string(string: PtrString) {
+    with_current(|arena| arena.strings.push(string))
+}
+
+// ========== FFI Functions ==========
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn ur_registry_arena_new() -> PtrResponse {
+    Response::success_object(Box::into_raw(Box::<Arena>::default()) as PtrVoid).c_ptr()
+}
+
+/// Makes `arena` own what calls on this thread return until
+/// `ur_registry_arena_leave`. Entering another arena replaces it. False,
+/// changing nothing, when another thread has `arena` entered.
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn ur_registry_arena_enter(arena: &mut Arena) -> bool {
+    if !arena.claim() {
+        return false;
+    }
+    let previous = CURRENT.with(|current| current.replace(arena));
+    if !previous.is_null() && !std::ptr::eq(previous, arena) {
+        unsafe { &*previous }.release();
+    }
+    true
+}
+
+/// Stops collecting on this thread; what was collected stays in the arena
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn ur_registry_arena_leave() {
+    let previous = CURRENT.with(|current| current.replace(null_mut()));
+    if !previous.is_null() {
+        unsafe { &*previous }.release();
+    }
+}
+
+/// Frees everything the arena collected. Must not be called while a
+/// result it owns is still in use.
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn ur_registry_arena_reset(arena: &mut Arena) {
+    arena.reset()
+}
+
+/// Frees the arena and everything it collected, leaving it first if it's
+/// entered on this thread. False, freeing nothing, while another thread
+/// has it entered: that thread leaves it first.
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn ur_registry_arena_free(arena: *mut Arena) -> bool {
+    if arena.is_null() {
+        return true;
+    }
+    if unsafe { &*arena }.is_entered_elsewhere() {
+        return false;
+    }
+    CURRENT.with(|current| {
+        if current.get() == arena {
+            current.set(null_mut())
+        }
+    });
+    drop(unsafe { Box::from_raw(arena) });
+    true
+}
+
+#[cfg(all(test, feature = "ffi"))]
+mod tests {
+    use super::*;
+
+    #[cfg(debug_assertions)]
+    #[test]
+    fn test_arena_frees_results() {
+        use crate::utils::string_tracker::live_strings;
+
+        let arena = Response::from_ptr(ur_registry_arena_new()).value.get_object() as *mut Arena;
+        let before = live_strings();
+
+        ur_registry_arena_enter(unsafe { &mut *arena });
+        Response::success_string("frame".to_string()).c_ptr();
+        Response::error("bad frame".to_string()).c_ptr();
+        ur_registry_arena_leave();
+        let outside = Response::from_ptr(Response::success_null().c_ptr());
+
+        assert_eq!((unsafe { &*arena }.responses.len(), unsafe { &*arena }.strings.len()), (2, 4));
+        ur_registry_arena_reset(unsafe { &mut *arena });
+        assert!(unsafe { &*arena }.strings.is_empty());
+        assert_eq!(live_strings(), before + 1);
+
+        ur_registry_free_string(outside.value_type);
+        ur_registry_arena_enter(unsafe { &mut *arena });
+        Response::success_uint32(1).c_ptr();
+        assert!(ur_registry_arena_free(arena));
+        assert_eq!(live_strings(), before);
+        assert!(CURRENT.with(|current| current.get().is_null()));
+    }
+
+    #[test]
+    fn test_arena_entered_on_another_thread() {
+        let arena = Response::from_ptr(ur_registry_arena_new()).value.get_object() as *mut Arena;
+        let address = arena as usize;
+        let (entered, release) = (std::sync::mpsc::channel(), std::sync::mpsc::channel::<()>());
+        let other = thread::spawn(move || {
+            assert!(ur_registry_arena_enter(unsafe { &mut *(address as *mut Arena) }));
+            entered.0.send(()).unwrap();
+            release.1.recv().unwrap();
+            ur_registry_arena_leave();
+        });
+
+        entered.1.recv().unwrap();
+        assert!(!ur_registry_arena_enter(unsafe { &mut *arena }));
+        assert!(!ur_registry_arena_free(arena));
+        release.0.send(()).unwrap();
+        other.join().unwrap();
+        assert!(ur_registry_arena_enter(unsafe { &mut *arena }));
+        assert!(ur_registry_arena_free(arena));
+    }
+}
@@ -0,0 +1,147 @@
+//! UR parts as raw bytes, for binary QR mode and BLE packets where the
+//! `ur:` text framing and bytewords double the size for nothing. A frame is
+//!
+//! `kind (1) | type length (1) | UR type | payload | CRC-32 (4, big-endian)`
+//!
+//! with payload the message itself for a single-part UR and the fountain
+//! part's CBOR otherwise. The CRC stands in for the bytewords checksum text
+//! parts carry. Decoding rebuilds the text part, so the fountain decoder is
+//! the same for both inputs.
+
+use crc::{Crc, CRC_32_ISO_HDLC};
+use serde_cbor::Value;
+use ur::bytewords::{self, Style};
+
+pub const KIND_SINGLE: u8 = 0;
+pub const KIND_FOUNTAIN: u8 = 1;
+
+const HEADER_LEN: usize = 2;
+const CHECKSUM_LEN: usize = 4;
+
+fn crc32(bytes: &[u8]) -> u32 {
+    Crc::<u32>::new(&CRC_32_ISO_HDLC).checksum(bytes)
+}
+
+/// Frame of a text part as `UREncoder::next_part` emits it
+pub fn encode_part(part: &str) -> Result<Vec<u8>, String> {
+    let part = part.trim().to_lowercase();
+    let segments: Vec<&str> = part.strip_prefix("ur:").ok_or("Not a UR part")?.split('/').collect();
+    let (kind, ur_type, body) = match segments[..] {
+        [ur_type, body] => (KIND_SINGLE, ur_type, body),
+        [ur_type, _, body] => (KIND_FOUNTAIN, ur_type, body),
+        _ => return Err("Invalid UR part".to_string()),
+    };
+    let type_len = u8::try_from(ur_type.len()).map_err(|_| "UR type too long".to_string())?;
+    let payload = bytewords::decode(body, &Style::Minimal).map_err(|e| e.to_string())?;
+
+    let mut frame = Vec::with_capacity(HEADER_LEN + ur_type.len() + payload.len() + CHECKSUM_LEN);
+    frame.push(kind);
+    frame.push(type_len);
+    frame.extend_from_slice(ur_type.as_bytes());
+    frame.extend_from_slice(&payload);
+    frame.extend_from_slice(&crc32(&frame).to_be_bytes());
+    Ok(frame)
+}
+
+// Sequence number and count of a fountain part's CBOR array
+fn fountain_position(payload: &[u8]) -> Result<(u64, u64), String> {
+    match serde_cbor::from_slice(payload).map_err(|e| e.to_string())? {
+        Value::Array(items) => match (items.first(), items.get(1)) {
+            (Some(Value::Integer(seq)), Some(Value::Integer(count))) => Ok((
+                u64::try_from(*seq).map_err(|_| "Invalid part sequence number")?,
+                u64::try_from(*count).map_err(|_| "Invalid part count")?,
+            )),
+            _ => Err("Missing part sequence number".to_string()),
+        },
+        _ => Err("Expected fountain part array".to_string()),
+    }
+}
+
+/// Text part a frame carries, as `URDecoder::receive` takes it
+pub fn decode_part(frame: &[u8]) -> Result<String, String> {
+    if frame.len() < HEADER_LEN + CHECKSUM_LEN {
+        return Err("Truncated binary part".to_string());
+    }
+    let (content, checksum) = frame.split_at(frame.len() - CHECKSUM_LEN);
+    if crc32(content).to_be_bytes() != checksum {
+        return Err("Binary part checksum mismatch".to_string());
+    }
+    let type_end = HEADER_LEN + content[1] as usize;
+    let ur_type = content.get(HEADER_LEN..type_end).ok_or("Truncated binary part")?;
+    let ur_type = std::str::from_utf8(ur_type).map_err(|_| "Invalid UR type".to_string())?;
+    if ur_type.is_empty() || !ur_type.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-') {
+        return Err(format!("Invalid UR type: {:?}", ur_type));
+    }
+    let payload = &content[type_end..];
+    let body = bytewords::encode(payload, &Style::Minimal);
+    match content[0] {
+        KIND_SINGLE => Ok(format!("ur:{}/{}", ur_type, body)),
+        KIND_FOUNTAIN => {
+            let (seq, count) = fountain_position(payload)?;
+            Ok(format!("ur:{}/{}-{}/{}", ur_type, seq, count, body))
+        }
+        kind => Err(format!("Unknown binary part kind {}", kind)),
+    }
+}
+
+#[cfg(all(test, feature = "ffi"))]
+mod tests {
+    use super::*;
+    use crate::ur_decoder::URDecoder;
+    use crate::ur_encoder::UREncoder;
+
+    #[test]
+    fn test_binary_parts() {
+        let message: Vec<u8> = (0..600u32).map(|i| (i * 7) as u8).collect();
+        let mut encoder = UREncoder::new(&message, 100, "bytes").unwrap();
+        let mut decoder = URDecoder::default();
+        while !decoder.complete() {
+            let text = encoder.next_part().unwrap();
+            let frame = encode_part(&text).unwrap();
+            assert!(frame.len() < text.len() * 3 / 4);
+            assert_eq!(decode_part(&frame).unwrap(), text);
+            decoder.receive_bytes(&frame).unwrap();
+        }
+        assert_eq!(decoder.message_checksum().unwrap(), encoder.checksum());
+
+        let single = UREncoder::new(&[1, 2, 3], 100, "bytes").unwrap().next_part().unwrap();
+        let mut frame = encode_part(&single).unwrap();
+        assert_eq!(frame[0], KIND_SINGLE);
+        assert_eq!(decode_part(&frame).unwrap(), single);
+
+        frame[3] ^= 1;
+        assert!(decode_part(&frame).is_err());
+        assert!(decode_part(&[KIND_SINGLE, 0]).is_err());
+    }
+
+    #[test]
+    fn test_binary_parts_into() {
+        use crate::response::{ERROR, SUCCESS};
+        use crate::ur_decoder::{ur_decoder_receive_bytes_into, ReceiveSlot, RECEIVE_INVALID_PART, RECEIVE_OK};
+        use crate::ur_encoder::ur_encoder_next_part_bytes_into;
+
+        let message: Vec<u8> = (0..600u32).map(|i| (i * 7) as u8).collect();
+        let mut encoder = UREncoder::new(&message, 100, "bytes").unwrap();
+        let mut decoder = URDecoder::default();
+        let mut slot = ReceiveSlot::default();
+        let mut buffer = [0u8; 256];
+
+        // Sizing with a null or short buffer keeps the frame for the next
+        // call, and it isn't emitted until then
+        let needed = ur_encoder_next_part_bytes_into(&mut encoder, std::ptr::null_mut(), 0);
+        assert_eq!(ur_encoder_next_part_bytes_into(&mut encoder, buffer.as_mut_ptr(), needed - 1), needed);
+        assert_eq!(encoder.current_index(), 0);
+        while !slot.complete {
+            let len = ur_encoder_next_part_bytes_into(&mut encoder, buffer.as_mut_ptr(), buffer.len());
+            assert!(len > 0 && len <= buffer.len());
+            ur_decoder_receive_bytes_into(&mut decoder, buffer.as_ptr(), len, &mut slot);
+            assert_eq!((slot.status_code, slot.error_code), (SUCCESS, RECEIVE_OK));
+        }
+        assert_eq!(decoder.message_checksum().unwrap(), encoder.checksum());
+
+        ur_decoder_receive_bytes_into(&mut decoder, buffer.as_ptr(), 3, &mut slot);
+        assert_eq!((slot.status_code, slot.error_code), (ERROR, RECEIVE_INVALID_PART));
+        ur_decoder_receive_bytes_into(&mut decoder, std::ptr::null(), 0, &mut slot);
+        assert_eq!(slot.error_code, RECEIVE_INVALID_PART);
+    }
+}
@@ -1,6 +1,7 @@
 use crate::response::{PtrResponse, Response};
 use crate::types::{PtrString, PtrVoid};
 use crate::utils::{convert_ptr_string_to_string, remove_prefix_0x};
+use crate::ur_encoder::UREncoder;
 use serde::Deserialize;
 use serde_json::json;
 use ur_registry::cardano::cardano_catalyst_voting_registration::CardanoCatalystVotingRegistrationRequest;
@@ -141,7 +142,7 @@ pub extern "C" fn cardano_catalyst_voting_registration_get_ur_encoder(
     cardano_catalyst_voting_registration: &mut CardanoCatalystVotingRegistrationRequest,
 ) -> PtrResponse {
     let message = cardano_catalyst_voting_registration.to_bytes().unwrap();
-    let ur_encoder = ur::Encoder::new(
+    let ur_encoder = UREncoder::new(
         message.as_slice(),
         200,
         CardanoCatalystVotingRegistrationRequest::get_registry_type().get_type(),
@@ -156,7 +157,7 @@ pub extern "C" fn cardano_catalyst_voting_registration_get_request_id(
 ) -> PtrResponse {
     cardano_catalyst_voting_registration
         .get_request_id()
-        .map_or(Response::success_null().c_ptr(), |id| {
+        .map_or(Response::not_present().c_ptr(), |id| {
             Response::success_string(hex::encode(id)).c_ptr()
         })
 }
@@ -2,6 +2,7 @@ use crate::response::{PtrResponse, Response};
 use crate::types::{PtrString, PtrVoid};
 use crate::utils::convert_ptr_string_to_string;
 use crate::utils::remove_prefix_0x;
+use crate::ur_encoder::UREncoder;
 use serde_json::json;
 use ur_registry::cardano::cardano_sign_cip8_data_request::{
     CardanoSignCip8DataRequest, Cip8AddressType,
@@ -124,7 +125,7 @@ pub extern "C" fn cardano_sign_cip8_data_request_get_ur_encoder(
     cardano_sign_cip8_data_request: &mut CardanoSignCip8DataRequest,
 ) -> PtrResponse {
     let message = cardano_sign_cip8_data_request.to_bytes().unwrap();
-    let ur_encoder = ur::Encoder::new(
+    let ur_encoder = UREncoder::new(
         message.as_slice(),
         200,
         CardanoSignCip8DataRequest::get_registry_type().get_type(),
@@ -139,7 +140,7 @@ pub extern "C" fn cardano_sign_cip8_data_request_get_request_id(
 ) -> PtrResponse {
     cardano_sign_cip8_data_request
         .get_request_id()
-        .map_or(Response::success_null().c_ptr(), |id| {
+        .map_or(Response::not_present().c_ptr(), |id| {
             Response::success_string(hex::encode(id)).c_ptr()
         })
 }
@@ -1,4 +1,5 @@
 use crate::utils::{convert_ptr_string_to_string, remove_prefix_0x};
+use crate::ur_encoder::UREncoder;
 use crate::{
     response::{PtrResponse, Response},
     types::{PtrString, PtrVoid},
@@ -99,7 +100,7 @@ pub extern "C" fn cardano_sign_data_request_get_ur_encoder(
     cardano_sign_data_request: &mut CardanoSignDataRequest,
 ) -> PtrResponse {
     let message = cardano_sign_data_request.to_bytes().unwrap();
-    let ur_encoder = ur::Encoder::new(
+    let ur_encoder = UREncoder::new(
         message.as_slice(),
         200,
         CardanoSignDataRequest::get_registry_type().get_type(),
@@ -114,7 +115,7 @@ pub extern "C" fn cardano_sign_data_request_get_request_id(
 ) -> PtrResponse {
     cardano_sign_data_request
         .get_request_id()
-        .map_or(Response::success_null().c_ptr(), |id| {
+        .map_or(Response::not_present().c_ptr(), |id| {
             Response::success_string(hex::encode(id)).c_ptr()
         })
 }
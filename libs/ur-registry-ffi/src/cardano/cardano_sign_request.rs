@@ -3,6 +3,7 @@ use serde::Deserialize;
 use serde_json::json;
 use crate::types::{PtrString, PtrVoid};
 use crate::utils::{convert_ptr_string_to_string, remove_prefix_0x};
+use crate::ur_encoder::UREncoder;
 use ur_registry::crypto_key_path::CryptoKeyPath;
 use ur_registry::cardano::cardano_sign_request::CardanoSignRequest;
 use ur_registry::cardano::cardano_cert_key::CardanoCertKey;
@@ -156,7 +157,7 @@ pub extern "C" fn cardano_sign_request_construct(
 #[no_mangle]
 pub extern "C" fn cardano_sign_request_get_ur_encoder(cardano_sign_request: &mut CardanoSignRequest) -> PtrResponse {
     let message = cardano_sign_request.to_bytes().unwrap();
-    let ur_encoder = ur::Encoder::new(
+    let ur_encoder = UREncoder::new(
         message.as_slice(),
         200,
         CardanoSignRequest::get_registry_type().get_type(),
@@ -167,7 +168,7 @@ pub extern "C" fn cardano_sign_request_get_ur_encoder(cardano_sign_request: &mut
 
 #[no_mangle]
 pub extern "C" fn cardano_sign_request_get_request_id(cardano_sign_request: &mut CardanoSignRequest) -> PtrResponse {
-    cardano_sign_request.get_request_id().map_or(Response::success_null().c_ptr(), |id| {
+    cardano_sign_request.get_request_id().map_or(Response::not_present().c_ptr(), |id| {
         Response::success_string(hex::encode(id)).c_ptr()
     })
 }
@@ -241,11 +242,11 @@ mod tests {
     let origin = "cardano-wallet";
 
     let response = cardano_sign_request_construct(
-        str_to_ptr_c_char(request_id.to_string()),
-        str_to_ptr_c_char(sign_data.to_string()),
-        str_to_ptr_c_char(utxos.to_string()),
-        str_to_ptr_c_char(cert_keys.to_string()),
-        str_to_ptr_c_char(origin.to_string()),
+        str_to_ptr_c_char(request_id.to_string()).unwrap(),
+        str_to_ptr_c_char(sign_data.to_string()).unwrap(),
+        str_to_ptr_c_char(utxos.to_string()).unwrap(),
+        str_to_ptr_c_char(cert_keys.to_string()).unwrap(),
+        str_to_ptr_c_char(origin.to_string()).unwrap(),
         );
     let response = unsafe { Response::from_ptr(response) };
 
@@ -9,6 +9,7 @@ use uuid::Uuid;
 use crate::{
     response::{PtrResponse, Response},
     types::{PtrString, PtrVoid},
+    ur_encoder::UREncoder,
     utils::convert_ptr_string_to_string,
 };
 
@@ -90,7 +91,7 @@ pub extern "C" fn cardano_sign_tx_hash_request_construct(
 #[no_mangle]
 pub extern "C" fn cardano_sign_tx_hash_request_get_ur_encoder(cardano_sign_tx_hash_request: &mut CardanoSignTxHashRequest) -> PtrResponse {
     let message = cardano_sign_tx_hash_request.to_bytes().unwrap();
-    let ur_encoder = ur::Encoder::new(
+    let ur_encoder = UREncoder::new(
         message.as_slice(),
         200,
         CardanoSignTxHashRequest::get_registry_type().get_type(),
@@ -101,7 +102,7 @@ pub extern "C" fn cardano_sign_tx_hash_request_get_ur_encoder(cardano_sign_tx_ha
 
 #[no_mangle]
 pub extern "C" fn cardano_sign_tx_hash_request_get_request_id(cardano_sign_tx_hash_request: &mut CardanoSignTxHashRequest) -> PtrResponse {
-    cardano_sign_tx_hash_request.get_request_id().map_or(Response::success_null().c_ptr(), |id| {
+    cardano_sign_tx_hash_request.get_request_id().map_or(Response::not_present().c_ptr(), |id| {
         Response::success_string(hex::encode(id)).c_ptr()
     })
 }
\ No newline at end of file
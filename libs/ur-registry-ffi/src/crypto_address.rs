@@ -0,0 +1,236 @@
+use serde_cbor::Value;
+use std::collections::BTreeMap;
+use ur_registry::crypto_coin_info::Network;
+
+use crate::{
+    response::{PtrResponse, Response},
+    tron::address::{decode_address, encode_address, validate_address},
+    tron::decode::decode_value,
+    types::{PtrString, PtrVoid},
+    ur_encoder::UREncoder,
+    utils::convert_ptr_string_to_string,
+};
+
+pub const CRYPTO_ADDRESS_TYPE: &str = "crypto-address";
+
+// CBOR map keys for CryptoAddress
+const INFO: i128 = 1;
+const DATA: i128 = 3;
+
+// CBOR map keys for the embedded crypto-coininfo
+const COIN_TYPE: i128 = 1;
+const NETWORK: i128 = 2;
+
+const CRYPTO_COIN_INFO_TAG: u64 = 305;
+
+// SLIP-44 coin type for TRON
+pub const COIN_TYPE_TRON: u32 = 195;
+
+/// A standalone address with its coin-info. Only TRON addresses are accepted.
+/// The network is upstream's; its `CoinType` only knows Bitcoin and Ethereum
+/// and reads any other coin type as Bitcoin, so the coin type is kept here.
+#[derive(Clone, Debug)]
+pub struct CryptoAddress {
+    network: Network,
+    data: Vec<u8>,
+}
+
+impl CryptoAddress {
+    pub fn new(data: Vec<u8>, network: Network) -> Result<Self, String> {
+        validate_address(&data)?;
+        Ok(CryptoAddress { network, data })
+    }
+
+    pub fn from_base58(address: &str) -> Result<Self, String> {
+        CryptoAddress::new(decode_address(address)?, Network::MainNet)
+    }
+
+    pub fn get_coin_type(&self) -> u32 {
+        COIN_TYPE_TRON
+    }
+
+    pub fn get_network(&self) -> &Network {
+        &self.network
+    }
+
+    pub fn get_data(&self) -> &Vec<u8> {
+        &self.data
+    }
+
+    pub fn get_address(&self) -> Result<String, String> {
+        encode_address(&self.data)
+    }
+
+    /// Serialize to CBOR bytes
+    pub fn to_bytes(&self) -> Result<Vec<u8>, String> {
+        let mut info: BTreeMap<Value, Value> = BTreeMap::new();
+        info.insert(Value::Integer(COIN_TYPE), Value::Integer(COIN_TYPE_TRON as i128));
+        if self.network != Network::MainNet {
+            info.insert(Value::Integer(NETWORK), Value::Integer(self.network.clone() as i128));
+        }
+
+        let mut map: BTreeMap<Value, Value> = BTreeMap::new();
+        map.insert(
+            Value::Integer(INFO),
+            Value::Tag(CRYPTO_COIN_INFO_TAG, Box::new(Value::Map(info))),
+        );
+        map.insert(Value::Integer(DATA), Value::Bytes(self.data.clone()));
+
+        serde_cbor::to_vec(&Value::Map(map)).map_err(|e| e.to_string())
+    }
+}
+
+fn decode_coin_info(value: Option<&Value>) -> Result<(u32, Network), String> {
+    let info = match value {
+        Some(Value::Tag(CRYPTO_COIN_INFO_TAG, inner)) => inner.as_ref(),
+        Some(v) => v,
+        // Absent coin-info means Bitcoin mainnet
+        None => return Ok((0, Network::MainNet)),
+    };
+    if let Value::Map(map) = info {
+        let read = |key: i128| match map.get(&Value::Integer(key)) {
+            Some(Value::Integer(i)) => u32::try_from(*i).map_err(|_| format!("Invalid coin info value: {}", i)),
+            Some(_) => Err("Invalid coin info".to_string()),
+            None => Ok(0),
+        };
+        Ok((read(COIN_TYPE)?, Network::from_u32(read(NETWORK)?)))
+    } else {
+        Err("Invalid coin info".to_string())
+    }
+}
+
+impl TryFrom<Vec<u8>> for CryptoAddress {
+    type Error = String;
+
+    fn try_from(data: Vec<u8>) -> Result<Self, Self::Error> {
+        let cbor_value = decode_value(&data)?;
+
+        if let Value::Map(map) = cbor_value {
+            let (coin_type, network) = decode_coin_info(map.get(&Value::Integer(INFO)))?;
+            if coin_type != COIN_TYPE_TRON {
+                return Err(format!("Unsupported coin type: {}", coin_type));
+            }
+
+            let address = map.get(&Value::Integer(DATA))
+                .and_then(|v| if let Value::Bytes(b) = v { Some(b.clone()) } else { None })
+                .ok_or("Missing address data")?;
+
+            CryptoAddress::new(address, network)
+        } else {
+            Err("Expected CBOR map".to_string())
+        }
+    }
+}
+
+pub fn resolve(data: Vec<u8>) -> PtrResponse {
+    match CryptoAddress::try_from(data) {
+        Ok(result) => Response::success_object(Box::into_raw(Box::new(result)) as PtrVoid).c_ptr(),
+        Err(error) => Response::error(error.to_string()).c_ptr(),
+    }
+}
+
+/// `address` is the Base58Check form, e.g. `TXYZ...`
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn crypto_address_construct(address: PtrString) -> PtrResponse {
+    let address = match convert_ptr_string_to_string(address).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    match CryptoAddress::from_base58(&address) {
+        Ok(crypto_address) => Response::success_object(Box::into_raw(Box::new(crypto_address)) as PtrVoid),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn crypto_address_get_address(crypto_address: &CryptoAddress) -> PtrResponse {
+    match crypto_address.get_address() {
+        Ok(v) => Response::success_string(v),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn crypto_address_get_data(crypto_address: &CryptoAddress) -> PtrResponse {
+    Response::success_string(hex::encode(crypto_address.get_data())).c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn crypto_address_get_coin_type(crypto_address: &CryptoAddress) -> PtrResponse {
+    Response::success_uint32(crypto_address.get_coin_type()).c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn crypto_address_get_network(crypto_address: &CryptoAddress) -> PtrResponse {
+    Response::success_uint32(crypto_address.get_network().clone() as u32).c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn crypto_address_get_ur_encoder(crypto_address: &CryptoAddress) -> PtrResponse {
+    let message = match crypto_address.to_bytes() {
+        Ok(v) => v,
+        Err(e) => return Response::error(e).c_ptr(),
+    };
+    match UREncoder::new(message.as_slice(), 400, CRYPTO_ADDRESS_TYPE) {
+        Ok(ur_encoder) => Response::success_object(Box::into_raw(Box::new(ur_encoder)) as PtrVoid),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ADDRESS: &str = "TMVQGm1qAQYVdetCeGRRkTWYYrLXuHK2HC";
+
+    #[test]
+    fn test_crypto_address_round_trip() {
+        let address = CryptoAddress::from_base58(ADDRESS).unwrap();
+        let decoded = CryptoAddress::try_from(address.to_bytes().unwrap()).unwrap();
+        assert_eq!(decoded.get_coin_type(), COIN_TYPE_TRON);
+        assert_eq!(decoded.get_network(), &Network::MainNet);
+        assert_eq!(decoded.get_address().unwrap(), ADDRESS);
+
+        let testnet = CryptoAddress::new(address.get_data().clone(), Network::TestNet).unwrap();
+        let decoded = CryptoAddress::try_from(testnet.to_bytes().unwrap()).unwrap();
+        assert_eq!(decoded.get_network(), &Network::TestNet);
+        assert_eq!(decoded.get_data(), address.get_data());
+    }
+
+    #[test]
+    fn test_crypto_address_rejects_invalid() {
+        let address = CryptoAddress::from_base58(ADDRESS).unwrap();
+        assert!(CryptoAddress::new(vec![0x41; 10], Network::MainNet).is_err());
+        assert!(CryptoAddress::from_base58("TMVQGm1qAQYVdetCeGRRkTWYYrLXuHK2HD").is_err());
+
+        // Without coin-info the address reads as Bitcoin
+        let mut map = BTreeMap::new();
+        map.insert(Value::Integer(DATA), Value::Bytes(address.get_data().clone()));
+        let cbor = serde_cbor::to_vec(&Value::Map(map.clone())).unwrap();
+        assert_eq!(CryptoAddress::try_from(cbor).unwrap_err(), "Unsupported coin type: 0");
+
+        map.insert(Value::Integer(INFO), Value::Text("tron".to_string()));
+        assert!(CryptoAddress::try_from(serde_cbor::to_vec(&Value::Map(map)).unwrap()).is_err());
+        assert!(CryptoAddress::try_from(serde_cbor::to_vec(&Value::Array(vec![])).unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_crypto_address_rejects_duplicate_keys() {
+        // A second DATA key naming another address
+        let mut cbor = CryptoAddress::from_base58(ADDRESS).unwrap().to_bytes().unwrap();
+        let other = decode_address("TR7NHqjeKQxGTCi8q8ZY4pL8otSzgjLj6t").unwrap();
+        cbor[0] += 1;
+        cbor.extend(serde_cbor::to_vec(&Value::Integer(DATA)).unwrap());
+        cbor.extend(serde_cbor::to_vec(&Value::Bytes(other)).unwrap());
+        assert!(CryptoAddress::try_from(cbor).unwrap_err().starts_with("Duplicate CBOR map key"));
+    }
+}
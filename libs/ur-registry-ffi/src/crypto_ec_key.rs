@@ -0,0 +1,163 @@
+use secp256k1::{PublicKey, SecretKey};
+use ur_registry::crypto_ec_key::CryptoECKey;
+use ur_registry::error::URError;
+
+use crate::{
+    response::{PtrResponse, Response},
+    tron::address::{encode_address, public_key_to_address},
+    tron::decode::decode_value,
+    types::{PtrString, PtrVoid},
+    ur_encoder::UREncoder,
+    utils::parse_ptr_string_to_bytes,
+};
+
+pub const CRYPTO_EC_KEY_TYPE: &str = "crypto-eckey";
+
+// secp256k1, the only curve defined so far
+const CURVE_SECP256K1: u32 = 0;
+
+fn validate(key: &CryptoECKey) -> Result<(), String> {
+    if key.get_curve() != CURVE_SECP256K1 as i128 {
+        return Err(format!("Unsupported curve: {}", key.get_curve()));
+    }
+    if key.get_is_private_key() {
+        SecretKey::from_slice(&key.get_data()).map_err(|e| format!("Invalid private key: {}", e))?;
+    } else {
+        PublicKey::from_slice(&key.get_data()).map_err(|e| format!("Invalid public key: {}", e))?;
+    }
+    Ok(())
+}
+
+/// A single EC key (not an xpub), e.g. one TRON account's public key.
+/// Defaults (secp256k1, public) are left out of the CBOR.
+pub fn new_crypto_ec_key(curve: u32, is_private: bool, data: Vec<u8>) -> Result<CryptoECKey, String> {
+    let key = CryptoECKey::new(
+        (curve != CURVE_SECP256K1).then_some(curve as i128),
+        is_private.then_some(true),
+        data,
+    );
+    validate(&key)?;
+    Ok(key)
+}
+
+/// Decodes and checks the key is one this library can use
+pub fn decode(data: Vec<u8>) -> Result<CryptoECKey, String> {
+    // Upstream decoding keeps the last of repeated keys
+    decode_value(&data)?;
+    let key = CryptoECKey::try_from(data).map_err(|e| e.to_string())?;
+    validate(&key)?;
+    Ok(key)
+}
+
+/// TRON address of a public key
+pub fn get_tron_address(key: &CryptoECKey) -> Result<String, String> {
+    if key.get_is_private_key() {
+        return Err("Address is only derived from public keys".to_string());
+    }
+    encode_address(&public_key_to_address(&key.get_data())?)
+}
+
+pub fn resolve(data: Vec<u8>) -> PtrResponse {
+    match decode(data) {
+        Ok(result) => Response::success_object(Box::into_raw(Box::new(result)) as PtrVoid).c_ptr(),
+        Err(error) => Response::error(error).c_ptr(),
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn crypto_ec_key_construct(curve: u32, is_private: bool, data: PtrString) -> PtrResponse {
+    let data = match parse_ptr_string_to_bytes(data).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    match new_crypto_ec_key(curve, is_private, data) {
+        Ok(crypto_ec_key) => Response::success_object(Box::into_raw(Box::new(crypto_ec_key)) as PtrVoid),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[no_mangle]
+pub extern "C" fn crypto_ec_key_get_curve(crypto_ec_key: &CryptoECKey) -> PtrResponse {
+    Response::success_uint32(crypto_ec_key.get_curve() as u32).c_ptr()
+}
+
+#[no_mangle]
+pub extern "C" fn crypto_ec_key_is_private(crypto_ec_key: &CryptoECKey) -> PtrResponse {
+    Response::success_boolean(crypto_ec_key.get_is_private_key()).c_ptr()
+}
+
+#[no_mangle]
+pub extern "C" fn crypto_ec_key_get_data(crypto_ec_key: &CryptoECKey) -> PtrResponse {
+    Response::success_string(hex::encode(crypto_ec_key.get_data())).c_ptr()
+}
+
+#[no_mangle]
+pub extern "C" fn crypto_ec_key_get_tron_address(crypto_ec_key: &CryptoECKey) -> PtrResponse {
+    match get_tron_address(crypto_ec_key) {
+        Ok(v) => Response::success_string(v),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[no_mangle]
+pub extern "C" fn crypto_ec_key_get_ur_encoder(crypto_ec_key: &CryptoECKey) -> PtrResponse {
+    let message = match TryInto::<Vec<u8>>::try_into(crypto_ec_key.clone()) {
+        Ok(v) => v,
+        Err(e) => return Response::error(URError::to_string(&e)).c_ptr(),
+    };
+    match UREncoder::new(message.as_slice(), 400, CRYPTO_EC_KEY_TYPE) {
+        Ok(ur_encoder) => Response::success_object(Box::into_raw(Box::new(ur_encoder)) as PtrVoid),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PUBLIC_KEY: &str = "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798";
+
+    #[test]
+    fn test_crypto_ec_key_round_trip() {
+        let key = new_crypto_ec_key(0, false, hex::decode(PUBLIC_KEY).unwrap()).unwrap();
+        let cbor: Vec<u8> = key.clone().try_into().unwrap();
+        // Only the data key; the defaults are left out
+        assert_eq!(&cbor[..2], &[0xa1, 0x03]);
+        let decoded = decode(cbor).unwrap();
+        assert_eq!(decoded.get_curve(), 0);
+        assert!(!decoded.get_is_private_key());
+        assert_eq!(hex::encode(decoded.get_data()), PUBLIC_KEY);
+        assert_eq!(get_tron_address(&decoded).unwrap(), get_tron_address(&key).unwrap());
+
+        let private = new_crypto_ec_key(0, true, vec![1; 32]).unwrap();
+        let decoded = decode(private.try_into().unwrap()).unwrap();
+        assert!(decoded.get_is_private_key());
+        assert_eq!(decoded.get_data(), vec![1; 32]);
+        assert!(get_tron_address(&decoded).is_err());
+    }
+
+    #[test]
+    fn test_crypto_ec_key_rejects_invalid() {
+        assert!(new_crypto_ec_key(1, false, hex::decode(PUBLIC_KEY).unwrap()).is_err());
+        assert!(new_crypto_ec_key(0, false, vec![5; 33]).is_err());
+        assert!(new_crypto_ec_key(0, true, vec![0; 32]).is_err());
+
+        let other_curve = CryptoECKey::new(Some(1), None, hex::decode(PUBLIC_KEY).unwrap());
+        assert!(decode(other_curve.try_into().unwrap()).is_err());
+        assert!(decode(vec![0xa1, 0x03, 0x43, 1, 2, 3]).is_err());
+        assert!(decode(vec![0xff]).is_err());
+    }
+
+    #[test]
+    fn test_crypto_ec_key_rejects_duplicate_keys() {
+        let other = new_crypto_ec_key(0, true, vec![1; 32]).unwrap();
+        let mut cbor: Vec<u8> = new_crypto_ec_key(0, false, hex::decode(PUBLIC_KEY).unwrap()).unwrap().try_into().unwrap();
+        cbor[0] += 1;
+        cbor.extend(serde_cbor::to_vec(&serde_cbor::Value::Integer(3)).unwrap());
+        cbor.extend(serde_cbor::to_vec(&serde_cbor::Value::Bytes(other.get_data())).unwrap());
+        assert!(decode(cbor).unwrap_err().starts_with("Duplicate CBOR map key"));
+    }
+}
@@ -50,7 +50,7 @@ pub extern "C" fn crypto_hd_key_get_uncompressed_key_data(
 pub extern "C" fn crypto_hd_key_get_chain_code(crypto_hdkey: &mut CryptoHDKey) -> PtrResponse {
     match crypto_hdkey.get_chain_code() {
         Some(chain_code) => Response::success_string(hex::encode(chain_code)),
-        None => Response::success_null(),
+        None => Response::not_present(),
     }
         .c_ptr()
 }
@@ -59,7 +59,7 @@ pub extern "C" fn crypto_hd_key_get_chain_code(crypto_hdkey: &mut CryptoHDKey) -
 pub extern "C" fn crypto_hd_key_get_name(crypto_hdkey: &mut CryptoHDKey) -> PtrResponse {
     crypto_hdkey
         .get_name()
-        .map_or(Response::success_null(), |v| Response::success_string(v))
+        .map_or(Response::not_present(), |v| Response::success_string(v))
         .c_ptr()
 }
 
@@ -68,9 +68,9 @@ pub extern "C" fn crypto_hd_key_get_path(crypto_hdkey: &mut CryptoHDKey) -> PtrR
     match crypto_hdkey.get_origin() {
         Some(v) => match v.get_path() {
             Some(s) => Response::success_string(s),
-            None => Response::success_null(),
+            None => Response::not_present(),
         },
-        None => Response::success_null(),
+        None => Response::not_present(),
     }.c_ptr()
 }
 
@@ -79,9 +79,9 @@ pub extern "C" fn crypto_hd_key_get_children_path(crypto_hdkey: &mut CryptoHDKey
     match crypto_hdkey.get_children() {
         Some(v) => match v.get_path() {
             Some(s) => Response::success_string(s),
-            None => Response::success_null(),
+            None => Response::not_present(),
         },
-        None => Response::success_null(),
+        None => Response::not_present(),
     }.c_ptr()
 }
 
@@ -92,9 +92,9 @@ pub extern "C" fn crypto_hd_key_get_source_fingerprint(
     match crypto_hdkey.get_origin() {
         Some(o) => match o.get_source_fingerprint() {
             Some(f) => Response::success_string(hex::encode(f)),
-            None => Response::success_null(),
+            None => Response::not_present(),
         },
-        None => Response::success_null(),
+        None => Response::not_present(),
     }
         .c_ptr()
 }
@@ -106,7 +106,7 @@ pub extern "C" fn crypto_hd_key_get_account_index(
 ) -> PtrResponse {
     crypto_hdkey
         .get_account_index(level)
-        .map_or(Response::success_null(), |v| Response::success_uint32(v))
+        .map_or(Response::not_present(), |v| Response::success_uint32(v))
         .c_ptr()
 }
 
@@ -114,7 +114,7 @@ pub extern "C" fn crypto_hd_key_get_account_index(
 pub extern "C" fn crypto_hd_key_get_depth(crypto_hdkey: &mut CryptoHDKey) -> PtrResponse {
     crypto_hdkey
         .get_depth()
-        .map_or(Response::success_null(), |v| Response::success_uint32(v))
+        .map_or(Response::not_present(), |v| Response::success_uint32(v))
         .c_ptr()
 }
 
@@ -122,7 +122,7 @@ pub extern "C" fn crypto_hd_key_get_depth(crypto_hdkey: &mut CryptoHDKey) -> Ptr
 pub extern "C" fn crypto_hd_key_get_note(crypto_hdkey: &mut CryptoHDKey) -> PtrResponse {
     crypto_hdkey
         .get_note()
-        .map_or(Response::success_null(), |v| Response::success_string(v))
+        .map_or(Response::not_present(), |v| Response::success_string(v))
         .c_ptr()
 }
 
@@ -3,6 +3,7 @@ use ur_registry::{crypto_psbt::CryptoPSBT, traits::{To, RegistryItem}};
 use crate::{
     response::{PtrResponse, Response},
     types::{PtrString, PtrVoid},
+    ur_encoder::UREncoder,
     utils::parse_ptr_string_to_bytes,
 };
 
@@ -31,7 +32,7 @@ pub extern "C" fn crypto_psbt_construct(data: PtrString) -> PtrResponse {
 #[no_mangle]
 pub extern "C" fn crypto_psbt_get_ur_encoder(crypto_psbt: &mut CryptoPSBT) -> PtrResponse {
     let message = crypto_psbt.to_bytes().unwrap();
-    let ur_encoder = ur::Encoder::new(
+    let ur_encoder = UREncoder::new(
         message.as_slice(),
         400,
         CryptoPSBT::get_registry_type().get_type(),
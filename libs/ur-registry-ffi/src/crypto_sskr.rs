@@ -0,0 +1,201 @@
+use serde_cbor::Value;
+
+use crate::{
+    response::{PtrResponse, Response},
+    tron::decode::decode_value,
+    types::{PtrString, PtrVoid},
+    ur_encoder::UREncoder,
+    utils::parse_ptr_string_to_bytes,
+};
+
+pub const CRYPTO_SSKR_TYPE: &str = "crypto-sskr";
+
+// Legacy tag some encoders still wrap the share in
+const CRYPTO_SSKR_TAG: u64 = 309;
+
+// identifier(2) + thresholds/counts/indexes(3)
+const METADATA_LEN: usize = 5;
+const MIN_SECRET_LEN: usize = 16;
+const MAX_SECRET_LEN: usize = 32;
+
+/// A single SSKR share, carried as a CBOR byte string
+#[derive(Clone, Debug, Default)]
+pub struct CryptoSSKR {
+    share: Vec<u8>,
+}
+
+impl CryptoSSKR {
+    pub fn new(share: Vec<u8>) -> Result<Self, String> {
+        let secret_len = share.len().saturating_sub(METADATA_LEN);
+        if !(MIN_SECRET_LEN..=MAX_SECRET_LEN).contains(&secret_len) || secret_len % 2 != 0 {
+            return Err(format!("Invalid SSKR share length: {}", share.len()));
+        }
+        Ok(CryptoSSKR { share })
+    }
+
+    pub fn get_share(&self) -> &Vec<u8> {
+        &self.share
+    }
+
+    pub fn get_identifier(&self) -> u16 {
+        u16::from_be_bytes([self.share[0], self.share[1]])
+    }
+
+    pub fn get_group_threshold(&self) -> u32 {
+        (self.share[2] >> 4) as u32 + 1
+    }
+
+    pub fn get_group_count(&self) -> u32 {
+        (self.share[2] & 0x0f) as u32 + 1
+    }
+
+    pub fn get_group_index(&self) -> u32 {
+        (self.share[3] >> 4) as u32
+    }
+
+    pub fn get_member_threshold(&self) -> u32 {
+        (self.share[3] & 0x0f) as u32 + 1
+    }
+
+    pub fn get_member_index(&self) -> u32 {
+        (self.share[4] & 0x0f) as u32
+    }
+
+    /// Serialize to CBOR bytes
+    pub fn to_bytes(&self) -> Result<Vec<u8>, String> {
+        serde_cbor::to_vec(&Value::Bytes(self.share.clone())).map_err(|e| e.to_string())
+    }
+}
+
+impl TryFrom<Vec<u8>> for CryptoSSKR {
+    type Error = String;
+
+    fn try_from(data: Vec<u8>) -> Result<Self, Self::Error> {
+        let cbor_value = decode_value(&data)?;
+
+        match cbor_value {
+            Value::Bytes(share) => CryptoSSKR::new(share),
+            Value::Tag(CRYPTO_SSKR_TAG, inner) => match *inner {
+                Value::Bytes(share) => CryptoSSKR::new(share),
+                _ => Err("Expected SSKR share bytes".to_string()),
+            },
+            _ => Err("Expected SSKR share bytes".to_string()),
+        }
+    }
+}
+
+pub fn resolve(data: Vec<u8>) -> PtrResponse {
+    match CryptoSSKR::try_from(data) {
+        Ok(result) => Response::success_object(Box::into_raw(Box::new(result)) as PtrVoid).c_ptr(),
+        Err(error) => Response::error(error.to_string()).c_ptr(),
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn crypto_sskr_construct(share: PtrString) -> PtrResponse {
+    let share = match parse_ptr_string_to_bytes(share).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    match CryptoSSKR::new(share) {
+        Ok(crypto_sskr) => Response::success_object(Box::into_raw(Box::new(crypto_sskr)) as PtrVoid),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[no_mangle]
+pub extern "C" fn crypto_sskr_get_data(crypto_sskr: &CryptoSSKR) -> PtrResponse {
+    Response::success_string(hex::encode(crypto_sskr.get_share())).c_ptr()
+}
+
+#[no_mangle]
+pub extern "C" fn crypto_sskr_get_identifier(crypto_sskr: &CryptoSSKR) -> PtrResponse {
+    Response::success_uint32(crypto_sskr.get_identifier() as u32).c_ptr()
+}
+
+#[no_mangle]
+pub extern "C" fn crypto_sskr_get_group_threshold(crypto_sskr: &CryptoSSKR) -> PtrResponse {
+    Response::success_uint32(crypto_sskr.get_group_threshold()).c_ptr()
+}
+
+#[no_mangle]
+pub extern "C" fn crypto_sskr_get_group_count(crypto_sskr: &CryptoSSKR) -> PtrResponse {
+    Response::success_uint32(crypto_sskr.get_group_count()).c_ptr()
+}
+
+#[no_mangle]
+pub extern "C" fn crypto_sskr_get_group_index(crypto_sskr: &CryptoSSKR) -> PtrResponse {
+    Response::success_uint32(crypto_sskr.get_group_index()).c_ptr()
+}
+
+#[no_mangle]
+pub extern "C" fn crypto_sskr_get_member_threshold(crypto_sskr: &CryptoSSKR) -> PtrResponse {
+    Response::success_uint32(crypto_sskr.get_member_threshold()).c_ptr()
+}
+
+#[no_mangle]
+pub extern "C" fn crypto_sskr_get_member_index(crypto_sskr: &CryptoSSKR) -> PtrResponse {
+    Response::success_uint32(crypto_sskr.get_member_index()).c_ptr()
+}
+
+#[no_mangle]
+pub extern "C" fn crypto_sskr_get_ur_encoder(crypto_sskr: &CryptoSSKR) -> PtrResponse {
+    let message = match crypto_sskr.to_bytes() {
+        Ok(v) => v,
+        Err(e) => return Response::error(e).c_ptr(),
+    };
+    match UREncoder::new(message.as_slice(), 400, CRYPTO_SSKR_TYPE) {
+        Ok(ur_encoder) => Response::success_object(Box::into_raw(Box::new(ur_encoder)) as PtrVoid),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Identifier 0x1234, 2 of 3 groups, group 1, 3 of 5 members, member 4
+    fn share(secret_len: usize) -> Vec<u8> {
+        let mut share = vec![0x12, 0x34, 0x12, 0x12, 0x04];
+        share.extend(std::iter::repeat_n(0xaa, secret_len));
+        share
+    }
+
+    #[test]
+    fn test_sskr_round_trip() {
+        let sskr = CryptoSSKR::new(share(16)).unwrap();
+        assert_eq!(sskr.get_identifier(), 0x1234);
+        assert_eq!((sskr.get_group_threshold(), sskr.get_group_count(), sskr.get_group_index()), (2, 3, 1));
+        assert_eq!((sskr.get_member_threshold(), sskr.get_member_index()), (3, 4));
+
+        let decoded = CryptoSSKR::try_from(sskr.to_bytes().unwrap()).unwrap();
+        assert_eq!(decoded.get_share(), &share(16));
+        let tagged = serde_cbor::to_vec(&Value::Tag(CRYPTO_SSKR_TAG, Box::new(Value::Bytes(share(32))))).unwrap();
+        assert_eq!(CryptoSSKR::try_from(tagged).unwrap().get_share(), &share(32));
+    }
+
+    #[test]
+    fn test_sskr_rejects_malformed() {
+        for secret_len in [0, 14, 17, 34] {
+            assert!(CryptoSSKR::new(share(secret_len)).is_err(), "{}", secret_len);
+        }
+        let text = serde_cbor::to_vec(&Value::Text("share".to_string())).unwrap();
+        assert_eq!(CryptoSSKR::try_from(text).unwrap_err(), "Expected SSKR share bytes");
+        let tagged_integer = serde_cbor::to_vec(&Value::Tag(CRYPTO_SSKR_TAG, Box::new(Value::Integer(1)))).unwrap();
+        assert_eq!(CryptoSSKR::try_from(tagged_integer).unwrap_err(), "Expected SSKR share bytes");
+        let short = serde_cbor::to_vec(&Value::Bytes(share(8))).unwrap();
+        assert!(CryptoSSKR::try_from(short).unwrap_err().starts_with("Invalid SSKR share length"));
+        assert!(CryptoSSKR::try_from(vec![0xff]).unwrap_err().starts_with("Failed to decode CBOR"));
+    }
+
+    #[test]
+    fn test_sskr_rejects_duplicate_keys() {
+        // 309({1: h'00', 1: h'01'}) is refused as duplicated, not as the wrong shape
+        let duplicated = vec![0xd9, 0x01, 0x35, 0xa2, 0x01, 0x41, 0x00, 0x01, 0x41, 0x01];
+        assert!(CryptoSSKR::try_from(duplicated).unwrap_err().starts_with("Duplicate CBOR map key"));
+        let trailing = [serde_cbor::to_vec(&Value::Bytes(share(16))).unwrap(), vec![0x00]].concat();
+        assert!(CryptoSSKR::try_from(trailing).unwrap_err().contains("trailing bytes"));
+    }
+}
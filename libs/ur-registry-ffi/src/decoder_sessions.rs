@@ -0,0 +1,424 @@
+//! Several multi-part decodes in flight at once, for screens that
+//! interleave frames of different UR messages. Each part is routed by the
+//! message checksum fountain parts carry, so frames of one message never
+//! reach another's decoder. Single-part URs complete on arrival, keyed by
+//! the same CRC-32 of their message. A session is addressed by its UR type
+//! and checksum together, as parts are routed.
+//!
+//! Every session applies the type allowlist and inactivity timeout set on
+//! the sessions, the way a lone `URDecoder` applies its own.
+
+use crate::response::{PtrResponse, Response};
+use crate::types::{PtrString, PtrVoid};
+use crate::ur_decoder::{resolve, ReceiveError, URDecoder};
+use crate::utils::convert_ptr_string_to_string;
+use crc::{Crc, CRC_32_ISO_HDLC};
+use serde_cbor::Value;
+use ur::bytewords::{self, Style};
+
+/// Sessions kept at once; a part for a new message past this evicts the one
+/// idle longest
+pub const MAX_SESSIONS: usize = 8;
+
+// Index of the message checksum in a fountain part's CBOR array
+const PART_CHECKSUM_INDEX: usize = 3;
+
+struct Session {
+    ur_type: String,
+    checksum: u32,
+    decoder: URDecoder,
+    // Message of a single-part UR
+    single: Option<Vec<u8>>,
+    // Why the last part routed here failed, reported while the message
+    // isn't complete
+    error: Option<String>,
+}
+
+impl Session {
+    fn is_complete(&self) -> bool {
+        self.single.is_some() || self.decoder.complete()
+    }
+}
+
+#[derive(Default)]
+pub struct DecoderSessions {
+    // Least recently fed first
+    sessions: Vec<Session>,
+    // Never fed; new sessions copy its allowlist and timeout
+    limits: URDecoder,
+}
+
+fn fountain_checksum(body: &str) -> Result<u32, String> {
+    let cbor = bytewords::decode(body, &Style::Minimal).map_err(|e| e.to_string())?;
+    match serde_cbor::from_slice(&cbor).map_err(|e| e.to_string())? {
+        Value::Array(items) => match items.get(PART_CHECKSUM_INDEX) {
+            Some(Value::Integer(checksum)) => u32::try_from(*checksum).map_err(|_| "Invalid part checksum".to_string()),
+            _ => Err("Missing part checksum".to_string()),
+        },
+        _ => Err("Expected fountain part array".to_string()),
+    }
+}
+
+impl DecoderSessions {
+    /// Accept only `types` in every session, or any type for None
+    pub fn set_allowed_types(&mut self, types: Option<Vec<String>>) {
+        self.limits.set_allowed_types(types.clone());
+        for session in self.sessions.iter_mut() {
+            session.decoder.set_allowed_types(types.clone());
+        }
+    }
+
+    /// Drop a half-scanned session once `timeout_ms` passes without a part
+    /// for it; None keeps sessions until evicted
+    pub fn set_inactivity_timeout(&mut self, timeout_ms: Option<u64>) {
+        self.limits.set_inactivity_timeout(timeout_ms);
+        for session in self.sessions.iter_mut() {
+            session.decoder.set_inactivity_timeout(timeout_ms);
+        }
+    }
+
+    /// Ends the sessions idle past the timeout, returning how many
+    pub fn check_timeout(&mut self, now_ms: u64) -> usize {
+        let len = self.sessions.len();
+        self.sessions.retain_mut(|session| session.decoder.check_timeout(now_ms).is_ok());
+        len - self.sessions.len()
+    }
+
+    /// Feeds a part to the session of its message, starting one if needed,
+    /// and returns the UR type and checksum that key that session. A part
+    /// that fails to decode leaves the sessions as they were.
+    pub fn receive(&mut self, part: &str) -> Result<(String, u32), String> {
+        self.route(part, None)
+    }
+
+    /// `receive` for a part arriving at `now_ms`. A part for a session idle
+    /// past the timeout ends that session and is refused, as
+    /// `URDecoder::receive_at` refuses it.
+    pub fn receive_at(&mut self, part: &str, now_ms: u64) -> Result<(String, u32), String> {
+        self.route(part, Some(now_ms))
+    }
+
+    fn route(&mut self, part: &str, now_ms: Option<u64>) -> Result<(String, u32), String> {
+        let part = part.trim().to_lowercase();
+        let segments: Vec<&str> = part.strip_prefix("ur:").ok_or("Not a UR part")?.split('/').collect();
+        if let Some(ur_type) = segments.first().filter(|t| !self.limits.is_allowed(t)) {
+            return Err(ReceiveError::TypeNotAllowed(ur_type.to_string()).to_string());
+        }
+        let (ur_type, checksum, single) = match segments[..] {
+            [ur_type, body] => {
+                let message = bytewords::decode(body, &Style::Minimal).map_err(|e| e.to_string())?;
+                (ur_type, Crc::<u32>::new(&CRC_32_ISO_HDLC).checksum(&message), Some(message))
+            }
+            [ur_type, _, body] => (ur_type, fountain_checksum(body)?, None),
+            _ => return Err("Invalid UR part".to_string()),
+        };
+
+        let index = self.position(checksum, ur_type);
+        let mut session = match index {
+            Some(index) => self.sessions.remove(index),
+            None => Session {
+                ur_type: ur_type.to_string(),
+                checksum,
+                decoder: self.limits.with_same_limits(),
+                single: None,
+                error: None,
+            },
+        };
+        let received = match (single, now_ms) {
+            (Some(message), _) => {
+                session.single = Some(message);
+                Ok(())
+            }
+            (None, Some(now_ms)) => session.decoder.receive_at(&part, now_ms),
+            (None, None) => session.decoder.receive(&part),
+        };
+        match (received, index) {
+            (Ok(()), _) => {
+                if index.is_none() && self.sessions.len() >= MAX_SESSIONS {
+                    self.sessions.remove(0);
+                }
+                session.error = None;
+                self.sessions.push(session);
+                Ok((ur_type.to_string(), checksum))
+            }
+            (Err(ReceiveError::SessionExpired), _) => Err(ReceiveError::SessionExpired.to_string()),
+            (Err(e), Some(index)) => {
+                session.error = Some(e.to_string());
+                self.sessions.insert(index, session);
+                Err(e.to_string())
+            }
+            (Err(e), None) => Err(e.to_string()),
+        }
+    }
+
+    // Routing lowercases types, whatever case callers look them up in
+    fn position(&self, checksum: u32, ur_type: &str) -> Option<usize> {
+        self.sessions.iter().position(|s| s.checksum == checksum && s.ur_type.eq_ignore_ascii_case(ur_type))
+    }
+
+    fn get(&self, checksum: u32, ur_type: &str) -> Result<&Session, String> {
+        self.position(checksum, ur_type)
+            .map(|index| &self.sessions[index])
+            .ok_or(format!("No {} session for checksum {:08x}", ur_type, checksum))
+    }
+
+    pub fn is_complete(&self, checksum: u32, ur_type: &str) -> Result<bool, String> {
+        self.get(checksum, ur_type).map(Session::is_complete)
+    }
+
+    pub fn len(&self) -> usize {
+        self.sessions.len()
+    }
+
+    /// Message of a complete session, which is ended. Fails with the error
+    /// of the session's last part when that part was refused.
+    pub fn take_message(&mut self, checksum: u32, ur_type: &str) -> Result<Vec<u8>, String> {
+        let session = self.get(checksum, ur_type)?;
+        session.decoder.check_target(&session.ur_type)?;
+        if !session.is_complete() {
+            return Err(session.error.clone().unwrap_or(format!("Session {:08x} is not complete yet", checksum)));
+        }
+        let index = self.position(checksum, ur_type).unwrap();
+        let session = self.sessions.remove(index);
+        match session.single {
+            Some(message) => Ok(message),
+            None => session.decoder.message(),
+        }
+    }
+
+    pub fn remove(&mut self, checksum: u32, ur_type: &str) {
+        if let Some(index) = self.position(checksum, ur_type) {
+            self.sessions.remove(index);
+        }
+    }
+}
+
+// ========== FFI Functions ==========
+
+#[no_mangle]
+pub extern "C" fn ur_decoder_sessions_new() -> PtrResponse {
+    Response::success_object(Box::into_raw(Box::new(DecoderSessions::default())) as PtrVoid).c_ptr()
+}
+
+/// Checksum of the message the part belongs to; with the part's UR type,
+/// the id of its session
+#[no_mangle]
+pub extern "C" fn ur_decoder_sessions_receive(decoder_sessions: &mut DecoderSessions, ur: PtrString) -> PtrResponse {
+    let ur = match convert_ptr_string_to_string(ur).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    match decoder_sessions.receive(&ur) {
+        Ok((_, checksum)) => Response::success_uint32(checksum),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+/// `ur_decoder_sessions_receive` for a part arriving at `now_ms`; fails
+/// with a "session expired" error, ending the part's session, after the
+/// inactivity timeout
+#[no_mangle]
+pub extern "C" fn ur_decoder_sessions_receive_at(decoder_sessions: &mut DecoderSessions, ur: PtrString, now_ms: u64) -> PtrResponse {
+    let ur = match convert_ptr_string_to_string(ur).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    match decoder_sessions.receive_at(&ur, now_ms) {
+        Ok((_, checksum)) => Response::success_uint32(checksum),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+/// Inactivity timeout in ms for each session fed through
+/// `ur_decoder_sessions_receive_at`, 0 for none
+#[no_mangle]
+pub extern "C" fn ur_decoder_sessions_set_inactivity_timeout(decoder_sessions: &mut DecoderSessions, timeout_ms: u64) -> PtrResponse {
+    decoder_sessions.set_inactivity_timeout(Some(timeout_ms).filter(|t| *t > 0));
+    Response::success_null().c_ptr()
+}
+
+/// Number of sessions that were idle past the timeout and have ended
+#[no_mangle]
+pub extern "C" fn ur_decoder_sessions_check_timeout(decoder_sessions: &mut DecoderSessions, now_ms: u64) -> PtrResponse {
+    Response::success_uint32(decoder_sessions.check_timeout(now_ms) as u32).c_ptr()
+}
+
+/// Limits every session to `types`, one UR type per line; empty accepts
+/// every type again
+#[no_mangle]
+pub extern "C" fn ur_decoder_sessions_set_allowed_types(decoder_sessions: &mut DecoderSessions, types: PtrString) -> PtrResponse {
+    let types = match convert_ptr_string_to_string(types).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    let types: Vec<String> = types.lines().map(str::trim).filter(|t| !t.is_empty()).map(String::from).collect();
+    decoder_sessions.set_allowed_types(Some(types).filter(|t| !t.is_empty()));
+    Response::success_null().c_ptr()
+}
+
+#[no_mangle]
+pub extern "C" fn ur_decoder_sessions_is_complete(
+    decoder_sessions: &DecoderSessions,
+    checksum: u32,
+    ur_type: PtrString,
+) -> PtrResponse {
+    let ur_type = match convert_ptr_string_to_string(ur_type).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    match decoder_sessions.is_complete(checksum, &ur_type) {
+        Ok(complete) => Response::success_boolean(complete),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[no_mangle]
+pub extern "C" fn ur_decoder_sessions_get_len(decoder_sessions: &DecoderSessions) -> PtrResponse {
+    Response::success_uint32(decoder_sessions.len() as u32).c_ptr()
+}
+
+/// The registry object of a complete session, as `ur_decoder_resolve`
+/// returns it for the session's UR type; the session ends
+#[no_mangle]
+pub extern "C" fn ur_decoder_sessions_resolve(
+    decoder_sessions: &mut DecoderSessions,
+    checksum: u32,
+    ur_type: PtrString,
+) -> PtrResponse {
+    let ur_type = match convert_ptr_string_to_string(ur_type).map_err(|e| Response::error(e)) {
+        Ok(v) => v.to_lowercase(),
+        Err(e) => return e.c_ptr(),
+    };
+    match decoder_sessions.take_message(checksum, &ur_type) {
+        Ok(message) => resolve(&ur_type, message),
+        Err(e) => Response::error(e).c_ptr(),
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn ur_decoder_sessions_remove(
+    decoder_sessions: &mut DecoderSessions,
+    checksum: u32,
+    ur_type: PtrString,
+) -> PtrResponse {
+    let ur_type = match convert_ptr_string_to_string(ur_type).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    decoder_sessions.remove(checksum, &ur_type);
+    Response::success_null().c_ptr()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ur_encoder::UREncoder;
+
+    #[test]
+    fn test_interleaved_sessions() {
+        let first: Vec<u8> = (0..500u32).map(|i| i as u8).collect();
+        let second: Vec<u8> = (0..700u32).map(|i| (i * 3) as u8).collect();
+        let mut first_encoder = UREncoder::new(&first, 100, "bytes").unwrap();
+        let mut second_encoder = UREncoder::new(&second, 100, "bytes").unwrap();
+        let mut sessions = DecoderSessions::default();
+
+        let (mut first_id, mut second_id) = (0, 0);
+        let complete = |sessions: &DecoderSessions, id| sessions.is_complete(id, "bytes").unwrap_or(false);
+        while !complete(&sessions, first_id) || !complete(&sessions, second_id) {
+            first_id = sessions.receive(&first_encoder.next_part().unwrap()).unwrap().1;
+            second_id = sessions.receive(&second_encoder.next_part().unwrap().to_uppercase()).unwrap().1;
+        }
+        assert_eq!(first_id, first_encoder.checksum());
+        assert_eq!(sessions.len(), 2);
+        assert_eq!(sessions.take_message(second_id, "BYTES").unwrap(), second);
+        assert_eq!(sessions.take_message(first_id, "bytes").unwrap(), first);
+        assert_eq!(sessions.len(), 0);
+
+        let single = UREncoder::new(&[1, 2, 3], 100, "bytes").unwrap().next_part().unwrap();
+        let (ur_type, id) = sessions.receive(&single).unwrap();
+        assert_eq!(ur_type, "bytes");
+        assert!(sessions.is_complete(id, "bytes").unwrap());
+
+        for i in 0..MAX_SESSIONS as u8 {
+            sessions.receive(&UREncoder::new(&[i; 300], 100, "bytes").unwrap().next_part().unwrap()).unwrap();
+        }
+        assert_eq!(sessions.len(), MAX_SESSIONS);
+        assert!(sessions.is_complete(id, "bytes").is_err());
+    }
+
+    #[test]
+    fn test_sessions_keyed_by_type_and_checksum() {
+        let mut sessions = DecoderSessions::default();
+        let message: Vec<u8> = (0..300u32).map(|i| i as u8).collect();
+        let mut bytes = UREncoder::new(&message, 100, "bytes").unwrap();
+        let mut other = UREncoder::new(&message, 100, "tron-sign-request").unwrap();
+        let (_, id) = sessions.receive(&bytes.next_part().unwrap()).unwrap();
+        assert_eq!(sessions.receive(&other.next_part().unwrap()).unwrap().1, id);
+        assert_eq!(sessions.len(), 2);
+        sessions.remove(id, "tron-sign-request");
+        assert_eq!(sessions.len(), 1);
+        assert!(sessions.is_complete(id, "tron-sign-request").is_err());
+        assert!(!sessions.is_complete(id, "bytes").unwrap());
+
+        // Failed parts neither start sessions nor evict them. A part without
+        // its fragment routes by its checksum, then fails in the decoder.
+        for i in 0..MAX_SESSIONS as u8 - 1 {
+            sessions.receive(&UREncoder::new(&[i; 300], 100, "bytes").unwrap().next_part().unwrap()).unwrap();
+        }
+        let truncated = |checksum: u32| {
+            let cbor = serde_cbor::to_vec(&Value::Array(vec![
+                Value::Integer(2),
+                Value::Integer(3),
+                Value::Integer(300),
+                Value::Integer(checksum.into()),
+            ]))
+            .unwrap();
+            format!("ur:bytes/2-3/{}", bytewords::encode(&cbor, &Style::Minimal))
+        };
+        let error = sessions.receive(&truncated(id)).unwrap_err();
+        assert!(sessions.receive(&truncated(id ^ 1)).is_err());
+        assert_eq!(sessions.len(), MAX_SESSIONS);
+        assert!(!sessions.is_complete(id, "bytes").unwrap());
+        assert_eq!(sessions.take_message(id, "bytes").unwrap_err(), error);
+        sessions.receive(&bytes.next_part().unwrap()).unwrap();
+        assert_eq!(sessions.take_message(id, "bytes").unwrap_err(), format!("Session {:08x} is not complete yet", id));
+    }
+
+    #[test]
+    fn test_sessions_apply_limits() {
+        let message: Vec<u8> = (0..300u32).map(|i| i as u8).collect();
+        let mut sessions = DecoderSessions::default();
+        sessions.set_allowed_types(Some(vec!["tron-signature".to_string()]));
+        let bytes = UREncoder::new(&message, 100, "bytes").unwrap().next_part().unwrap();
+        let single = UREncoder::new(&[1, 2, 3], 100, "bytes").unwrap().next_part().unwrap();
+        let refused = ReceiveError::TypeNotAllowed("bytes".to_string()).to_string();
+        assert_eq!(sessions.receive(&bytes).unwrap_err(), refused);
+        assert_eq!(sessions.receive(&single).unwrap_err(), refused);
+        assert_eq!(sessions.len(), 0);
+
+        // Allowed again, then refused while the session is open
+        sessions.set_allowed_types(None);
+        let (_, id) = sessions.receive(&single).unwrap();
+        sessions.set_allowed_types(Some(vec!["tron-signature".to_string()]));
+        assert_eq!(sessions.take_message(id, "bytes").unwrap_err(), refused);
+        sessions.set_allowed_types(None);
+        assert_eq!(sessions.take_message(id, "bytes").unwrap(), vec![1, 2, 3]);
+
+        let mut encoder = UREncoder::new(&message, 100, "bytes").unwrap();
+        let mut other = UREncoder::new(&[7; 300], 100, "bytes").unwrap();
+        sessions.set_inactivity_timeout(Some(1000));
+        let (_, id) = sessions.receive_at(&encoder.next_part().unwrap(), 0).unwrap();
+        sessions.receive_at(&other.next_part().unwrap(), 900).unwrap();
+        assert_eq!(sessions.check_timeout(1500), 1);
+        assert!(sessions.is_complete(id, "bytes").is_err());
+        let (_, other_id) = sessions.receive_at(&other.next_part().unwrap(), 1800).unwrap();
+        assert_eq!(
+            sessions.receive_at(&other.next_part().unwrap(), 3000).unwrap_err(),
+            ReceiveError::SessionExpired.to_string()
+        );
+        assert!(sessions.is_complete(other_id, "bytes").is_err());
+    }
+}
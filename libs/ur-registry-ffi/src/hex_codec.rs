@@ -0,0 +1,114 @@
+//! Table-driven hex for the payloads crossing the FFI, where sign_data and
+//! decoded messages run to hundreds of KB. Encoding looks up both digits of
+//! a byte at once and writes into a buffer sized up front; decoding reads
+//! each digit's value from a table. `_into` getters write straight into a
+//! caller-provided buffer, skipping the response and its string.
+//!
+//! Output and errors match the `hex` crate: lowercase digits,
+//! "Odd number of digits" and "Invalid character 'g' at position 3".
+
+use std::os::raw::c_char;
+
+const DIGITS: &[u8; 16] = b"0123456789abcdef";
+// Marks bytes that aren't hex digits in VALUES
+const INVALID: u8 = 0xff;
+
+// Both digits of every byte
+static PAIRS: [[u8; 2]; 256] = {
+    let mut pairs = [[0; 2]; 256];
+    let mut i = 0;
+    while i < 256 {
+        pairs[i] = [DIGITS[i >> 4], DIGITS[i & 0x0f]];
+        i += 1;
+    }
+    pairs
+};
+
+// Value of every ASCII hex digit, either case
+static VALUES: [u8; 256] = {
+    let mut values = [INVALID; 256];
+    let mut i = 0;
+    while i < 16 {
+        values[DIGITS[i] as usize] = i as u8;
+        values[DIGITS[i].to_ascii_uppercase() as usize] = i as u8;
+        i += 1;
+    }
+    values
+};
+
+/// Writes the hex of `bytes` to the start of `out`, which must hold twice
+/// as many bytes
+pub fn encode_to_slice(bytes: &[u8], out: &mut [u8]) -> Result<(), String> {
+    if out.len() < bytes.len() * 2 {
+        return Err(format!("Buffer holds {} bytes, {} needed", out.len(), bytes.len() * 2));
+    }
+    for (pair, byte) in out.chunks_exact_mut(2).zip(bytes) {
+        pair.copy_from_slice(&PAIRS[*byte as usize]);
+    }
+    Ok(())
+}
+
+/// Lowercase hex, as `hex::encode` returns it
+pub fn encode(bytes: &[u8]) -> String {
+    let mut out = vec![0; bytes.len() * 2];
+    encode_to_slice(bytes, &mut out).expect("buffer sized for the input");
+    // Only ASCII digits were written
+    unsafe { String::from_utf8_unchecked(out) }
+}
+
+pub fn decode(hex: &str) -> Result<Vec<u8>, String> {
+    let digits = hex.as_bytes();
+    if digits.len() % 2 != 0 {
+        return Err("Odd number of digits".to_string());
+    }
+    let mut bytes = Vec::with_capacity(digits.len() / 2);
+    for pair in digits.chunks_exact(2) {
+        let (high, low) = (VALUES[pair[0] as usize], VALUES[pair[1] as usize]);
+        // Digit values fit in four bits, INVALID doesn't
+        if (high | low) & 0xf0 != 0 {
+            return Err(invalid_character(hex, bytes.len() * 2 + (high != INVALID) as usize));
+        }
+        bytes.push(high << 4 | low);
+    }
+    Ok(bytes)
+}
+
+fn invalid_character(hex: &str, index: usize) -> String {
+    let c = hex.get(index..).and_then(|rest| rest.chars().next()).unwrap_or_default();
+    format!("Invalid character {:?} at position {}", c, index)
+}
+
+/// Writes the NUL-terminated hex of `bytes` to `buffer` when `capacity`
+/// allows, returning the size needed, NUL included, either way
+pub fn write_c_hex(bytes: &[u8], buffer: *mut c_char, capacity: usize) -> usize {
+    let needed = bytes.len() * 2 + 1;
+    if !buffer.is_null() && capacity >= needed {
+        let out = unsafe { std::slice::from_raw_parts_mut(buffer as *mut u8, needed) };
+        encode_to_slice(bytes, &mut out[..needed - 1]).expect("buffer sized for the input");
+        out[needed - 1] = 0;
+    }
+    needed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches_hex_crate() {
+        let bytes: Vec<u8> = (0..=255).collect();
+        assert_eq!(encode(&bytes), hex::encode(&bytes));
+        assert_eq!(decode(&encode(&bytes)).unwrap(), bytes);
+        assert_eq!(decode("ABcd").unwrap(), vec![0xab, 0xcd]);
+        for invalid in ["abc", "0g", "é0", "zz"] {
+            assert_eq!(decode(invalid).unwrap_err(), hex::decode(invalid).unwrap_err().to_string(), "{}", invalid);
+        }
+
+        let mut buffer = [0x55 as c_char; 8];
+        assert_eq!(write_c_hex(&[0xde, 0xad, 0xbe, 0xef], buffer.as_mut_ptr(), 8), 9);
+        assert_eq!(buffer[0], 0x55);
+        assert_eq!(write_c_hex(&[0xbe, 0xef], buffer.as_mut_ptr(), 8), 5);
+        let written = unsafe { std::ffi::CStr::from_ptr(buffer.as_ptr()) };
+        assert_eq!(written.to_str().unwrap(), "beef");
+    }
+}
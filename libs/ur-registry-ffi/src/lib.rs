@@ -1,14 +1,44 @@
+// Without `ffi` the Rust API is what's left; helpers only the exports use
+// are expected to go unused
+#![cfg_attr(not(feature = "ffi"), allow(dead_code, unused_imports))]
+
+mod alloc;
+mod arena;
+mod binary_part;
+pub mod hex_codec;
+#[cfg(feature = "ffi")]
 mod crypto_hd_key;
 mod response;
+#[cfg(feature = "ffi")]
 mod solana;
+#[cfg(feature = "ffi")]
 mod extend;
 mod types;
+#[cfg(feature = "ffi")]
 mod ur_decoder;
+#[cfg(feature = "ffi")]
+mod decoder_sessions;
 mod utils;
-mod ur_encoder;
+pub mod ur_encoder;
+#[cfg(feature = "ffi")]
+mod registry_item;
+#[cfg(feature = "ffi")]
+mod ur_file;
+#[cfg(feature = "ffi")]
 mod ethereum;
+#[cfg(feature = "ffi")]
+mod ndef;
+#[cfg(all(feature = "ffi", feature = "json"))]
 mod cardano;
+#[cfg(feature = "ffi")]
 mod crypto_output;
+#[cfg(feature = "ffi")]
 mod crypto_account;
+mod crypto_address;
+#[cfg(feature = "ffi")]
+mod crypto_ec_key;
+#[cfg(feature = "ffi")]
 mod crypto_psbt;
-mod tron;
+#[cfg(feature = "ffi")]
+mod crypto_sskr;
+pub mod tron;
@@ -0,0 +1,210 @@
+//! NDEF framing for NFC taps. A single-part UR travels as an NDEF URI
+//! record, which phones open like any link; raw CBOR travels as an NFC Forum
+//! external type record named `ur:<type>`, a convention both ends of a
+//! transfer using this library share.
+//!
+//! Messages hold one short or normal record; chunked records aren't
+//! supported.
+
+use crate::response::{PtrResponse, Response};
+use crate::types::PtrString;
+use crate::ur_encoder::UREncoder;
+use crate::utils::{convert_ptr_string_to_string, parse_ptr_string_to_bytes};
+
+// Header flags: message begin, message end, chunked, short record, id length
+const MB: u8 = 0x80;
+const ME: u8 = 0x40;
+const CF: u8 = 0x20;
+const SR: u8 = 0x10;
+const IL: u8 = 0x08;
+const TNF_MASK: u8 = 0x07;
+
+const TNF_WELL_KNOWN: u8 = 0x01;
+const TNF_EXTERNAL: u8 = 0x04;
+const URI_TYPE: &[u8] = b"U";
+// URI identifier code for "no abbreviation"; `ur:` has none of its own
+const URI_NO_PREFIX: u8 = 0x00;
+const EXTERNAL_TYPE_PREFIX: &str = "ur:";
+
+fn encode_record(tnf: u8, record_type: &[u8], payload: &[u8]) -> Result<Vec<u8>, String> {
+    let type_length = u8::try_from(record_type.len()).map_err(|_| "NDEF record type too long".to_string())?;
+    let mut record = vec![MB | ME | tnf, type_length];
+    match u8::try_from(payload.len()) {
+        Ok(length) => {
+            record[0] |= SR;
+            record.push(length);
+        }
+        Err(_) => {
+            let length = u32::try_from(payload.len()).map_err(|_| "NDEF payload too long".to_string())?;
+            record.extend_from_slice(&length.to_be_bytes());
+        }
+    }
+    record.extend_from_slice(record_type);
+    record.extend_from_slice(payload);
+    Ok(record)
+}
+
+/// URI record carrying a single-part UR
+pub fn encode_ur(ur: &str) -> Result<Vec<u8>, String> {
+    let body = ur.to_lowercase();
+    let segments: Vec<&str> = body.strip_prefix("ur:").ok_or("Not a UR")?.split('/').collect();
+    if segments.len() != 2 {
+        return Err("Only single-part URs fit an NDEF record".to_string());
+    }
+    let payload: Vec<u8> = std::iter::once(URI_NO_PREFIX).chain(ur.bytes()).collect();
+    encode_record(TNF_WELL_KNOWN, URI_TYPE, &payload)
+}
+
+/// External type record carrying `cbor` as `ur_type`
+pub fn encode_cbor(ur_type: &str, cbor: &[u8]) -> Result<Vec<u8>, String> {
+    encode_record(TNF_EXTERNAL, format!("{}{}", EXTERNAL_TYPE_PREFIX, ur_type).as_bytes(), cbor)
+}
+
+/// The single-part UR an NDEF message carries, whichever record it uses
+pub fn decode(message: &[u8]) -> Result<String, String> {
+    let truncated = || "Truncated NDEF record".to_string();
+    let header = *message.first().ok_or_else(truncated)?;
+    if header & MB == 0 || header & CF != 0 {
+        return Err("Unsupported NDEF record layout".to_string());
+    }
+    if header & ME == 0 {
+        return Err("NDEF messages of more than one record aren't supported".to_string());
+    }
+    let type_length = *message.get(1).ok_or_else(truncated)? as usize;
+    let mut offset = 2;
+    let payload_length = if header & SR != 0 {
+        offset += 1;
+        *message.get(2).ok_or_else(truncated)? as usize
+    } else {
+        offset += 4;
+        let bytes = message.get(2..6).ok_or_else(truncated)?;
+        u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize
+    };
+    let id_length = if header & IL != 0 {
+        offset += 1;
+        *message.get(offset - 1).ok_or_else(truncated)? as usize
+    } else {
+        0
+    };
+    let record_type = message.get(offset..offset + type_length).ok_or_else(truncated)?;
+    let start = offset + type_length + id_length;
+    let payload = message.get(start..start + payload_length).ok_or_else(truncated)?;
+    if message.len() > start + payload_length {
+        return Err("Trailing bytes after the NDEF record".to_string());
+    }
+
+    match (header & TNF_MASK, record_type) {
+        (TNF_WELL_KNOWN, URI_TYPE) => match payload.split_first() {
+            Some((&URI_NO_PREFIX, uri)) => {
+                let uri = String::from_utf8(uri.to_vec()).map_err(|e| e.to_string())?;
+                if !uri.to_lowercase().starts_with("ur:") {
+                    return Err("NDEF URI is not a UR".to_string());
+                }
+                Ok(uri)
+            }
+            _ => Err("NDEF URI is not a UR".to_string()),
+        },
+        (TNF_EXTERNAL, _) => {
+            let record_type = std::str::from_utf8(record_type).map_err(|e| e.to_string())?;
+            let ur_type = record_type.strip_prefix(EXTERNAL_TYPE_PREFIX).ok_or("NDEF record is not a UR payload")?;
+            if payload.is_empty() {
+                return Err("Empty NDEF payload".to_string());
+            }
+            UREncoder::new(payload, payload.len() + 1, ur_type)?.next_part()
+        }
+        _ => Err("NDEF record is not a UR payload".to_string()),
+    }
+}
+
+// ========== FFI Functions ==========
+
+/// Hex NDEF message holding a single-part UR as a URI record
+#[no_mangle]
+pub extern "C" fn ur_ndef_encode_ur(ur: PtrString) -> PtrResponse {
+    let ur = match convert_ptr_string_to_string(ur).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    match encode_ur(&ur) {
+        Ok(message) => Response::success_string(hex::encode(message)),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+/// Hex NDEF message holding hex `cbor` as an external type record
+#[no_mangle]
+pub extern "C" fn ur_ndef_encode_cbor(ur_type: PtrString, cbor: PtrString) -> PtrResponse {
+    let ur_type = match convert_ptr_string_to_string(ur_type).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    let cbor = match parse_ptr_string_to_bytes(cbor).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    match encode_cbor(&ur_type, &cbor) {
+        Ok(message) => Response::success_string(hex::encode(message)),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+/// Single-part UR from a hex NDEF message, ready for `ur_decoder_receive`
+#[no_mangle]
+pub extern "C" fn ur_ndef_decode(message: PtrString) -> PtrResponse {
+    let message = match parse_ptr_string_to_bytes(message).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    match decode(&message) {
+        Ok(ur) => Response::success_string(ur),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ur::bytewords::{self, Style};
+
+    fn ur_payload(ur: &str) -> Result<Vec<u8>, String> {
+        let (_, payload) = ur.rsplit_once('/').ok_or("Not a UR")?;
+        bytewords::decode(payload, &Style::Minimal).map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn test_ndef_round_trip() {
+        let cbor = vec![0xa1, 0x01, 0x42, 0xde, 0xad];
+        let ur = UREncoder::new(&cbor, 400, "tron-signature").unwrap().next_part().unwrap();
+
+        let record = encode_ur(&ur).unwrap();
+        assert_eq!(&record[..5], &[MB | ME | SR | TNF_WELL_KNOWN, 1, (ur.len() + 1) as u8, b'U', 0]);
+        assert_eq!(decode(&record).unwrap(), ur);
+
+        let record = encode_cbor("tron-signature", &cbor).unwrap();
+        assert_eq!(decode(&record).unwrap(), ur);
+        assert_eq!(ur_payload(&ur).unwrap(), cbor);
+
+        // Normal records carry a four-byte length
+        let large = vec![0x40; 300];
+        let record = encode_cbor("bytes", &large).unwrap();
+        assert_eq!(record[0] & SR, 0);
+        assert_eq!(ur_payload(&decode(&record).unwrap()).unwrap(), large);
+
+        assert!(encode_ur("ur:bytes/1-2/lpadaobkcywkwmhfwnfeghihjtcxiansvomopr").is_err());
+        assert!(decode(&record[..record.len() - 1]).is_err());
+        let mut trailing = record.clone();
+        trailing.push(0);
+        assert!(decode(&trailing).unwrap_err().contains("Trailing"));
+        // A first record without ME, followed by another
+        let mut first = encode_cbor("bytes", &cbor).unwrap();
+        first[0] &= !ME;
+        let mut second = encode_cbor("bytes", &cbor).unwrap();
+        second[0] &= !MB;
+        assert!(decode(&first).is_err());
+        assert!(decode(&[first, second].concat()).is_err());
+        assert!(decode(&encode_record(TNF_WELL_KNOWN, b"T", b"\x02enhi").unwrap()).is_err());
+    }
+}
@@ -0,0 +1,266 @@
+//! Raw CBOR of the registry objects the library hands out, for tooling that
+//! dumps exactly what goes over the wire. `ur_decoder_resolve_item` returns
+//! a `RegistryItem`, an object tagged with its UR type, which encodes
+//! itself whatever type it holds. Bare object pointers carry no type, so
+//! `registry_object_to_cbor` needs the caller to name it.
+
+use crate::crypto_address::CryptoAddress;
+use crate::crypto_sskr::CryptoSSKR;
+use crate::response::{PtrResponse, Response};
+use crate::tron::tron_address_book::TronAddressBook;
+use crate::tron::tron_encrypted_payload::TronEncryptedPayload;
+use crate::tron::tron_pairing::TronPairing;
+use crate::tron::tron_sign_rejection::TronSignRejection;
+use crate::tron::tron_sign_request::TronSignRequest;
+use crate::tron::tron_sign_session::TronSignSession;
+use crate::tron::tron_signature::TronSignature;
+use crate::tron::tron_wallet_bundle::TronWalletBundle;
+use crate::types::{PtrString, PtrVoid};
+use crate::utils::convert_ptr_string_to_string;
+#[cfg(feature = "json")]
+use ur_registry::cardano::cardano_catalyst_signature::CardanoCatalystSignature;
+#[cfg(feature = "json")]
+use ur_registry::cardano::cardano_sign_cip8_data_signature::CardanoSignCip8DataSignature;
+#[cfg(feature = "json")]
+use ur_registry::cardano::cardano_sign_data_signature::CardanoSignDataSignature;
+#[cfg(feature = "json")]
+use ur_registry::cardano::cardano_signature::CardanoSignature;
+use ur_registry::crypto_account::CryptoAccount;
+use ur_registry::crypto_ec_key::CryptoECKey;
+use ur_registry::crypto_hd_key::CryptoHDKey;
+use ur_registry::crypto_output::CryptoOutput;
+use ur_registry::crypto_psbt::CryptoPSBT;
+use ur_registry::error::URError;
+use ur_registry::ethereum::eth_sign_request::EthSignRequest;
+use ur_registry::ethereum::eth_signature::EthSignature;
+use ur_registry::extend::crypto_multi_accounts::CryptoMultiAccounts;
+use ur_registry::solana::sol_sign_request::SolSignRequest;
+use ur_registry::solana::sol_signature::SolSignature;
+
+/// Encoding shared by the upstream `RegistryItem` types and this crate's own
+/// TRON types, so one lookup can dump any object the library hands out.
+pub trait ToCbor {
+    fn to_cbor(&self) -> Result<Vec<u8>, String>;
+}
+
+macro_rules! to_cbor_via_to {
+    ($($t:ty),*) => {$(
+        impl ToCbor for $t {
+            fn to_cbor(&self) -> Result<Vec<u8>, String> {
+                TryInto::<Vec<u8>>::try_into(self.clone()).map_err(|e: URError| e.to_string())
+            }
+        }
+    )*};
+}
+
+macro_rules! to_cbor_via_to_bytes {
+    ($($t:ty),*) => {$(
+        impl ToCbor for $t {
+            fn to_cbor(&self) -> Result<Vec<u8>, String> {
+                self.to_bytes()
+            }
+        }
+    )*};
+}
+
+to_cbor_via_to!(
+    CryptoMultiAccounts, CryptoHDKey, CryptoAccount, CryptoECKey, CryptoOutput, CryptoPSBT, SolSignature,
+    SolSignRequest, EthSignature, EthSignRequest
+);
+#[cfg(feature = "json")]
+to_cbor_via_to!(CardanoSignature, CardanoCatalystSignature, CardanoSignCip8DataSignature, CardanoSignDataSignature);
+to_cbor_via_to_bytes!(
+    CryptoAddress, CryptoSSKR, TronSignRequest, TronSignature, TronEncryptedPayload, TronPairing, TronWalletBundle,
+    TronSignRejection, TronAddressBook, TronSignSession
+);
+
+/// A decoded object and the UR type it was decoded as
+pub struct RegistryItem {
+    ur_type: String,
+    object: Box<dyn ToCbor>,
+}
+
+fn decode<T>(ur_type: &str, cbor: Vec<u8>) -> Result<RegistryItem, String>
+where
+    T: ToCbor + TryFrom<Vec<u8>> + 'static,
+    T::Error: ToString,
+{
+    let object = T::try_from(cbor).map_err(|e| e.to_string())?;
+    Ok(RegistryItem { ur_type: ur_type.to_string(), object: Box::new(object) })
+}
+
+impl RegistryItem {
+    /// Decodes `cbor` as `ur_type`, as `ur_decoder_resolve` would
+    pub fn decode(ur_type: &str, cbor: Vec<u8>) -> Result<Self, String> {
+        match ur_type {
+            "crypto-multi-accounts" => decode::<CryptoMultiAccounts>(ur_type, cbor),
+            "crypto-hdkey" => decode::<CryptoHDKey>(ur_type, cbor),
+            "crypto-account" => decode::<CryptoAccount>(ur_type, cbor),
+            "crypto-address" => decode::<CryptoAddress>(ur_type, cbor),
+            // Upstream decoding takes any curve and key bytes
+            "crypto-eckey" => crate::crypto_ec_key::decode(cbor)
+                .map(|key| RegistryItem { ur_type: ur_type.to_string(), object: Box::new(key) }),
+            "crypto-output" => decode::<CryptoOutput>(ur_type, cbor),
+            "crypto-psbt" => decode::<CryptoPSBT>(ur_type, cbor),
+            "crypto-sskr" => decode::<CryptoSSKR>(ur_type, cbor),
+            "sol-signature" => decode::<SolSignature>(ur_type, cbor),
+            "sol-sign-request" => decode::<SolSignRequest>(ur_type, cbor),
+            "eth-signature" => decode::<EthSignature>(ur_type, cbor),
+            "eth-sign-request" => decode::<EthSignRequest>(ur_type, cbor),
+            #[cfg(feature = "json")]
+            "cardano-signature" => decode::<CardanoSignature>(ur_type, cbor),
+            #[cfg(feature = "json")]
+            "cardano-catalyst-signature" => decode::<CardanoCatalystSignature>(ur_type, cbor),
+            #[cfg(feature = "json")]
+            "cardano-sign-cip8-data-signature" => decode::<CardanoSignCip8DataSignature>(ur_type, cbor),
+            #[cfg(feature = "json")]
+            "cardano-sign-data-signature" => decode::<CardanoSignDataSignature>(ur_type, cbor),
+            "tron-sign-request" => decode::<TronSignRequest>(ur_type, cbor),
+            "tron-signature" => decode::<TronSignature>(ur_type, cbor),
+            "tron-encrypted-payload" => decode::<TronEncryptedPayload>(ur_type, cbor),
+            "tron-pairing" => decode::<TronPairing>(ur_type, cbor),
+            "tron-wallet-bundle" => decode::<TronWalletBundle>(ur_type, cbor),
+            "tron-sign-rejection" => decode::<TronSignRejection>(ur_type, cbor),
+            "tron-address-book" => decode::<TronAddressBook>(ur_type, cbor),
+            "tron-sign-session" => decode::<TronSignSession>(ur_type, cbor),
+            t => Err(format!("type {} is not supported yet", t)),
+        }
+    }
+
+    pub fn get_ur_type(&self) -> &str {
+        &self.ur_type
+    }
+
+    pub fn to_cbor(&self) -> Result<Vec<u8>, String> {
+        self.object.to_cbor()
+    }
+
+    /// The object itself, for its type's functions; valid while the item is
+    pub fn get_object(&self) -> PtrVoid {
+        &*self.object as *const dyn ToCbor as *const () as PtrVoid
+    }
+}
+
+fn encode<T: ToCbor>(item: PtrVoid) -> Result<Vec<u8>, String> {
+    unsafe { &*(item as *const T) }.to_cbor()
+}
+
+/// CBOR of the object at `item`, which must be of the type `ur_type` resolves to
+pub fn registry_item_cbor(item: PtrVoid, ur_type: &str) -> Result<Vec<u8>, String> {
+    if item.is_null() {
+        return Err("Null registry item".to_string());
+    }
+    match ur_type {
+        "crypto-multi-accounts" => encode::<CryptoMultiAccounts>(item),
+        "crypto-hdkey" => encode::<CryptoHDKey>(item),
+        "crypto-account" => encode::<CryptoAccount>(item),
+        "crypto-address" => encode::<CryptoAddress>(item),
+        "crypto-eckey" => encode::<CryptoECKey>(item),
+        "crypto-output" => encode::<CryptoOutput>(item),
+        "crypto-psbt" => encode::<CryptoPSBT>(item),
+        "crypto-sskr" => encode::<CryptoSSKR>(item),
+        "sol-signature" => encode::<SolSignature>(item),
+        "sol-sign-request" => encode::<SolSignRequest>(item),
+        "eth-signature" => encode::<EthSignature>(item),
+        "eth-sign-request" => encode::<EthSignRequest>(item),
+        #[cfg(feature = "json")]
+        "cardano-signature" => encode::<CardanoSignature>(item),
+        #[cfg(feature = "json")]
+        "cardano-catalyst-signature" => encode::<CardanoCatalystSignature>(item),
+        #[cfg(feature = "json")]
+        "cardano-sign-cip8-data-signature" => encode::<CardanoSignCip8DataSignature>(item),
+        #[cfg(feature = "json")]
+        "cardano-sign-data-signature" => encode::<CardanoSignDataSignature>(item),
+        "tron-sign-request" => encode::<TronSignRequest>(item),
+        "tron-signature" => encode::<TronSignature>(item),
+        "tron-encrypted-payload" => encode::<TronEncryptedPayload>(item),
+        "tron-pairing" => encode::<TronPairing>(item),
+        "tron-wallet-bundle" => encode::<TronWalletBundle>(item),
+        "tron-sign-rejection" => encode::<TronSignRejection>(item),
+        "tron-address-book" => encode::<TronAddressBook>(item),
+        "tron-sign-session" => encode::<TronSignSession>(item),
+        t => Err(format!("type {} can't be encoded", t)),
+    }
+}
+
+// ========== FFI Functions ==========
+
+/// Hex CBOR of the item, whatever type it holds
+#[no_mangle]
+pub extern "C" fn registry_item_to_cbor(registry_item: &RegistryItem) -> PtrResponse {
+    match registry_item.to_cbor() {
+        Ok(cbor) => Response::success_string(hex::encode(cbor)),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+/// UR type the item was decoded as, e.g. `tron-sign-request`
+#[no_mangle]
+pub extern "C" fn registry_item_get_ur_type(registry_item: &RegistryItem) -> PtrResponse {
+    Response::success_string(registry_item.get_ur_type().to_string()).c_ptr()
+}
+
+/// The typed object, to pass to its type's functions. It belongs to the
+/// item: don't free it, and don't use it once the item is freed.
+#[no_mangle]
+pub extern "C" fn registry_item_get_object(registry_item: &RegistryItem) -> PtrResponse {
+    Response::success_object(registry_item.get_object()).c_ptr()
+}
+
+#[no_mangle]
+pub extern "C" fn registry_item_free(registry_item: *mut RegistryItem) {
+    if !registry_item.is_null() {
+        drop(unsafe { Box::from_raw(registry_item) });
+    }
+}
+
+/// Hex CBOR of an object that isn't a `RegistryItem`, such as one a
+/// constructor returned; `ur_type` names its type as `ur_decoder_resolve`
+/// does. Passing an object of another type is undefined behaviour.
+#[no_mangle]
+pub extern "C" fn registry_object_to_cbor(object: PtrVoid, ur_type: PtrString) -> PtrResponse {
+    let ur_type = match convert_ptr_string_to_string(ur_type).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    match registry_item_cbor(object, ur_type.trim()) {
+        Ok(cbor) => Response::success_string(hex::encode(cbor)),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registry_item_cbor() {
+        let request = TronSignRequest::new(None, b"hi".to_vec(), 2, "m/44'/195'/0'/0/0".to_string(), None, None, None);
+        let item = &request as *const TronSignRequest as PtrVoid;
+        assert_eq!(registry_item_cbor(item, "tron-sign-request").unwrap(), request.to_bytes().unwrap());
+
+        let psbt = CryptoPSBT::new(vec![0x70, 0x73, 0x62, 0x74]);
+        let cbor = registry_item_cbor(&psbt as *const CryptoPSBT as PtrVoid, "crypto-psbt").unwrap();
+        assert_eq!(CryptoPSBT::try_from(cbor).unwrap().get_psbt(), psbt.get_psbt());
+
+        assert!(registry_item_cbor(item, "cardano-sign-request").is_err());
+        assert!(registry_item_cbor(std::ptr::null_mut(), "tron-sign-request").is_err());
+    }
+
+    #[test]
+    fn test_registry_item_handle() {
+        let request = TronSignRequest::new(None, b"hi".to_vec(), 2, "m/44'/195'/0'/0/0".to_string(), None, None, None);
+        let cbor = request.to_bytes().unwrap();
+        let item = RegistryItem::decode("tron-sign-request", cbor.clone()).unwrap();
+        assert_eq!((item.get_ur_type(), item.to_cbor().unwrap()), ("tron-sign-request", cbor.clone()));
+        let object = unsafe { &*(item.get_object() as *const TronSignRequest) };
+        assert_eq!(object.get_sign_data(), &b"hi".to_vec());
+
+        let psbt: Vec<u8> = CryptoPSBT::new(vec![0x70, 0x73, 0x62, 0x74]).try_into().unwrap();
+        assert_eq!(RegistryItem::decode("crypto-psbt", psbt.clone()).unwrap().to_cbor().unwrap(), psbt);
+        assert!(RegistryItem::decode("crypto-psbt", cbor).is_err());
+        assert!(RegistryItem::decode("cardano-sign-request", psbt).is_err());
+    }
+}
@@ -1,23 +1,49 @@
+use crate::{alloc, arena};
 use crate::types::{PtrString, PtrVoid};
 use crate::utils::str_to_ptr_c_char;
 use std::any::Any;
-use std::ffi::CString;
+use std::mem::{align_of, offset_of, size_of};
 use std::ptr::null_mut;
 
+/// Result of every exported function. The layout is part of the ABI and
+/// mirrors `Response` in `lib_ur_registry_ffi.h`; fields must not be
+/// reordered, removed or retyped, and new fields may only be appended.
+///
+/// The strings it points to (`error_message`, `value_type` and a `STRING`
+/// value) belong to the caller, who frees each with
+/// `ur_registry_free_string` once done with it.
 #[repr(C)]
 pub struct Response {
+    /// `SUCCESS`, `ERROR` or `NOT_PRESENT`
     pub status_code: u32,
+    /// Set only when `status_code` is `ERROR`, null otherwise
     pub error_message: PtrString,
+    /// Tag telling which `value` member is set: `NULL`, `OBJECT`,
+    /// `BOOLEAN`, `UINT32` or `STRING`
     pub value_type: PtrString,
     pub value: Value,
 }
 
+// Freeze the layout: binding generators and hand-written headers rely on it
+const _: () = {
+    let ptr = size_of::<PtrVoid>();
+    assert!(size_of::<Value>() == ptr && align_of::<Value>() == align_of::<PtrVoid>());
+    assert!(offset_of!(Response, status_code) == 0);
+    assert!(offset_of!(Response, error_message) == ptr);
+    assert!(offset_of!(Response, value_type) == 2 * ptr);
+    assert!(offset_of!(Response, value) == 3 * ptr);
+    assert!(size_of::<Response>() == 4 * ptr);
+};
+
 impl Response {
     pub fn from_ptr(ptr: PtrResponse) -> Self {
-        unsafe { *Box::from_raw(ptr) }
+        let response = unsafe { ptr.read() };
+        alloc::free_response(ptr);
+        response
     }
 }
 
+/// Pointer-sized; which member is valid is given by `Response::value_type`
 #[repr(C)]
 pub union Value {
     _object: PtrVoid,
@@ -37,10 +63,11 @@ impl Value {
     pub fn uint32(u: u32) -> Self {
         Value { _uint32: u }
     }
-    pub fn string(s: String) -> Self {
-        Value {
-            _string: str_to_ptr_c_char(s),
-        }
+    /// Fails for text holding a NUL byte
+    pub fn string(s: String) -> Result<Self, String> {
+        Ok(Value {
+            _string: str_to_ptr_c_char(s)?,
+        })
     }
     pub fn null() -> Self {
         Value { _null: null_mut() }
@@ -55,7 +82,9 @@ pub type PtrResponse = *mut Response;
 
 impl Response {
     pub fn c_ptr(self) -> PtrResponse {
-        Box::into_raw(Box::new(self))
+        let ptr = alloc::alloc_response(self);
+        arena::adopt_response(ptr);
+        ptr
     }
 
     pub fn success_null() -> Self {
@@ -63,7 +92,7 @@ impl Response {
             status_code: SUCCESS,
             error_message: null_mut(),
             value: Value::null(),
-            value_type: str_to_ptr_c_char("NULL".to_string()),
+            value_type: c_text("NULL"),
         }
     }
 
@@ -72,7 +101,7 @@ impl Response {
             status_code: SUCCESS,
             error_message: null_mut(),
             value: Value::object(o),
-            value_type: str_to_ptr_c_char("OBJECT".to_string()),
+            value_type: c_text("OBJECT"),
         }
     }
 
@@ -81,7 +110,7 @@ impl Response {
             status_code: SUCCESS,
             error_message: null_mut(),
             value: Value::uint32(u),
-            value_type: str_to_ptr_c_char("UINT32".to_string()),
+            value_type: c_text("UINT32"),
         }
     }
 
@@ -90,28 +119,105 @@ impl Response {
             status_code: SUCCESS,
             error_message: null_mut(),
             value: Value::boolean(b),
-            value_type: str_to_ptr_c_char("BOOLEAN".to_string()),
+            value_type: c_text("BOOLEAN"),
         }
     }
 
+    /// `ERROR` instead when `s` holds a NUL byte, which a C string can't
     pub fn success_string(s: String) -> Self {
+        match Value::string(s) {
+            Ok(value) => Response {
+                status_code: SUCCESS,
+                error_message: null_mut(),
+                value,
+                value_type: c_text("STRING"),
+            },
+            Err(e) => Response::error(e),
+        }
+    }
+
+    /// An optional value that isn't set; not an error
+    pub fn not_present() -> Self {
         Response {
-            status_code: SUCCESS,
+            status_code: NOT_PRESENT,
             error_message: null_mut(),
-            value: Value::string(s),
-            value_type: str_to_ptr_c_char("STRING".to_string()),
+            value: Value::null(),
+            value_type: c_text("NULL"),
         }
     }
 
+    /// A message holding a NUL byte is replaced by one saying so
     pub fn error(error_message: String) -> Self {
         Response {
             status_code: ERROR,
-            error_message: CString::new(error_message).unwrap().into_raw(),
+            error_message: str_to_ptr_c_char(error_message).unwrap_or_else(|e| c_text(&e)),
             value: Value::null(),
-            value_type: str_to_ptr_c_char("NULL".to_string()),
+            value_type: c_text("NULL"),
         }
     }
 }
 
 pub const SUCCESS: u32 = 0;
 pub const ERROR: u32 = 1;
+pub const NOT_PRESENT: u32 = 2;
+
+// Value type tags and messages of our own, which hold no NUL
+fn c_text(text: &str) -> PtrString {
+    str_to_ptr_c_char(text.to_string()).expect("text has no NUL byte")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_response_layout() {
+        #[cfg(target_pointer_width = "64")]
+        assert_eq!(
+            (offset_of!(Response, error_message), offset_of!(Response, value_type), offset_of!(Response, value), size_of::<Response>()),
+            (8, 16, 24, 32)
+        );
+        #[cfg(target_pointer_width = "32")]
+        assert_eq!(
+            (offset_of!(Response, error_message), offset_of!(Response, value_type), offset_of!(Response, value), size_of::<Response>()),
+            (4, 8, 12, 16)
+        );
+
+        let response = Response::from_ptr(Response::success_uint32(7).c_ptr());
+        assert_eq!(response.status_code, SUCCESS);
+        assert!(response.error_message.is_null());
+        assert_eq!(unsafe { response.value._uint32 }, 7);
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    fn test_returned_strings_freed() {
+        use crate::utils::{string_tracker::live_strings, ur_registry_free_string};
+
+        let before = live_strings();
+        let response = Response::from_ptr(Response::error("failed".to_string()).c_ptr());
+        let string = Response::from_ptr(Response::success_string("value".to_string()).c_ptr());
+        assert_eq!(live_strings(), before + 4);
+
+        ur_registry_free_string(response.error_message);
+        ur_registry_free_string(response.value_type);
+        ur_registry_free_string(string.value_type);
+        ur_registry_free_string(unsafe { string.value._string });
+        ur_registry_free_string(std::ptr::null_mut());
+        assert_eq!(live_strings(), before, "returned strings leaked");
+    }
+
+    #[test]
+    fn test_string_with_nul_is_an_error() {
+        use crate::utils::{convert_ptr_string_to_string, ur_registry_free_string};
+
+        let response = Response::from_ptr(Response::success_string("memo\0text".to_string()).c_ptr());
+        assert_eq!(response.status_code, ERROR);
+        assert_eq!(convert_ptr_string_to_string(response.error_message).unwrap(), "String has a NUL byte at 4");
+        let error = Response::from_ptr(Response::error("bad\0".to_string()).c_ptr());
+        assert_eq!(convert_ptr_string_to_string(error.error_message).unwrap(), "String has a NUL byte at 3");
+        for ptr in [response.error_message, response.value_type, error.error_message, error.value_type] {
+            ur_registry_free_string(ptr);
+        }
+    }
+}
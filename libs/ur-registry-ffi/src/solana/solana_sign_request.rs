@@ -1,6 +1,7 @@
 use crate::response::{PtrResponse, Response};
 use crate::types::{PtrString, PtrVoid};
 use crate::utils::{convert_ptr_string_to_string, parse_ptr_string_to_bytes};
+use crate::ur_encoder::UREncoder;
 
 use ur_registry::crypto_key_path::CryptoKeyPath;
 use ur_registry::solana::sol_sign_request::{SignType, SolSignRequest};
@@ -71,7 +72,7 @@ pub extern "C" fn solana_sign_request_construct(
 #[no_mangle]
 pub extern "C" fn solana_sign_request_get_ur_encoder(sol_sign_request: &mut SolSignRequest) -> PtrResponse {
     let message = sol_sign_request.to_bytes().unwrap();
-    let ur_encoder = ur::Encoder::new(
+    let ur_encoder = UREncoder::new(
         message.as_slice(),
         400,
         SolSignRequest::get_registry_type().get_type(),
@@ -82,7 +83,7 @@ pub extern "C" fn solana_sign_request_get_ur_encoder(sol_sign_request: &mut SolS
 
 #[no_mangle]
 pub extern "C" fn solana_sign_request_get_request_id(sol_sign_request: &mut SolSignRequest) -> PtrResponse {
-    sol_sign_request.get_request_id().map_or(Response::success_null().c_ptr(), |id| {
+    sol_sign_request.get_request_id().map_or(Response::not_present().c_ptr(), |id| {
         Response::success_string(hex::encode(id)).c_ptr()
     })
 }
\ No newline at end of file
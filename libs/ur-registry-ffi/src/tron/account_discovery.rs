@@ -0,0 +1,141 @@
+#[cfg(feature = "json")]
+use crate::response::{PtrResponse, Response};
+use crate::tron::address::{encode_address, public_key_to_address};
+use crate::tron::keypath::KeyPath;
+
+use hmac::{Hmac, Mac};
+use secp256k1::{PublicKey, Scalar, Secp256k1};
+#[cfg(feature = "json")]
+use serde_json::json;
+use sha2::Sha512;
+use ur_registry::crypto_hd_key::CryptoHDKey;
+
+// Upper bound per call, so a bad count from the host can't stall the UI thread
+pub const MAX_DISCOVERY_COUNT: u32 = 1000;
+
+// Receive (external) chain under the account key
+const RECEIVE_CHAIN: u32 = 0;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DerivedAddress {
+    pub path: String,
+    pub public_key: Vec<u8>,
+    pub address: String,
+}
+
+/// BIP32 CKDpub for a non-hardened child: (compressed public key, chain code)
+pub(crate) fn derive_child(public_key: &[u8], chain_code: &[u8], index: u32) -> Result<(Vec<u8>, Vec<u8>), String> {
+    if index & 0x8000_0000 != 0 {
+        return Err("Hardened children can't be derived from a public key".to_string());
+    }
+    let parent = PublicKey::from_slice(public_key).map_err(|e| format!("Invalid public key: {}", e))?;
+
+    let mut mac = Hmac::<Sha512>::new_from_slice(chain_code).map_err(|e| e.to_string())?;
+    mac.update(&parent.serialize());
+    mac.update(&index.to_be_bytes());
+    let i = mac.finalize().into_bytes();
+
+    let mut tweak = [0u8; 32];
+    tweak.copy_from_slice(&i[..32]);
+    // Out-of-range tweaks have probability below 2^-127; BIP32 says skip to the next index
+    let tweak = Scalar::from_be_bytes(tweak).map_err(|_| format!("Index {} yields an invalid key", index))?;
+    let child = parent
+        .add_exp_tweak(&Secp256k1::verification_only(), &tweak)
+        .map_err(|_| format!("Index {} yields an invalid key", index))?;
+
+    Ok((child.serialize().to_vec(), i[32..].to_vec()))
+}
+
+/// First `count` receive addresses (`<origin>/0/i`) below an account-level
+/// hdkey, e.g. `m/44'/195'/0'`. Paths are relative (`m/0/i`) when the hdkey
+/// carries no origin.
+pub fn derive_receive_addresses(hdkey: &CryptoHDKey, count: u32) -> Result<Vec<DerivedAddress>, String> {
+    if count > MAX_DISCOVERY_COUNT {
+        return Err(format!("At most {} addresses can be derived at once", MAX_DISCOVERY_COUNT));
+    }
+    if hdkey.is_private_key() {
+        return Err("Expected a public hdkey".to_string());
+    }
+    let chain_code = hdkey.get_chain_code().ok_or("hdkey has no chain code")?;
+    let origin = match hdkey.get_origin().and_then(|o| o.get_path()) {
+        Some(path) => KeyPath::parse(&path)?,
+        None => KeyPath::default(),
+    };
+
+    let (chain_key, chain_code) = derive_child(&hdkey.get_key(), &chain_code, RECEIVE_CHAIN)?;
+    (0..count)
+        .map(|index| {
+            let (public_key, _) = derive_child(&chain_key, &chain_code, index)?;
+            let address = encode_address(&public_key_to_address(&public_key)?)?;
+            Ok(DerivedAddress {
+                path: origin.child(RECEIVE_CHAIN, false).child(index, false).to_string(),
+                public_key,
+                address,
+            })
+        })
+        .collect()
+}
+
+// ========== FFI Functions ==========
+
+/// JSON array of `{"path", "publicKey", "address"}`
+#[cfg(feature = "json")]
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_hdkey_derive_addresses(crypto_hdkey: &CryptoHDKey, count: u32) -> PtrResponse {
+    match derive_receive_addresses(crypto_hdkey, count) {
+        Ok(addresses) => {
+            let addresses: Vec<_> = addresses
+                .iter()
+                .map(|a| json!({ "path": a.path, "publicKey": hex::encode(&a.public_key), "address": a.address }))
+                .collect();
+            Response::success_string(json!(addresses).to_string())
+        }
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ur_registry::crypto_key_path::CryptoKeyPath;
+
+    #[test]
+    fn test_derive_child() {
+        // BIP32 test vector 2: m -> m/0
+        let master = bs58::decode("xpub661MyMwAqRbcFW31YEwpkMuc5THy2PSt5bDMsktWQcFF8syAmRUapSCGu8ED9W6oDMSgv6Zz8idoc4a6mr8BDzTJY47LJhkJ8UB7WEGuduB")
+            .with_check(None).into_vec().unwrap();
+        let child = bs58::decode("xpub69H7F5d8KSRgmmdJg2KhpAK8SR3DjMwAdkxj3ZuxV27CprR9LgpeyGmXUbC6wb7ERfvrnKZjXoUmmDznezpbZb7ap6r1D3tgFxHmwMkQTPH")
+            .with_check(None).into_vec().unwrap();
+
+        let (key, chain_code) = derive_child(&master[45..], &master[13..45], 0).unwrap();
+        assert_eq!(key, child[45..].to_vec());
+        assert_eq!(chain_code, child[13..45].to_vec());
+        assert!(derive_child(&master[45..], &master[13..45], 0x8000_0000).is_err());
+    }
+
+    #[test]
+    fn test_receive_addresses() {
+        let master = bs58::decode("xpub661MyMwAqRbcFW31YEwpkMuc5THy2PSt5bDMsktWQcFF8syAmRUapSCGu8ED9W6oDMSgv6Zz8idoc4a6mr8BDzTJY47LJhkJ8UB7WEGuduB")
+            .with_check(None).into_vec().unwrap();
+        let hdkey = CryptoHDKey::new_extended_key(
+            Some(false),
+            master[45..].to_vec(),
+            Some(master[13..45].to_vec()),
+            None,
+            Some(CryptoKeyPath::from_path("m/44'/195'/0'".to_string(), None).unwrap()),
+            None,
+            None,
+            None,
+            None,
+        );
+
+        let addresses = derive_receive_addresses(&hdkey, 3).unwrap();
+        assert_eq!(addresses.len(), 3);
+        assert_eq!(addresses[2].path, "m/44'/195'/0'/0/2");
+        assert!(addresses[0].address.starts_with('T'));
+        assert_ne!(addresses[0].address, addresses[1].address);
+        assert!(derive_receive_addresses(&hdkey, MAX_DISCOVERY_COUNT + 1).is_err());
+    }
+}
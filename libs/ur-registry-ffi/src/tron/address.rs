@@ -0,0 +1,104 @@
+use crate::response::{PtrResponse, Response};
+use crate::types::PtrString;
+use crate::utils::convert_ptr_string_to_string;
+
+use secp256k1::PublicKey;
+use sha3::{Digest, Keccak256};
+
+// Mainnet address prefix byte
+pub const ADDRESS_PREFIX: u8 = 0x41;
+pub const ADDRESS_LEN: usize = 21;
+// Characters at each end that poisoning addresses copy, since wallets
+// truncate to them; the first is always `T`
+pub const SIMILARITY_PREFIX_LEN: usize = 4;
+pub const SIMILARITY_SUFFIX_LEN: usize = 4;
+
+/// 21-byte TRON address (0x41 || last 20 bytes of keccak256(uncompressed pubkey))
+pub fn public_key_to_address(public_key: &[u8]) -> Result<Vec<u8>, String> {
+    let public_key = PublicKey::from_slice(public_key).map_err(|e| format!("Invalid public key: {}", e))?;
+    let hash = Keccak256::digest(&public_key.serialize_uncompressed()[1..]);
+    let mut address = Vec::with_capacity(ADDRESS_LEN);
+    address.push(ADDRESS_PREFIX);
+    address.extend_from_slice(&hash[12..]);
+    Ok(address)
+}
+
+pub fn validate_address(address: &[u8]) -> Result<(), String> {
+    if address.len() != ADDRESS_LEN || address[0] != ADDRESS_PREFIX {
+        return Err(format!("Invalid TRON address: {}", hex::encode(address)));
+    }
+    Ok(())
+}
+
+/// Base58Check form, e.g. `TXYZ...`
+pub fn encode_address(address: &[u8]) -> Result<String, String> {
+    validate_address(address)?;
+    Ok(bs58::encode(address).with_check().into_string())
+}
+
+pub fn decode_address(address: &str) -> Result<Vec<u8>, String> {
+    let decoded = bs58::decode(address)
+        .with_check(None)
+        .into_vec()
+        .map_err(|e| format!("Invalid TRON address {}: {}", address, e))?;
+    validate_address(&decoded)?;
+    Ok(decoded)
+}
+
+/// Two different addresses that share the characters shown when truncated,
+/// the shape of an address-poisoning lookalike
+pub fn is_similar_address(a: &str, b: &str) -> bool {
+    let affixes = |s: &str| {
+        let start = s.get(..SIMILARITY_PREFIX_LEN).map(str::to_string);
+        let end = s.len().checked_sub(SIMILARITY_SUFFIX_LEN).and_then(|i| s.get(i..)).map(str::to_string);
+        (start, end)
+    };
+    let (a_start, a_end) = affixes(a);
+    a != b && a_start.is_some() && a_end.is_some() && (a_start, a_end) == affixes(b)
+}
+
+/// Entries of `address_book` that look like `address` without being it
+pub fn find_similar_addresses<'a>(address: &str, address_book: &'a [String]) -> Vec<&'a String> {
+    address_book.iter().filter(|entry| is_similar_address(address, entry)).collect()
+}
+
+// ========== FFI Functions ==========
+
+/// Lookalikes of `address` among the newline-separated `address_book`,
+/// joined by newlines; empty when there are none
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_address_find_similar(address: PtrString, address_book: PtrString) -> PtrResponse {
+    let address = match convert_ptr_string_to_string(address).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    let address_book: Vec<String> = match convert_ptr_string_to_string(address_book).map_err(|e| Response::error(e)) {
+        Ok(v) => v.lines().map(str::trim).filter(|s| !s.is_empty()).map(str::to_string).collect(),
+        Err(e) => return e.c_ptr(),
+    };
+    let similar: Vec<&str> = find_similar_addresses(address.trim(), &address_book).into_iter().map(String::as_str).collect();
+    Response::success_string(similar.join("\n")).c_ptr()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_address_from_public_key() {
+        // secp256k1 generator point, private key 1
+        let public_key = hex::decode("0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798").unwrap();
+        let address = public_key_to_address(&public_key).unwrap();
+        assert_eq!(hex::encode(&address), "417e5f4552091a69125d5dfcb7b8c2659029395bdf");
+        let encoded = encode_address(&address).unwrap();
+        assert_eq!(encoded, "TMVQGm1qAQYVdetCeGRRkTWYYrLXuHK2HC");
+        assert_eq!(decode_address(&encoded).unwrap(), address);
+        assert!(decode_address("TMVQGm1qAQYVdetCeGRRkTWYYrLXuHK2HD").is_err());
+
+        let book = vec![encoded.clone(), "TR7NHqjeKQxGTCi8q8ZY4pL8otSzgjLj6t".to_string()];
+        assert_eq!(find_similar_addresses("TMVQxxxxxxxxxxxxxxxxxxxxxxxxxxK2HC", &book), vec![&encoded]);
+        assert!(find_similar_addresses(&encoded, &book).is_empty());
+        assert!(!is_similar_address("TMV", "TMV"));
+    }
+}
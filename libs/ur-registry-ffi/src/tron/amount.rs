@@ -0,0 +1,192 @@
+//! Fixed-point formatting of on-chain amounts: SUN/TRX and TRC-20 token
+//! amounts with their decimals, so bindings don't each re-implement it.
+
+use crate::response::{PtrResponse, Response};
+#[cfg(feature = "protobuf")]
+use crate::tron::transaction::{parse_request, TriggerSmartContract, TRC20_APPROVE, TRC20_TRANSFER, TRC20_TRANSFER_FROM};
+#[cfg(feature = "protobuf")]
+use crate::tron::tron_sign_request::{MetadataValue, TronSignRequest};
+use crate::types::PtrString;
+use crate::utils::{convert_ptr_string_to_string, parse_ptr_string_to_bytes};
+
+pub const TRX_DECIMALS: u32 = 6;
+// Metadata keys a requester can set to describe the token being moved
+pub const TOKEN_DECIMALS_KEY: &str = "token.decimals";
+pub const TOKEN_SYMBOL_KEY: &str = "token.symbol";
+
+// Digits of the largest uint256
+const MAX_DECIMALS: u32 = 78;
+const WORD_LEN: usize = 32;
+
+/// Big-endian unsigned `amount` scaled down by `decimals`, without trailing
+/// zeros: `format_units(&[0x0f, 0x42, 0x40], 6)` is `"1"`
+pub fn format_units(amount: &[u8], decimals: u32) -> Result<String, String> {
+    if amount.len() > WORD_LEN {
+        return Err(format!("Amount exceeds {} bytes", WORD_LEN));
+    }
+    if decimals > MAX_DECIMALS {
+        return Err(format!("Decimals exceed {}", MAX_DECIMALS));
+    }
+    let mut digits = to_decimal(amount);
+    let decimals = decimals as usize;
+    if digits.len() <= decimals {
+        digits = format!("{}{}", "0".repeat(decimals + 1 - digits.len()), digits);
+    }
+    let (whole, fraction) = digits.split_at(digits.len() - decimals);
+    let fraction = fraction.trim_end_matches('0');
+    Ok(match fraction.is_empty() {
+        true => whole.to_string(),
+        false => format!("{}.{}", whole, fraction),
+    })
+}
+
+/// `1500000` SUN is `"1.5"` TRX
+pub fn sun_to_trx(sun: u64) -> String {
+    format_units(&sun.to_be_bytes(), TRX_DECIMALS).unwrap()
+}
+
+/// Parses a TRX amount like `"1.5"` into SUN
+pub fn trx_to_sun(trx: &str) -> Result<u64, String> {
+    let invalid = || format!("Invalid TRX amount: {}", trx);
+    let (whole, fraction) = trx.trim().split_once('.').unwrap_or((trx.trim(), ""));
+    if (whole.is_empty() && fraction.is_empty()) || fraction.len() > TRX_DECIMALS as usize {
+        return Err(invalid());
+    }
+    if !whole.chars().chain(fraction.chars()).all(|c| c.is_ascii_digit()) {
+        return Err(invalid());
+    }
+    let digits = format!("{}{:0<width$}", whole, fraction, width = TRX_DECIMALS as usize);
+    digits.parse::<u64>().map_err(|_| invalid())
+}
+
+/// Decimal digits of a big-endian unsigned integer
+fn to_decimal(amount: &[u8]) -> String {
+    let mut value: Vec<u8> = amount.iter().skip_while(|b| **b == 0).copied().collect();
+    let mut digits = Vec::new();
+    while !value.is_empty() {
+        let mut remainder = 0u32;
+        for byte in value.iter_mut() {
+            let current = (remainder << 8) | *byte as u32;
+            *byte = (current / 10) as u8;
+            remainder = current % 10;
+        }
+        digits.push(b'0' + remainder as u8);
+        value = value.into_iter().skip_while(|b| *b == 0).collect();
+    }
+    if digits.is_empty() {
+        return "0".to_string();
+    }
+    digits.reverse();
+    String::from_utf8(digits).unwrap()
+}
+
+/// Token amount argument of a TRC-20 `transfer`, `approve` or `transferFrom`
+#[cfg(feature = "protobuf")]
+pub fn trc20_amount(call: &TriggerSmartContract) -> Option<&[u8]> {
+    match call.get_selector()? {
+        TRC20_TRANSFER | TRC20_APPROVE => call.get_argument(1),
+        TRC20_TRANSFER_FROM => call.get_argument(2),
+        _ => None,
+    }
+}
+
+/// The TRC-20 amount of a transaction request, formatted with the decimals
+/// and symbol from its metadata; `None` for other transactions
+#[cfg(feature = "protobuf")]
+pub fn format_request_token_amount(request: &TronSignRequest) -> Result<Option<String>, String> {
+    let transaction = parse_request(request)?;
+    let call = match transaction.contracts.first().map(|c| c.as_trigger_smart_contract()).transpose()? {
+        Some(Some(call)) => call,
+        _ => return Ok(None),
+    };
+    let amount = match trc20_amount(&call) {
+        Some(amount) => amount,
+        None => return Ok(None),
+    };
+    let metadata = request.get_metadata();
+    let decimals = match metadata.get(TOKEN_DECIMALS_KEY) {
+        Some(MetadataValue::Text(decimals)) => decimals.parse().map_err(|_| format!("Invalid {}: {}", TOKEN_DECIMALS_KEY, decimals))?,
+        Some(_) => return Err(format!("{} must be text", TOKEN_DECIMALS_KEY)),
+        None => return Err(format!("Missing {} metadata", TOKEN_DECIMALS_KEY)),
+    };
+    let formatted = format_units(amount, decimals)?;
+    Ok(Some(match metadata.get(TOKEN_SYMBOL_KEY) {
+        Some(MetadataValue::Text(symbol)) => format!("{} {}", formatted, symbol),
+        _ => formatted,
+    }))
+}
+
+// ========== FFI Functions ==========
+
+/// `amount` is big-endian hex
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_format_units(amount: PtrString, decimals: u32) -> PtrResponse {
+    let amount = match parse_ptr_string_to_bytes(amount).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    match format_units(&amount, decimals) {
+        Ok(formatted) => Response::success_string(formatted),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sun_to_trx(sun: u64) -> PtrResponse {
+    Response::success_string(sun_to_trx(sun)).c_ptr()
+}
+
+/// SUN as a decimal string, since responses carry no 64-bit integers
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_trx_to_sun(trx: PtrString) -> PtrResponse {
+    let trx = match convert_ptr_string_to_string(trx).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    match trx_to_sun(&trx) {
+        Ok(sun) => Response::success_string(sun.to_string()),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(feature = "protobuf")]
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_request_format_token_amount(tron_sign_request: &TronSignRequest) -> PtrResponse {
+    match format_request_token_amount(tron_sign_request) {
+        Ok(formatted) => formatted.map_or(Response::not_present(), Response::success_string),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_formatting() {
+        assert_eq!(format_units(&[0x0f, 0x42, 0x40], 6).unwrap(), "1");
+        assert_eq!(format_units(&[0x01], 6).unwrap(), "0.000001");
+        assert_eq!(format_units(&[], 18).unwrap(), "0");
+        assert_eq!(format_units(&[0xff; 32], 0).unwrap().len(), 78);
+        assert_eq!(
+            format_units(&[0xff; 32], 18).unwrap(),
+            "115792089237316195423570985008687907853269984665640564039457.584007913129639935"
+        );
+        assert!(format_units(&[0; 33], 6).is_err());
+
+        assert_eq!(sun_to_trx(1_500_000), "1.5");
+        assert_eq!(trx_to_sun("1.5").unwrap(), 1_500_000);
+        assert_eq!(trx_to_sun("0.000001").unwrap(), 1);
+        assert_eq!(trx_to_sun("42").unwrap(), 42_000_000);
+        for invalid in ["", ".", "1.0000001", "-1", "1e6", "99999999999999999"] {
+            assert!(trx_to_sun(invalid).is_err(), "{}", invalid);
+        }
+    }
+}
@@ -0,0 +1,515 @@
+//! Machine-readable warnings for risky sign requests, for signer firmware
+//! and hot wallets to localize and style consistently.
+
+use crate::response::{PtrResponse, Response};
+use crate::tron::address::{encode_address, find_similar_addresses};
+use crate::tron::amount::format_units;
+use crate::tron::catalog::CatalogMessage;
+use crate::tron::contracts::ContractRegistry;
+use crate::tron::message::is_printable;
+use crate::tron::telemetry::{self, EventKind};
+use crate::tron::transaction::{
+    parse_request, RawTransaction, TriggerSmartContract, ACCOUNT_CREATE_CONTRACT, ACCOUNT_PERMISSION_UPDATE_CONTRACT, PROPOSAL_APPROVE_CONTRACT,
+    PROPOSAL_CREATE_CONTRACT, PROPOSAL_DELETE_CONTRACT, TRANSFER_ASSET_CONTRACT, TRANSFER_CONTRACT, TRC20_APPROVE,
+    SHIELDED_TRANSFER_CONTRACT, TRIGGER_SMART_CONTRACT, WITHDRAW_BALANCE_CONTRACT,
+};
+use crate::tron::tron_sign_request::{DataType, TronSignRequest};
+#[cfg(feature = "json")]
+use crate::types::PtrString;
+#[cfg(feature = "json")]
+use crate::utils::convert_ptr_string_to_string;
+
+#[cfg(feature = "json")]
+use serde_json::json;
+
+// Telemetry source of analyzer events
+const ANALYZER_SOURCE: &str = "analyzer";
+
+// 100 TRX, in SUN
+pub const DEFAULT_MAX_FEE_LIMIT: u64 = 100_000_000;
+
+/// `tron_sign_request_analyze_with_policy` flags. Fail requests that need
+/// blind signing instead of warning
+pub const POLICY_REFUSE_BLIND_SIGNING: u32 = 1;
+/// Fail transactions with more than one contract
+pub const POLICY_REFUSE_MULTIPLE_CONTRACTS: u32 = 2;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WarningCode {
+    /// `approve` for an unlimited or near-max allowance
+    UnlimitedApproval,
+    /// Transfer to an address missing from the known recipients
+    NewRecipient,
+    /// Transfer to a lookalike of a known recipient
+    SimilarRecipient,
+    /// fee_limit above the configured maximum
+    HighFeeLimit,
+    /// Call to a contract or function missing from the registry
+    UnknownContract,
+    /// sign_data that can't be decoded for display
+    BlindSigning,
+    /// AccountPermissionUpdate, which can hand control of the account over
+    PermissionUpdate,
+    /// More than one contract, which wallets may not all show
+    MultipleContracts,
+    /// Shielded transfer, whose pool-side amounts and parties are hidden
+    Shielded,
+}
+
+impl WarningCode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WarningCode::UnlimitedApproval => "UnlimitedApproval",
+            WarningCode::NewRecipient => "NewRecipient",
+            WarningCode::SimilarRecipient => "SimilarRecipient",
+            WarningCode::HighFeeLimit => "HighFeeLimit",
+            WarningCode::UnknownContract => "UnknownContract",
+            WarningCode::BlindSigning => "BlindSigning",
+            WarningCode::PermissionUpdate => "PermissionUpdate",
+            WarningCode::MultipleContracts => "MultipleContracts",
+            WarningCode::Shielded => "Shielded",
+        }
+    }
+
+    /// `warning.<code>` in the message catalog
+    pub fn get_message(&self) -> CatalogMessage {
+        CatalogMessage::new(&format!("warning.{}", self.as_str()))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Approval {
+    /// Base58 address allowed to spend
+    pub spender: String,
+    /// Big-endian allowance
+    pub amount: Vec<u8>,
+    /// Allowance in the top half of the uint256 range, which covers the
+    /// max, max - 1 and 2^255 values wallets use for "unlimited"
+    pub unlimited: bool,
+}
+
+impl Approval {
+    fn from_call(call: &TriggerSmartContract) -> Result<Option<Self>, String> {
+        if call.get_selector() != Some(TRC20_APPROVE) {
+            return Ok(None);
+        }
+        let (spender, amount) = match (call.get_address_argument(0), call.get_argument(1)) {
+            (Some(spender), Some(amount)) => (spender, amount),
+            _ => return Ok(None),
+        };
+        Ok(Some(Approval {
+            spender: encode_address(&spender)?,
+            amount: amount.to_vec(),
+            unlimited: amount[0] & 0x80 != 0,
+        }))
+    }
+}
+
+/// The TRC-20 `approve` a transaction request makes, if any
+pub fn get_approval(request: &TronSignRequest) -> Result<Option<Approval>, String> {
+    for contract in parse_request(request)?.contracts.iter() {
+        if let Some(call) = contract.as_trigger_smart_contract()? {
+            if let Some(approval) = Approval::from_call(&call)? {
+                return Ok(Some(approval));
+            }
+        }
+    }
+    Ok(None)
+}
+
+#[derive(Clone, Debug)]
+pub struct AnalyzerConfig {
+    /// Base58 addresses the user has sent to before
+    pub known_recipients: Vec<String>,
+    /// In SUN
+    pub max_fee_limit: u64,
+    pub registry: ContractRegistry,
+    /// Refuse requests the signer can't display, see `is_blind_signing`
+    pub refuse_blind_signing: bool,
+    /// Refuse transactions with more than one contract
+    pub refuse_multiple_contracts: bool,
+}
+
+impl Default for AnalyzerConfig {
+    fn default() -> Self {
+        AnalyzerConfig {
+            known_recipients: vec![],
+            max_fee_limit: DEFAULT_MAX_FEE_LIMIT,
+            registry: ContractRegistry::builtin(),
+            refuse_blind_signing: false,
+            refuse_multiple_contracts: false,
+        }
+    }
+}
+
+/// Whether the signer can't show what is being signed: undecodable
+/// transactions or calls missing from the registry, binary messages such as
+/// raw hashes, and malformed typed data
+pub fn is_blind_signing(request: &TronSignRequest, registry: &ContractRegistry) -> Result<bool, String> {
+    let sign_data = request.get_sign_data();
+    Ok(match DataType::from_u32(request.get_data_type())? {
+        DataType::Transaction => match RawTransaction::parse(sign_data) {
+            Ok(transaction) => !is_decodable(&transaction, registry)?,
+            Err(_) => true,
+        },
+        DataType::Message => !is_printable(sign_data),
+        DataType::TypedData => !is_valid_typed_data(sign_data),
+    })
+}
+
+/// `Err` with the reason when the config refuses blind signing and
+/// `request` needs it
+pub fn check_blind_signing(request: &TronSignRequest, config: &AnalyzerConfig) -> Result<(), String> {
+    if config.refuse_blind_signing && is_blind_signing(request, &config.registry)? {
+        return Err(policy_rejection(request, WarningCode::BlindSigning, "Blind signing is disabled".to_string()));
+    }
+    Ok(())
+}
+
+/// `Err` when the config refuses multi-contract transactions and
+/// `request` is one
+pub fn check_multiple_contracts(request: &TronSignRequest, config: &AnalyzerConfig) -> Result<(), String> {
+    if !config.refuse_multiple_contracts {
+        return Ok(());
+    }
+    match parse_request(request) {
+        Ok(transaction) if transaction.has_multiple_contracts() => Err(policy_rejection(
+            request,
+            WarningCode::MultipleContracts,
+            format!("Transaction has {} contracts", transaction.contracts.len()),
+        )),
+        _ => Ok(()),
+    }
+}
+
+// Reports the rejection and hands the reason back
+fn policy_rejection(request: &TronSignRequest, code: WarningCode, reason: String) -> String {
+    telemetry::emit(EventKind::PolicyRejection, ANALYZER_SOURCE, code.as_str(), &request_label(request));
+    reason
+}
+
+// Request id in hex, for correlating events
+fn request_label(request: &TronSignRequest) -> String {
+    request.get_request_id().map(hex::encode).unwrap_or_default()
+}
+
+fn is_decodable(transaction: &RawTransaction, registry: &ContractRegistry) -> Result<bool, String> {
+    if transaction.contracts.is_empty() {
+        return Ok(false);
+    }
+    for contract in transaction.contracts.iter() {
+        let decodable = match contract.contract_type {
+            TRANSFER_CONTRACT | TRANSFER_ASSET_CONTRACT | WITHDRAW_BALANCE_CONTRACT => true,
+            ACCOUNT_CREATE_CONTRACT | ACCOUNT_PERMISSION_UPDATE_CONTRACT => true,
+            PROPOSAL_CREATE_CONTRACT | PROPOSAL_APPROVE_CONTRACT | PROPOSAL_DELETE_CONTRACT => true,
+            TRIGGER_SMART_CONTRACT => is_known_call(&contract.as_trigger_smart_contract()?.unwrap_or_default(), registry),
+            _ => false,
+        };
+        if !decodable {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+fn is_known_call(call: &TriggerSmartContract, registry: &ContractRegistry) -> bool {
+    match (registry.get(&call.contract_address), call.get_selector()) {
+        (Some(contract), Some(selector)) => contract.get_function(&selector).is_some(),
+        _ => false,
+    }
+}
+
+#[cfg(feature = "json")]
+fn is_valid_typed_data(data: &[u8]) -> bool {
+    crate::tron::typed_data::validate_typed_data(data).is_ok()
+}
+
+#[cfg(not(feature = "json"))]
+fn is_valid_typed_data(_data: &[u8]) -> bool {
+    false
+}
+
+fn report(request: &TronSignRequest, warnings: &[(WarningCode, CatalogMessage)]) {
+    for (code, _) in warnings {
+        telemetry::emit(EventKind::ValidationWarning, ANALYZER_SOURCE, code.as_str(), &request_label(request));
+    }
+}
+
+/// Warnings for `request`, each code at most once, in the order found
+pub fn analyze(request: &TronSignRequest, config: &AnalyzerConfig) -> Result<Vec<WarningCode>, String> {
+    Ok(analyze_messages(request, config)?.into_iter().map(|(code, _)| code).collect())
+}
+
+/// `analyze` with each warning's catalog message, whose arguments name
+/// the spender, recipient and amount of the contract that raised it.
+/// Amounts are in the asset's smallest unit. `Err` when the config's
+/// policy refuses the request.
+pub fn analyze_messages(
+    request: &TronSignRequest,
+    config: &AnalyzerConfig,
+) -> Result<Vec<(WarningCode, CatalogMessage)>, String> {
+    check_blind_signing(request, config)?;
+    check_multiple_contracts(request, config)?;
+    let mut warnings: Vec<(WarningCode, CatalogMessage)> = Vec::new();
+    if is_blind_signing(request, &config.registry)? {
+        warnings.push((WarningCode::BlindSigning, WarningCode::BlindSigning.get_message()));
+    }
+    let transaction = match parse_request(request) {
+        Ok(transaction) => transaction,
+        Err(_) => {
+            report(request, &warnings);
+            return Ok(warnings);
+        }
+    };
+    let mut warn = |code: WarningCode, message: CatalogMessage| {
+        if !warnings.iter().any(|(c, _)| *c == code) {
+            warnings.push((code, message));
+        }
+    };
+
+    if transaction.has_multiple_contracts() {
+        warn(WarningCode::MultipleContracts, WarningCode::MultipleContracts.get_message());
+    }
+    for contract in transaction.contracts.iter() {
+        match contract.contract_type {
+            ACCOUNT_PERMISSION_UPDATE_CONTRACT => warn(WarningCode::PermissionUpdate, WarningCode::PermissionUpdate.get_message()),
+            SHIELDED_TRANSFER_CONTRACT => warn(WarningCode::Shielded, WarningCode::Shielded.get_message()),
+            _ => {}
+        }
+        if let Some(call) = contract.as_trigger_smart_contract()? {
+            if !is_known_call(&call, &config.registry) {
+                warn(WarningCode::UnknownContract, WarningCode::UnknownContract.get_message());
+            }
+            if let Some(approval) = Approval::from_call(&call)?.filter(|approval| approval.unlimited) {
+                let message = WarningCode::UnlimitedApproval
+                    .get_message()
+                    .with_arg("spender", &approval.spender)
+                    .with_arg("amount", format_units(&approval.amount, 0)?);
+                warn(WarningCode::UnlimitedApproval, message);
+            }
+        }
+        if let Some(transfer) = contract.get_transfer()? {
+            let to = encode_address(&transfer.to)?;
+            if !config.known_recipients.contains(&to) {
+                let amount = format_units(&transfer.amount, 0)?;
+                let message = |code: WarningCode| code.get_message().with_arg("recipient", &to).with_arg("amount", &amount);
+                warn(WarningCode::NewRecipient, message(WarningCode::NewRecipient));
+                if !find_similar_addresses(&to, &config.known_recipients).is_empty() {
+                    warn(WarningCode::SimilarRecipient, message(WarningCode::SimilarRecipient));
+                }
+            }
+        }
+    }
+    if transaction.fee_limit > config.max_fee_limit {
+        warn(WarningCode::HighFeeLimit, WarningCode::HighFeeLimit.get_message());
+    }
+    report(request, &warnings);
+    Ok(warnings)
+}
+
+// ========== FFI Functions ==========
+
+/// JSON array of warnings as catalog messages `{"key", "args"}`, keyed
+/// `warning.<code>`. `known_recipients` is newline-separated; a `max_fee_limit` of 0 uses
+/// the default.
+#[cfg(feature = "json")]
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_request_analyze(
+    tron_sign_request: &TronSignRequest,
+    registry: &ContractRegistry,
+    known_recipients: PtrString,
+    max_fee_limit: u64,
+) -> PtrResponse {
+    tron_sign_request_analyze_with_policy(tron_sign_request, registry, known_recipients, max_fee_limit, 0)
+}
+
+/// `tron_sign_request_analyze` that errors with the reason when `policy`,
+/// a set of `POLICY_*` flags, refuses the request
+#[cfg(feature = "json")]
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_request_analyze_with_policy(
+    tron_sign_request: &TronSignRequest,
+    registry: &ContractRegistry,
+    known_recipients: PtrString,
+    max_fee_limit: u64,
+    policy: u32,
+) -> PtrResponse {
+    let known_recipients = match convert_ptr_string_to_string(known_recipients).map_err(|e| Response::error(e)) {
+        Ok(v) => v.lines().map(str::trim).filter(|s| !s.is_empty()).map(str::to_string).collect(),
+        Err(e) => return e.c_ptr(),
+    };
+    let config = AnalyzerConfig {
+        known_recipients,
+        max_fee_limit: Some(max_fee_limit).filter(|l| *l != 0).unwrap_or(DEFAULT_MAX_FEE_LIMIT),
+        registry: registry.clone(),
+        refuse_blind_signing: policy & POLICY_REFUSE_BLIND_SIGNING != 0,
+        refuse_multiple_contracts: policy & POLICY_REFUSE_MULTIPLE_CONTRACTS != 0,
+    };
+    match analyze_messages(tron_sign_request, &config) {
+        Ok(warnings) => {
+            let messages: Vec<_> = warnings.iter().map(|(_, message)| crate::tron::catalog::to_json(message)).collect();
+            Response::success_string(json!(messages).to_string())
+        }
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+/// JSON `{"spender", "amount", "unlimited"}` with the amount in hex, or
+/// `NOT_PRESENT` when the request approves nothing
+#[cfg(feature = "json")]
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_request_get_approval(tron_sign_request: &TronSignRequest) -> PtrResponse {
+    match get_approval(tron_sign_request) {
+        Ok(approval) => approval.map_or(Response::not_present(), |a| {
+            Response::success_string(
+                json!({ "spender": a.spender, "amount": hex::encode(&a.amount), "unlimited": a.unlimited }).to_string(),
+            )
+        }),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_request_is_blind_signing(
+    tron_sign_request: &TronSignRequest,
+    registry: &ContractRegistry,
+) -> PtrResponse {
+    match is_blind_signing(tron_sign_request, registry) {
+        Ok(blind) => Response::success_boolean(blind),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tron::protobuf::encode::{bytes_field, varint_field};
+    use crate::tron::transaction::TRC20_TRANSFER;
+
+    fn trigger_request(data: &[u8], fee_limit: u64) -> TronSignRequest {
+        let usdt = crate::tron::address::decode_address("TR7NHqjeKQxGTCi8q8ZY4pL8otSzgjLj6t").unwrap();
+        let mut call = Vec::new();
+        bytes_field(1, &[0x41; 21], &mut call);
+        bytes_field(2, &usdt, &mut call);
+        bytes_field(4, data, &mut call);
+        let mut any = Vec::new();
+        bytes_field(2, &call, &mut any);
+        let mut contract = Vec::new();
+        varint_field(1, TRIGGER_SMART_CONTRACT, &mut contract);
+        bytes_field(2, &any, &mut contract);
+        let mut raw_data = Vec::new();
+        bytes_field(11, &contract, &mut raw_data);
+        varint_field(18, fee_limit, &mut raw_data);
+        TronSignRequest::new_with_derived_request_id(raw_data, 1, "m/44'/195'/0'/0/0".to_string(), None, None, None)
+    }
+
+    #[test]
+    fn test_warnings() {
+        let config = AnalyzerConfig::default();
+        let mut approve = TRC20_APPROVE.to_vec();
+        approve.extend_from_slice(&[0; 12]);
+        approve.extend_from_slice(&[0xa6; 20]);
+        approve.extend_from_slice(&[0xff; 32]);
+        let request = trigger_request(&approve, 500_000_000);
+        assert_eq!(
+            analyze(&request, &config).unwrap(),
+            vec![WarningCode::UnlimitedApproval, WarningCode::HighFeeLimit]
+        );
+        let approval = get_approval(&request).unwrap().unwrap();
+        assert!(approval.unlimited);
+        assert!(approval.spender.starts_with('T'));
+        let messages = analyze_messages(&request, &config).unwrap();
+        let (code, message) = &messages[0];
+        assert_eq!(*code, WarningCode::UnlimitedApproval);
+        assert_eq!(message.args["spender"], approval.spender);
+        assert_eq!(message.args["amount"], format_units(&[0xff; 32], 0).unwrap());
+        assert_eq!(
+            crate::tron::catalog::render_english(message).unwrap(),
+            format!("Grants {} an unlimited token allowance", approval.spender)
+        );
+        approve[36] = 0x7f;
+        assert!(!get_approval(&trigger_request(&approve, 0)).unwrap().unwrap().unlimited);
+
+        let mut transfer = TRC20_TRANSFER.to_vec();
+        transfer.extend_from_slice(&[0; 12]);
+        transfer.extend_from_slice(&[0xa6; 20]);
+        transfer.extend_from_slice(&[1; 32]);
+        let request = trigger_request(&transfer, 10_000_000);
+        assert_eq!(analyze(&request, &config).unwrap(), vec![WarningCode::NewRecipient]);
+        let mut recipient = vec![0x41];
+        recipient.extend_from_slice(&[0xa6; 20]);
+        let (_, message) = analyze_messages(&request, &config).unwrap().remove(0);
+        assert_eq!(message.args["recipient"], encode_address(&recipient).unwrap());
+        assert_eq!(message.args["amount"], format_units(&[1; 32], 0).unwrap());
+        let config = AnalyzerConfig { known_recipients: vec![encode_address(&recipient).unwrap()], ..config };
+        assert!(analyze(&request, &config).unwrap().is_empty());
+
+        assert_eq!(
+            analyze(&trigger_request(&[1, 2, 3, 4], 0), &config).unwrap(),
+            vec![WarningCode::BlindSigning, WarningCode::UnknownContract]
+        );
+        let garbage = TronSignRequest::new_with_derived_request_id(vec![0xff], 1, "m/44'/195'/0'/0/0".to_string(), None, None, None);
+        assert_eq!(analyze(&garbage, &config).unwrap(), vec![WarningCode::BlindSigning]);
+        assert!(check_blind_signing(&garbage, &config).is_ok());
+        let config = AnalyzerConfig { refuse_blind_signing: true, ..config };
+        assert!(check_blind_signing(&garbage, &config).is_err());
+        assert!(check_blind_signing(&request, &config).is_ok());
+
+        let doubled = request.get_sign_data().repeat(2);
+        let doubled = TronSignRequest::new_with_derived_request_id(doubled, 1, "m/44'/195'/0'/0/0".to_string(), None, None, None);
+        assert_eq!(analyze(&doubled, &config).unwrap(), vec![WarningCode::MultipleContracts]);
+        assert!(check_multiple_contracts(&doubled, &config).is_ok());
+        let config = AnalyzerConfig { refuse_multiple_contracts: true, ..config };
+        assert!(check_multiple_contracts(&doubled, &config).is_err());
+        assert!(check_multiple_contracts(&request, &config).is_ok());
+
+        let hash = TronSignRequest::new_with_derived_request_id(vec![0xab; 32], 2, "m/44'/195'/0'/0/0".to_string(), None, None, None);
+        assert!(is_blind_signing(&hash, &config.registry).unwrap());
+        let text = TronSignRequest::new_with_derived_request_id(b"Sign in\n".to_vec(), 2, "m/44'/195'/0'/0/0".to_string(), None, None, None);
+        assert!(!is_blind_signing(&text, &config.registry).unwrap());
+
+    }
+
+    #[cfg(all(feature = "ffi", feature = "json"))]
+    #[test]
+    fn test_analyze_with_policy() {
+        let registry = ContractRegistry::builtin();
+        let recipients = std::ffi::CString::new("").unwrap();
+        let analyze = |request: &TronSignRequest, policy: u32| {
+            Response::from_ptr(tron_sign_request_analyze_with_policy(
+                request,
+                &registry,
+                recipients.as_ptr() as PtrString,
+                0,
+                policy,
+            ))
+        };
+        let garbage = TronSignRequest::new_with_derived_request_id(vec![0xff], 1, "m/44'/195'/0'/0/0".to_string(), None, None, None);
+        assert_eq!(analyze(&garbage, 0).status_code, crate::response::SUCCESS);
+        let response = analyze(&garbage, POLICY_REFUSE_BLIND_SIGNING);
+        assert_eq!(response.status_code, crate::response::ERROR);
+        assert_eq!(convert_ptr_string_to_string(response.error_message).unwrap(), "Blind signing is disabled");
+        let text = TronSignRequest::new_with_derived_request_id(b"Sign in\n".to_vec(), 2, "m/44'/195'/0'/0/0".to_string(), None, None, None);
+        assert_eq!(analyze(&text, POLICY_REFUSE_BLIND_SIGNING).status_code, crate::response::SUCCESS);
+
+        let mut transfer = TRC20_TRANSFER.to_vec();
+        transfer.extend_from_slice(&[0; 12]);
+        transfer.extend_from_slice(&[0xa6; 20]);
+        transfer.extend_from_slice(&[1; 32]);
+        let single = trigger_request(&transfer, 0);
+        let doubled = single.get_sign_data().repeat(2);
+        let doubled = TronSignRequest::new_with_derived_request_id(doubled, 1, "m/44'/195'/0'/0/0".to_string(), None, None, None);
+        assert_eq!(analyze(&doubled, 0).status_code, crate::response::SUCCESS);
+        let response = analyze(&doubled, POLICY_REFUSE_MULTIPLE_CONTRACTS);
+        assert_eq!(response.status_code, crate::response::ERROR);
+        assert_eq!(convert_ptr_string_to_string(response.error_message).unwrap(), "Transaction has 2 contracts");
+        assert_eq!(analyze(&single, POLICY_REFUSE_MULTIPLE_CONTRACTS).status_code, crate::response::SUCCESS);
+    }
+}
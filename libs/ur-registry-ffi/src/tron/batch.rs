@@ -0,0 +1,154 @@
+//! Decodes queues of tron-sign-request payloads at once, for backends
+//! validating them in bulk. With the `parallel` feature payloads are parsed
+//! across the rayon thread pool; results keep the input order either way.
+//!
+//! Over the FFI the payloads come packed into one buffer, each preceded by
+//! its length as a 4-byte big-endian integer.
+
+use crate::response::{PtrResponse, Response};
+use crate::tron::decode::DecodeError;
+use crate::tron::telemetry;
+use crate::tron::tron_sign_request::{TronSignRequest, TRON_SIGN_REQUEST_TYPE};
+use crate::types::{PtrString, PtrVoid};
+use crate::utils::parse_ptr_string_to_bytes;
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+const LENGTH_PREFIX_LEN: usize = 4;
+
+/// Decodes every payload, one result per payload in the same order
+#[cfg(feature = "parallel")]
+pub fn decode_batch(payloads: Vec<Vec<u8>>) -> Vec<Result<TronSignRequest, DecodeError>> {
+    payloads.into_par_iter().map(TronSignRequest::try_from).collect()
+}
+
+/// Decodes every payload, one result per payload in the same order
+#[cfg(not(feature = "parallel"))]
+pub fn decode_batch(payloads: Vec<Vec<u8>>) -> Vec<Result<TronSignRequest, DecodeError>> {
+    payloads.into_iter().map(TronSignRequest::try_from).collect()
+}
+
+/// Payloads of a packed buffer; fails as a whole when a length runs past
+/// the end, since what follows can't be framed
+pub fn unpack(packed: &[u8]) -> Result<Vec<Vec<u8>>, String> {
+    let mut payloads = Vec::new();
+    let mut rest = packed;
+    while !rest.is_empty() {
+        if rest.len() < LENGTH_PREFIX_LEN {
+            return Err(format!("Truncated length prefix at offset {}", packed.len() - rest.len()));
+        }
+        let (prefix, tail) = rest.split_at(LENGTH_PREFIX_LEN);
+        let len = u32::from_be_bytes([prefix[0], prefix[1], prefix[2], prefix[3]]) as usize;
+        if tail.len() < len {
+            return Err(format!("Payload {} needs {} bytes, {} left", payloads.len(), len, tail.len()));
+        }
+        let (payload, tail) = tail.split_at(len);
+        payloads.push(payload.to_vec());
+        rest = tail;
+    }
+    Ok(payloads)
+}
+
+pub fn pack(payloads: &[Vec<u8>]) -> Result<Vec<u8>, String> {
+    let mut packed = Vec::with_capacity(payloads.iter().map(|p| LENGTH_PREFIX_LEN + p.len()).sum());
+    for payload in payloads {
+        let len = u32::try_from(payload.len()).map_err(|_| format!("Payload of {} bytes is too long", payload.len()))?;
+        packed.extend_from_slice(&len.to_be_bytes());
+        packed.extend_from_slice(payload);
+    }
+    Ok(packed)
+}
+
+/// Per-payload results of a batch decode
+#[derive(Debug)]
+pub struct DecodeBatch {
+    results: Vec<Result<TronSignRequest, DecodeError>>,
+}
+
+impl DecodeBatch {
+    pub fn decode(payloads: Vec<Vec<u8>>) -> Self {
+        let results = decode_batch(payloads);
+        for error in results.iter().filter_map(|r| r.as_ref().err()) {
+            telemetry::decode_failure(TRON_SIGN_REQUEST_TYPE, error);
+        }
+        DecodeBatch { results }
+    }
+
+    pub fn len(&self) -> usize {
+        self.results.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.results.is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> Option<&Result<TronSignRequest, DecodeError>> {
+        self.results.get(index)
+    }
+}
+
+// ========== FFI Functions ==========
+
+/// `packed` is the hex of the length-prefixed payloads
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_request_decode_batch(packed: PtrString) -> PtrResponse {
+    let payloads = match parse_ptr_string_to_bytes(packed).and_then(|p| unpack(&p)).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    Response::success_object(Box::into_raw(Box::new(DecodeBatch::decode(payloads))) as PtrVoid).c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_decode_batch_get_len(decode_batch: &DecodeBatch) -> PtrResponse {
+    Response::success_uint32(decode_batch.len() as u32).c_ptr()
+}
+
+/// The request decoded from payload `index`, or the error it failed with
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_decode_batch_get_request(decode_batch: &DecodeBatch, index: u32) -> PtrResponse {
+    match decode_batch.get(index as usize) {
+        Some(Ok(request)) => Response::success_object(Box::into_raw(Box::new(request.clone())) as PtrVoid),
+        Some(Err(e)) => Response::error(e.to_string()),
+        None => Response::error(format!("No payload for index {} was found", index)),
+    }
+    .c_ptr()
+}
+
+/// `DecodeError` code of payload `index`, absent when it decoded
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_decode_batch_get_error_code(decode_batch: &DecodeBatch, index: u32) -> PtrResponse {
+    match decode_batch.get(index as usize) {
+        Some(result) => result.as_ref().err().map_or(Response::not_present(), |e| Response::success_string(e.code().to_string())),
+        None => Response::error(format!("No payload for index {} was found", index)),
+    }
+    .c_ptr()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_batch() {
+        let request = TronSignRequest::new(None, b"hi".to_vec(), 2, "m/44'/195'/0'/0/0".to_string(), None, None, None);
+        let valid = request.to_bytes().unwrap();
+        let payloads = vec![valid.clone(), vec![0x01], vec![], valid];
+        let packed = pack(&payloads).unwrap();
+        assert_eq!(unpack(&packed).unwrap(), payloads);
+        assert!(unpack(&packed[..packed.len() - 1]).is_err());
+        assert!(unpack(&[0, 0]).is_err());
+
+        let batch = DecodeBatch::decode(unpack(&packed).unwrap());
+        assert_eq!(batch.len(), 4);
+        assert_eq!(batch.get(0).unwrap().as_ref().unwrap().get_sign_data(), &b"hi".to_vec());
+        assert_eq!(batch.get(1).unwrap().as_ref().unwrap_err().code(), "ExpectedMap");
+        assert!(batch.get(2).unwrap().is_err());
+        assert!(batch.get(3).unwrap().is_ok());
+    }
+}
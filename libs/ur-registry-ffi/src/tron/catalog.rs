@@ -0,0 +1,163 @@
+//! Stable message keys for the text the analyzer and summaries generate,
+//! with their arguments kept apart, so bindings localize by key instead of
+//! parsing English. The built-in English catalog renders them for signers
+//! that don't.
+//!
+//! Keys are `warning.<WarningCode>`, `summary.*` and `contract.<kind>`;
+//! templates name their arguments in braces, e.g. `Send {amount} {symbol}
+//! to {to}`. Amounts are written with a '.' for bindings to reformat.
+//! `contract.<kind>` renders as `ContractKind::get_title`, so it needs the
+//! `protobuf` feature.
+
+use crate::response::{PtrResponse, Response};
+#[cfg(feature = "protobuf")]
+use crate::tron::transaction::ContractKind;
+use crate::types::PtrString;
+use crate::utils::convert_ptr_string_to_string;
+
+#[cfg(feature = "json")]
+use serde_json::json;
+use std::collections::BTreeMap;
+
+const ENGLISH: &[(&str, &str)] = &[
+    ("warning.UnlimitedApproval", "Grants {spender} an unlimited token allowance"),
+    ("warning.NewRecipient", "You haven't sent to {recipient} before"),
+    ("warning.SimilarRecipient", "{recipient} looks like one of your contacts"),
+    ("warning.HighFeeLimit", "Fee limit is unusually high"),
+    ("warning.UnknownContract", "Calls an unverified contract"),
+    ("warning.BlindSigning", "Contents can't be shown"),
+    ("warning.PermissionUpdate", "Changes who controls the account"),
+    ("warning.MultipleContracts", "Contains several operations"),
+    ("warning.Shielded", "Shielded amounts and parties are hidden"),
+    ("summary.send", "Send {amount} {symbol} to {to}"),
+    ("summary.send_token_units", "Send {units} token units to {to}"),
+    ("summary.send_token_units_of", "Send {units} units of {token} to {to}"),
+    ("summary.call", "Call {contract}"),
+    ("summary.call_function", "Call {function} on {contract}"),
+    ("summary.activate_account", "Activate account {address}"),
+    ("summary.more_contracts", "plus {count} more"),
+    ("summary.fee_limit", "fee limit {amount} {symbol}"),
+    ("summary.expires_in_seconds", "expires in {count} s"),
+    ("summary.expires_in_minutes", "expires in {count} min"),
+    ("summary.expires_in_hours", "expires in {count} h"),
+    ("summary.expired", "expired"),
+    ("summary.origin", "from {origin}"),
+    ("summary.sign_message", "Sign message"),
+    ("summary.sign_typed_data", "Sign typed data"),
+    ("summary.empty_transaction", "Empty transaction"),
+];
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CatalogMessage {
+    pub key: String,
+    pub args: BTreeMap<String, String>,
+}
+
+impl CatalogMessage {
+    pub fn new(key: &str) -> Self {
+        CatalogMessage { key: key.to_string(), args: BTreeMap::new() }
+    }
+
+    pub fn with_arg(mut self, name: &str, value: impl ToString) -> Self {
+        self.args.insert(name.to_string(), value.to_string());
+        self
+    }
+}
+
+/// English text of `message`; fails for unknown keys and missing arguments
+pub fn render_english(message: &CatalogMessage) -> Result<String, String> {
+    #[cfg(feature = "protobuf")]
+    if let Some(kind) = message.key.strip_prefix("contract.").and_then(ContractKind::from_name) {
+        return Ok(kind.get_title().to_string());
+    }
+    let template = ENGLISH
+        .iter()
+        .find(|(key, _)| *key == message.key)
+        .map(|(_, template)| *template)
+        .ok_or(format!("Unknown message key {}", message.key))?;
+    let mut text = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let end = rest[start..].find('}').map(|i| start + i).ok_or(format!("Unclosed argument in {}", message.key))?;
+        let name = &rest[start + 1..end];
+        let value = message.args.get(name).ok_or(format!("{} needs argument {}", message.key, name))?;
+        text.push_str(&rest[..start]);
+        text.push_str(value);
+        rest = &rest[end + 1..];
+    }
+    text.push_str(rest);
+    Ok(text)
+}
+
+/// JSON `{"key", "args": {name: value}}`
+#[cfg(feature = "json")]
+pub fn to_json(message: &CatalogMessage) -> serde_json::Value {
+    json!({ "key": message.key, "args": message.args })
+}
+
+#[cfg(feature = "json")]
+fn parse_args(args: &str) -> Result<BTreeMap<String, String>, String> {
+    match args.trim().is_empty() {
+        true => Ok(BTreeMap::new()),
+        false => serde_json::from_str(args).map_err(|e| format!("Invalid message arguments: {}", e)),
+    }
+}
+
+#[cfg(not(feature = "json"))]
+fn parse_args(args: &str) -> Result<BTreeMap<String, String>, String> {
+    match args.trim().is_empty() {
+        true => Ok(BTreeMap::new()),
+        false => Err("Message arguments need the json feature".to_string()),
+    }
+}
+
+// ========== FFI Functions ==========
+
+/// English text for `key`; `args` is a JSON object of string arguments,
+/// empty when the message takes none
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_catalog_render_english(key: PtrString, args: PtrString) -> PtrResponse {
+    let key = match convert_ptr_string_to_string(key).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    let args = match convert_ptr_string_to_string(args).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    let message = match parse_args(&args) {
+        Ok(args) => CatalogMessage { key, args },
+        Err(e) => return Response::error(e).c_ptr(),
+    };
+    match render_english(&message) {
+        Ok(text) => Response::success_string(text),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_english() {
+        let send = CatalogMessage::new("summary.send").with_arg("amount", "1.5").with_arg("symbol", "TRX").with_arg("to", "TAbc…wxyz");
+        assert_eq!(render_english(&send).unwrap(), "Send 1.5 TRX to TAbc…wxyz");
+        assert_eq!(render_english(&CatalogMessage::new("warning.BlindSigning")).unwrap(), "Contents can't be shown");
+        assert!(render_english(&CatalogMessage::new("summary.send")).unwrap_err().contains("needs argument"));
+        assert!(render_english(&CatalogMessage::new("summary.nope")).is_err());
+        for (key, template) in ENGLISH.iter() {
+            assert_eq!(template.matches('{').count(), template.matches('}').count(), "{}", key);
+        }
+    }
+
+    #[cfg(feature = "protobuf")]
+    #[test]
+    fn test_render_contract_titles() {
+        assert_eq!(render_english(&CatalogMessage::new("contract.WithdrawBalance")).unwrap(), "Claim rewards");
+        assert_eq!(render_english(&CatalogMessage::new("contract.Other")).unwrap(), "Unknown transaction");
+        assert!(render_english(&CatalogMessage::new("contract.Nope")).is_err());
+    }
+}
@@ -0,0 +1,207 @@
+use crate::tron::decode::DecodeError;
+
+use serde_cbor::Value;
+use std::collections::BTreeSet;
+use std::ops::Range;
+
+// Nesting limit while skipping over values
+const MAX_DEPTH: usize = 64;
+
+const BREAK: u8 = 0xff;
+
+/// Byte ranges of each top-level entry of a CBOR map, found by walking item
+/// headers only; values are left undecoded. Duplicate keys are rejected, in
+/// nested maps too.
+pub(crate) struct MapIndex {
+    pub entries: Vec<(Value, Range<usize>)>,
+}
+
+impl MapIndex {
+    pub fn parse(data: &[u8]) -> Result<Self, DecodeError> {
+        let (major, len, mut pos) = read_head(data, 0)?;
+        if major != 5 {
+            return Err(DecodeError::ExpectedMap);
+        }
+
+        let mut entries = Vec::new();
+        let mut keys = BTreeSet::new();
+        let mut remaining = len;
+        loop {
+            match remaining {
+                Some(0) => break,
+                None if data.get(pos) == Some(&BREAK) => {
+                    pos += 1;
+                    break;
+                }
+                _ => {}
+            }
+            let key_end = skip_item(data, pos, 0)?;
+            let key = decode_key(&data[pos..key_end])?;
+            if !keys.insert(key.clone()) {
+                return Err(DecodeError::DuplicateKey(format!("{:?}", key)));
+            }
+            let value_end = skip_item(data, key_end, 0)?;
+            entries.push((key, key_end..value_end));
+            pos = value_end;
+            remaining = remaining.map(|n| n - 1);
+        }
+
+        if pos != data.len() {
+            return Err(DecodeError::TrailingBytes(data.len() - pos));
+        }
+        Ok(MapIndex { entries })
+    }
+}
+
+/// A whole payload of any shape, tagged or not, checked as strictly as
+/// `MapIndex::parse` checks maps: no map in it may repeat a key, and
+/// nothing may follow it
+pub(crate) fn decode_value(data: &[u8]) -> Result<Value, DecodeError> {
+    let end = skip_item(data, 0, 0)?;
+    if end != data.len() {
+        return Err(DecodeError::TrailingBytes(data.len() - end));
+    }
+    serde_cbor::from_slice(data).map_err(|e| DecodeError::InvalidCbor(e.to_string()))
+}
+
+fn decode_key(bytes: &[u8]) -> Result<Value, DecodeError> {
+    serde_cbor::from_slice(bytes).map_err(|e| DecodeError::InvalidCbor(e.to_string()))
+}
+
+/// Keys seen so far in one map
+#[derive(Default)]
+struct KeySet(BTreeSet<Value>);
+
+impl KeySet {
+    fn insert(&mut self, data: &[u8], key: Range<usize>) -> Result<(), DecodeError> {
+        let key = decode_key(&data[key])?;
+        if !self.0.insert(key.clone()) {
+            return Err(DecodeError::DuplicateKey(format!("{:?}", key)));
+        }
+        Ok(())
+    }
+}
+
+fn truncated() -> DecodeError {
+    DecodeError::InvalidCbor("unexpected end of data".to_string())
+}
+
+/// Major type, argument (None for indefinite length) and position after the head
+fn read_head(data: &[u8], pos: usize) -> Result<(u8, Option<u64>, usize), DecodeError> {
+    let initial = *data.get(pos).ok_or_else(truncated)?;
+    let major = initial >> 5;
+    let info = initial & 0x1f;
+    let width = match info {
+        0..=23 => return Ok((major, Some(info as u64), pos + 1)),
+        24 => 1,
+        25 => 2,
+        26 => 4,
+        27 => 8,
+        31 if matches!(major, 2..=5) => return Ok((major, None, pos + 1)),
+        _ => return Err(DecodeError::InvalidCbor(format!("invalid additional info {} at {}", info, pos))),
+    };
+    let bytes = data
+        .get(pos + 1..pos + 1 + width)
+        .ok_or_else(truncated)?;
+    let arg = bytes.iter().fold(0u64, |acc, b| (acc << 8) | *b as u64);
+    Ok((major, Some(arg), pos + 1 + width))
+}
+
+/// Position just past the item starting at `pos`
+fn skip_item(data: &[u8], pos: usize, depth: usize) -> Result<usize, DecodeError> {
+    if depth > MAX_DEPTH {
+        return Err(DecodeError::InvalidCbor("nesting too deep".to_string()));
+    }
+    let (major, arg, mut pos) = read_head(data, pos)?;
+    match (major, arg) {
+        (0 | 1 | 7, _) => Ok(pos),
+        (2 | 3, Some(len)) => {
+            let end = usize::try_from(len)
+                .ok()
+                .and_then(|len| pos.checked_add(len))
+                .filter(|end| *end <= data.len())
+                .ok_or_else(truncated)?;
+            Ok(end)
+        }
+        (6, _) => skip_item(data, pos, depth + 1),
+        (_, Some(count)) => {
+            // Every entry takes at least one byte, which bounds the loop
+            let items = if major == 5 { count.saturating_mul(2) } else { count };
+            if items > (data.len() - pos) as u64 {
+                return Err(truncated());
+            }
+            let mut keys = KeySet::default();
+            for item in 0..items {
+                let end = skip_item(data, pos, depth + 1)?;
+                if major == 5 && item % 2 == 0 {
+                    keys.insert(data, pos..end)?;
+                }
+                pos = end;
+            }
+            Ok(pos)
+        }
+        (_, None) => {
+            let mut keys = KeySet::default();
+            let mut item = 0;
+            while data.get(pos) != Some(&BREAK) {
+                if pos >= data.len() {
+                    return Err(truncated());
+                }
+                let end = skip_item(data, pos, depth + 1)?;
+                if major == 5 && item % 2 == 0 {
+                    keys.insert(data, pos..end)?;
+                }
+                pos = end;
+                item += 1;
+            }
+            Ok(pos + 1)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_index_ranges() {
+        let mut map: BTreeMap<Value, Value> = BTreeMap::new();
+        map.insert(Value::Integer(1), Value::Bytes(vec![1, 2, 3]));
+        map.insert(Value::Integer(2), Value::Array(vec![Value::Text("a".to_string()), Value::Null]));
+        map.insert(Value::Integer(3), Value::Tag(51195, Box::new(Value::Float(1.5))));
+        let data = serde_cbor::to_vec(&Value::Map(map.clone())).unwrap();
+
+        let index = MapIndex::parse(&data).unwrap();
+        assert_eq!(index.entries.len(), 3);
+        for ((key, range), (expected_key, value)) in index.entries.iter().zip(map.iter()) {
+            assert_eq!(key, expected_key);
+            assert_eq!(&serde_cbor::from_slice::<Value>(&data[range.clone()]).unwrap(), value);
+        }
+
+        // indefinite-length map {1: h'00'}
+        assert_eq!(MapIndex::parse(&[0xbf, 0x01, 0x41, 0x00, 0xff]).unwrap().entries.len(), 1);
+        assert!(MapIndex::parse(&data[..data.len() - 1]).is_err());
+        assert_eq!(
+            MapIndex::parse(&[data.as_slice(), &[0x00, 0x00]].concat()).err(),
+            Some(DecodeError::TrailingBytes(2))
+        );
+        // {1: 0, 1: 1}
+        assert!(matches!(MapIndex::parse(&[0xa2, 0x01, 0x00, 0x01, 0x01]), Err(DecodeError::DuplicateKey(_))));
+        // {1: {2: 0, 2: 1}}, and the same nested map indefinite-length
+        assert!(matches!(MapIndex::parse(&[0xa1, 0x01, 0xa2, 0x02, 0x00, 0x02, 0x01]), Err(DecodeError::DuplicateKey(_))));
+        assert!(matches!(
+            MapIndex::parse(&[0xa1, 0x01, 0xbf, 0x02, 0x00, 0x02, 0x01, 0xff]),
+            Err(DecodeError::DuplicateKey(_))
+        ));
+        // Non-canonical 0x1801 is the same key as 0x01
+        assert!(matches!(decode_value(&[0xa2, 0x01, 0x00, 0x18, 0x01, 0x01]), Err(DecodeError::DuplicateKey(_))));
+        // 305({1: 0, 1: 1}), [{}], h'00' 0x00
+        assert!(matches!(
+            decode_value(&[0xd9, 0x01, 0x31, 0xa2, 0x01, 0x00, 0x01, 0x01]),
+            Err(DecodeError::DuplicateKey(_))
+        ));
+        assert_eq!(decode_value(&[0x81, 0xa0]).unwrap(), Value::Array(vec![Value::Map(BTreeMap::new())]));
+        assert_eq!(decode_value(&[0x41, 0x00, 0x00]).err(), Some(DecodeError::TrailingBytes(1)));
+    }
+}
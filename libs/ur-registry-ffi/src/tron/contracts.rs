@@ -0,0 +1,263 @@
+//! Registry of well-known TRON contracts with their names and trusted ABI
+//! fragments, so signers show "USDT (verified)" instead of a raw address.
+//! The built-in entries can be extended at runtime.
+
+use crate::response::{PtrResponse, Response};
+use crate::tron::address::{decode_address, encode_address};
+#[cfg(feature = "protobuf")]
+use crate::tron::transaction::parse_request;
+#[cfg(feature = "protobuf")]
+use crate::tron::tron_sign_request::TronSignRequest;
+use crate::types::{PtrString, PtrVoid};
+use crate::utils::convert_ptr_string_to_string;
+
+use sha3::{Digest, Keccak256};
+use std::collections::BTreeMap;
+
+const TRC20_FUNCTIONS: [&str; 3] = [
+    "transfer(address,uint256)",
+    "approve(address,uint256)",
+    "transferFrom(address,address,uint256)",
+];
+
+// Mainnet deployments: (address, name, token decimals, functions)
+const BUILTIN_CONTRACTS: [(&str, &str, Option<u32>, &[&str]); 5] = [
+    ("TR7NHqjeKQxGTCi8q8ZY4pL8otSzgjLj6t", "USDT", Some(6), &TRC20_FUNCTIONS),
+    ("TEkxiTehnzSmSe2XqrBj4w32RUN966rdz8", "USDC", Some(6), &TRC20_FUNCTIONS),
+    ("TNUC9Qb1rRpS5CbWLmNMxXBjyFoydXjWFR", "WTRX", Some(6), &TRC20_FUNCTIONS),
+    ("TXJgMdjVX5dKiQaUi9QobwNxtSQaFqccvd", "JustLend jUSDT", Some(8), &[
+        "mint(uint256)",
+        "redeem(uint256)",
+        "redeemUnderlying(uint256)",
+        "borrow(uint256)",
+        "repayBorrow(uint256)",
+    ]),
+    ("TKzxdSv2FZKQrEqkKVgp5DcwEXBEKMg2Ax", "SunSwap V2 Router", None, &[
+        "swapExactTokensForTokens(uint256,uint256,address[],address,uint256)",
+        "swapTokensForExactTokens(uint256,uint256,address[],address,uint256)",
+        "swapExactETHForTokens(uint256,address[],address,uint256)",
+        "swapExactTokensForETH(uint256,uint256,address[],address,uint256)",
+        "addLiquidity(address,address,uint256,uint256,uint256,uint256,address,uint256)",
+        "removeLiquidity(address,address,uint256,uint256,uint256,address,uint256)",
+    ]),
+];
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct AbiFunction {
+    pub selector: [u8; 4],
+    /// Canonical signature, e.g. `transfer(address,uint256)`
+    pub signature: String,
+}
+
+impl AbiFunction {
+    pub fn new(signature: &str) -> Self {
+        let hash = Keccak256::digest(signature.as_bytes());
+        AbiFunction {
+            selector: hash[..4].try_into().unwrap(),
+            signature: signature.to_string(),
+        }
+    }
+
+    /// Function name without the argument list
+    pub fn get_name(&self) -> &str {
+        self.signature.split('(').next().unwrap_or_default()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct KnownContract {
+    /// The token symbol for tokens
+    pub name: String,
+    /// Set for tokens, whose amounts the signer shows scaled by it
+    pub decimals: Option<u32>,
+    pub functions: Vec<AbiFunction>,
+}
+
+impl KnownContract {
+    pub fn get_function(&self, selector: &[u8; 4]) -> Option<&AbiFunction> {
+        self.functions.iter().find(|f| f.selector == *selector)
+    }
+
+    pub fn get_label(&self) -> String {
+        format!("{} (verified)", self.name)
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ContractRegistry {
+    // Keyed by 21-byte address
+    contracts: BTreeMap<Vec<u8>, KnownContract>,
+}
+
+impl ContractRegistry {
+    /// Registry holding the built-in mainnet contracts
+    pub fn builtin() -> Self {
+        let mut registry = ContractRegistry::default();
+        for (address, name, decimals, functions) in BUILTIN_CONTRACTS.iter() {
+            registry.insert(address, name, *decimals, functions).expect("built-in contract address is valid");
+        }
+        registry
+    }
+
+    /// Adds or replaces a contract; `functions` are canonical signatures
+    pub fn register(&mut self, address: &str, name: &str, functions: &[&str]) -> Result<(), String> {
+        self.insert(address, name, None, functions)
+    }
+
+    /// Adds or replaces a TRC-20 token, whose transfers then show in
+    /// `symbol` scaled by `decimals`
+    pub fn register_token(&mut self, address: &str, symbol: &str, decimals: u32) -> Result<(), String> {
+        self.insert(address, symbol, Some(decimals), &TRC20_FUNCTIONS)
+    }
+
+    fn insert(&mut self, address: &str, name: &str, decimals: Option<u32>, functions: &[&str]) -> Result<(), String> {
+        let contract = KnownContract {
+            name: name.to_string(),
+            decimals,
+            functions: functions.iter().map(|f| AbiFunction::new(f)).collect(),
+        };
+        self.contracts.insert(decode_address(address)?, contract);
+        Ok(())
+    }
+
+    pub fn get(&self, address: &[u8]) -> Option<&KnownContract> {
+        self.contracts.get(address)
+    }
+
+    /// "USDT (verified)" for known contracts, the base58 address otherwise
+    pub fn get_label(&self, address: &[u8]) -> Result<String, String> {
+        match self.get(address) {
+            Some(contract) => Ok(contract.get_label()),
+            None => encode_address(address),
+        }
+    }
+}
+
+/// Label of the contract the first smart-contract call targets
+#[cfg(feature = "protobuf")]
+pub fn get_request_contract_label(request: &TronSignRequest, registry: &ContractRegistry) -> Result<Option<String>, String> {
+    for contract in parse_request(request)?.contracts.iter() {
+        if let Some(call) = contract.as_trigger_smart_contract()? {
+            return registry.get_label(&call.contract_address).map(Some);
+        }
+    }
+    Ok(None)
+}
+
+// ========== FFI Functions ==========
+
+/// Registry preloaded with the built-in contracts
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_contract_registry_new() -> PtrResponse {
+    Response::success_object(Box::into_raw(Box::new(ContractRegistry::builtin())) as PtrVoid).c_ptr()
+}
+
+/// `functions` are newline-separated canonical signatures
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_contract_registry_register(
+    registry: &mut ContractRegistry,
+    address: PtrString,
+    name: PtrString,
+    functions: PtrString,
+) -> PtrResponse {
+    let address = match convert_ptr_string_to_string(address).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    let name = match convert_ptr_string_to_string(name).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    let functions = match convert_ptr_string_to_string(functions).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    let functions: Vec<&str> = functions.lines().map(str::trim).filter(|f| !f.is_empty()).collect();
+    match registry.register(&address, &name, &functions) {
+        Ok(()) => Response::success_null(),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_contract_registry_register_token(
+    registry: &mut ContractRegistry,
+    address: PtrString,
+    symbol: PtrString,
+    decimals: u32,
+) -> PtrResponse {
+    let address = match convert_ptr_string_to_string(address).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    let symbol = match convert_ptr_string_to_string(symbol).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    match registry.register_token(&address, &symbol, decimals) {
+        Ok(()) => Response::success_null(),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_contract_registry_get_label(registry: &ContractRegistry, address: PtrString) -> PtrResponse {
+    let address = match convert_ptr_string_to_string(address).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    match decode_address(&address).and_then(|address| registry.get_label(&address)) {
+        Ok(label) => Response::success_string(label),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+/// `NOT_PRESENT` when the request calls no contract
+#[cfg(feature = "protobuf")]
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_request_get_contract_label(
+    tron_sign_request: &TronSignRequest,
+    registry: &ContractRegistry,
+) -> PtrResponse {
+    match get_request_contract_label(tron_sign_request, registry) {
+        Ok(label) => label.map_or(Response::not_present(), Response::success_string),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registry() {
+        let mut registry = ContractRegistry::builtin();
+        let usdt = decode_address("TR7NHqjeKQxGTCi8q8ZY4pL8otSzgjLj6t").unwrap();
+        assert_eq!(registry.get_label(&usdt).unwrap(), "USDT (verified)");
+        let transfer = registry.get(&usdt).unwrap().get_function(&[0xa9, 0x05, 0x9c, 0xbb]).unwrap();
+        assert_eq!(transfer.get_name(), "transfer");
+
+        let other_bytes = [0x41; 21];
+        let other = encode_address(&other_bytes).unwrap();
+        assert_eq!(registry.get_label(&other_bytes).unwrap(), other);
+        registry.register(&other, "Vault", &["deposit(uint256)"]).unwrap();
+        assert_eq!(registry.get_label(&other_bytes).unwrap(), "Vault (verified)");
+        assert!(registry.register("not an address", "Bad", &[]).is_err());
+
+        assert_eq!(registry.get(&usdt).unwrap().decimals, Some(6));
+        assert_eq!(registry.get(&other_bytes).unwrap().decimals, None);
+        registry.register_token(&other, "VLT", 18).unwrap();
+        let token = registry.get(&other_bytes).unwrap();
+        assert_eq!((token.name.as_str(), token.decimals), ("VLT", Some(18)));
+        assert!(token.get_function(&[0xa9, 0x05, 0x9c, 0xbb]).is_some());
+    }
+}
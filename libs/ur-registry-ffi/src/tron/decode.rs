@@ -0,0 +1,125 @@
+pub(crate) use crate::tron::cbor_index::decode_value;
+use crate::tron::cbor_index::MapIndex;
+
+use serde_cbor::Value;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Why a TRON registry payload was rejected
+#[derive(Clone, Debug, PartialEq)]
+pub enum DecodeError {
+    /// Not well-formed CBOR, truncated input included
+    InvalidCbor(String),
+    ExpectedMap,
+    /// The same top-level key appears twice; parsers disagree on which one wins
+    DuplicateKey(String),
+    /// Bytes left over after the top-level item
+    TrailingBytes(usize),
+    MissingField(&'static str),
+    InvalidType { field: &'static str, expected: &'static str },
+    InvalidValue { field: &'static str, reason: String },
+    UnsupportedVersion(u32),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::InvalidCbor(e) => write!(f, "Failed to decode CBOR: {}", e),
+            DecodeError::ExpectedMap => write!(f, "Expected CBOR map"),
+            DecodeError::DuplicateKey(key) => write!(f, "Duplicate CBOR map key: {}", key),
+            DecodeError::TrailingBytes(len) => write!(f, "{} trailing bytes after CBOR item", len),
+            DecodeError::MissingField(field) => write!(f, "Missing {}", field),
+            DecodeError::InvalidType { field, expected } => write!(f, "Invalid {}: expected {}", field, expected),
+            DecodeError::InvalidValue { field, reason } => write!(f, "Invalid {}: {}", field, reason),
+            DecodeError::UnsupportedVersion(version) => write!(
+                f,
+                "Unsupported protocol version {}, this build understands up to {}",
+                version,
+                crate::tron::version::PROTOCOL_VERSION
+            ),
+        }
+    }
+}
+
+impl DecodeError {
+    /// Variant name, for telemetry and bindings that branch on the failure
+    pub fn code(&self) -> &'static str {
+        match self {
+            DecodeError::InvalidCbor(_) => "InvalidCbor",
+            DecodeError::ExpectedMap => "ExpectedMap",
+            DecodeError::DuplicateKey(_) => "DuplicateKey",
+            DecodeError::TrailingBytes(_) => "TrailingBytes",
+            DecodeError::MissingField(_) => "MissingField",
+            DecodeError::InvalidType { .. } => "InvalidType",
+            DecodeError::InvalidValue { .. } => "InvalidValue",
+            DecodeError::UnsupportedVersion(_) => "UnsupportedVersion",
+        }
+    }
+}
+
+impl From<DecodeError> for String {
+    fn from(e: DecodeError) -> Self {
+        e.to_string()
+    }
+}
+
+pub(crate) fn invalid_value(field: &'static str, reason: impl Into<String>) -> DecodeError {
+    DecodeError::InvalidValue { field, reason: reason.into() }
+}
+
+/// Top-level map of a payload. Duplicate keys and trailing bytes are
+/// rejected before decoding: the map would silently keep only one of the
+/// keys, and other parsers may read what follows the map.
+pub(crate) fn decode_map(data: &[u8]) -> Result<BTreeMap<Value, Value>, DecodeError> {
+    MapIndex::parse(data)?;
+    match serde_cbor::from_slice(data).map_err(|e| DecodeError::InvalidCbor(e.to_string()))? {
+        Value::Map(map) => Ok(map),
+        _ => Err(DecodeError::ExpectedMap),
+    }
+}
+
+/// Entries whose keys this build doesn't know, kept so re-encoding a
+/// decoded payload doesn't drop fields added by newer implementations
+pub(crate) fn unknown_entries(map: &BTreeMap<Value, Value>, known: &[i128]) -> BTreeMap<Value, Value> {
+    map.iter()
+        .filter(|(k, _)| !matches!(k, Value::Integer(i) if known.contains(i)))
+        .map(|(k, v)| (k.clone(), v.clone()))
+        .collect()
+}
+
+// Typed accessors for a decoded map: absent keys are `None`, present keys
+// of the wrong type are errors rather than being treated as absent
+
+pub(crate) fn get_bytes(map: &BTreeMap<Value, Value>, key: i128, field: &'static str) -> Result<Option<Vec<u8>>, DecodeError> {
+    match map.get(&Value::Integer(key)) {
+        None => Ok(None),
+        Some(Value::Bytes(b)) => Ok(Some(b.clone())),
+        Some(_) => Err(DecodeError::InvalidType { field, expected: "bytes" }),
+    }
+}
+
+pub(crate) fn get_text(map: &BTreeMap<Value, Value>, key: i128, field: &'static str) -> Result<Option<String>, DecodeError> {
+    match map.get(&Value::Integer(key)) {
+        None => Ok(None),
+        Some(Value::Text(s)) => Ok(Some(s.clone())),
+        Some(_) => Err(DecodeError::InvalidType { field, expected: "text" }),
+    }
+}
+
+pub(crate) fn get_bool(map: &BTreeMap<Value, Value>, key: i128, field: &'static str) -> Result<Option<bool>, DecodeError> {
+    match map.get(&Value::Integer(key)) {
+        None => Ok(None),
+        Some(Value::Bool(b)) => Ok(Some(*b)),
+        Some(_) => Err(DecodeError::InvalidType { field, expected: "bool" }),
+    }
+}
+
+pub(crate) fn get_u32(map: &BTreeMap<Value, Value>, key: i128, field: &'static str) -> Result<Option<u32>, DecodeError> {
+    match map.get(&Value::Integer(key)) {
+        None => Ok(None),
+        Some(Value::Integer(i)) => u32::try_from(*i)
+            .map(Some)
+            .map_err(|_| invalid_value(field, format!("{} is out of range", i))),
+        Some(_) => Err(DecodeError::InvalidType { field, expected: "unsigned integer" }),
+    }
+}
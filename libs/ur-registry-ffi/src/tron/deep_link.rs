@@ -0,0 +1,300 @@
+//! Deep links for app-to-app signing on one phone: the requesting app opens
+//! `tronsign://sign?ur=<single-part UR>&callback=<url>` (or a scheme of its
+//! choosing), and the signer answers by opening the callback with the
+//! response UR appended as `ur`. Bare `ur:` links are parsed too, but can't
+//! carry a callback.
+
+use crate::response::{PtrResponse, Response};
+use crate::tron::tron_sign_request::{TronSignRequest, TRON_SIGN_REQUEST_TYPE};
+use crate::types::{PtrString, PtrVoid};
+use crate::ur_encoder::UREncoder;
+use crate::utils::convert_ptr_string_to_string;
+
+pub const DEEP_LINK_SCHEME: &str = "tronsign";
+const DEEP_LINK_PATH: &str = "sign";
+const UR_PARAMETER: &str = "ur";
+const CALLBACK_PARAMETER: &str = "callback";
+
+// Left as is in query values: RFC 3986 unreserved characters, plus the
+// separators of UR bodies and URLs that queries allow
+fn is_query_safe(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b"-._~:/".contains(&b)
+}
+
+pub fn percent_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| if is_query_safe(b) { (b as char).to_string() } else { format!("%{:02X}", b) })
+        .collect()
+}
+
+pub fn percent_decode(value: &str) -> Result<String, String> {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' => {
+                let hex = value.get(i + 1..i + 3).ok_or("Truncated percent escape")?;
+                decoded.push(u8::from_str_radix(hex, 16).map_err(|e| e.to_string())?);
+                i += 3;
+            }
+            b'+' => {
+                decoded.push(b' ');
+                i += 1;
+            }
+            b => {
+                decoded.push(b);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8(decoded).map_err(|e| e.to_string())
+}
+
+// RFC 3986 scheme: a letter, then letters, digits, '+', '-' and '.'
+fn check_scheme(scheme: &str) -> Result<(), String> {
+    let mut chars = scheme.chars();
+    let valid = chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c));
+    if !valid {
+        return Err(format!("Invalid deep link scheme {:?}", scheme));
+    }
+    Ok(())
+}
+
+// `<scheme>://...` without whitespace or control characters, so the signer
+// only ever opens an app or web link, never `javascript:` and the like
+fn check_callback(callback: &str) -> Result<(), String> {
+    let (scheme, rest) = callback.split_once("://").ok_or(format!("Invalid callback {:?}", callback))?;
+    check_scheme(scheme)?;
+    if rest.is_empty() || callback.chars().any(|c| c.is_whitespace() || c.is_control()) {
+        return Err(format!("Invalid callback {:?}", callback));
+    }
+    Ok(())
+}
+
+fn check_single_part(ur: &str) -> Result<(), String> {
+    let body = ur.to_lowercase();
+    match body.strip_prefix("ur:").map(|v| v.split('/').count()) {
+        Some(2) => Ok(()),
+        Some(_) => Err("Deep links carry single-part URs only".to_string()),
+        None => Err("Not a UR".to_string()),
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DeepLink {
+    pub ur: String,
+    pub callback: Option<String>,
+}
+
+impl DeepLink {
+    pub fn new(ur: String, callback: Option<String>) -> Result<Self, String> {
+        check_single_part(&ur)?;
+        if let Some(ref callback) = callback {
+            check_callback(callback)?;
+        }
+        Ok(DeepLink { ur, callback })
+    }
+
+    pub fn for_request(request: &TronSignRequest, callback: Option<String>) -> Result<Self, String> {
+        let message = request.encoded_bytes()?;
+        let ur = UREncoder::new(message, message.len() + 1, TRON_SIGN_REQUEST_TYPE)?.next_part()?;
+        DeepLink::new(ur, callback)
+    }
+
+    pub fn to_uri(&self, scheme: &str) -> Result<String, String> {
+        check_scheme(scheme)?;
+        let mut uri = format!("{}://{}?{}={}", scheme, DEEP_LINK_PATH, UR_PARAMETER, percent_encode(&self.ur));
+        if let Some(ref callback) = self.callback {
+            uri.push_str(&format!("&{}={}", CALLBACK_PARAMETER, percent_encode(callback)));
+        }
+        Ok(uri)
+    }
+
+    /// Parses `<scheme>://sign?ur=...` whatever the scheme, or a bare `ur:`
+    pub fn parse(uri: &str) -> Result<Self, String> {
+        let uri = uri.trim();
+        if uri.get(..3).is_some_and(|prefix| prefix.eq_ignore_ascii_case("ur:")) {
+            return DeepLink::new(uri.to_string(), None);
+        }
+        let (_, rest) = uri.split_once("://").ok_or("Not a deep link")?;
+        let (path, query) = rest.split_once('?').ok_or("Deep link has no payload")?;
+        if path.trim_end_matches('/') != DEEP_LINK_PATH {
+            return Err(format!("Unsupported deep link path {}", path));
+        }
+        let (mut ur, mut callback) = (None, None);
+        for pair in query.split('&') {
+            match pair.split_once('=') {
+                Some((UR_PARAMETER, value)) => ur = Some(percent_decode(value)?),
+                Some((CALLBACK_PARAMETER, value)) => callback = Some(percent_decode(value)?),
+                _ => {}
+            }
+        }
+        DeepLink::new(ur.ok_or("Deep link has no payload")?, callback.filter(|c| !c.is_empty()))
+    }
+
+    /// The callback with `response_ur` added to its query, ahead of any
+    /// fragment, for the signer to open
+    pub fn response_uri(&self, response_ur: &str) -> Result<Option<String>, String> {
+        check_single_part(response_ur)?;
+        Ok(self.callback.as_ref().map(|callback| {
+            let (base, fragment) = match callback.split_once('#') {
+                Some((base, fragment)) => (base, format!("#{}", fragment)),
+                None => (callback.as_str(), String::new()),
+            };
+            let separator = if base.contains('?') { '&' } else { '?' };
+            format!("{}{}{}={}{}", base, separator, UR_PARAMETER, percent_encode(response_ur), fragment)
+        }))
+    }
+}
+
+fn optional_string(value: PtrString) -> Result<Option<String>, String> {
+    convert_ptr_string_to_string(value).map(|v| Some(v).filter(|s| !s.is_empty()))
+}
+
+// ========== FFI Functions ==========
+
+/// Deep link opening `ur`; an empty `scheme` means `tronsign` and an empty
+/// `callback` none
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_deep_link_build(scheme: PtrString, ur: PtrString, callback: PtrString) -> PtrResponse {
+    let scheme = match optional_string(scheme).map_err(|e| Response::error(e)) {
+        Ok(v) => v.unwrap_or_else(|| DEEP_LINK_SCHEME.to_string()),
+        Err(e) => return e.c_ptr(),
+    };
+    let ur = match convert_ptr_string_to_string(ur).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    let callback = match optional_string(callback).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    match DeepLink::new(ur, callback).and_then(|link| link.to_uri(&scheme)) {
+        Ok(uri) => Response::success_string(uri),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+/// Deep link carrying the request, with the same conventions as
+/// `tron_deep_link_build`
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_request_get_deep_link(
+    tron_sign_request: &TronSignRequest,
+    scheme: PtrString,
+    callback: PtrString,
+) -> PtrResponse {
+    let scheme = match optional_string(scheme).map_err(|e| Response::error(e)) {
+        Ok(v) => v.unwrap_or_else(|| DEEP_LINK_SCHEME.to_string()),
+        Err(e) => return e.c_ptr(),
+    };
+    let callback = match optional_string(callback).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    match DeepLink::for_request(tron_sign_request, callback).and_then(|link| link.to_uri(&scheme)) {
+        Ok(uri) => Response::success_string(uri),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_deep_link_parse(uri: PtrString) -> PtrResponse {
+    let uri = match convert_ptr_string_to_string(uri).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    match DeepLink::parse(&uri) {
+        Ok(link) => Response::success_object(Box::into_raw(Box::new(link)) as PtrVoid),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+/// The single-part UR; `ur_file_read` turns it into a registry object
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_deep_link_get_ur(deep_link: &DeepLink) -> PtrResponse {
+    Response::success_string(deep_link.ur.clone()).c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_deep_link_get_callback(deep_link: &DeepLink) -> PtrResponse {
+    deep_link
+        .callback
+        .as_ref()
+        .map_or(Response::not_present(), |v| Response::success_string(v.clone()))
+        .c_ptr()
+}
+
+/// URI answering the link with `response_ur`, or `NOT_PRESENT` when the
+/// requester left no callback
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_deep_link_get_response_uri(deep_link: &DeepLink, response_ur: PtrString) -> PtrResponse {
+    let response_ur = match convert_ptr_string_to_string(response_ur).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    match deep_link.response_uri(&response_ur) {
+        Ok(uri) => uri.map_or(Response::not_present(), Response::success_string),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deep_link_round_trip() {
+        let request = TronSignRequest::new_with_derived_request_id(
+            vec![0x0a, 0x02], 1, "m/44'/195'/0'/0/0".to_string(), None, None, None,
+        );
+        let callback = "https://dapp.example/return?session=a&b".to_string();
+        let link = DeepLink::for_request(&request, Some(callback.clone())).unwrap();
+        let uri = link.to_uri(DEEP_LINK_SCHEME).unwrap();
+        assert!(uri.starts_with("tronsign://sign?ur=ur:tron-sign-request/"));
+        assert!(uri.ends_with("&callback=https://dapp.example/return%3Fsession%3Da%26b"));
+        assert_eq!(DeepLink::parse(&uri).unwrap(), link);
+
+        let response = "ur:tron-signature/oyadtpdagd";
+        assert_eq!(
+            link.response_uri(response).unwrap().unwrap(),
+            format!("{}&ur={}", callback, response)
+        );
+
+        let bare = DeepLink::parse(&link.ur.to_uppercase()).unwrap();
+        assert_eq!((bare.callback.as_deref(), bare.response_uri(response).unwrap()), (None, None));
+        assert!(DeepLink::parse("tronsign://sign?callback=x").is_err());
+        assert!(DeepLink::parse("tronsign://sign?ur=ur:bytes/1-2/lpad").is_err());
+        assert_eq!(percent_decode("a%20b+c").unwrap(), "a b c");
+    }
+
+    #[test]
+    fn test_deep_link_checks() {
+        let ur = "ur:tron-signature/oyadtpdagd";
+        let link = DeepLink::new(ur.to_string(), Some("myapp://done?state=1#top".to_string())).unwrap();
+        assert_eq!(link.response_uri(ur).unwrap().unwrap(), format!("myapp://done?state=1&ur={}#top", ur));
+        let link = DeepLink::new(ur.to_string(), Some("myapp://done#top".to_string())).unwrap();
+        assert_eq!(link.response_uri(ur).unwrap().unwrap(), format!("myapp://done?ur={}#top", ur));
+
+        assert!(link.to_uri("my-app+v2.x").is_ok());
+        assert!(link.to_uri("").is_err());
+        assert!(link.to_uri("evil://x?").is_err());
+        assert!(link.to_uri("2fa").is_err());
+        for callback in ["javascript:alert(1)", "://x", "myapp://", "https://a b", "https://a\nb"] {
+            assert!(DeepLink::new(ur.to_string(), Some(callback.to_string())).is_err(), "{}", callback);
+        }
+        assert!(DeepLink::parse(&format!("tronsign://sign?ur={}&callback=javascript:alert(1)", ur)).is_err());
+    }
+}
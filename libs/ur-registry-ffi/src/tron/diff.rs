@@ -0,0 +1,229 @@
+//! Field-by-field comparison of two sign requests, for when a dApp
+//! re-submits a "modified" transaction and the user or support needs to
+//! see exactly what changed since the reviewed one.
+//!
+//! Requests flatten into named fields, e.g. `derivation_path`,
+//! `metadata.memo` or `transaction.contracts[0].amount`. Transaction
+//! sign_data is compared by its decoded fields, falling back to the raw
+//! bytes when either side doesn't decode. Decoded sign_data also carries a
+//! `sign_data.sha256` digest, so bytes no named field covers (owner
+//! addresses, fields this build doesn't parse) still show as a change.
+
+use crate::response::{PtrResponse, Response};
+#[cfg(feature = "protobuf")]
+use crate::tron::address::encode_address;
+use crate::tron::tron_sign_request::{MetadataValue, TronSignRequest};
+
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct FieldChange {
+    pub field: String,
+    /// `None` when the field is absent from that side
+    pub a: Option<String>,
+    pub b: Option<String>,
+}
+
+#[cfg(feature = "protobuf")]
+fn transaction_fields(request: &TronSignRequest, fields: &mut BTreeMap<String, String>) -> bool {
+    use crate::tron::transaction::parse_request;
+
+    let transaction = match parse_request(request) {
+        Ok(transaction) => transaction,
+        Err(_) => return false,
+    };
+    let mut put = |name: &str, value: String| {
+        fields.insert(format!("transaction.{}", name), value);
+    };
+    put("ref_block_bytes", hex::encode(&transaction.ref_block_bytes));
+    put("ref_block_num", transaction.ref_block_num.to_string());
+    put("ref_block_hash", hex::encode(&transaction.ref_block_hash));
+    put("expiration", transaction.expiration.to_string());
+    put("timestamp", transaction.timestamp.to_string());
+    put("fee_limit", transaction.fee_limit.to_string());
+    if let Some(memo) = transaction.get_memo() {
+        put("memo", memo);
+    }
+    for (i, contract) in transaction.contracts.iter().enumerate() {
+        let mut put = |name: &str, value: String| put(&format!("contracts[{}].{}", i, name), value);
+        let kind = contract.get_kind();
+        put("type", kind.as_str().map_or(contract.contract_type.to_string(), str::to_string));
+        put("permission_id", contract.permission_id.to_string());
+        let transfer = contract.get_transfer().ok().flatten();
+        let call = contract.as_trigger_smart_contract().ok().flatten();
+        if let Some(ref transfer) = transfer {
+            put("to", encode_address(&transfer.to).unwrap_or_else(|_| hex::encode(&transfer.to)));
+            put("amount", hex::encode(&transfer.amount));
+        }
+        match call {
+            Some(call) => {
+                put("owner_address", encode_address(&call.owner_address).unwrap_or_else(|_| hex::encode(&call.owner_address)));
+                put("contract_address", encode_address(&call.contract_address).unwrap_or_else(|_| hex::encode(&call.contract_address)));
+                put("call_value", call.call_value.to_string());
+                put("data", hex::encode(&call.data));
+            }
+            None if transfer.is_none() => put("value", hex::encode(&contract.value)),
+            None => {}
+        }
+    }
+    true
+}
+
+#[cfg(not(feature = "protobuf"))]
+fn transaction_fields(_request: &TronSignRequest, _fields: &mut BTreeMap<String, String>) -> bool {
+    false
+}
+
+/// Every present field of `request`, by name; `decode` says whether to
+/// compare transaction sign_data by its decoded fields
+fn flatten(request: &TronSignRequest, decode: bool) -> BTreeMap<String, String> {
+    let mut fields = BTreeMap::new();
+    let mut put = |name: &str, value: Option<String>| {
+        if let Some(value) = value {
+            fields.insert(name.to_string(), value);
+        }
+    };
+    put("request_id", request.get_request_id().map(hex::encode));
+    put("data_type", Some(request.get_data_type().to_string()));
+    put("derivation_path", Some(request.get_derivation_path().to_string()));
+    put("xfp", request.get_xfp().map(|xfp| hex::encode(xfp.to_be_bytes())));
+    put("address", request.get_address().cloned());
+    let origin = request.get_origin();
+    put("origin", origin.and_then(|o| o.app.clone()));
+    put("origin.dapp_url", origin.and_then(|o| o.dapp_url.clone()));
+    put("origin.device", origin.and_then(|o| o.device.clone()));
+    put("version", request.get_version().map(|v| v.to_string()));
+    put("wallet_id", request.get_wallet_id().map(hex::encode));
+    put("block_header", request.get_block_header().map(hex::encode));
+    put("fee_payer", request.get_fee_payer().cloned());
+    put("salt", request.get_salt().map(hex::encode));
+    put("mac", request.get_mac().map(hex::encode));
+    for (key, value) in request.get_metadata().iter() {
+        let value = match value {
+            MetadataValue::Text(text) => text.clone(),
+            MetadataValue::Bytes(bytes) => format!("0x{}", hex::encode(bytes)),
+        };
+        fields.insert(format!("metadata.{}", key), value);
+    }
+    for (key, value) in request.get_unknown_fields().iter() {
+        let value = serde_cbor::to_vec(value).map(hex::encode).unwrap_or_default();
+        fields.insert(format!("unknown.{:?}", key), value);
+    }
+    if decode && transaction_fields(request, &mut fields) {
+        fields.insert("sign_data.sha256".to_string(), hex::encode(Sha256::digest(request.get_sign_data())));
+    } else {
+        fields.insert("sign_data".to_string(), hex::encode(request.get_sign_data()));
+    }
+    fields
+}
+
+fn decodes(request: &TronSignRequest) -> bool {
+    let mut fields = BTreeMap::new();
+    transaction_fields(request, &mut fields)
+}
+
+/// Fields that differ, in name order
+pub fn diff(a: &TronSignRequest, b: &TronSignRequest) -> Vec<FieldChange> {
+    let decode = decodes(a) && decodes(b);
+    let (a, b) = (flatten(a, decode), flatten(b, decode));
+    let mut names: Vec<&String> = a.keys().chain(b.keys()).collect();
+    names.sort();
+    names.dedup();
+    names
+        .into_iter()
+        .filter(|name| a.get(*name) != b.get(*name))
+        .map(|name| FieldChange { field: name.clone(), a: a.get(name).cloned(), b: b.get(name).cloned() })
+        .collect()
+}
+
+// ========== FFI Functions ==========
+
+/// JSON `{"identical", "changes": [{"field", "a", "b"}]}`, with `a` or `b`
+/// null for a field only the other request has
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_request_diff(a: &TronSignRequest, b: &TronSignRequest) -> PtrResponse {
+    let changes: Vec<_> = diff(a, b)
+        .into_iter()
+        .map(|c| json!({ "field": c.field, "a": c.a, "b": c.b }))
+        .collect();
+    Response::success_string(json!({ "identical": changes.is_empty(), "changes": changes }).to_string()).c_ptr()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff() {
+        let path = "m/44'/195'/0'/0/0".to_string();
+        let a = TronSignRequest::new(Some(vec![1; 16]), b"Sign in".to_vec(), 2, path.clone(), None, None, Some("dapp".to_string()));
+        assert!(diff(&a, &a.clone()).is_empty());
+
+        let mut b = TronSignRequest::new(Some(vec![1; 16]), b"Sign in!".to_vec(), 2, path.clone(), Some(0x12345678), None, None);
+        b.set_metadata("memo".to_string(), MetadataValue::Text("hi".to_string()));
+        let changes = diff(&a, &b);
+        let fields: Vec<&str> = changes.iter().map(|c| c.field.as_str()).collect();
+        assert_eq!(fields, vec!["metadata.memo", "origin", "sign_data", "xfp"]);
+        assert_eq!(changes[1], FieldChange { field: "origin".to_string(), a: Some("dapp".to_string()), b: None });
+        assert_eq!(changes[3].b.as_deref(), Some("12345678"));
+
+        #[cfg(feature = "protobuf")]
+        {
+            use crate::tron::protobuf::encode::{bytes_field, varint_field};
+            use crate::tron::transaction::TRANSFER_CONTRACT;
+            let transfer = |owner: u8, amount| {
+                let mut value = Vec::new();
+                let mut owner_address = [owner; 21];
+                owner_address[0] = 0x41;
+                bytes_field(1, &owner_address, &mut value);
+                bytes_field(2, &[0x41; 21], &mut value);
+                varint_field(3, amount, &mut value);
+                let mut any = Vec::new();
+                bytes_field(2, &value, &mut any);
+                let mut contract = Vec::new();
+                varint_field(1, TRANSFER_CONTRACT, &mut contract);
+                bytes_field(2, &any, &mut contract);
+                let mut raw_data = Vec::new();
+                bytes_field(11, &contract, &mut raw_data);
+                TronSignRequest::new(Some(vec![1; 16]), raw_data, 1, path.clone(), None, None, None)
+            };
+            let changes = diff(&transfer(0x41, 1_000_000), &transfer(0x41, 9_000_000));
+            let fields: Vec<&str> = changes.iter().map(|c| c.field.as_str()).collect();
+            assert_eq!(fields, vec!["sign_data.sha256", "transaction.contracts[0].amount"]);
+            assert_eq!(changes[1].b.as_deref(), Some("0000000000895440"));
+        }
+    }
+
+    #[cfg(feature = "protobuf")]
+    #[test]
+    fn test_diff_undisplayed_bytes() {
+        use crate::tron::protobuf::encode::{bytes_field, varint_field};
+        use crate::tron::transaction::TRANSFER_CONTRACT;
+        let request = |owner: u8, ref_block_num: u64| {
+            let mut value = Vec::new();
+            let mut owner_address = [owner; 21];
+            owner_address[0] = 0x41;
+            bytes_field(1, &owner_address, &mut value);
+            bytes_field(2, &[0x41; 21], &mut value);
+            varint_field(3, 1_000_000, &mut value);
+            let mut any = Vec::new();
+            bytes_field(2, &value, &mut any);
+            let mut contract = Vec::new();
+            varint_field(1, TRANSFER_CONTRACT, &mut contract);
+            bytes_field(2, &any, &mut contract);
+            let mut raw_data = Vec::new();
+            varint_field(3, ref_block_num, &mut raw_data);
+            bytes_field(11, &contract, &mut raw_data);
+            TronSignRequest::new(Some(vec![1; 16]), raw_data, 1, "m/44'/195'/0'/0/0".to_string(), None, None, None)
+        };
+        let changes = diff(&request(1, 7), &request(2, 7));
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].field, "sign_data.sha256");
+
+        let fields: Vec<String> = diff(&request(1, 7), &request(1, 8)).into_iter().map(|c| c.field).collect();
+        assert_eq!(fields, vec!["sign_data.sha256", "transaction.ref_block_num"]);
+    }
+}
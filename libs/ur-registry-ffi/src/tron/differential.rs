@@ -0,0 +1,191 @@
+//! Cross-checks this crate against another ur-registry implementation
+//! (e.g. the TypeScript one) through JSON vectors:
+//!
+//! `[{"type": "tron-sign-request", "cbor": "<hex>", "fields": {...}}]`
+//!
+//! `fields` is how the other implementation interpreted `cbor`; byte
+//! strings are hex. `generate_vectors` writes the same format for the
+//! other side to check against.
+
+use crate::tron::origin::Origin;
+use crate::tron::tron_sign_request::{TronSignRequest, TRON_SIGN_REQUEST_TYPE};
+use crate::tron::tron_signature::{TronSignature, TRON_SIGNATURE_TYPE};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Vector {
+    #[serde(rename = "type")]
+    pub ur_type: String,
+    pub cbor: String,
+    pub fields: Value,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Divergence {
+    pub index: usize,
+    pub field: String,
+    pub expected: Value,
+    pub actual: Value,
+}
+
+/// Decodes and re-encodes every vector, reporting each field whose
+/// interpretation differs and any re-encoding that isn't byte-identical
+pub fn check_vectors(vectors: &str) -> Result<Vec<Divergence>, String> {
+    let vectors: Vec<Vector> = serde_json::from_str(vectors).map_err(|e| format!("Invalid vectors: {}", e))?;
+    let mut divergences = Vec::new();
+
+    for (index, vector) in vectors.iter().enumerate() {
+        let cbor = hex::decode(&vector.cbor).map_err(|e| format!("Vector {}: {}", index, e))?;
+        let (fields, reencoded) = match describe(&vector.ur_type, cbor.clone()) {
+            Ok(v) => v,
+            Err(e) => {
+                divergences.push(Divergence {
+                    index,
+                    field: "decode".to_string(),
+                    expected: json!("ok"),
+                    actual: json!(e),
+                });
+                continue;
+            }
+        };
+
+        if let Value::Object(expected) = &vector.fields {
+            for (field, expected) in expected {
+                let actual = fields.get(field).cloned().unwrap_or(Value::Null);
+                if actual != *expected {
+                    divergences.push(Divergence {
+                        index,
+                        field: field.clone(),
+                        expected: expected.clone(),
+                        actual,
+                    });
+                }
+            }
+        }
+
+        if reencoded != cbor {
+            divergences.push(Divergence {
+                index,
+                field: "cbor".to_string(),
+                expected: json!(vector.cbor),
+                actual: json!(hex::encode(reencoded)),
+            });
+        }
+    }
+    Ok(divergences)
+}
+
+/// `count` random vectors per type, reproducible from `seed`
+pub fn generate_vectors(seed: u64, count: usize) -> Result<String, String> {
+    let mut rng = XorShift(seed.max(1));
+    let mut vectors = Vec::new();
+
+    for _ in 0..count {
+        let path = format!(
+            "m/44'/195'/{}'/{}/{}",
+            rng.next() % 8,
+            rng.next() % 2,
+            rng.next() % 1000
+        );
+        let sign_data_len = 1 + (rng.next() % 300) as usize;
+        let request = TronSignRequest::new(
+            Some(rng.bytes(16)),
+            rng.bytes(sign_data_len),
+            1 + (rng.next() % 3) as u32,
+            path,
+            None,
+            Some("TXYZopYRdj2D9XRtbG411XZZ3kM5VkAeBf".to_string()).filter(|_| rng.next() & 1 == 0),
+            Some("differential".to_string()).filter(|_| rng.next() & 1 == 0),
+        );
+        vectors.push(vector(TRON_SIGN_REQUEST_TYPE, request.to_bytes()?)?);
+
+        let signature = TronSignature::new(Some(rng.bytes(16)), rng.bytes(65));
+        vectors.push(vector(TRON_SIGNATURE_TYPE, signature.to_bytes()?)?);
+    }
+    serde_json::to_string_pretty(&vectors).map_err(|e| e.to_string())
+}
+
+fn vector(ur_type: &str, cbor: Vec<u8>) -> Result<Vector, String> {
+    let (fields, _) = describe(ur_type, cbor.clone())?;
+    Ok(Vector {
+        ur_type: ur_type.to_string(),
+        cbor: hex::encode(cbor),
+        fields,
+    })
+}
+
+/// Field interpretation and re-encoded bytes
+fn describe(ur_type: &str, cbor: Vec<u8>) -> Result<(Value, Vec<u8>), String> {
+    match ur_type {
+        TRON_SIGN_REQUEST_TYPE => {
+            let request = TronSignRequest::try_from(cbor)?;
+            let fields = json!({
+                "requestId": request.get_request_id().map(hex::encode),
+                "signData": hex::encode(request.get_sign_data()),
+                "dataType": request.get_data_type(),
+                "derivationPath": request.get_derivation_path(),
+                "address": request.get_address(),
+                "origin": request.get_origin().map(Origin::to_json),
+            });
+            Ok((fields, request.to_bytes()?))
+        }
+        TRON_SIGNATURE_TYPE => {
+            let signature = TronSignature::try_from(cbor)?;
+            let fields = json!({
+                "requestId": signature.get_request_id().map(hex::encode),
+                "signature": hex::encode(signature.get_signature()),
+            });
+            Ok((fields, signature.to_bytes()?))
+        }
+        t => Err(format!("type {} is not supported yet", t)),
+    }
+}
+
+struct XorShift(u64);
+
+impl XorShift {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn bytes(&mut self, len: usize) -> Vec<u8> {
+        (0..len).map(|_| self.next() as u8).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generated_vectors_agree() {
+        let vectors = generate_vectors(0x7e0, 32).unwrap();
+        assert_eq!(check_vectors(&vectors).unwrap(), vec![]);
+
+        let mut tampered: Vec<Vector> = serde_json::from_str(&vectors).unwrap();
+        tampered[0].fields["dataType"] = json!(99);
+        let divergences = check_vectors(&serde_json::to_string(&tampered).unwrap()).unwrap();
+        assert_eq!(divergences.len(), 1);
+        assert_eq!(divergences[0].field, "dataType");
+    }
+
+    /// The committed vectors are byte-assembled from the wire layout rather
+    /// than written by this crate's encoder. A file from the other
+    /// implementation is checked too:
+    /// `TRON_DIFF_VECTORS=path/to/vectors.json cargo test --features differential`
+    #[test]
+    fn test_external_vectors() {
+        let mut paths = vec![format!("{}/fixtures/differential/vectors.json", env!("CARGO_MANIFEST_DIR"))];
+        paths.extend(std::env::var("TRON_DIFF_VECTORS"));
+        for path in paths {
+            let vectors = std::fs::read_to_string(&path).unwrap();
+            let divergences = check_vectors(&vectors).unwrap();
+            assert!(divergences.is_empty(), "{}: {:#?}", path, divergences);
+        }
+    }
+}
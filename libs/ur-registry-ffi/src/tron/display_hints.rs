@@ -0,0 +1,183 @@
+//! Rendering hints a hot wallet attaches to a request as metadata: the
+//! user's locale and the unit TRX amounts are shown in. A signer shared by
+//! users in different locales honors them in summaries unless its caller
+//! passes a locale of its own.
+//!
+//! Hints are advisory: values this build doesn't understand are ignored
+//! rather than failing the request.
+
+use crate::response::{PtrResponse, Response};
+use crate::tron::amount::{format_units, TRX_DECIMALS};
+use crate::tron::tron_sign_request::{MetadataValue, TronSignRequest};
+use crate::types::PtrString;
+use crate::utils::convert_ptr_string_to_string;
+
+// Metadata keys carrying the hints
+pub const LOCALE_HINT_KEY: &str = "display.locale";
+pub const CURRENCY_HINT_KEY: &str = "display.currency";
+
+// Languages writing 1,5 rather than 1.5
+const COMMA_DECIMAL_LANGUAGES: [&str; 17] =
+    ["cs", "da", "de", "es", "fi", "fr", "id", "it", "nb", "nl", "pl", "pt", "ru", "sv", "tr", "uk", "vi"];
+
+pub fn uses_decimal_comma(locale: &str) -> bool {
+    let language = locale.split(['-', '_']).next().unwrap_or_default().to_ascii_lowercase();
+    COMMA_DECIMAL_LANGUAGES.contains(&language.as_str())
+}
+
+/// `amount` as written with a '.', with the decimal separator of `locale`
+pub fn localize_amount(amount: &str, locale: &str) -> String {
+    if uses_decimal_comma(locale) {
+        amount.replace('.', ",")
+    } else {
+        amount.to_string()
+    }
+}
+
+/// Unit TRX amounts are shown in
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum CurrencyDisplay {
+    #[default]
+    Trx,
+    Sun,
+}
+
+impl CurrencyDisplay {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "trx" => Some(CurrencyDisplay::Trx),
+            "sun" => Some(CurrencyDisplay::Sun),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CurrencyDisplay::Trx => "trx",
+            CurrencyDisplay::Sun => "sun",
+        }
+    }
+
+    /// Amount and symbol of the big-endian SUN amount `sun` in this unit
+    pub fn format_sun(&self, sun: &[u8]) -> Result<(String, &'static str), String> {
+        match self {
+            CurrencyDisplay::Trx => Ok((format_units(sun, TRX_DECIMALS)?, "TRX")),
+            CurrencyDisplay::Sun => Ok((format_units(sun, 0)?, "SUN")),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DisplayHints {
+    /// BCP 47 tag such as `de-DE`
+    pub locale: Option<String>,
+    pub currency: CurrencyDisplay,
+}
+
+fn text_metadata<'a>(request: &'a TronSignRequest, key: &str) -> Option<&'a str> {
+    match request.get_metadata().get(key) {
+        Some(MetadataValue::Text(value)) if !value.trim().is_empty() => Some(value.trim()),
+        _ => None,
+    }
+}
+
+impl DisplayHints {
+    pub fn from_request(request: &TronSignRequest) -> Self {
+        DisplayHints {
+            locale: text_metadata(request, LOCALE_HINT_KEY).map(str::to_string),
+            currency: text_metadata(request, CURRENCY_HINT_KEY).and_then(CurrencyDisplay::parse).unwrap_or_default(),
+        }
+    }
+
+    /// Writes the hints to `request`; no locale clears one set before
+    pub fn apply(&self, request: &mut TronSignRequest) {
+        match self.locale {
+            Some(ref locale) => request.set_metadata(LOCALE_HINT_KEY.to_string(), MetadataValue::Text(locale.clone())),
+            None => {
+                request.remove_metadata(LOCALE_HINT_KEY);
+            }
+        }
+        request.set_metadata(CURRENCY_HINT_KEY.to_string(), MetadataValue::Text(self.currency.as_str().to_string()));
+    }
+
+    /// `locale` when the caller gives one, else the request's hint
+    pub fn effective_locale<'a>(&'a self, locale: &'a str) -> &'a str {
+        match locale.trim() {
+            "" => self.locale.as_deref().unwrap_or_default(),
+            locale => locale,
+        }
+    }
+}
+
+// ========== FFI Functions ==========
+
+/// `locale` is a BCP 47 tag, empty to clear it; `currency` is `trx`
+/// or `sun`
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_request_set_display_hints(
+    tron_sign_request: &mut TronSignRequest,
+    locale: PtrString,
+    currency: PtrString,
+) -> PtrResponse {
+    let locale = match convert_ptr_string_to_string(locale).map_err(|e| Response::error(e)) {
+        Ok(v) => Some(v).filter(|s| !s.trim().is_empty()),
+        Err(e) => return e.c_ptr(),
+    };
+    let currency = match convert_ptr_string_to_string(currency).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    let currency = match CurrencyDisplay::parse(&currency) {
+        Some(currency) => currency,
+        None => return Response::error(format!("Invalid currency display: {}", currency)).c_ptr(),
+    };
+    DisplayHints { locale, currency }.apply(tron_sign_request);
+    Response::success_null().c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_request_get_locale_hint(tron_sign_request: &TronSignRequest) -> PtrResponse {
+    DisplayHints::from_request(tron_sign_request)
+        .locale
+        .map_or(Response::not_present(), Response::success_string)
+        .c_ptr()
+}
+
+/// `trx` or `sun`; `trx` when the request carries no hint
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_request_get_currency_hint(tron_sign_request: &TronSignRequest) -> PtrResponse {
+    Response::success_string(DisplayHints::from_request(tron_sign_request).currency.as_str().to_string()).c_ptr()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display_hints() {
+        let mut request = TronSignRequest::new(None, b"hi".to_vec(), 2, "m/44'/195'/0'/0/0".to_string(), None, None, None);
+        assert_eq!(DisplayHints::from_request(&request), DisplayHints::default());
+
+        let hints = DisplayHints { locale: Some("de-DE".to_string()), currency: CurrencyDisplay::Sun };
+        hints.apply(&mut request);
+        let mut decoded = TronSignRequest::try_from(request.to_bytes().unwrap()).unwrap();
+        assert_eq!(DisplayHints::from_request(&decoded), hints);
+        assert_eq!(hints.effective_locale(""), "de-DE");
+        assert_eq!(hints.effective_locale("en-US"), "en-US");
+
+        let cleared = DisplayHints { locale: None, currency: CurrencyDisplay::Sun };
+        cleared.apply(&mut decoded);
+        assert!(!decoded.get_metadata().contains_key(LOCALE_HINT_KEY));
+        let decoded = TronSignRequest::try_from(decoded.to_bytes().unwrap()).unwrap();
+        assert_eq!(DisplayHints::from_request(&decoded), cleared);
+        assert_eq!(request.remove_metadata("missing"), None);
+
+        request.set_metadata(CURRENCY_HINT_KEY.to_string(), MetadataValue::Text("btc".to_string()));
+        assert_eq!(DisplayHints::from_request(&request).currency, CurrencyDisplay::Trx);
+        assert_eq!(CurrencyDisplay::Sun.format_sun(&1_500_000u64.to_be_bytes()).unwrap(), ("1500000".to_string(), "SUN"));
+        assert_eq!(localize_amount("1.5", "fr"), "1,5");
+    }
+}
@@ -0,0 +1,412 @@
+//! JSON-RPC 2.0 envelopes for sign requests and their answers, so servers
+//! speaking JSON-RPC to wallets transcode to and from UR payloads in one
+//! call. The message id is the hex request id.
+//!
+//! Every envelope also carries the payload as a transport string (`ur`),
+//! which is preferred when parsing, so nothing is lost in a round trip.
+//! Without it, requests are rebuilt from their params and signatures from
+//! their result.
+//!
+//! Rejections become errors with EIP-1193 codes: 4001 user declined, 4100
+//! policy violation, 4200 unsupported request, -32602 invalid request, and
+//! -32000 for reasons this build doesn't know.
+
+use crate::response::{PtrResponse, Response};
+use crate::tron::origin::Origin;
+use crate::tron::transport;
+use crate::tron::tron_sign_rejection::{RejectionReason, TronSignRejection, TRON_SIGN_REJECTION_TYPE};
+use crate::tron::tron_sign_request::{DataType, TronSignRequest};
+use crate::tron::tron_signature::TronSignature;
+use crate::types::{PtrString, PtrVoid};
+use crate::utils::convert_ptr_string_to_string;
+
+use serde_json::{json, Map, Value};
+
+pub const JSON_RPC_VERSION: &str = "2.0";
+pub const SIGN_TRANSACTION_METHOD: &str = "tron_signTransaction";
+pub const SIGN_MESSAGE_METHOD: &str = "tron_signMessage";
+pub const SIGN_TYPED_DATA_METHOD: &str = "tron_signTypedData";
+const UNKNOWN_REASON_CODE: i64 = -32000;
+
+fn method_name(data_type: u32) -> Result<&'static str, String> {
+    match DataType::from_u32(data_type)? {
+        DataType::Transaction => Ok(SIGN_TRANSACTION_METHOD),
+        DataType::Message => Ok(SIGN_MESSAGE_METHOD),
+        DataType::TypedData => Ok(SIGN_TYPED_DATA_METHOD),
+    }
+}
+
+fn method_data_type(method: &str) -> Result<DataType, String> {
+    match method {
+        SIGN_TRANSACTION_METHOD => Ok(DataType::Transaction),
+        SIGN_MESSAGE_METHOD => Ok(DataType::Message),
+        SIGN_TYPED_DATA_METHOD => Ok(DataType::TypedData),
+        _ => Err(format!("Unsupported JSON-RPC method {}", method)),
+    }
+}
+
+fn error_code(reason: Option<RejectionReason>) -> i64 {
+    match reason {
+        Some(RejectionReason::UserDeclined) => 4001,
+        Some(RejectionReason::PolicyViolation) => 4100,
+        Some(RejectionReason::UnsupportedRequest) => 4200,
+        Some(RejectionReason::InvalidRequest) => -32602,
+        None => UNKNOWN_REASON_CODE,
+    }
+}
+
+fn code_reason(code: i64) -> Option<RejectionReason> {
+    [
+        RejectionReason::UserDeclined,
+        RejectionReason::PolicyViolation,
+        RejectionReason::UnsupportedRequest,
+        RejectionReason::InvalidRequest,
+    ]
+    .into_iter()
+    .find(|reason| error_code(Some(*reason)) == code)
+}
+
+fn id_value(request_id: Option<&Vec<u8>>) -> Value {
+    request_id.map_or(Value::Null, |id| json!(hex::encode(id)))
+}
+
+/// Hex message id; null and absent ids give `None`
+fn parse_id(message: &Map<String, Value>) -> Result<Option<Vec<u8>>, String> {
+    match message.get("id") {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::String(id)) => hex::decode(id).map(Some).map_err(|_| "JSON-RPC id is not a hex request id".to_string()),
+        Some(_) => Err("JSON-RPC id is not a hex request id".to_string()),
+    }
+}
+
+fn parse_message(message: &str) -> Result<Map<String, Value>, String> {
+    match serde_json::from_str(message).map_err(|e| e.to_string())? {
+        Value::Object(message) if message.get("jsonrpc").and_then(Value::as_str) == Some(JSON_RPC_VERSION) => Ok(message),
+        _ => Err("Not a JSON-RPC 2.0 message".to_string()),
+    }
+}
+
+fn text<'a>(object: &'a Map<String, Value>, key: &str) -> Option<&'a str> {
+    object.get(key).and_then(Value::as_str)
+}
+
+fn hex_field(object: &Map<String, Value>, key: &str) -> Result<Vec<u8>, String> {
+    hex::decode(text(object, key).ok_or(format!("Missing {}", key))?).map_err(|e| e.to_string())
+}
+
+fn u32_field(object: &Map<String, Value>, key: &str) -> Result<Option<u32>, String> {
+    match object.get(key) {
+        None | Some(Value::Null) => Ok(None),
+        Some(value) => value
+            .as_u64()
+            .and_then(|v| u32::try_from(v).ok())
+            .map(Some)
+            .ok_or(format!("Invalid {}: expected a 32-bit unsigned integer", key)),
+    }
+}
+
+// Params a server reads next to `ur` must say what the request does
+fn check_params(params: &Map<String, Value>, request: &TronSignRequest) -> Result<(), String> {
+    if params.contains_key("sign_data") && hex_field(params, "sign_data")? != *request.get_sign_data() {
+        return Err("Params sign_data doesn't match the request".to_string());
+    }
+    if params.get("derivation_path").is_some_and(|path| path.as_str() != Some(request.get_derivation_path())) {
+        return Err("Params derivation_path doesn't match the request".to_string());
+    }
+    if params.get("address").is_some_and(|address| address.as_str() != request.get_address().map(String::as_str)) {
+        return Err("Params address doesn't match the request".to_string());
+    }
+    Ok(())
+}
+
+pub fn request_to_json_rpc(request: &TronSignRequest) -> Result<String, String> {
+    let mut params = json!({
+        "sign_data": hex::encode(request.get_sign_data()),
+        "derivation_path": request.get_derivation_path(),
+        "ur": request.to_transport_string()?,
+    });
+    if let Some(xfp) = request.get_xfp() {
+        params["xfp"] = json!(xfp);
+    }
+    if let Some(address) = request.get_address() {
+        params["address"] = json!(address);
+    }
+    if let Some(origin) = request.get_origin() {
+        params["origin"] = origin.to_json();
+    }
+    Ok(json!({
+        "jsonrpc": JSON_RPC_VERSION,
+        "id": id_value(request.get_request_id()),
+        "method": method_name(request.get_data_type())?,
+        "params": params,
+    })
+    .to_string())
+}
+
+pub fn request_from_json_rpc(message: &str) -> Result<TronSignRequest, String> {
+    let message = parse_message(message)?;
+    let method = text(&message, "method").ok_or("Missing method")?;
+    let data_type = method_data_type(method)?.to_u32();
+    let params = message.get("params").and_then(Value::as_object).ok_or("Missing params")?;
+    let id = parse_id(&message)?;
+
+    let request = match text(params, "ur") {
+        Some(ur) => {
+            let request = TronSignRequest::from_transport_string(ur)?;
+            check_params(params, &request)?;
+            request
+        }
+        None => {
+            let sign_data = hex_field(params, "sign_data")?;
+            let path = text(params, "derivation_path").ok_or("Missing derivation_path")?.to_string();
+            let xfp = u32_field(params, "xfp")?;
+            let address = text(params, "address").map(str::to_string);
+            let mut request = match id.clone() {
+                Some(id) => TronSignRequest::new(Some(id), sign_data, data_type, path, xfp, address, None),
+                None => TronSignRequest::new_with_derived_request_id(sign_data, data_type, path, xfp, address, None),
+            };
+            request.set_origin(params.get("origin").map(Origin::from_json).transpose()?);
+            request
+        }
+    };
+    if request.get_data_type() != data_type {
+        return Err(format!("Method {} doesn't match the request's data type", method));
+    }
+    if id.is_some() && request.get_request_id() != id.as_ref() {
+        return Err("JSON-RPC id doesn't match the request id".to_string());
+    }
+    Ok(request)
+}
+
+pub fn signature_to_json_rpc(signature: &TronSignature) -> Result<String, String> {
+    let mut result = json!({
+        "signature": hex::encode(signature.get_signature()),
+        "ur": signature.to_transport_string()?,
+    });
+    if let Some(public_key) = signature.get_public_key() {
+        result["public_key"] = json!(hex::encode(public_key));
+    }
+    Ok(json!({
+        "jsonrpc": JSON_RPC_VERSION,
+        "id": id_value(signature.get_request_id()),
+        "result": result,
+    })
+    .to_string())
+}
+
+pub fn signature_from_json_rpc(message: &str) -> Result<TronSignature, String> {
+    let message = parse_message(message)?;
+    if let Some(error) = message.get("error") {
+        return Err(format!("JSON-RPC error: {}", error));
+    }
+    let result = message.get("result").and_then(Value::as_object).ok_or("Missing result")?;
+    let id = parse_id(&message)?;
+    match text(result, "ur") {
+        Some(ur) => {
+            let signature = TronSignature::from_transport_string(ur)?;
+            if id.is_some() && signature.get_request_id() != id.as_ref() {
+                return Err("JSON-RPC id doesn't match the request id".to_string());
+            }
+            Ok(signature)
+        }
+        None => {
+            let mut signature = TronSignature::new(id, hex_field(result, "signature")?);
+            if result.contains_key("public_key") {
+                signature.set_public_key(Some(hex_field(result, "public_key")?))?;
+            }
+            Ok(signature)
+        }
+    }
+}
+
+pub fn rejection_to_json_rpc(rejection: &TronSignRejection) -> Result<String, String> {
+    let reason = rejection.get_reason();
+    let message = rejection
+        .get_message()
+        .cloned()
+        .unwrap_or_else(|| reason.map_or("Request rejected".to_string(), |r| format!("{:?}", r)));
+    let cbor = rejection.to_bytes()?;
+    Ok(json!({
+        "jsonrpc": JSON_RPC_VERSION,
+        "id": hex::encode(rejection.get_request_id()),
+        "error": {
+            "code": error_code(reason),
+            "message": message,
+            "data": { "reason": rejection.get_reason_code(), "ur": transport::encode(TRON_SIGN_REJECTION_TYPE, &cbor) },
+        },
+    })
+    .to_string())
+}
+
+pub fn rejection_from_json_rpc(message: &str) -> Result<TronSignRejection, String> {
+    let message = parse_message(message)?;
+    let error = message.get("error").and_then(Value::as_object).ok_or("Not a JSON-RPC error")?;
+    let data = error.get("data").and_then(Value::as_object);
+    let id = parse_id(&message)?;
+    if let Some(ur) = data.and_then(|d| text(d, "ur")) {
+        let rejection = TronSignRejection::try_from(transport::decode_as(TRON_SIGN_REJECTION_TYPE, ur)?)?;
+        if id.as_ref().is_some_and(|id| id != rejection.get_request_id()) {
+            return Err("JSON-RPC id doesn't match the request id".to_string());
+        }
+        return Ok(rejection);
+    }
+    let request_id = id.ok_or("Missing JSON-RPC id")?;
+    let reason_code = match data {
+        Some(data) => u32_field(data, "reason")?,
+        None => None,
+    };
+    let reason = reason_code
+        .and_then(|code| RejectionReason::from_u32(code).ok())
+        .or_else(|| error.get("code").and_then(Value::as_i64).and_then(code_reason))
+        .ok_or("Unknown rejection reason")?;
+    Ok(TronSignRejection::new(request_id, reason, text(error, "message").map(str::to_string)))
+}
+
+fn message_arg(message: PtrString) -> Result<String, PtrResponse> {
+    convert_ptr_string_to_string(message).map_err(|e| Response::error(e).c_ptr())
+}
+
+// ========== FFI Functions ==========
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_request_to_json_rpc(tron_sign_request: &TronSignRequest) -> PtrResponse {
+    match request_to_json_rpc(tron_sign_request) {
+        Ok(message) => Response::success_string(message),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_request_from_json_rpc(message: PtrString) -> PtrResponse {
+    let message = match message_arg(message) {
+        Ok(v) => v,
+        Err(e) => return e,
+    };
+    match request_from_json_rpc(&message) {
+        Ok(request) => Response::success_object(Box::into_raw(Box::new(request)) as PtrVoid),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_signature_to_json_rpc(tron_signature: &TronSignature) -> PtrResponse {
+    match signature_to_json_rpc(tron_signature) {
+        Ok(message) => Response::success_string(message),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+/// Fails with the error object when the response is a JSON-RPC error
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_signature_from_json_rpc(message: PtrString) -> PtrResponse {
+    let message = match message_arg(message) {
+        Ok(v) => v,
+        Err(e) => return e,
+    };
+    match signature_from_json_rpc(&message) {
+        Ok(signature) => Response::success_object(Box::into_raw(Box::new(signature)) as PtrVoid),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_rejection_to_json_rpc(tron_sign_rejection: &TronSignRejection) -> PtrResponse {
+    match rejection_to_json_rpc(tron_sign_rejection) {
+        Ok(message) => Response::success_string(message),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_rejection_from_json_rpc(message: PtrString) -> PtrResponse {
+    let message = match message_arg(message) {
+        Ok(v) => v,
+        Err(e) => return e,
+    };
+    match rejection_from_json_rpc(&message) {
+        Ok(rejection) => Response::success_object(Box::into_raw(Box::new(rejection)) as PtrVoid),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_rpc_round_trip() {
+        let mut request = TronSignRequest::new_with_derived_request_id(
+            b"hello".to_vec(), 2, "m/44'/195'/0'/0/0".to_string(), Some(0x12345678), None, Some("dapp".to_string()),
+        );
+        request.set_wallet_id(Some(vec![9; 16]));
+        let message = request_to_json_rpc(&request).unwrap();
+        let value: Value = serde_json::from_str(&message).unwrap();
+        assert_eq!(value["method"], SIGN_MESSAGE_METHOD);
+        assert_eq!(value["id"], hex::encode(request.get_request_id().unwrap()));
+        let parsed = request_from_json_rpc(&message).unwrap();
+        assert_eq!(parsed.to_bytes().unwrap(), request.to_bytes().unwrap());
+
+        // Built by a server from params alone, the id is the request id
+        let id = request.get_request_id().unwrap().clone();
+        let bare = json!({
+            "jsonrpc": "2.0", "id": hex::encode(&id), "method": SIGN_MESSAGE_METHOD,
+            "params": { "sign_data": hex::encode(b"hello"), "derivation_path": "m/44'/195'/0'/0/0" },
+        });
+        let parsed = request_from_json_rpc(&bare.to_string()).unwrap();
+        assert_eq!((parsed.get_request_id(), parsed.get_sign_data()), (Some(&id), &b"hello".to_vec()));
+        assert!(request_from_json_rpc(&message.replace(SIGN_MESSAGE_METHOD, SIGN_TRANSACTION_METHOD)).is_err());
+
+        let signature = TronSignature::new(Some(id.clone()), vec![1; 65]);
+        let parsed = signature_from_json_rpc(&signature_to_json_rpc(&signature).unwrap()).unwrap();
+        assert_eq!(parsed.get_signature(), signature.get_signature());
+
+        let rejection = TronSignRejection::new(id.clone(), RejectionReason::UserDeclined, None);
+        let message = rejection_to_json_rpc(&rejection).unwrap();
+        assert_eq!(serde_json::from_str::<Value>(&message).unwrap()["error"]["code"], 4001);
+        assert!(signature_from_json_rpc(&message).is_err());
+        let bare = json!({ "jsonrpc": "2.0", "id": hex::encode(&id), "error": { "code": 4100, "message": "blocked" } });
+        let parsed = rejection_from_json_rpc(&bare.to_string()).unwrap();
+        assert_eq!((parsed.get_reason(), parsed.get_message().map(String::as_str)), (Some(RejectionReason::PolicyViolation), Some("blocked")));
+    }
+
+    #[test]
+    fn test_json_rpc_rejects_mismatches() {
+        let request = TronSignRequest::new_with_derived_request_id(
+            b"hello".to_vec(), 2, "m/44'/195'/0'/0/0".to_string(), None, None, None,
+        );
+        let mut message: Value = serde_json::from_str(&request_to_json_rpc(&request).unwrap()).unwrap();
+        message["params"]["address"] = json!("TXYZopYRdj2D9XRtbG411XZZ3kM5VkAeBf");
+        assert!(request_from_json_rpc(&message.to_string()).unwrap_err().contains("address"));
+        message["params"].as_object_mut().unwrap().remove("address");
+        message["params"]["sign_data"] = json!(hex::encode(b"other"));
+        assert!(request_from_json_rpc(&message.to_string()).unwrap_err().contains("sign_data"));
+        message["params"]["sign_data"] = json!(hex::encode(b"hello"));
+        message["params"]["derivation_path"] = json!("m/44'/195'/1'/0/0");
+        assert!(request_from_json_rpc(&message.to_string()).unwrap_err().contains("derivation_path"));
+
+        let bare = json!({
+            "jsonrpc": "2.0", "method": SIGN_MESSAGE_METHOD,
+            "params": { "sign_data": "00", "derivation_path": "m/44'/195'/0'/0/0", "xfp": 1u64 << 32 },
+        });
+        assert!(request_from_json_rpc(&bare.to_string()).is_err());
+
+        let rejection = TronSignRejection::new(vec![1; 16], RejectionReason::UserDeclined, None);
+        let mut message: Value = serde_json::from_str(&rejection_to_json_rpc(&rejection).unwrap()).unwrap();
+        message["id"] = json!(hex::encode([2; 16]));
+        assert!(rejection_from_json_rpc(&message.to_string()).is_err());
+        let bare = json!({ "jsonrpc": "2.0", "id": "01", "error": { "code": 4001, "data": { "reason": (1u64 << 32) + 1 } } });
+        assert!(rejection_from_json_rpc(&bare.to_string()).is_err());
+    }
+}
@@ -0,0 +1,364 @@
+use crate::crypto_address::COIN_TYPE_TRON;
+use crate::response::{PtrResponse, Response};
+use crate::tron::decode::{get_u32, invalid_value, DecodeError};
+use crate::types::PtrString;
+use crate::utils::convert_ptr_string_to_string;
+
+use ripemd::Ripemd160;
+use secp256k1::PublicKey;
+use serde_cbor::Value;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fmt;
+
+// CBOR map keys for the keypath
+const COMPONENTS: i128 = 1;
+const SOURCE_FINGERPRINT: i128 = 2;
+const DEPTH: i128 = 3;
+
+// crypto-hdkey map keys carrying key origin
+const HDKEY_ORIGIN: i128 = 6;
+const HDKEY_PARENT_FINGERPRINT: i128 = 8;
+
+const BIP44_PURPOSE: u32 = 44;
+
+// Indexes at or above this only exist as hardened children
+const HARDENED_OFFSET: i128 = 0x8000_0000;
+
+// Serialized BIP32 extended key: version(4) depth(1) parent(4) child(4) chain code(32) key(33)
+const XPUB_LEN: usize = 78;
+const XPUB_DEPTH_OFFSET: usize = 4;
+const XPUB_KEY_OFFSET: usize = 45;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PathComponent {
+    pub index: u32,
+    pub hardened: bool,
+}
+
+/// BIP32 derivation path, always formatted as `m/44'/195'/0'/0/0`
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct KeyPath {
+    components: Vec<PathComponent>,
+    source_fingerprint: Option<u32>,
+    depth: Option<u32>,
+    // Not part of crypto-keypath; exported as the hdkey's parent fingerprint
+    parent_fingerprint: Option<u32>,
+}
+
+impl KeyPath {
+    pub fn new(components: Vec<PathComponent>) -> Self {
+        KeyPath {
+            components,
+            ..Default::default()
+        }
+    }
+
+    /// `m/44'/195'/{account}'`
+    pub fn tron_account(account: u32) -> Result<Self, String> {
+        let purpose = PathComponent { index: BIP44_PURPOSE, hardened: true };
+        let coin_type = PathComponent { index: COIN_TYPE_TRON, hardened: true };
+        Ok(KeyPath::new(vec![purpose, coin_type, hardened_component(account)?]))
+    }
+
+    /// `m/44'/195'/{account}'/{change}/{index}`
+    pub fn tron_address(account: u32, change: u32, index: u32) -> Result<Self, String> {
+        let mut path = KeyPath::tron_account(account)?;
+        path.components.push(normal_component(change)?);
+        path.components.push(normal_component(index)?);
+        Ok(path)
+    }
+
+    /// Accepts an optional `m/` prefix and `'`, `h` or `H` for hardened
+    /// components, mixed freely: `44'/195'/0'`, `m/44h/195h/0h/0/0`
+    pub fn parse(path: &str) -> Result<Self, String> {
+        let path = path.trim();
+        let rest = match path {
+            "m" | "M" | "" => return Ok(KeyPath::default()),
+            _ => path
+                .strip_prefix("m/")
+                .or_else(|| path.strip_prefix("M/"))
+                .unwrap_or(path),
+        };
+
+        let components = rest
+            .split('/')
+            .map(|part| {
+                let (index, hardened) = match part.strip_suffix(['\'', 'h', 'H']) {
+                    Some(index) => (index, true),
+                    None => (part, false),
+                };
+                let index: u32 = index
+                    .parse()
+                    .map_err(|_| format!("Invalid path component: {}", part))?;
+                if index as i128 >= HARDENED_OFFSET {
+                    return Err(format!("Path component out of range: {}", part));
+                }
+                Ok(PathComponent { index, hardened })
+            })
+            .collect::<Result<Vec<PathComponent>, String>>()?;
+        Ok(KeyPath::new(components))
+    }
+
+    /// This path extended by one component; origin fields are not carried over
+    pub fn child(&self, index: u32, hardened: bool) -> KeyPath {
+        let mut components = self.components.clone();
+        components.push(PathComponent { index, hardened });
+        KeyPath::new(components)
+    }
+
+    pub fn get_components(&self) -> &Vec<PathComponent> {
+        &self.components
+    }
+
+    pub fn get_source_fingerprint(&self) -> Option<u32> {
+        self.source_fingerprint
+    }
+
+    pub fn set_source_fingerprint(&mut self, source_fingerprint: Option<u32>) {
+        self.source_fingerprint = source_fingerprint;
+    }
+
+    /// Depth of the key the path leads to, when the path itself is
+    /// partial (e.g. `m/0/0` below an account key at depth 3)
+    pub fn get_depth(&self) -> Option<u32> {
+        self.depth
+    }
+
+    pub fn set_depth(&mut self, depth: Option<u32>) {
+        self.depth = depth;
+    }
+
+    pub fn get_parent_fingerprint(&self) -> Option<u32> {
+        self.parent_fingerprint
+    }
+
+    pub fn set_parent_fingerprint(&mut self, parent_fingerprint: Option<u32>) {
+        self.parent_fingerprint = parent_fingerprint;
+    }
+
+    pub(crate) fn to_cbor(&self) -> Value {
+        // CBOR array per component: [index, hardened]
+        let components = self
+            .components
+            .iter()
+            .map(|c| Value::Array(vec![Value::Integer(c.index as i128), Value::Bool(c.hardened)]))
+            .collect();
+
+        let mut map: BTreeMap<Value, Value> = BTreeMap::new();
+        map.insert(Value::Integer(COMPONENTS), Value::Array(components));
+
+        if let Some(fingerprint) = self.source_fingerprint {
+            map.insert(Value::Integer(SOURCE_FINGERPRINT), Value::Integer(fingerprint as i128));
+        }
+
+        if let Some(depth) = self.depth {
+            map.insert(Value::Integer(DEPTH), Value::Integer(depth as i128));
+        }
+
+        Value::Map(map)
+    }
+
+    /// Origin entries for a crypto-hdkey map: the keypath itself and,
+    /// when known, the parent fingerprint which crypto-keypath has no key for
+    pub fn to_hdkey_entries(&self) -> Vec<(Value, Value)> {
+        let mut entries = vec![(Value::Integer(HDKEY_ORIGIN), self.to_cbor())];
+        if let Some(fingerprint) = self.parent_fingerprint {
+            entries.push((Value::Integer(HDKEY_PARENT_FINGERPRINT), Value::Integer(fingerprint as i128)));
+        }
+        entries
+    }
+
+    pub(crate) fn from_cbor(value: &Value) -> Result<Self, DecodeError> {
+        const FIELD: &str = "derivation path";
+        let map = match value {
+            Value::Map(map) => map,
+            _ => return Err(DecodeError::InvalidType { field: FIELD, expected: "map" }),
+        };
+        let components = match map.get(&Value::Integer(COMPONENTS)) {
+            Some(Value::Array(arr)) => arr,
+            Some(_) => return Err(DecodeError::InvalidType { field: FIELD, expected: "array of components" }),
+            None => return Err(DecodeError::MissingField("path components")),
+        };
+
+        let components = components
+            .iter()
+            .map(|component| {
+                let (index, hardened) = match component {
+                    Value::Array(arr) if arr.len() >= 2 => match (&arr[0], &arr[1]) {
+                        (Value::Integer(i), Value::Bool(b)) => (*i, *b),
+                        _ => return Err(DecodeError::InvalidType { field: FIELD, expected: "[index, hardened]" }),
+                    },
+                    _ => return Err(DecodeError::InvalidType { field: FIELD, expected: "[index, hardened]" }),
+                };
+                if !(0..HARDENED_OFFSET).contains(&index) {
+                    return Err(invalid_value(FIELD, format!("index {} is out of range", index)));
+                }
+                Ok(PathComponent { index: index as u32, hardened })
+            })
+            .collect::<Result<Vec<PathComponent>, DecodeError>>()?;
+
+        Ok(KeyPath {
+            components,
+            source_fingerprint: get_u32(map, SOURCE_FINGERPRINT, "source fingerprint")?,
+            depth: get_u32(map, DEPTH, "depth")?,
+            parent_fingerprint: None,
+        })
+    }
+
+    /// Reads back `to_hdkey_entries` from a crypto-hdkey map, parent
+    /// fingerprint included
+    pub fn from_hdkey_entries(map: &BTreeMap<Value, Value>) -> Result<Self, DecodeError> {
+        let origin = map.get(&Value::Integer(HDKEY_ORIGIN)).ok_or(DecodeError::MissingField("origin"))?;
+        let mut path = KeyPath::from_cbor(origin)?;
+        path.parent_fingerprint = get_u32(map, HDKEY_PARENT_FINGERPRINT, "parent fingerprint")?;
+        Ok(path)
+    }
+}
+
+fn hardened_component(index: u32) -> Result<PathComponent, String> {
+    Ok(PathComponent { index: normal_component(index)?.index, hardened: true })
+}
+
+fn normal_component(index: u32) -> Result<PathComponent, String> {
+    if index as i128 >= HARDENED_OFFSET {
+        return Err(format!("Path index out of range: {}", index));
+    }
+    Ok(PathComponent { index, hardened: false })
+}
+
+impl fmt::Display for KeyPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "m")?;
+        for c in self.components.iter() {
+            write!(f, "/{}{}", c.index, if c.hardened { "'" } else { "" })?;
+        }
+        Ok(())
+    }
+}
+
+/// Canonical `m/44'/...` spelling of any accepted path format
+pub fn normalize_path(path: &str) -> Result<String, String> {
+    KeyPath::parse(path).map(|p| p.to_string())
+}
+
+/// First four bytes of hash160 (ripemd160 of sha256) of the compressed
+/// master public key. Takes the master xpub, or the master public key as
+/// hex (compressed or uncompressed).
+pub fn compute_master_fingerprint(xpub_or_pubkey: &str) -> Result<u32, String> {
+    let public_key = match hex::decode(xpub_or_pubkey) {
+        Ok(key) => key,
+        Err(_) => {
+            let xpub = bs58::decode(xpub_or_pubkey)
+                .with_check(None)
+                .into_vec()
+                .map_err(|e| format!("Invalid xpub: {}", e))?;
+            if xpub.len() != XPUB_LEN {
+                return Err("Invalid xpub length".to_string());
+            }
+            // A child xpub hashes to its own fingerprint, not the master's
+            if xpub[XPUB_DEPTH_OFFSET] != 0 {
+                return Err(format!("xpub is at depth {}, expected the master key", xpub[XPUB_DEPTH_OFFSET]));
+            }
+            xpub[XPUB_KEY_OFFSET..].to_vec()
+        }
+    };
+    let public_key = PublicKey::from_slice(&public_key).map_err(|e| format!("Invalid public key: {}", e))?;
+    let hash = Ripemd160::digest(Sha256::digest(public_key.serialize()));
+    Ok(u32::from_be_bytes([hash[0], hash[1], hash[2], hash[3]]))
+}
+
+// ========== FFI Functions ==========
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_compute_master_fingerprint(xpub_or_pubkey: PtrString) -> PtrResponse {
+    let key = match convert_ptr_string_to_string(xpub_or_pubkey).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    match compute_master_fingerprint(&key) {
+        Ok(fingerprint) => Response::success_uint32(fingerprint),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_keypath_account(account: u32) -> PtrResponse {
+    match KeyPath::tron_account(account) {
+        Ok(path) => Response::success_string(path.to_string()),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_keypath_address(account: u32, change: u32, index: u32) -> PtrResponse {
+    match KeyPath::tron_address(account, change, index) {
+        Ok(path) => Response::success_string(path.to_string()),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_path_formats() {
+        for path in ["m/44'/195'/0'/0/0", "44'/195'/0'/0/0", "m/44h/195h/0h/0/0", "44H/195'/0h/0/0"] {
+            assert_eq!(normalize_path(path).unwrap(), "m/44'/195'/0'/0/0", "{}", path);
+        }
+        assert_eq!(normalize_path("m").unwrap(), "m");
+        assert!(KeyPath::parse("m/44'/x").is_err());
+        assert!(KeyPath::parse("m/44'//0").is_err());
+        assert!(KeyPath::parse("m/2147483648").is_err());
+
+    }
+
+    #[test]
+    fn test_tron_templates() {
+        assert_eq!(KeyPath::tron_account(2).unwrap().to_string(), "m/44'/195'/2'");
+        assert_eq!(KeyPath::tron_address(0, 1, 7).unwrap(), KeyPath::parse("m/44'/195'/0'/1/7").unwrap());
+        assert!(KeyPath::tron_account(0x8000_0000).is_err());
+        assert!(KeyPath::tron_address(0, 0, 0x8000_0000).is_err());
+    }
+
+    #[test]
+    fn test_origin_round_trip() {
+        let mut path = KeyPath::parse("m/44'/195'/0'").unwrap();
+        path.set_source_fingerprint(Some(0x12345678));
+        path.set_depth(Some(3));
+        assert_eq!(KeyPath::from_cbor(&path.to_cbor()).unwrap(), path);
+
+        path.set_parent_fingerprint(Some(0x9abcdef0));
+        let entries = path.to_hdkey_entries();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[1], (Value::Integer(HDKEY_PARENT_FINGERPRINT), Value::Integer(0x9abcdef0)));
+        let mut hdkey: BTreeMap<Value, Value> = entries.into_iter().collect();
+        let decoded = KeyPath::from_hdkey_entries(&hdkey).unwrap();
+        assert_eq!(decoded.get_parent_fingerprint(), Some(0x9abcdef0));
+        assert_eq!(decoded, path);
+
+        hdkey.insert(Value::Integer(HDKEY_PARENT_FINGERPRINT), Value::Integer(-1));
+        assert!(KeyPath::from_hdkey_entries(&hdkey).is_err());
+        hdkey.remove(&Value::Integer(HDKEY_ORIGIN));
+        assert!(KeyPath::from_hdkey_entries(&hdkey).is_err());
+    }
+
+    #[test]
+    fn test_master_fingerprint() {
+        // BIP32 test vector 1
+        let xpub = "xpub661MyMwAqRbcFtXgS5sYJABqqG9YLmC4Q1Rdap9gSE8NqtwybGhePY2gZ29ESFjqJoCu1Rupje8YtGqsefD265TMg7usUDFdp6W1EGMcet8";
+        assert_eq!(compute_master_fingerprint(xpub).unwrap(), 0x3442193e);
+        let public_key = "0339a36013301597daef41fbe593a02cc513d0b55527ec2df1050e2e8ff49c85c2";
+        assert_eq!(compute_master_fingerprint(public_key).unwrap(), 0x3442193e);
+
+        let child = "xpub68Gmy5EdvgibQVfPdqkBBCHxA5htiqg55crXYuXoQRKfDBFA1WEjWgP6LHhwBZeNK1VTsfTFUHCdrfp1bgwQ9xv5ski8PX9rL2dZXvgGDnw";
+        assert!(compute_master_fingerprint(child).is_err());
+    }
+}
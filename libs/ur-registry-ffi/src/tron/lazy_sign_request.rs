@@ -0,0 +1,242 @@
+use crate::response::{PtrResponse, Response};
+use crate::tron::cbor_index::MapIndex;
+use crate::tron::decode::{invalid_value, DecodeError};
+use crate::tron::origin::Origin;
+use crate::tron::tron_sign_request::{
+    decode_derivation_path, inflate_sign_data, TronSignRequest, ADDRESS, DATA_TYPE, DEFLATE_TAG,
+    DERIVATION_PATH, ORIGIN, REQUEST_ID, SIGN_DATA,
+};
+use crate::types::{PtrString, PtrVoid};
+use crate::utils::parse_ptr_string_to_bytes;
+
+use serde_cbor::Value;
+use std::sync::OnceLock;
+use std::ops::Range;
+
+/// A TronSignRequest whose top-level map has only been indexed; each field
+/// is decoded on first access and cached. Field semantics match the full
+/// `TryFrom` decode, which `to_request` falls back to.
+pub struct LazyTronSignRequest {
+    data: Vec<u8>,
+    fields: Vec<(Value, Range<usize>, OnceLock<Value>)>,
+}
+
+impl LazyTronSignRequest {
+    pub fn parse(data: Vec<u8>) -> Result<Self, String> {
+        let fields = MapIndex::parse(&data)?
+            .entries
+            .into_iter()
+            .map(|(key, range)| (key, range, OnceLock::new()))
+            .collect();
+        Ok(LazyTronSignRequest { data, fields })
+    }
+
+    fn field(&self, key: i128) -> Result<Option<&Value>, DecodeError> {
+        let key = Value::Integer(key);
+        let (_, range, cell) = match self.fields.iter().find(|(k, _, _)| *k == key) {
+            Some(field) => field,
+            None => return Ok(None),
+        };
+        if let Some(value) = cell.get() {
+            return Ok(Some(value));
+        }
+        let value: Value =
+            serde_cbor::from_slice(&self.data[range.clone()]).map_err(|e| DecodeError::InvalidCbor(e.to_string()))?;
+        Ok(Some(cell.get_or_init(|| value)))
+    }
+
+    pub fn has_field(&self, key: i128) -> bool {
+        self.fields.iter().any(|(k, _, _)| *k == Value::Integer(key))
+    }
+
+    pub fn get_request_id(&self) -> Result<Option<Vec<u8>>, DecodeError> {
+        match self.field(REQUEST_ID)? {
+            None => Ok(None),
+            Some(Value::Bytes(b)) => Ok(Some(b.clone())),
+            Some(_) => Err(DecodeError::InvalidType { field: "request id", expected: "bytes" }),
+        }
+    }
+
+    pub fn get_sign_data(&self) -> Result<Vec<u8>, DecodeError> {
+        match self.field(SIGN_DATA)? {
+            Some(Value::Bytes(b)) => Ok(b.clone()),
+            Some(Value::Tag(DEFLATE_TAG, inner)) => match inner.as_ref() {
+                Value::Bytes(b) => inflate_sign_data(b),
+                _ => Err(DecodeError::InvalidType { field: "sign_data", expected: "bytes" }),
+            },
+            Some(_) => Err(DecodeError::InvalidType { field: "sign_data", expected: "bytes" }),
+            None => Err(DecodeError::MissingField("sign_data")),
+        }
+    }
+
+    pub fn get_data_type(&self) -> Result<u32, DecodeError> {
+        match self.field(DATA_TYPE)? {
+            None => Ok(1),
+            Some(Value::Integer(i)) => {
+                u32::try_from(*i).map_err(|_| invalid_value("data type", format!("{} is out of range", i)))
+            }
+            Some(_) => Err(DecodeError::InvalidType { field: "data type", expected: "unsigned integer" }),
+        }
+    }
+
+    pub fn get_derivation_path(&self) -> Result<String, DecodeError> {
+        decode_derivation_path(self.field(DERIVATION_PATH)?)
+    }
+
+    pub fn get_address(&self) -> Result<Option<String>, DecodeError> {
+        match self.field(ADDRESS)? {
+            None => Ok(None),
+            Some(Value::Text(s)) => Ok(Some(s.clone())),
+            Some(_) => Err(DecodeError::InvalidType { field: "address", expected: "text" }),
+        }
+    }
+
+    /// The origin as `Origin`'s display string, text or structured
+    pub fn get_origin(&self) -> Result<Option<String>, DecodeError> {
+        Ok(self.field(ORIGIN)?.map(Origin::from_cbor).transpose()?.map(|origin| origin.to_string()))
+    }
+
+    /// Full decode with all validation
+    pub fn to_request(&self) -> Result<TronSignRequest, DecodeError> {
+        TronSignRequest::try_from(self.data.clone())
+    }
+}
+
+// ========== FFI Functions ==========
+
+/// `cbor` is the hex encoded CBOR body of a `tron-sign-request`
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_lazy_sign_request_parse(cbor: PtrString) -> PtrResponse {
+    let cbor = match parse_ptr_string_to_bytes(cbor).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    match LazyTronSignRequest::parse(cbor) {
+        Ok(request) => Response::success_object(Box::into_raw(Box::new(request)) as PtrVoid),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_lazy_sign_request_has_field(lazy_request: &LazyTronSignRequest, key: u32) -> PtrResponse {
+    Response::success_boolean(lazy_request.has_field(key as i128)).c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_lazy_sign_request_get_request_id(lazy_request: &LazyTronSignRequest) -> PtrResponse {
+    match lazy_request.get_request_id() {
+        Ok(v) => v.map_or(Response::not_present(), |id| Response::success_string(hex::encode(id))),
+        Err(e) => Response::error(e.to_string()),
+    }
+    .c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_lazy_sign_request_get_sign_data(lazy_request: &LazyTronSignRequest) -> PtrResponse {
+    match lazy_request.get_sign_data() {
+        Ok(v) => Response::success_string(hex::encode(v)),
+        Err(e) => Response::error(e.to_string()),
+    }
+    .c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_lazy_sign_request_get_data_type(lazy_request: &LazyTronSignRequest) -> PtrResponse {
+    match lazy_request.get_data_type() {
+        Ok(v) => Response::success_uint32(v),
+        Err(e) => Response::error(e.to_string()),
+    }
+    .c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_lazy_sign_request_get_derivation_path(lazy_request: &LazyTronSignRequest) -> PtrResponse {
+    match lazy_request.get_derivation_path() {
+        Ok(v) => Response::success_string(v),
+        Err(e) => Response::error(e.to_string()),
+    }
+    .c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_lazy_sign_request_get_address(lazy_request: &LazyTronSignRequest) -> PtrResponse {
+    match lazy_request.get_address() {
+        Ok(v) => v.map_or(Response::not_present(), Response::success_string),
+        Err(e) => Response::error(e.to_string()),
+    }
+    .c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_lazy_sign_request_get_origin(lazy_request: &LazyTronSignRequest) -> PtrResponse {
+    match lazy_request.get_origin() {
+        Ok(v) => v.map_or(Response::not_present(), Response::success_string),
+        Err(e) => Response::error(e.to_string()),
+    }
+    .c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_lazy_sign_request_to_request(lazy_request: &LazyTronSignRequest) -> PtrResponse {
+    match lazy_request.to_request() {
+        Ok(request) => Response::success_object(Box::into_raw(Box::new(request)) as PtrVoid),
+        Err(e) => Response::error(e.to_string()),
+    }
+    .c_ptr()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lazy_matches_full_decode() {
+        let request = TronSignRequest::new(
+            Some(vec![7; 16]),
+            vec![0x0a, 0x02, 0x07, 0x90],
+            1,
+            "m/44'/195'/0'/0/0".to_string(),
+            None,
+            Some("TXYZopYRdj2D9XRtbG411XZZ3kM5VkAeBf".to_string()),
+            None,
+        );
+        let lazy = LazyTronSignRequest::parse(request.to_bytes().unwrap()).unwrap();
+        assert!(lazy.fields.iter().all(|(_, _, cell)| cell.get().is_none()));
+
+        assert_eq!(lazy.get_request_id().unwrap(), Some(vec![7; 16]));
+        assert_eq!(lazy.get_derivation_path().unwrap(), "m/44'/195'/0'/0/0");
+        assert_eq!(lazy.fields.iter().filter(|(_, _, cell)| cell.get().is_some()).count(), 2);
+
+        assert_eq!(&lazy.get_sign_data().unwrap(), request.get_sign_data());
+        assert_eq!(lazy.get_origin().unwrap(), None);
+        assert!(!lazy.has_field(ORIGIN));
+        assert_eq!(lazy.to_request().unwrap().get_derivation_path(), request.get_derivation_path());
+    }
+
+    #[test]
+    fn test_lazy_rejects_wrong_types() {
+        let field = |key: i128, value: Value| {
+            let mut map = std::collections::BTreeMap::new();
+            map.insert(Value::Integer(SIGN_DATA), Value::Bytes(vec![1]));
+            map.insert(Value::Integer(key), value);
+            LazyTronSignRequest::parse(serde_cbor::to_vec(&Value::Map(map)).unwrap()).unwrap()
+        };
+        let invalid_type = |field, expected| DecodeError::InvalidType { field, expected };
+        assert_eq!(field(REQUEST_ID, Value::Text("id".into())).get_request_id(), Err(invalid_type("request id", "bytes")));
+        assert_eq!(field(ADDRESS, Value::Integer(1)).get_address(), Err(invalid_type("address", "text")));
+        assert_eq!(field(DATA_TYPE, Value::Text("1".into())).get_data_type(), Err(invalid_type("data type", "unsigned integer")));
+        assert_eq!(field(DATA_TYPE, Value::Integer(1 << 32)).get_data_type().unwrap_err().code(), "InvalidValue");
+        let origin = field(ORIGIN, Value::Integer(5));
+        assert_eq!(origin.get_origin().unwrap_err(), origin.to_request().unwrap_err());
+    }
+}
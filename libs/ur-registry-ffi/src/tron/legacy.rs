@@ -0,0 +1,268 @@
+//! Pre-UR TRON sign payloads from older Keystone firmware, converted into
+//! `TronSignRequest`. Two encodings were emitted:
+//!
+//! JSON (`json` feature):
+//!
+//! ```text
+//! {"type": "TYPE_SIGN_TX", "xfp": "12345678",
+//!  "signTx": {"coinCode": "TRON", "signId": "<uuid>", "hdPath": "M/44'/195'/0'/0/0",
+//!             "tronTx": {"rawData": "<hex>", "from": "T..."}}}
+//! {"type": "TYPE_SIGN_MSG", "xfp": "12345678",
+//!  "signMsg": {"coinCode": "TRON", "signId": "<uuid>", "hdPath": "...", "message": "<hex>"}}
+//! ```
+//!
+//! Protobuf (`protobuf` feature), hex encoded and optionally gzipped, with
+//! the same fields: `Base { 1: version, 3: Payload }`,
+//! `Payload { 1: type, 2: xfp, 4: SignTransaction, 5: SignMessage }`,
+//! `SignTransaction { 1: coinCode, 2: signId, 3: hdPath, 10: TronTx }`,
+//! `TronTx { 1: rawData, 2: from }`, `SignMessage { 1: coinCode, 2: signId, 3: hdPath, 4: message }`.
+
+use crate::response::{PtrResponse, Response};
+#[cfg(feature = "protobuf")]
+use crate::tron::protobuf::{find_field, parse_fields, WireValue};
+#[cfg(any(feature = "json", feature = "protobuf"))]
+use crate::tron::tron_sign_request::DataType;
+use crate::tron::tron_sign_request::TronSignRequest;
+use crate::types::{PtrString, PtrVoid};
+use crate::utils::convert_ptr_string_to_string;
+
+#[cfg(feature = "protobuf")]
+use flate2::read::GzDecoder;
+#[cfg(feature = "protobuf")]
+use std::io::Read;
+use uuid::Uuid;
+
+const COIN_CODE: &str = "TRON";
+const DEFAULT_PATH: &str = "m/44'/195'/0'/0/0";
+
+// Payload.type values
+#[cfg(feature = "protobuf")]
+const TYPE_SIGN_TX: u64 = 2;
+#[cfg(feature = "protobuf")]
+const TYPE_SIGN_MSG: u64 = 3;
+
+// Upper bound for gunzipped payloads
+#[cfg(feature = "protobuf")]
+const MAX_INFLATED_LEN: u64 = 1024 * 1024;
+
+/// Fields common to both encodings
+#[derive(Debug, Default)]
+struct LegacyRequest {
+    data_type: u32,
+    xfp: Option<String>,
+    coin_code: Option<String>,
+    sign_id: Option<String>,
+    hd_path: Option<String>,
+    sign_data: Option<Vec<u8>>,
+    from: Option<String>,
+}
+
+impl LegacyRequest {
+    fn into_request(self) -> Result<TronSignRequest, String> {
+        match self.coin_code.as_deref() {
+            Some(COIN_CODE) | None => {}
+            Some(other) => return Err(format!("Not a TRON payload: coin code {}", other)),
+        }
+        let xfp = match self.xfp {
+            Some(xfp) => Some(u32::from_str_radix(&xfp, 16).map_err(|_| format!("Invalid xfp: {}", xfp))?),
+            None => None,
+        };
+        let sign_data = self.sign_data.ok_or("Missing sign data")?;
+        let path = self.hd_path.unwrap_or_else(|| DEFAULT_PATH.to_string());
+        // signId was a UUID string; anything else gets a derived id
+        let request_id = self.sign_id.and_then(|id| Uuid::parse_str(&id).ok()).map(|id| id.as_bytes().to_vec());
+
+        Ok(match request_id {
+            Some(id) => TronSignRequest::new(Some(id), sign_data, self.data_type, path, xfp, self.from, None),
+            None => TronSignRequest::new_with_derived_request_id(sign_data, self.data_type, path, xfp, self.from, None),
+        })
+    }
+}
+
+/// Converts a scanned legacy QR payload; errors for anything else
+pub fn decode_legacy_payload(payload: &str) -> Result<TronSignRequest, String> {
+    let payload = payload.trim();
+    if payload.starts_with('{') {
+        return decode_json(payload)?.into_request();
+    }
+    let bytes = hex::decode(payload).map_err(|_| "Not a legacy TRON payload".to_string())?;
+    decode_protobuf(&bytes)?.into_request()
+}
+
+#[cfg(not(feature = "json"))]
+fn decode_json(_payload: &str) -> Result<LegacyRequest, String> {
+    Err("JSON legacy payloads need the json feature".to_string())
+}
+
+#[cfg(not(feature = "protobuf"))]
+fn decode_protobuf(_bytes: &[u8]) -> Result<LegacyRequest, String> {
+    Err("Protobuf legacy payloads need the protobuf feature".to_string())
+}
+
+#[cfg(feature = "json")]
+fn decode_json(payload: &str) -> Result<LegacyRequest, String> {
+    let value: serde_json::Value = serde_json::from_str(payload).map_err(|e| format!("Invalid legacy JSON: {}", e))?;
+    let text = |v: &serde_json::Value, key: &str| v.get(key).and_then(|s| s.as_str()).map(str::to_string);
+    let hex_field = |v: &serde_json::Value, key: &str| -> Result<Option<Vec<u8>>, String> {
+        text(v, key).map(|h| hex::decode(h).map_err(|e| format!("Invalid {}: {}", key, e))).transpose()
+    };
+
+    let (data_type, body, sign_data, from) = match value.get("type").and_then(|t| t.as_str()) {
+        Some("TYPE_SIGN_TX") => {
+            let body = value.get("signTx").ok_or("Missing signTx")?;
+            let tx = body.get("tronTx").ok_or("Missing tronTx")?;
+            (DataType::Transaction, body, hex_field(tx, "rawData")?, text(tx, "from"))
+        }
+        Some("TYPE_SIGN_MSG") => {
+            let body = value.get("signMsg").ok_or("Missing signMsg")?;
+            (DataType::Message, body, hex_field(body, "message")?, None)
+        }
+        other => return Err(format!("Unsupported legacy payload type: {:?}", other)),
+    };
+
+    Ok(LegacyRequest {
+        data_type: data_type.to_u32(),
+        xfp: text(&value, "xfp"),
+        coin_code: text(body, "coinCode"),
+        sign_id: text(body, "signId"),
+        hd_path: text(body, "hdPath"),
+        sign_data,
+        from,
+    })
+}
+
+#[cfg(feature = "protobuf")]
+fn decode_protobuf(bytes: &[u8]) -> Result<LegacyRequest, String> {
+    let inflated;
+    let bytes = if bytes.starts_with(&[0x1f, 0x8b]) {
+        let mut out = Vec::new();
+        GzDecoder::new(bytes)
+            .take(MAX_INFLATED_LEN + 1)
+            .read_to_end(&mut out)
+            .map_err(|e| format!("Invalid gzip payload: {}", e))?;
+        if out.len() as u64 > MAX_INFLATED_LEN {
+            return Err("Legacy payload exceeds size limit".to_string());
+        }
+        inflated = out;
+        &inflated[..]
+    } else {
+        bytes
+    };
+
+    let text = |fields: &[(u32, WireValue)], number: u32| {
+        find_field(fields, number).and_then(|v| v.as_str()).map(str::to_string)
+    };
+
+    let base = parse_fields(bytes)?;
+    let payload = submessage(&base, 3)?;
+    let (data_type, body, sign_data, from) = match find_field(&payload, 1).and_then(|v| v.as_varint()) {
+        Some(TYPE_SIGN_TX) => {
+            let body = submessage(&payload, 4)?;
+            let tx = submessage(&body, 10)?;
+            let raw_data = find_field(&tx, 1).and_then(|v| v.as_bytes()).map(<[u8]>::to_vec);
+            (DataType::Transaction, body, raw_data, text(&tx, 2))
+        }
+        Some(TYPE_SIGN_MSG) => {
+            let body = submessage(&payload, 5)?;
+            let message = find_field(&body, 4).and_then(|v| v.as_bytes()).map(<[u8]>::to_vec);
+            (DataType::Message, body, message, None)
+        }
+        other => return Err(format!("Unsupported legacy payload type: {:?}", other)),
+    };
+
+    Ok(LegacyRequest {
+        data_type: data_type.to_u32(),
+        xfp: text(&payload, 2),
+        coin_code: text(&body, 1),
+        sign_id: text(&body, 2),
+        hd_path: text(&body, 3),
+        sign_data,
+        from,
+    })
+}
+
+/// Fields of an embedded message; an absent one reads as empty
+#[cfg(feature = "protobuf")]
+fn submessage<'a>(fields: &[(u32, WireValue<'a>)], number: u32) -> Result<Vec<(u32, WireValue<'a>)>, String> {
+    parse_fields(find_field(fields, number).and_then(|v| v.as_bytes()).unwrap_or_default())
+}
+
+// ========== FFI Functions ==========
+
+/// `payload` is the scanned QR text; returns a TronSignRequest object
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_legacy_decode(payload: PtrString) -> PtrResponse {
+    let payload = match convert_ptr_string_to_string(payload).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    match decode_legacy_payload(&payload) {
+        Ok(request) => Response::success_object(Box::into_raw(Box::new(request)) as PtrVoid),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(all(test, any(feature = "json", feature = "protobuf")))]
+mod tests {
+    use super::*;
+
+    const SIGN_ID: &str = "9b1deb4d-3b7d-4bad-9bdd-2b0d7b3dcb6d";
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_json_payload() {
+        let payload = format!(
+            r#"{{"type": "TYPE_SIGN_TX", "xfp": "12345678", "signTx": {{"coinCode": "TRON", "signId": "{}",
+               "hdPath": "M/44'/195'/0'/0/0", "tronTx": {{"rawData": "0a020790", "from": "TXYZopYRdj2D9XRtbG411XZZ3kM5VkAeBf"}}}}}}"#,
+            SIGN_ID
+        );
+        let request = decode_legacy_payload(&payload).unwrap();
+        assert_eq!(request.get_request_id().unwrap(), &Uuid::parse_str(SIGN_ID).unwrap().as_bytes().to_vec());
+        assert_eq!(request.get_sign_data(), &vec![0x0a, 0x02, 0x07, 0x90]);
+        assert_eq!(request.get_derivation_path(), "m/44'/195'/0'/0/0");
+        assert_eq!(request.get_xfp(), Some(0x12345678));
+        assert_eq!(request.get_address().unwrap(), "TXYZopYRdj2D9XRtbG411XZZ3kM5VkAeBf");
+
+        let message = r#"{"type": "TYPE_SIGN_MSG", "signMsg": {"coinCode": "TRON", "message": "68656c6c6f"}}"#;
+        let request = decode_legacy_payload(message).unwrap();
+        assert_eq!(request.get_data_type(), 2);
+        assert!(request.is_request_id_derived());
+        assert!(decode_legacy_payload(&message.replace("TRON", "ETH")).is_err());
+    }
+
+    #[cfg(feature = "protobuf")]
+    #[test]
+    fn test_protobuf_payload() {
+        use crate::tron::protobuf::encode::{bytes_field, varint_field};
+        use flate2::read::GzEncoder;
+        use flate2::Compression;
+
+        let mut tron_tx = Vec::new();
+        bytes_field(1, &[0x0a, 0x02, 0x07, 0x90], &mut tron_tx);
+        let mut sign_tx = Vec::new();
+        bytes_field(1, b"TRON", &mut sign_tx);
+        bytes_field(2, SIGN_ID.as_bytes(), &mut sign_tx);
+        bytes_field(3, b"M/44'/195'/1'/0/0", &mut sign_tx);
+        bytes_field(10, &tron_tx, &mut sign_tx);
+        let mut payload = Vec::new();
+        varint_field(1, TYPE_SIGN_TX, &mut payload);
+        bytes_field(2, b"12345678", &mut payload);
+        bytes_field(4, &sign_tx, &mut payload);
+        let mut base = Vec::new();
+        varint_field(1, 1, &mut base);
+        bytes_field(3, &payload, &mut base);
+
+        let mut gzipped = Vec::new();
+        GzEncoder::new(&base[..], Compression::default()).read_to_end(&mut gzipped).unwrap();
+
+        for bytes in [base, gzipped] {
+            let request = decode_legacy_payload(&hex::encode(bytes)).unwrap();
+            assert_eq!(request.get_sign_data(), &vec![0x0a, 0x02, 0x07, 0x90]);
+            assert_eq!(request.get_derivation_path(), "m/44'/195'/1'/0/0");
+            assert_eq!(request.get_xfp(), Some(0x12345678));
+        }
+        assert!(decode_legacy_payload("zz").is_err());
+    }
+}
@@ -0,0 +1,107 @@
+//! Safe preview of the payload of Message requests for signer screens:
+//! control and bidi-override characters are stripped and the length capped.
+
+#[cfg(feature = "json")]
+use crate::response::{PtrResponse, Response};
+use crate::tron::tron_sign_request::{DataType, TronSignRequest};
+
+#[cfg(feature = "json")]
+use serde_json::json;
+
+pub const MAX_PREVIEW_CHARS: usize = 1024;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MessagePreview {
+    /// Sanitized text, or `0x`-prefixed hex for binary messages
+    pub text: String,
+    /// The message is UTF-8 and nothing had to be stripped
+    pub printable: bool,
+    pub truncated: bool,
+}
+
+// Characters that reorder the text around them and can hide what is signed
+fn is_bidi_control(c: char) -> bool {
+    matches!(c, '\u{202a}'..='\u{202e}' | '\u{2066}'..='\u{2069}' | '\u{200e}' | '\u{200f}')
+}
+
+fn is_displayable(c: char) -> bool {
+    (!c.is_control() || c == '\n' || c == '\t') && !is_bidi_control(c)
+}
+
+/// UTF-8 text with nothing a preview would strip
+pub fn is_printable(data: &[u8]) -> bool {
+    std::str::from_utf8(data).is_ok_and(|text| text.chars().all(is_displayable))
+}
+
+/// Preview of at most `max_chars` characters
+pub fn message_preview(data: &[u8], max_chars: usize) -> MessagePreview {
+    match std::str::from_utf8(data) {
+        Ok(text) => {
+            let displayable: Vec<char> = text.chars().filter(|c| is_displayable(*c)).collect();
+            let printable = displayable.len() == text.chars().count();
+            MessagePreview {
+                text: displayable.iter().take(max_chars).collect(),
+                printable,
+                truncated: displayable.len() > max_chars,
+            }
+        }
+        Err(_) => {
+            // Two characters per byte after the prefix
+            let bytes = max_chars.saturating_sub(2) / 2;
+            MessagePreview {
+                text: format!("0x{}", hex::encode(&data[..data.len().min(bytes)])),
+                printable: false,
+                truncated: data.len() > bytes,
+            }
+        }
+    }
+}
+
+/// Preview of a Message request's sign_data
+pub fn request_message_preview(request: &TronSignRequest) -> Result<MessagePreview, String> {
+    match DataType::from_u32(request.get_data_type())? {
+        DataType::Message => Ok(message_preview(request.get_sign_data(), MAX_PREVIEW_CHARS)),
+        _ => Err("Request is not a message".to_string()),
+    }
+}
+
+// ========== FFI Functions ==========
+
+/// JSON `{"text", "printable", "truncated"}`
+#[cfg(feature = "json")]
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_request_get_message_preview(tron_sign_request: &TronSignRequest) -> PtrResponse {
+    match request_message_preview(tron_sign_request) {
+        Ok(preview) => Response::success_string(
+            json!({ "text": preview.text, "printable": preview.printable, "truncated": preview.truncated }).to_string(),
+        ),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_message_preview() {
+        let preview = message_preview("Sign in to dApp\nNonce: 42".as_bytes(), MAX_PREVIEW_CHARS);
+        assert_eq!(preview.text, "Sign in to dApp\nNonce: 42");
+        assert!(preview.printable && !preview.truncated);
+
+        let preview = message_preview("pay \u{202e}XRT 01\u{7}".as_bytes(), MAX_PREVIEW_CHARS);
+        assert_eq!(preview.text, "pay XRT 01");
+        assert!(!preview.printable);
+
+        let preview = message_preview(&[0xab; 32], 10);
+        assert_eq!(preview.text, "0xabababab");
+        assert!(!preview.printable && preview.truncated);
+
+        let preview = message_preview("héllo".as_bytes(), 3);
+        assert_eq!(preview.text, "hél");
+        assert!(preview.truncated);
+        assert!(!is_printable(&[0xff]));
+    }
+}
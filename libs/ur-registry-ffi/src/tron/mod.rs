@@ -1,5 +1,94 @@
+//! TRON registry types and their FFI.
+//!
+//! Aliasing: functions that only read an object take it as a shared
+//! reference (`const T*` in C) and may run concurrently on the same object
+//! from any number of threads. Setters and other mutators take `*mut T` and
+//! need exclusive access: no other call on that object may be in flight.
+//! Objects must not be freed while any call still uses them.
+//!
+//! Results: getters of optional fields return status `NOT_PRESENT` when the
+//! field isn't set, keeping `ERROR` for calls that actually failed.
+//!
+//! Ownership: every string a function returns, hex, JSON and newline
+//! separated lists included, is owned by the caller and must be released
+//! with `ur_registry_free_string`, unless an arena entered with
+//! `ur_registry_arena_enter` collected it. Responses themselves are
+//! released with `ur_registry_free_response`.
+
 use crate::response::{PtrResponse, Response};
 use crate::types::PtrVoid;
 
+pub mod address;
 pub mod tron_sign_request;
 pub mod tron_signature;
+pub mod pending_requests;
+pub mod replay_guard;
+pub mod tron_encrypted_payload;
+pub mod tron_pairing;
+pub mod tron_wallet_bundle;
+pub mod tron_sign_rejection;
+pub mod tron_address_book;
+pub mod tron_sign_session;
+pub mod version;
+pub mod decode;
+pub mod telemetry;
+pub mod transport;
+pub mod deep_link;
+pub mod transcript;
+pub mod cbor_index;
+pub mod lazy_sign_request;
+pub mod batch;
+pub mod keypath;
+pub mod origin;
+pub mod origin_url;
+pub mod account_discovery;
+pub mod validate;
+pub mod schema;
+#[cfg(feature = "protobuf")]
+pub mod protobuf;
+#[cfg(feature = "protobuf")]
+pub mod transaction;
+#[cfg(feature = "protobuf")]
+pub mod resources;
+pub mod amount;
+pub mod display_hints;
+#[cfg(feature = "protobuf")]
+pub mod summary;
+#[cfg(feature = "protobuf")]
+pub mod analyze;
+#[cfg(feature = "protobuf")]
+pub mod risk;
+#[cfg(feature = "protobuf")]
+pub mod permissions;
+#[cfg(feature = "protobuf")]
+pub mod multisig;
+#[cfg(feature = "protobuf")]
+pub mod proposals;
+#[cfg(feature = "protobuf")]
+pub mod nft;
+#[cfg(feature = "protobuf")]
+pub mod shielded;
+pub mod contracts;
+pub mod message;
+pub mod catalog;
+pub mod legacy;
+#[cfg(feature = "json")]
+pub mod tronlink;
+#[cfg(feature = "json")]
+pub mod typed_data;
+#[cfg(feature = "json")]
+pub mod json_rpc;
+#[cfg(feature = "json")]
+pub mod diff;
+#[cfg(feature = "differential")]
+pub mod differential;
+// Software keys: built for tests and the `test-signer` feature only, never
+// in wallet builds
+#[cfg(feature = "test-signer")]
+pub mod test_signer;
+#[cfg(all(test, not(feature = "test-signer")))]
+mod test_signer;
+#[cfg(any(test, feature = "test-signer"))]
+pub mod selftest;
+#[cfg(feature = "test-signer")]
+pub mod simulator;
@@ -0,0 +1,272 @@
+//! Collects signatures for a multisig transaction until the signing
+//! permission's threshold is met. Each signature is attributed to its key
+//! by recovering the signer, so a signature from a key outside the
+//! permission, or a second one from the same key, is refused rather than
+//! counted.
+
+use crate::response::{PtrResponse, Response};
+use crate::tron::address::decode_address;
+use crate::tron::permissions::{Permission, PermissionKey};
+use crate::tron::transaction::parse_request;
+use crate::tron::tron_sign_request::TronSignRequest;
+use crate::tron::tron_signature::{recover_address, TronSignature};
+use crate::types::{PtrString, PtrVoid};
+use crate::utils::convert_ptr_string_to_string;
+
+#[cfg(feature = "json")]
+use serde_json::json;
+
+#[derive(Clone, Debug)]
+pub struct MultisigCoordinator {
+    request: TronSignRequest,
+    permission_id: u64,
+    threshold: u64,
+    keys: Vec<PermissionKey>,
+    // Signer address and signature, in the order collected
+    signatures: Vec<(String, TronSignature)>,
+}
+
+impl MultisigCoordinator {
+    /// Fails unless every contract of the transaction is signed under
+    /// `permission_id`
+    pub fn new(request: TronSignRequest, permission_id: u64, threshold: u64, keys: Vec<PermissionKey>) -> Result<Self, String> {
+        let transaction = parse_request(&request)?;
+        if let Some(contract) = transaction.contracts.iter().find(|c| c.permission_id != permission_id) {
+            return Err(format!(
+                "Transaction is signed under permission {}, not {}",
+                contract.permission_id, permission_id
+            ));
+        }
+        if threshold == 0 {
+            return Err("Permission threshold is 0".to_string());
+        }
+        // A repeated key would count one signature's weight twice
+        if let Some((_, key)) = keys.iter().enumerate().find(|(i, k)| keys[..*i].iter().any(|other| other.address == k.address)) {
+            return Err(format!("Key {} is listed twice", key.address));
+        }
+        Ok(MultisigCoordinator { request, permission_id, threshold, keys, signatures: vec![] })
+    }
+
+    pub fn from_permission(request: TronSignRequest, permission: &Permission) -> Result<Self, String> {
+        MultisigCoordinator::new(request, permission.id, permission.threshold, permission.keys.clone())
+    }
+
+    /// Attributes the signature to its key and returns the weight collected
+    /// so far
+    pub fn add_signature(&mut self, signature: &TronSignature) -> Result<u64, String> {
+        if let (Some(expected), Some(actual)) = (self.request.get_request_id(), signature.get_request_id()) {
+            if expected != actual {
+                return Err("Signature answers another request".to_string());
+            }
+        }
+        if !signature.check_salt(&self.request) {
+            return Err("Signature doesn't prove the request salt".to_string());
+        }
+        let signer = recover_address(&self.request, signature)?;
+        if !self.keys.iter().any(|k| k.address == signer) {
+            return Err(format!("{} is not a key of permission {}", signer, self.permission_id));
+        }
+        if self.is_signed_by(&signer) {
+            return Err(format!("{} has already signed", signer));
+        }
+        self.signatures.push((signer, signature.clone()));
+        Ok(self.get_collected_weight())
+    }
+
+    pub fn is_signed_by(&self, address: &str) -> bool {
+        self.signatures.iter().any(|(signer, _)| signer == address)
+    }
+
+    pub fn get_threshold(&self) -> u64 {
+        self.threshold
+    }
+
+    pub fn get_keys(&self) -> &Vec<PermissionKey> {
+        &self.keys
+    }
+
+    pub fn get_collected_weight(&self) -> u64 {
+        self.keys.iter().filter(|k| self.is_signed_by(&k.address)).map(|k| k.weight).sum()
+    }
+
+    /// Whether the collected weight reaches the threshold
+    pub fn is_broadcastable(&self) -> bool {
+        self.get_collected_weight() >= self.threshold
+    }
+
+    /// Signatures in the order collected, as the transaction carries them
+    pub fn get_signatures(&self) -> Vec<&TronSignature> {
+        self.signatures.iter().map(|(_, signature)| signature).collect()
+    }
+}
+
+// `address:weight` per line
+fn parse_keys(keys: &str) -> Result<Vec<PermissionKey>, String> {
+    keys.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            let (address, weight) = line.split_once(':').ok_or(format!("Expected address:weight, got {}", line))?;
+            decode_address(address.trim())?;
+            let weight = weight.trim().parse().map_err(|_| format!("Invalid key weight {}", weight))?;
+            Ok(PermissionKey { address: address.trim().to_string(), weight })
+        })
+        .collect()
+}
+
+fn clamp(weight: u64) -> u32 {
+    u32::try_from(weight).unwrap_or(u32::MAX)
+}
+
+// ========== FFI Functions ==========
+
+/// `keys` is one `address:weight` per line, as the permission lists them
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_multisig_new(
+    tron_sign_request: &TronSignRequest,
+    permission_id: u32,
+    threshold: u32,
+    keys: PtrString,
+) -> PtrResponse {
+    let keys = match convert_ptr_string_to_string(keys).and_then(|k| parse_keys(&k)).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    match MultisigCoordinator::new(tron_sign_request.clone(), permission_id as u64, threshold as u64, keys) {
+        Ok(coordinator) => Response::success_object(Box::into_raw(Box::new(coordinator)) as PtrVoid),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+/// Weight collected so far, signature included
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_multisig_add_signature(
+    multisig_coordinator: &mut MultisigCoordinator,
+    tron_signature: &TronSignature,
+) -> PtrResponse {
+    match multisig_coordinator.add_signature(tron_signature) {
+        Ok(weight) => Response::success_uint32(clamp(weight)),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_multisig_is_broadcastable(multisig_coordinator: &MultisigCoordinator) -> PtrResponse {
+    Response::success_boolean(multisig_coordinator.is_broadcastable()).c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_multisig_get_threshold(multisig_coordinator: &MultisigCoordinator) -> PtrResponse {
+    Response::success_uint32(clamp(multisig_coordinator.get_threshold())).c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_multisig_get_collected_weight(multisig_coordinator: &MultisigCoordinator) -> PtrResponse {
+    Response::success_uint32(clamp(multisig_coordinator.get_collected_weight())).c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_multisig_is_signed_by(multisig_coordinator: &MultisigCoordinator, address: PtrString) -> PtrResponse {
+    match convert_ptr_string_to_string(address) {
+        Ok(address) => Response::success_boolean(multisig_coordinator.is_signed_by(address.trim())),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_multisig_get_signatures_len(multisig_coordinator: &MultisigCoordinator) -> PtrResponse {
+    Response::success_uint32(multisig_coordinator.get_signatures().len() as u32).c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_multisig_get_signature(multisig_coordinator: &MultisigCoordinator, index: u32) -> PtrResponse {
+    match multisig_coordinator.get_signatures().get(index as usize) {
+        Some(signature) => Response::success_object(Box::into_raw(Box::new((*signature).clone())) as PtrVoid).c_ptr(),
+        None => Response::error(format!("No signature for index {} was found", index)).c_ptr(),
+    }
+}
+
+/// JSON `{"threshold", "collected", "broadcastable", "keys": [{"address",
+/// "weight", "signed"}]}` for progress screens
+#[cfg(feature = "json")]
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_multisig_get_status(multisig_coordinator: &MultisigCoordinator) -> PtrResponse {
+    let keys: Vec<_> = multisig_coordinator
+        .get_keys()
+        .iter()
+        .map(|k| json!({ "address": k.address, "weight": k.weight, "signed": multisig_coordinator.is_signed_by(&k.address) }))
+        .collect();
+    Response::success_string(
+        json!({
+            "threshold": multisig_coordinator.get_threshold(),
+            "collected": multisig_coordinator.get_collected_weight(),
+            "broadcastable": multisig_coordinator.is_broadcastable(),
+            "keys": keys,
+        })
+        .to_string(),
+    )
+    .c_ptr()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tron::protobuf::encode::{bytes_field, varint_field};
+    use crate::tron::test_signer::TestSigner;
+    use crate::tron::transaction::TRANSFER_CONTRACT;
+
+    fn transfer(permission_id: u64) -> TronSignRequest {
+        let mut value = Vec::new();
+        bytes_field(1, &[0x41; 21], &mut value);
+        bytes_field(2, &[0x41; 21], &mut value);
+        varint_field(3, 1_000_000, &mut value);
+        let mut any = Vec::new();
+        bytes_field(2, &value, &mut any);
+        let mut contract = Vec::new();
+        varint_field(1, TRANSFER_CONTRACT, &mut contract);
+        bytes_field(2, &any, &mut contract);
+        varint_field(5, permission_id, &mut contract);
+        let mut raw_data = Vec::new();
+        bytes_field(11, &contract, &mut raw_data);
+        TronSignRequest::new_with_derived_request_id(raw_data, 1, "m/44'/195'/0'/0/0".to_string(), None, None, None)
+    }
+
+    #[test]
+    fn test_threshold() {
+        let signers: Vec<TestSigner> = (1..=3u8).map(|i| TestSigner::new(&[i; 32]).unwrap()).collect();
+        let keys = format!(
+            "{}:2\n{}:1\n",
+            signers[0].get_address().unwrap(),
+            signers[1].get_address().unwrap()
+        );
+        let request = transfer(2);
+        assert!(MultisigCoordinator::new(request.clone(), 0, 3, parse_keys(&keys).unwrap()).is_err());
+        let mut coordinator = MultisigCoordinator::new(request.clone(), 2, 3, parse_keys(&keys).unwrap()).unwrap();
+
+        assert_eq!(coordinator.add_signature(&signers[0].sign(&request).unwrap()).unwrap(), 2);
+        assert!(!coordinator.is_broadcastable());
+        assert!(coordinator.add_signature(&signers[0].sign(&request).unwrap()).unwrap_err().contains("already signed"));
+        assert!(coordinator.add_signature(&signers[2].sign(&request).unwrap()).unwrap_err().contains("is not a key"));
+        assert!(coordinator.add_signature(&signers[1].sign(&transfer(3)).unwrap()).is_err());
+        assert_eq!(coordinator.add_signature(&signers[1].sign(&request).unwrap()).unwrap(), 3);
+        assert!(coordinator.is_broadcastable());
+        assert_eq!(coordinator.get_signatures().len(), 2);
+
+        let twice = format!("{0}:2\n{0}:2", signers[0].get_address().unwrap());
+        assert!(MultisigCoordinator::new(request.clone(), 2, 3, parse_keys(&twice).unwrap()).unwrap_err().contains("twice"));
+        assert!(parse_keys("TXYZ:1").is_err());
+        assert!(parse_keys(&signers[0].get_address().unwrap()).is_err());
+    }
+}
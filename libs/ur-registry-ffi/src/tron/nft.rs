@@ -0,0 +1,127 @@
+//! TRC-721 transfers decoded from contract calls, so signers show the
+//! collection, token id and recipient of an NFT instead of raw calldata.
+
+#[cfg(feature = "json")]
+use crate::response::{PtrResponse, Response};
+use crate::tron::address::encode_address;
+use crate::tron::amount::format_units;
+use crate::tron::transaction::{parse_request, TriggerSmartContract, TRC20_TRANSFER_FROM};
+use crate::tron::tron_sign_request::{MetadataValue, TronSignRequest};
+
+#[cfg(feature = "json")]
+use serde_json::json;
+
+// safeTransferFrom(address,address,uint256) and its (..., bytes) overload
+pub const TRC721_SAFE_TRANSFER_FROM: [u8; 4] = [0x42, 0x84, 0x2e, 0x0e];
+pub const TRC721_SAFE_TRANSFER_FROM_WITH_DATA: [u8; 4] = [0xb8, 0x8d, 0x4f, 0xde];
+// transferFrom(address,address,uint256), shared with TRC-20
+pub const TRC721_TRANSFER_FROM: [u8; 4] = TRC20_TRANSFER_FROM;
+
+// Metadata key a requester sets to "TRC-721" for NFT contracts, since
+// transferFrom alone doesn't tell tokens and NFTs apart
+pub const TOKEN_STANDARD_KEY: &str = "token.standard";
+pub const TRC721_STANDARD: &str = "TRC-721";
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct NftTransfer {
+    /// Base58 address of the collection contract
+    pub collection: String,
+    pub from: String,
+    pub to: String,
+    /// Decimal token id
+    pub token_id: String,
+}
+
+impl NftTransfer {
+    /// `transferFrom` only counts when `is_nft` says the contract is TRC-721
+    pub fn from_call(call: &TriggerSmartContract, is_nft: bool) -> Result<Option<Self>, String> {
+        match call.get_selector() {
+            Some(TRC721_SAFE_TRANSFER_FROM) | Some(TRC721_SAFE_TRANSFER_FROM_WITH_DATA) => {}
+            Some(TRC721_TRANSFER_FROM) if is_nft => {}
+            _ => return Ok(None),
+        }
+        let (from, to, token_id) = match (call.get_address_argument(0), call.get_address_argument(1), call.get_argument(2)) {
+            (Some(from), Some(to), Some(token_id)) => (from, to, token_id),
+            _ => return Ok(None),
+        };
+        Ok(Some(NftTransfer {
+            collection: encode_address(&call.contract_address)?,
+            from: encode_address(&from)?,
+            to: encode_address(&to)?,
+            token_id: format_units(token_id, 0)?,
+        }))
+    }
+}
+
+/// Whether the request's metadata marks the contract as TRC-721
+pub fn is_nft_request(request: &TronSignRequest) -> bool {
+    matches!(request.get_metadata().get(TOKEN_STANDARD_KEY), Some(MetadataValue::Text(s)) if s == TRC721_STANDARD)
+}
+
+/// The NFT transfer a transaction request makes, if any
+pub fn get_nft_transfer(request: &TronSignRequest) -> Result<Option<NftTransfer>, String> {
+    let is_nft = is_nft_request(request);
+    for contract in parse_request(request)?.contracts.iter() {
+        if let Some(call) = contract.as_trigger_smart_contract()? {
+            if let Some(transfer) = NftTransfer::from_call(&call, is_nft)? {
+                return Ok(Some(transfer));
+            }
+        }
+    }
+    Ok(None)
+}
+
+// ========== FFI Functions ==========
+
+/// JSON `{"collection", "from", "to", "token_id"}`, or `NOT_PRESENT`
+/// when the request moves no NFT
+#[cfg(feature = "json")]
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_request_get_nft_transfer(tron_sign_request: &TronSignRequest) -> PtrResponse {
+    match get_nft_transfer(tron_sign_request) {
+        Ok(transfer) => transfer.map_or(Response::not_present(), |t| {
+            Response::success_string(
+                json!({ "collection": t.collection, "from": t.from, "to": t.to, "token_id": t.token_id }).to_string(),
+            )
+        }),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tron::contracts::AbiFunction;
+
+    #[test]
+    fn test_nft_transfer() {
+        assert_eq!(AbiFunction::new("safeTransferFrom(address,address,uint256)").selector, TRC721_SAFE_TRANSFER_FROM);
+        assert_eq!(AbiFunction::new("safeTransferFrom(address,address,uint256,bytes)").selector, TRC721_SAFE_TRANSFER_FROM_WITH_DATA);
+
+        let mut data = TRC721_SAFE_TRANSFER_FROM.to_vec();
+        for word in [[0x11; 20], [0x22; 20]] {
+            data.extend_from_slice(&[0; 12]);
+            data.extend_from_slice(&word);
+        }
+        data.extend_from_slice(&[0; 30]);
+        data.extend_from_slice(&[0x01, 0x00]);
+        let mut call = TriggerSmartContract { contract_address: vec![0x41; 21], data, ..Default::default() };
+
+        let transfer = NftTransfer::from_call(&call, false).unwrap().unwrap();
+        assert_eq!(transfer.collection, encode_address(&[0x41; 21]).unwrap());
+        let to: Vec<u8> = std::iter::once(0x41).chain([0x22; 20]).collect();
+        assert_eq!(transfer.to, encode_address(&to).unwrap());
+        assert_eq!(transfer.token_id, "256");
+
+        call.data[..4].copy_from_slice(&TRC721_TRANSFER_FROM);
+        assert_eq!(NftTransfer::from_call(&call, false).unwrap(), None);
+        assert_eq!(NftTransfer::from_call(&call, true).unwrap(), Some(transfer));
+
+        let mut request = TronSignRequest::default();
+        assert!(!is_nft_request(&request));
+        request.set_metadata(TOKEN_STANDARD_KEY.to_string(), MetadataValue::Text(TRC721_STANDARD.to_string()));
+        assert!(is_nft_request(&request));
+    }
+}
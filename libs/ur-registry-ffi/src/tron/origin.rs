@@ -0,0 +1,163 @@
+//! Where a sign request comes from: the requesting app, the dApp it acts
+//! for and the device it runs on, so signers can attribute and filter
+//! requests by their true source.
+//!
+//! On the wire an origin naming only the app stays the plain text string
+//! earlier versions sent; anything more is a nested map. Decoders accept
+//! both, reading text as the app.
+
+use crate::tron::decode::{get_text, DecodeError};
+use crate::tron::schema::{FieldSchema, FieldType};
+
+use serde_cbor::Value;
+#[cfg(feature = "json")]
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::fmt;
+
+// CBOR map keys for a structured origin
+pub(crate) const APP: i128 = 1;
+pub(crate) const DAPP_URL: i128 = 2;
+pub(crate) const DEVICE: i128 = 3;
+
+const KNOWN_KEYS: [i128; 3] = [APP, DAPP_URL, DEVICE];
+
+pub(crate) const FIELDS: &[FieldSchema] = &[
+    FieldSchema::optional(APP, "app", FieldType::Text),
+    FieldSchema::optional(DAPP_URL, "dapp_url", FieldType::Text),
+    FieldSchema::optional(DEVICE, "device", FieldType::Text),
+];
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Origin {
+    /// Wallet or app that built the request, e.g. "TronLink"
+    pub app: Option<String>,
+    /// Page the app signs on behalf of
+    pub dapp_url: Option<String>,
+    /// Model of the device the app runs on
+    pub device: Option<String>,
+    // Entries newer versions add, re-emitted so MACs over the body still verify
+    unknown: BTreeMap<Value, Value>,
+}
+
+impl Origin {
+    pub fn new(app: Option<String>, dapp_url: Option<String>, device: Option<String>) -> Self {
+        Origin { app, dapp_url, device, ..Default::default() }
+    }
+
+    /// The single origin string of earlier versions
+    pub fn from_text(text: impl Into<String>) -> Self {
+        Origin::new(Some(text.into()), None, None)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.app.is_none() && self.dapp_url.is_none() && self.device.is_none() && self.unknown.is_empty()
+    }
+
+    /// Encoded as text: nothing but the app is set
+    pub fn is_text(&self) -> bool {
+        self.app.is_some() && self.dapp_url.is_none() && self.device.is_none() && self.unknown.is_empty()
+    }
+
+    pub(crate) fn to_cbor(&self) -> Value {
+        if let (true, Some(app)) = (self.is_text(), &self.app) {
+            return Value::Text(app.clone());
+        }
+        let mut map = self.unknown.clone();
+        for (key, value) in [(APP, &self.app), (DAPP_URL, &self.dapp_url), (DEVICE, &self.device)] {
+            if let Some(value) = value {
+                map.insert(Value::Integer(key), Value::Text(value.clone()));
+            }
+        }
+        Value::Map(map)
+    }
+
+    pub(crate) fn from_cbor(value: &Value) -> Result<Self, DecodeError> {
+        match value {
+            Value::Text(text) => Ok(Origin::from_text(text.clone())),
+            Value::Map(map) => Ok(Origin {
+                app: get_text(map, APP, "origin app")?,
+                dapp_url: get_text(map, DAPP_URL, "origin dApp URL")?,
+                device: get_text(map, DEVICE, "origin device")?,
+                unknown: map
+                    .iter()
+                    .filter(|(k, _)| !matches!(k, Value::Integer(i) if KNOWN_KEYS.contains(i)))
+                    .map(|(k, v)| (k.clone(), v.clone()))
+                    .collect(),
+            }),
+            _ => Err(DecodeError::InvalidType { field: "origin", expected: "text or map" }),
+        }
+    }
+
+    /// Text for text origins, `{"app", "dapp_url", "device"}` otherwise
+    #[cfg(feature = "json")]
+    pub fn to_json(&self) -> serde_json::Value {
+        match (self.is_text(), &self.app) {
+            (true, Some(app)) => json!(app),
+            _ => json!({ "app": self.app, "dapp_url": self.dapp_url, "device": self.device }),
+        }
+    }
+
+    #[cfg(feature = "json")]
+    pub fn from_json(value: &serde_json::Value) -> Result<Self, String> {
+        if let Some(text) = value.as_str() {
+            return Ok(Origin::from_text(text));
+        }
+        let object = value.as_object().ok_or("Origin must be a string or an object")?;
+        let text = |key: &str| object.get(key).and_then(serde_json::Value::as_str).map(str::to_string);
+        Ok(Origin::new(text("app"), text("dapp_url"), text("device")))
+    }
+}
+
+/// "TronLink via https://sunswap.com on Pixel 8", leaving out what's unset
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let parts = [(None, &self.app), (Some("via"), &self.dapp_url), (Some("on"), &self.device)];
+        let mut first = true;
+        for (word, value) in parts.iter().filter_map(|(w, v)| v.as_ref().map(|v| (w, v))) {
+            match (first, word) {
+                (true, _) | (_, None) => write!(f, "{}", value)?,
+                (false, Some(word)) => write!(f, " {} {}", word, value)?,
+            }
+            first = false;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tron::tron_sign_request::TronSignRequest;
+
+    #[test]
+    fn test_origin_cbor() {
+        let text = Origin::from_text("dapp");
+        assert_eq!(text.to_cbor(), Value::Text("dapp".to_string()));
+        assert_eq!(Origin::from_cbor(&text.to_cbor()).unwrap(), text);
+
+        let mut origin = Origin::new(
+            Some("TronLink".to_string()),
+            Some("https://sunswap.com".to_string()),
+            Some("Pixel 8".to_string()),
+        );
+        assert_eq!(origin.to_string(), "TronLink via https://sunswap.com on Pixel 8");
+        let mut map = match origin.to_cbor() {
+            Value::Map(map) => map,
+            _ => unreachable!(),
+        };
+        map.insert(Value::Integer(9), Value::Bool(true));
+        origin = Origin::from_cbor(&Value::Map(map.clone())).unwrap();
+        assert_eq!(origin.to_cbor(), Value::Map(map));
+
+        assert_eq!(Origin::new(None, None, Some("Pixel 8".to_string())).to_string(), "Pixel 8");
+        assert!(Origin::from_cbor(&Value::Integer(1)).is_err());
+
+        let mut request = TronSignRequest::new(None, b"hi".to_vec(), 2, "m/44'/195'/0'/0/0".to_string(), None, None, None);
+        request.set_origin(Some(origin.clone()));
+        let decoded = TronSignRequest::try_from(request.to_bytes().unwrap()).unwrap();
+        assert_eq!(decoded.get_origin(), Some(&origin));
+        request.set_origin(Some(Origin::default()));
+        assert_eq!(request.get_origin(), None);
+    }
+}
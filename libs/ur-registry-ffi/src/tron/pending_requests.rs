@@ -0,0 +1,230 @@
+use crate::response::{PtrResponse, Response};
+use crate::tron::tron_sign_request::TronSignRequest;
+use crate::tron::tron_sign_rejection::TronSignRejection;
+use crate::tron::tron_signature::TronSignature;
+use crate::types::{PtrString, PtrVoid};
+use crate::utils::parse_ptr_string_to_bytes;
+
+use std::collections::HashMap;
+
+/// Correlates outgoing sign requests with the signatures scanned back,
+/// keyed by request_id. Timestamps are supplied by the caller (seconds).
+#[derive(Clone, Debug, Default)]
+pub struct PendingRequests {
+    ttl: u64,
+    entries: HashMap<Vec<u8>, PendingEntry>,
+}
+
+#[derive(Clone, Debug)]
+struct PendingEntry {
+    request: TronSignRequest,
+    expires_at: u64,
+}
+
+impl PendingRequests {
+    pub fn new(ttl: u64) -> Self {
+        PendingRequests {
+            ttl,
+            entries: HashMap::new(),
+        }
+    }
+
+    pub fn insert(&mut self, request: &TronSignRequest, now: u64) -> Result<(), String> {
+        let request_id = request
+            .get_request_id()
+            .ok_or("Cannot track a request without request id")?;
+        self.entries.insert(
+            request_id.clone(),
+            PendingEntry {
+                request: request.clone(),
+                expires_at: now.saturating_add(self.ttl),
+            },
+        );
+        Ok(())
+    }
+
+    /// Removes and returns the pending request answered by this signature.
+    /// A signature that doesn't prove the request's salt leaves it pending.
+    pub fn match_signature(&mut self, signature: &TronSignature, now: u64) -> Result<TronSignRequest, String> {
+        let request_id = signature
+            .get_request_id()
+            .ok_or("No request id supplied")?;
+        if let Some(entry) = self.entries.get(request_id).filter(|entry| entry.expires_at > now) {
+            if !signature.check_salt(&entry.request) {
+                return Err(format!("Signature doesn't prove the salt of request {}", hex::encode(request_id)));
+            }
+        }
+        match self.entries.remove(request_id) {
+            Some(entry) if entry.expires_at > now => Ok(entry.request),
+            Some(_) => Err(format!("Request {} has expired", hex::encode(request_id))),
+            None => Err(format!("No pending request for {}", hex::encode(request_id))),
+        }
+    }
+
+    /// Removes and returns the pending request the cold wallet declined
+    pub fn match_rejection(&mut self, rejection: &TronSignRejection, now: u64) -> Result<TronSignRequest, String> {
+        let request_id = rejection.get_request_id();
+        match self.entries.remove(request_id) {
+            Some(entry) if entry.expires_at > now => Ok(entry.request),
+            Some(_) => Err(format!("Request {} has expired", hex::encode(request_id))),
+            None => Err(format!("No pending request for {}", hex::encode(request_id))),
+        }
+    }
+
+    pub fn contains(&self, request_id: &[u8], now: u64) -> bool {
+        self.entries
+            .get(request_id)
+            .is_some_and(|entry| entry.expires_at > now)
+    }
+
+    /// Drops expired entries, returning how many were removed
+    pub fn expire(&mut self, now: u64) -> usize {
+        let before = self.entries.len();
+        self.entries.retain(|_, entry| entry.expires_at > now);
+        before - self.entries.len()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+// ========== FFI Functions ==========
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_pending_requests_new(ttl_seconds: u32) -> PtrResponse {
+    Response::success_object(Box::into_raw(Box::new(PendingRequests::new(ttl_seconds as u64))) as PtrVoid).c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_pending_requests_insert(
+    pending_requests: &mut PendingRequests,
+    tron_sign_request: &TronSignRequest,
+    now: u64,
+) -> PtrResponse {
+    match pending_requests.insert(tron_sign_request, now) {
+        Ok(_) => Response::success_null(),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_pending_requests_match(
+    pending_requests: &mut PendingRequests,
+    tron_signature: &TronSignature,
+    now: u64,
+) -> PtrResponse {
+    match pending_requests.match_signature(tron_signature, now) {
+        Ok(request) => Response::success_object(Box::into_raw(Box::new(request)) as PtrVoid),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_pending_requests_match_rejection(
+    pending_requests: &mut PendingRequests,
+    tron_sign_rejection: &TronSignRejection,
+    now: u64,
+) -> PtrResponse {
+    match pending_requests.match_rejection(tron_sign_rejection, now) {
+        Ok(request) => Response::success_object(Box::into_raw(Box::new(request)) as PtrVoid),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_pending_requests_contains(
+    pending_requests: &PendingRequests,
+    request_id: PtrString,
+    now: u64,
+) -> PtrResponse {
+    let request_id = match parse_ptr_string_to_bytes(request_id).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    Response::success_boolean(pending_requests.contains(&request_id, now)).c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_pending_requests_expire(pending_requests: &mut PendingRequests, now: u64) -> PtrResponse {
+    Response::success_uint32(pending_requests.expire(now) as u32).c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_pending_requests_len(pending_requests: &PendingRequests) -> PtrResponse {
+    Response::success_uint32(pending_requests.len() as u32).c_ptr()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tron::test_signer::TestSigner;
+    use crate::tron::tron_sign_rejection::RejectionReason;
+
+    fn request(id: u8) -> TronSignRequest {
+        TronSignRequest::new(
+            Some(vec![id; 16]),
+            vec![0x0a, 0x02],
+            1,
+            "m/44'/195'/0'/0/0".to_string(),
+            None,
+            None,
+            None,
+        )
+    }
+
+    #[test]
+    fn test_match_and_expire() {
+        let mut pending = PendingRequests::new(60);
+        pending.insert(&request(1), 100).unwrap();
+        pending.insert(&request(2), 100).unwrap();
+        assert_eq!(pending.len(), 2);
+
+        let matched = pending
+            .match_signature(&TronSignature::new(Some(vec![1; 16]), vec![0; 65]), 120)
+            .unwrap();
+        assert_eq!(matched.get_request_id(), Some(&vec![1; 16]));
+        assert!(pending
+            .match_signature(&TronSignature::new(Some(vec![1; 16]), vec![0; 65]), 120)
+            .is_err());
+
+        let mut salted = request(4);
+        salted.set_salt(Some(vec![9; 16]));
+        pending.insert(&salted, 100).unwrap();
+        let mut signature = TronSignature::new(Some(vec![4; 16]), vec![0; 65]);
+        assert!(pending.match_signature(&signature, 120).unwrap_err().contains("salt"));
+        // An echo alone doesn't prove the signer saw the salt
+        signature.set_salt(Some(vec![9; 16]));
+        assert!(pending.match_signature(&signature, 120).is_err());
+        let signature = TestSigner::new(&[1; 32]).unwrap().sign(&salted).unwrap();
+        assert!(pending.match_signature(&signature, 120).is_ok());
+
+        pending.insert(&request(3), 100).unwrap();
+        let rejection = TronSignRejection::new(vec![3; 16], RejectionReason::UserDeclined, None);
+        let rejection = TronSignRejection::try_from(rejection.to_bytes().unwrap()).unwrap();
+        assert_eq!(rejection.get_reason(), Some(RejectionReason::UserDeclined));
+        assert!(pending.match_rejection(&rejection, 120).is_ok());
+        assert!(!pending.contains(&[3; 16], 120));
+        pending.insert(&request(3), 100).unwrap();
+        assert!(pending.match_rejection(&rejection, 160).unwrap_err().contains("expired"));
+        assert!(pending.match_rejection(&rejection, 120).unwrap_err().contains("No pending request"));
+
+        assert!(!pending.contains(&[2; 16], 160));
+        assert_eq!(pending.expire(160), 1);
+        assert!(pending.is_empty());
+    }
+}
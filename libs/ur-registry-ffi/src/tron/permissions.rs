@@ -0,0 +1,263 @@
+//! Decoded `AccountPermissionUpdateContract`, which replaces the keys and
+//! thresholds controlling an account and so must be reviewed in full.
+
+#[cfg(feature = "json")]
+use crate::response::{PtrResponse, Response};
+use crate::tron::address::encode_address;
+use crate::tron::protobuf::{find_field, parse_fields, WireValue};
+use crate::tron::transaction::{parse_request, Contract, ACCOUNT_PERMISSION_UPDATE_CONTRACT};
+use crate::tron::tron_sign_request::TronSignRequest;
+
+#[cfg(feature = "json")]
+use serde_json::{json, Value};
+
+// AccountPermissionUpdateContract
+const UPDATE_OWNER_ADDRESS: u32 = 1;
+const UPDATE_OWNER: u32 = 2;
+const UPDATE_WITNESS: u32 = 3;
+const UPDATE_ACTIVES: u32 = 4;
+
+// Permission
+const PERMISSION_TYPE: u32 = 1;
+const PERMISSION_ID: u32 = 2;
+const PERMISSION_NAME: u32 = 3;
+const PERMISSION_THRESHOLD: u32 = 4;
+const PERMISSION_PARENT_ID: u32 = 5;
+const PERMISSION_OPERATIONS: u32 = 6;
+const PERMISSION_KEYS: u32 = 7;
+
+// Key
+const KEY_ADDRESS: u32 = 1;
+const KEY_WEIGHT: u32 = 2;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PermissionType {
+    Owner,
+    Witness,
+    Active,
+}
+
+impl PermissionType {
+    fn from_u64(value: u64) -> Result<Self, String> {
+        match value {
+            0 => Ok(PermissionType::Owner),
+            1 => Ok(PermissionType::Witness),
+            2 => Ok(PermissionType::Active),
+            v => Err(format!("Unknown permission type {}", v)),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PermissionType::Owner => "Owner",
+            PermissionType::Witness => "Witness",
+            PermissionType::Active => "Active",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PermissionKey {
+    /// Base58 address
+    pub address: String,
+    pub weight: u64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Permission {
+    pub permission_type: PermissionType,
+    pub id: u64,
+    pub name: String,
+    /// Sum of key weights needed to sign
+    pub threshold: u64,
+    pub parent_id: u64,
+    /// 32-byte bitmap of allowed contract types; empty for owner and witness
+    pub operations: Vec<u8>,
+    pub keys: Vec<PermissionKey>,
+}
+
+impl Permission {
+    fn parse(data: &[u8]) -> Result<Self, String> {
+        let fields = parse_fields(data)?;
+        let varint = |number| find_field(&fields, number).and_then(|v| v.as_varint()).unwrap_or_default();
+        let keys = repeated(&fields, PERMISSION_KEYS)?
+            .into_iter()
+            .map(|key| {
+                let fields = parse_fields(key)?;
+                let address = find_field(&fields, KEY_ADDRESS).and_then(|v| v.as_bytes()).unwrap_or_default();
+                Ok(PermissionKey {
+                    address: encode_address(address)?,
+                    weight: find_field(&fields, KEY_WEIGHT).and_then(|v| v.as_varint()).unwrap_or_default(),
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        Ok(Permission {
+            permission_type: PermissionType::from_u64(varint(PERMISSION_TYPE))?,
+            id: varint(PERMISSION_ID),
+            name: find_field(&fields, PERMISSION_NAME).and_then(|v| v.as_str()).unwrap_or_default().to_string(),
+            threshold: varint(PERMISSION_THRESHOLD),
+            parent_id: varint(PERMISSION_PARENT_ID),
+            operations: find_field(&fields, PERMISSION_OPERATIONS).and_then(|v| v.as_bytes()).unwrap_or_default().to_vec(),
+            keys,
+        })
+    }
+
+    /// Contract types the permission may sign, bit `n` of the bitmap being
+    /// contract type `n`
+    pub fn get_allowed_contract_types(&self) -> Vec<u64> {
+        (0..self.operations.len() * 8)
+            .filter(|n| self.operations[n / 8] & (1 << (n % 8)) != 0)
+            .map(|n| n as u64)
+            .collect()
+    }
+
+    /// Whether the keys can reach the threshold at all
+    pub fn is_satisfiable(&self) -> bool {
+        self.keys.iter().map(|k| k.weight).sum::<u64>() >= self.threshold
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct AccountPermissionUpdate {
+    /// Base58 address of the account being updated
+    pub owner_address: String,
+    pub owner: Option<Permission>,
+    pub witness: Option<Permission>,
+    pub actives: Vec<Permission>,
+}
+
+impl AccountPermissionUpdate {
+    pub fn from_contract(contract: &Contract) -> Result<Option<Self>, String> {
+        if contract.contract_type != ACCOUNT_PERMISSION_UPDATE_CONTRACT {
+            return Ok(None);
+        }
+        let fields = parse_fields(&contract.value)?;
+        let permission = |number| find_field(&fields, number).and_then(|v| v.as_bytes()).map(Permission::parse).transpose();
+        let owner_address = find_field(&fields, UPDATE_OWNER_ADDRESS).and_then(|v| v.as_bytes()).unwrap_or_default();
+        Ok(Some(AccountPermissionUpdate {
+            owner_address: encode_address(owner_address)?,
+            owner: permission(UPDATE_OWNER)?,
+            witness: permission(UPDATE_WITNESS)?,
+            actives: repeated(&fields, UPDATE_ACTIVES)?
+                .into_iter()
+                .map(Permission::parse)
+                .collect::<Result<Vec<_>, String>>()?,
+        }))
+    }
+}
+
+fn repeated<'a>(fields: &[(u32, WireValue<'a>)], number: u32) -> Result<Vec<&'a [u8]>, String> {
+    fields
+        .iter()
+        .filter(|(n, _)| *n == number)
+        .map(|(_, v)| v.as_bytes().ok_or_else(|| format!("Field {} is not a message", number)))
+        .collect()
+}
+
+/// The permission update a transaction request makes, if any
+pub fn get_permission_update(request: &TronSignRequest) -> Result<Option<AccountPermissionUpdate>, String> {
+    for contract in parse_request(request)?.contracts.iter() {
+        if let Some(update) = AccountPermissionUpdate::from_contract(contract)? {
+            return Ok(Some(update));
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(feature = "json")]
+fn permission_to_json(permission: &Permission) -> Value {
+    let keys: Vec<Value> = permission
+        .keys
+        .iter()
+        .map(|k| json!({ "address": k.address, "weight": k.weight }))
+        .collect();
+    json!({
+        "type": permission.permission_type.as_str(),
+        "id": permission.id,
+        "name": permission.name,
+        "threshold": permission.threshold,
+        "parent_id": permission.parent_id,
+        "operations": permission.get_allowed_contract_types(),
+        "keys": keys,
+        "satisfiable": permission.is_satisfiable(),
+    })
+}
+
+// ========== FFI Functions ==========
+
+/// JSON `{"owner_address", "owner", "witness", "actives"}`, or
+/// `NOT_PRESENT` when the request updates no permissions. `operations` lists contract type numbers.
+#[cfg(feature = "json")]
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_request_get_permission_update(tron_sign_request: &TronSignRequest) -> PtrResponse {
+    match get_permission_update(tron_sign_request) {
+        Ok(update) => update.map_or(Response::not_present(), |u| {
+            let actives: Vec<Value> = u.actives.iter().map(permission_to_json).collect();
+            Response::success_string(
+                json!({
+                    "owner_address": u.owner_address,
+                    "owner": u.owner.as_ref().map(permission_to_json),
+                    "witness": u.witness.as_ref().map(permission_to_json),
+                    "actives": actives,
+                })
+                .to_string(),
+            )
+        }),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tron::protobuf::encode::{bytes_field, varint_field};
+
+    fn permission(permission_type: u64, threshold: u64, operations: Option<&[u8]>, keys: &[(&[u8], u64)]) -> Vec<u8> {
+        let mut out = Vec::new();
+        varint_field(PERMISSION_TYPE, permission_type, &mut out);
+        bytes_field(PERMISSION_NAME, b"owner", &mut out);
+        varint_field(PERMISSION_THRESHOLD, threshold, &mut out);
+        if let Some(operations) = operations {
+            bytes_field(PERMISSION_OPERATIONS, operations, &mut out);
+        }
+        for (address, weight) in keys {
+            let mut key = Vec::new();
+            bytes_field(KEY_ADDRESS, address, &mut key);
+            varint_field(KEY_WEIGHT, *weight, &mut key);
+            bytes_field(PERMISSION_KEYS, &key, &mut out);
+        }
+        out
+    }
+
+    #[test]
+    fn test_permission_update() {
+        let mut operations = [0u8; 32];
+        // TransferContract and TriggerSmartContract
+        operations[0] = 0b10;
+        operations[3] = 0b1000_0000;
+        let other: Vec<u8> = std::iter::once(0x41).chain([0x42; 20]).collect();
+        let mut value = Vec::new();
+        bytes_field(UPDATE_OWNER_ADDRESS, &[0x41; 21], &mut value);
+        bytes_field(UPDATE_OWNER, &permission(0, 2, None, &[(&other, 1)]), &mut value);
+        bytes_field(UPDATE_ACTIVES, &permission(2, 1, Some(&operations), &[(&[0x41; 21][..], 1)]), &mut value);
+        let contract = Contract {
+            contract_type: ACCOUNT_PERMISSION_UPDATE_CONTRACT,
+            value,
+            ..Default::default()
+        };
+
+        let update = AccountPermissionUpdate::from_contract(&contract).unwrap().unwrap();
+        assert_eq!(update.owner_address, encode_address(&[0x41; 21]).unwrap());
+        let owner = update.owner.unwrap();
+        assert_eq!(owner.permission_type, PermissionType::Owner);
+        assert_eq!(owner.keys[0].address, encode_address(&other).unwrap());
+        assert!(!owner.is_satisfiable());
+        assert!(update.witness.is_none());
+        assert_eq!(update.actives[0].get_allowed_contract_types(), vec![1, 31]);
+        assert!(update.actives[0].is_satisfiable());
+
+        assert_eq!(AccountPermissionUpdate::from_contract(&Contract::default()).unwrap(), None);
+    }
+}
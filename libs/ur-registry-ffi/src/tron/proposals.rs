@@ -0,0 +1,250 @@
+//! Decoded governance transactions (ProposalCreate, ProposalApprove and
+//! ProposalDelete), with chain parameter ids mapped to their names, for
+//! super representatives signing air-gapped.
+
+use crate::response::{PtrResponse, Response};
+use crate::tron::address::encode_address;
+use crate::tron::protobuf::{find_field, parse_fields};
+use crate::tron::transaction::{
+    parse_request, Contract, PROPOSAL_APPROVE_CONTRACT, PROPOSAL_CREATE_CONTRACT, PROPOSAL_DELETE_CONTRACT,
+};
+use crate::tron::tron_sign_request::TronSignRequest;
+
+#[cfg(feature = "json")]
+use serde_json::{json, Value};
+
+// Proposal*Contract
+const PROPOSAL_OWNER_ADDRESS: u32 = 1;
+const PROPOSAL_PARAMETERS: u32 = 2;
+const PROPOSAL_ID: u32 = 2;
+const PROPOSAL_IS_ADD_APPROVAL: u32 = 3;
+// map<int64, int64> entries
+const MAP_KEY: u32 = 1;
+const MAP_VALUE: u32 = 2;
+
+// Chain parameter ids, as java-tron's ProposalType names them
+const PARAMETER_NAMES: [(u64, &str); 66] = [
+    (0, "MAINTENANCE_TIME_INTERVAL"),
+    (1, "ACCOUNT_UPGRADE_COST"),
+    (2, "CREATE_ACCOUNT_FEE"),
+    (3, "TRANSACTION_FEE"),
+    (4, "ASSET_ISSUE_FEE"),
+    (5, "WITNESS_PAY_PER_BLOCK"),
+    (6, "WITNESS_STANDBY_ALLOWANCE"),
+    (7, "CREATE_NEW_ACCOUNT_FEE_IN_SYSTEM_CONTRACT"),
+    (8, "CREATE_NEW_ACCOUNT_BANDWIDTH_RATE"),
+    (9, "ALLOW_CREATION_OF_CONTRACTS"),
+    (10, "REMOVE_THE_POWER_OF_THE_GR"),
+    (11, "ENERGY_FEE"),
+    (12, "EXCHANGE_CREATE_FEE"),
+    (13, "MAX_CPU_TIME_OF_ONE_TX"),
+    (14, "ALLOW_UPDATE_ACCOUNT_NAME"),
+    (15, "ALLOW_SAME_TOKEN_NAME"),
+    (16, "ALLOW_DELEGATE_RESOURCE"),
+    (17, "TOTAL_ENERGY_LIMIT"),
+    (18, "ALLOW_TVM_TRANSFER_TRC10"),
+    (19, "TOTAL_CURRENT_ENERGY_LIMIT"),
+    (20, "ALLOW_MULTI_SIGN"),
+    (21, "ALLOW_ADAPTIVE_ENERGY"),
+    (22, "UPDATE_ACCOUNT_PERMISSION_FEE"),
+    (23, "MULTI_SIGN_FEE"),
+    (24, "ALLOW_PROTO_FILTER_NUM"),
+    (25, "ALLOW_ACCOUNT_STATE_ROOT"),
+    (26, "ALLOW_TVM_CONSTANTINOPLE"),
+    (29, "ADAPTIVE_RESOURCE_LIMIT_MULTIPLIER"),
+    (30, "ALLOW_CHANGE_DELEGATION"),
+    (31, "WITNESS_127_PAY_PER_BLOCK"),
+    (32, "ALLOW_TVM_SOLIDITY_059"),
+    (33, "ADAPTIVE_RESOURCE_LIMIT_TARGET_RATIO"),
+    (35, "FORBID_TRANSFER_TO_CONTRACT"),
+    (39, "ALLOW_SHIELDED_TRC20_TRANSACTION"),
+    (40, "ALLOW_PBFT"),
+    (41, "ALLOW_TVM_ISTANBUL"),
+    (44, "ALLOW_MARKET_TRANSACTION"),
+    (45, "MARKET_SELL_FEE"),
+    (46, "MARKET_CANCEL_FEE"),
+    (47, "MAX_FEE_LIMIT"),
+    (48, "ALLOW_TRANSACTION_FEE_POOL"),
+    (49, "ALLOW_BLACKHOLE_OPTIMIZATION"),
+    (51, "ALLOW_NEW_RESOURCE_MODEL"),
+    (52, "ALLOW_TVM_FREEZE"),
+    (53, "ALLOW_ACCOUNT_ASSET_OPTIMIZATION"),
+    (59, "ALLOW_TVM_VOTE"),
+    (60, "ALLOW_TVM_COMPATIBLE_EVM"),
+    (61, "FREE_NET_LIMIT"),
+    (62, "TOTAL_NET_LIMIT"),
+    (63, "ALLOW_TVM_LONDON"),
+    (65, "ALLOW_HIGHER_LIMIT_FOR_MAX_CPU_TIME_OF_ONE_TX"),
+    (66, "ALLOW_ASSET_OPTIMIZATION"),
+    (67, "ALLOW_NEW_REWARD"),
+    (68, "MEMO_FEE"),
+    (69, "ALLOW_DELEGATE_OPTIMIZATION"),
+    (70, "UNFREEZE_DELAY_DAYS"),
+    (71, "ALLOW_OPTIMIZED_RETURN_VALUE_OF_CHAIN_ID"),
+    (72, "ALLOW_DYNAMIC_ENERGY"),
+    (73, "DYNAMIC_ENERGY_THRESHOLD"),
+    (74, "DYNAMIC_ENERGY_INCREASE_FACTOR"),
+    (75, "DYNAMIC_ENERGY_MAX_FACTOR"),
+    (76, "ALLOW_TVM_SHANGHAI"),
+    (77, "ALLOW_CANCEL_ALL_UNFREEZE_V2"),
+    (78, "MAX_DELEGATE_LOCK_PERIOD"),
+    (79, "ALLOW_OLD_REWARD_OPT"),
+    (81, "ALLOW_ENERGY_ADJUSTMENT"),
+];
+
+/// Name of chain parameter `id`; `None` for parameters added after this table
+pub fn parameter_name(id: u64) -> Option<&'static str> {
+    PARAMETER_NAMES.iter().find(|(n, _)| *n == id).map(|(_, name)| *name)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProposalParameter {
+    pub id: u64,
+    pub value: i64,
+}
+
+impl ProposalParameter {
+    pub fn get_name(&self) -> Option<&'static str> {
+        parameter_name(self.id)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Proposal {
+    Create { owner_address: String, parameters: Vec<ProposalParameter> },
+    /// `approve` is false when withdrawing an earlier approval
+    Approve { owner_address: String, proposal_id: u64, approve: bool },
+    Delete { owner_address: String, proposal_id: u64 },
+}
+
+impl Proposal {
+    pub fn from_contract(contract: &Contract) -> Result<Option<Self>, String> {
+        if !matches!(
+            contract.contract_type,
+            PROPOSAL_CREATE_CONTRACT | PROPOSAL_APPROVE_CONTRACT | PROPOSAL_DELETE_CONTRACT
+        ) {
+            return Ok(None);
+        }
+        let fields = parse_fields(&contract.value)?;
+        let varint = |number| find_field(&fields, number).and_then(|v| v.as_varint()).unwrap_or_default();
+        let owner_address = encode_address(find_field(&fields, PROPOSAL_OWNER_ADDRESS).and_then(|v| v.as_bytes()).unwrap_or_default())?;
+        Ok(Some(match contract.contract_type {
+            PROPOSAL_CREATE_CONTRACT => {
+                let mut parameters = Vec::new();
+                for (number, value) in fields.iter() {
+                    if *number != PROPOSAL_PARAMETERS {
+                        continue;
+                    }
+                    let entry = parse_fields(value.as_bytes().ok_or("Proposal parameter is not a message")?)?;
+                    parameters.push(ProposalParameter {
+                        id: find_field(&entry, MAP_KEY).and_then(|v| v.as_varint()).unwrap_or_default(),
+                        value: find_field(&entry, MAP_VALUE).and_then(|v| v.as_varint()).unwrap_or_default() as i64,
+                    });
+                }
+                Proposal::Create { owner_address, parameters }
+            }
+            PROPOSAL_APPROVE_CONTRACT => Proposal::Approve {
+                owner_address,
+                proposal_id: varint(PROPOSAL_ID),
+                approve: varint(PROPOSAL_IS_ADD_APPROVAL) != 0,
+            },
+            _ => Proposal::Delete { owner_address, proposal_id: varint(PROPOSAL_ID) },
+        }))
+    }
+}
+
+/// The proposal action a transaction request takes, if any
+pub fn get_proposal(request: &TronSignRequest) -> Result<Option<Proposal>, String> {
+    for contract in parse_request(request)?.contracts.iter() {
+        if let Some(proposal) = Proposal::from_contract(contract)? {
+            return Ok(Some(proposal));
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(feature = "json")]
+fn proposal_to_json(proposal: &Proposal) -> Value {
+    match proposal {
+        Proposal::Create { owner_address, parameters } => {
+            let parameters: Vec<Value> = parameters
+                .iter()
+                .map(|p| json!({ "id": p.id, "name": p.get_name(), "value": p.value }))
+                .collect();
+            json!({ "action": "create", "owner_address": owner_address, "parameters": parameters })
+        }
+        Proposal::Approve { owner_address, proposal_id, approve } => {
+            json!({ "action": "approve", "owner_address": owner_address, "proposal_id": proposal_id, "approve": approve })
+        }
+        Proposal::Delete { owner_address, proposal_id } => {
+            json!({ "action": "delete", "owner_address": owner_address, "proposal_id": proposal_id })
+        }
+    }
+}
+
+// ========== FFI Functions ==========
+
+/// `NOT_PRESENT` for parameters this build doesn't know
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_proposal_parameter_name(id: u64) -> PtrResponse {
+    parameter_name(id)
+        .map_or(Response::not_present(), |name| Response::success_string(name.to_string()))
+        .c_ptr()
+}
+
+/// JSON with an `action` of "create", "approve" or "delete", or
+/// `NOT_PRESENT` when the request isn't a proposal. Unknown parameters have a null `name`.
+#[cfg(feature = "json")]
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_request_get_proposal(tron_sign_request: &TronSignRequest) -> PtrResponse {
+    match get_proposal(tron_sign_request) {
+        Ok(proposal) => proposal.map_or(Response::not_present(), |p| Response::success_string(proposal_to_json(&p).to_string())),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tron::protobuf::encode::{bytes_field, varint_field};
+
+    #[test]
+    fn test_proposals() {
+        let mut value = Vec::new();
+        bytes_field(PROPOSAL_OWNER_ADDRESS, &[0x41; 21], &mut value);
+        for (id, parameter) in [(11, 420), (1000, 1)] {
+            let mut entry = Vec::new();
+            varint_field(MAP_KEY, id, &mut entry);
+            varint_field(MAP_VALUE, parameter, &mut entry);
+            bytes_field(PROPOSAL_PARAMETERS, &entry, &mut value);
+        }
+        let create = Contract { contract_type: PROPOSAL_CREATE_CONTRACT, value, ..Default::default() };
+        match Proposal::from_contract(&create).unwrap().unwrap() {
+            Proposal::Create { parameters, .. } => {
+                assert_eq!(parameters[0], ProposalParameter { id: 11, value: 420 });
+                assert_eq!(parameters[0].get_name(), Some("ENERGY_FEE"));
+                assert_eq!(parameters[1].get_name(), None);
+            }
+            p => panic!("{:?}", p),
+        }
+
+        let mut value = Vec::new();
+        bytes_field(PROPOSAL_OWNER_ADDRESS, &[0x41; 21], &mut value);
+        varint_field(PROPOSAL_ID, 87, &mut value);
+        let approve = Contract { contract_type: PROPOSAL_APPROVE_CONTRACT, value: value.clone(), ..Default::default() };
+        let owner_address = encode_address(&[0x41; 21]).unwrap();
+        assert_eq!(
+            Proposal::from_contract(&approve).unwrap(),
+            Some(Proposal::Approve { owner_address: owner_address.clone(), proposal_id: 87, approve: false })
+        );
+        let delete = Contract { contract_type: PROPOSAL_DELETE_CONTRACT, value, ..Default::default() };
+        assert_eq!(
+            Proposal::from_contract(&delete).unwrap(),
+            Some(Proposal::Delete { owner_address, proposal_id: 87 })
+        );
+        assert_eq!(Proposal::from_contract(&Contract::default()).unwrap(), None);
+    }
+}
@@ -0,0 +1,136 @@
+//! Minimal protobuf wire-format reader, enough to walk TRON transactions
+//! and legacy payloads without generated code
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WireValue<'a> {
+    Varint(u64),
+    Fixed64(u64),
+    Bytes(&'a [u8]),
+    Fixed32(u32),
+}
+
+impl<'a> WireValue<'a> {
+    pub fn as_varint(&self) -> Option<u64> {
+        if let WireValue::Varint(v) = self { Some(*v) } else { None }
+    }
+
+    pub fn as_bytes(&self) -> Option<&'a [u8]> {
+        if let WireValue::Bytes(b) = self { Some(b) } else { None }
+    }
+
+    pub fn as_str(&self) -> Option<&'a str> {
+        self.as_bytes().and_then(|b| std::str::from_utf8(b).ok())
+    }
+}
+
+/// Fields of one message in wire order; repeated fields appear repeatedly
+pub fn parse_fields(data: &[u8]) -> Result<Vec<(u32, WireValue<'_>)>, String> {
+    let mut fields = Vec::new();
+    let mut pos = 0;
+    while pos < data.len() {
+        let key = read_varint(data, &mut pos)?;
+        let number = u32::try_from(key >> 3).map_err(|_| "Field number out of range".to_string())?;
+        if number == 0 {
+            return Err("Invalid field number 0".to_string());
+        }
+        let value = match key & 0x7 {
+            0 => WireValue::Varint(read_varint(data, &mut pos)?),
+            1 => WireValue::Fixed64(u64::from_le_bytes(take(data, &mut pos, 8)?.try_into().unwrap())),
+            2 => {
+                let len = read_varint(data, &mut pos)?;
+                let len = usize::try_from(len).map_err(|_| "Length out of range".to_string())?;
+                WireValue::Bytes(take(data, &mut pos, len)?)
+            }
+            5 => WireValue::Fixed32(u32::from_le_bytes(take(data, &mut pos, 4)?.try_into().unwrap())),
+            t => return Err(format!("Unsupported wire type {} for field {}", t, number)),
+        };
+        fields.push((number, value));
+    }
+    Ok(fields)
+}
+
+/// Last occurrence wins, as protobuf specifies for singular fields
+pub fn find_field<'a>(fields: &[(u32, WireValue<'a>)], number: u32) -> Option<WireValue<'a>> {
+    fields.iter().rev().find(|(n, _)| *n == number).map(|(_, v)| *v)
+}
+
+fn read_varint(data: &[u8], pos: &mut usize) -> Result<u64, String> {
+    let mut value: u64 = 0;
+    for shift in (0..64).step_by(7) {
+        let byte = *data.get(*pos).ok_or("Truncated varint")?;
+        *pos += 1;
+        value |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err("Varint too long".to_string())
+}
+
+fn take<'a>(data: &'a [u8], pos: &mut usize, len: usize) -> Result<&'a [u8], String> {
+    let end = pos.checked_add(len).filter(|end| *end <= data.len()).ok_or("Truncated field")?;
+    let bytes = &data[*pos..end];
+    *pos = end;
+    Ok(bytes)
+}
+
+/// Writers for re-encoding edited messages
+pub mod encode {
+    use super::WireValue;
+
+    pub fn varint(mut value: u64, out: &mut Vec<u8>) {
+        while value >= 0x80 {
+            out.push((value as u8) | 0x80);
+            value >>= 7;
+        }
+        out.push(value as u8);
+    }
+
+    pub fn varint_field(number: u32, value: u64, out: &mut Vec<u8>) {
+        varint((number as u64) << 3, out);
+        varint(value, out);
+    }
+
+    pub fn bytes_field(number: u32, value: &[u8], out: &mut Vec<u8>) {
+        varint(((number as u64) << 3) | 2, out);
+        varint(value.len() as u64, out);
+        out.extend_from_slice(value);
+    }
+
+    pub fn field(number: u32, value: &WireValue, out: &mut Vec<u8>) {
+        match value {
+            WireValue::Varint(v) => varint_field(number, *v, out),
+            WireValue::Fixed64(v) => {
+                varint(((number as u64) << 3) | 1, out);
+                out.extend_from_slice(&v.to_le_bytes());
+            }
+            WireValue::Bytes(b) => bytes_field(number, b, out),
+            WireValue::Fixed32(v) => {
+                varint(((number as u64) << 3) | 5, out);
+                out.extend_from_slice(&v.to_le_bytes());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_fields() {
+        let mut data = Vec::new();
+        encode::varint_field(1, 300, &mut data);
+        encode::bytes_field(2, b"tron", &mut data);
+        encode::varint_field(1, 7, &mut data);
+
+        let fields = parse_fields(&data).unwrap();
+        assert_eq!(fields.len(), 3);
+        assert_eq!(fields[0], (1, WireValue::Varint(300)));
+        assert_eq!(find_field(&fields, 2).and_then(|v| v.as_str()), Some("tron"));
+        assert_eq!(find_field(&fields, 1).and_then(|v| v.as_varint()), Some(7));
+
+        assert!(parse_fields(&data[..data.len() - 1]).is_err());
+        assert!(parse_fields(&[0x12, 0x05, 0x01]).is_err());
+    }
+}
@@ -0,0 +1,270 @@
+//! Remembers what was recently signed and flags the same payload presented
+//! again. Transactions are keyed by txid, so a rebuilt request around the
+//! same raw_data still matches; messages and typed data by the sha256 of
+//! their data type and sign_data.
+//!
+//! Memory holds at most `capacity` keys, least recently signed evicted
+//! first, and keys count for `ttl` seconds. An optional host store extends
+//! this across restarts and evictions: every record is written through to
+//! it, and keys missing from memory are looked up there.
+
+use crate::response::{PtrResponse, Response};
+use crate::tron::telemetry::{self, EventKind};
+use crate::tron::tron_sign_request::{DataType, TronSignRequest};
+use crate::types::{PtrString, PtrVoid};
+use crate::utils::parse_ptr_string_to_bytes;
+
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, VecDeque};
+use std::ffi::CString;
+use std::os::raw::c_char;
+
+// Telemetry source of replay events
+const REPLAY_GUARD_SOURCE: &str = "replay-guard";
+
+/// Persists a hex key with the time it was signed
+pub type ReplayRecordCallback = extern "C" fn(key: *const c_char, signed_at: u64, context: PtrVoid);
+/// Time a hex key was signed, or 0 when the store doesn't have it
+pub type ReplayLookupCallback = extern "C" fn(key: *const c_char, context: PtrVoid) -> u64;
+
+#[derive(Clone, Copy, Debug)]
+struct ReplayStore {
+    record: ReplayRecordCallback,
+    lookup: ReplayLookupCallback,
+    // The caller's pointer, opaque to us
+    context: usize,
+}
+
+/// txid for transactions, sha256(data_type || sign_data) otherwise
+pub fn replay_key(request: &TronSignRequest) -> Result<[u8; 32], String> {
+    let sign_data = request.get_sign_data();
+    Ok(match DataType::from_u32(request.get_data_type())? {
+        DataType::Transaction => Sha256::digest(sign_data).into(),
+        data_type => {
+            let mut hasher = Sha256::new();
+            hasher.update([data_type.to_u32() as u8]);
+            hasher.update(sign_data);
+            hasher.finalize().into()
+        }
+    })
+}
+
+#[derive(Clone, Debug)]
+pub struct ReplayGuard {
+    capacity: usize,
+    ttl: u64,
+    signed_at: HashMap<[u8; 32], u64>,
+    // Least recently signed first
+    order: VecDeque<[u8; 32]>,
+    store: Option<ReplayStore>,
+}
+
+impl ReplayGuard {
+    pub fn new(capacity: usize, ttl: u64) -> Self {
+        ReplayGuard {
+            capacity: capacity.max(1),
+            ttl,
+            signed_at: HashMap::new(),
+            order: VecDeque::new(),
+            store: None,
+        }
+    }
+
+    fn is_live(&self, signed_at: u64, now: u64) -> bool {
+        signed_at.saturating_add(self.ttl) > now
+    }
+
+    /// When the key was signed before, if within the TTL
+    pub fn check_key(&self, key: &[u8; 32], now: u64) -> Option<u64> {
+        let signed_at = self.signed_at.get(key).copied().or_else(|| {
+            let store = self.store?;
+            let key = CString::new(hex::encode(key)).unwrap();
+            Some((store.lookup)(key.as_ptr(), store.context as PtrVoid)).filter(|t| *t != 0)
+        })?;
+        Some(signed_at).filter(|t| self.is_live(*t, now))
+    }
+
+    /// When `request` was signed before, reporting a telemetry warning if
+    /// it was
+    pub fn check(&self, request: &TronSignRequest, now: u64) -> Result<Option<u64>, String> {
+        let signed_at = self.check_key(&replay_key(request)?, now);
+        if signed_at.is_some() {
+            let label = request.get_request_id().map(hex::encode).unwrap_or_default();
+            telemetry::emit(EventKind::ValidationWarning, REPLAY_GUARD_SOURCE, "Replay", &label);
+        }
+        Ok(signed_at)
+    }
+
+    /// Loads a key without writing it through, e.g. from the store at startup
+    pub fn restore(&mut self, key: [u8; 32], signed_at: u64) {
+        self.order.retain(|k| k != &key);
+        self.order.push_back(key);
+        self.signed_at.insert(key, signed_at);
+        while self.order.len() > self.capacity {
+            if let Some(evicted) = self.order.pop_front() {
+                self.signed_at.remove(&evicted);
+            }
+        }
+    }
+
+    /// Remembers `request` as signed at `now`
+    pub fn record(&mut self, request: &TronSignRequest, now: u64) -> Result<(), String> {
+        let key = replay_key(request)?;
+        self.restore(key, now);
+        if let Some(store) = self.store {
+            let key = CString::new(hex::encode(key)).unwrap();
+            (store.record)(key.as_ptr(), now, store.context as PtrVoid);
+        }
+        Ok(())
+    }
+
+    /// Drops keys past their TTL, returning how many were removed
+    pub fn expire(&mut self, now: u64) -> usize {
+        let before = self.order.len();
+        let live: Vec<[u8; 32]> = self.order.iter().filter(|k| self.is_live(self.signed_at[*k], now)).copied().collect();
+        self.signed_at.retain(|_, t| t.saturating_add(self.ttl) > now);
+        self.order = live.into();
+        before - self.order.len()
+    }
+}
+
+// ========== FFI Functions ==========
+
+/// Guard holding up to `capacity` keys for `ttl_seconds` each
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_replay_guard_new(capacity: u32, ttl_seconds: u32) -> PtrResponse {
+    Response::success_object(Box::into_raw(Box::new(ReplayGuard::new(capacity as usize, ttl_seconds as u64))) as PtrVoid).c_ptr()
+}
+
+/// Sets the host store, or clears it when either callback is null. Keys are
+/// valid only during the callback; `context` is passed back untouched.
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_replay_guard_set_store(
+    replay_guard: &mut ReplayGuard,
+    record: Option<ReplayRecordCallback>,
+    lookup: Option<ReplayLookupCallback>,
+    context: PtrVoid,
+) -> PtrResponse {
+    replay_guard.store = record.zip(lookup).map(|(record, lookup)| ReplayStore { record, lookup, context: context as usize });
+    Response::success_null().c_ptr()
+}
+
+/// Whether `tron_sign_request` was signed within the TTL
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_replay_guard_check(
+    replay_guard: &ReplayGuard,
+    tron_sign_request: &TronSignRequest,
+    now: u64,
+) -> PtrResponse {
+    match replay_guard.check(tron_sign_request, now) {
+        Ok(signed_at) => Response::success_boolean(signed_at.is_some()),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_replay_guard_record(
+    replay_guard: &mut ReplayGuard,
+    tron_sign_request: &TronSignRequest,
+    now: u64,
+) -> PtrResponse {
+    match replay_guard.record(tron_sign_request, now) {
+        Ok(_) => Response::success_null(),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+/// Loads a hex key from the host store without writing it back
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_replay_guard_restore(replay_guard: &mut ReplayGuard, key: PtrString, signed_at: u64) -> PtrResponse {
+    let key = match parse_ptr_string_to_bytes(key).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    match <[u8; 32]>::try_from(key) {
+        Ok(key) => {
+            replay_guard.restore(key, signed_at);
+            Response::success_null()
+        }
+        Err(_) => Response::error("Replay keys are 32 bytes".to_string()),
+    }
+    .c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_replay_guard_expire(replay_guard: &mut ReplayGuard, now: u64) -> PtrResponse {
+    Response::success_uint32(replay_guard.expire(now) as u32).c_ptr()
+}
+
+/// Hex key the guard stores for `tron_sign_request`
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_request_get_replay_key(tron_sign_request: &TronSignRequest) -> PtrResponse {
+    match replay_key(tron_sign_request) {
+        Ok(key) => Response::success_string(hex::encode(key)),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CStr;
+    use std::sync::Mutex;
+
+    static STORE: Mutex<Vec<(String, u64)>> = Mutex::new(Vec::new());
+
+    extern "C" fn store_record(key: *const c_char, signed_at: u64, _context: PtrVoid) {
+        let key = unsafe { CStr::from_ptr(key) }.to_string_lossy().into_owned();
+        STORE.lock().unwrap().push((key, signed_at));
+    }
+
+    extern "C" fn store_lookup(key: *const c_char, _context: PtrVoid) -> u64 {
+        let key = unsafe { CStr::from_ptr(key) }.to_string_lossy().into_owned();
+        STORE.lock().unwrap().iter().find(|(k, _)| *k == key).map_or(0, |(_, t)| *t)
+    }
+
+    fn request(sign_data: &[u8], data_type: u32) -> TronSignRequest {
+        TronSignRequest::new_with_derived_request_id(sign_data.to_vec(), data_type, "m/44'/195'/0'/0/0".to_string(), None, None, None)
+    }
+
+    #[cfg(feature = "ffi")]
+    #[test]
+    fn test_replay_guard() {
+        let mut guard = ReplayGuard::new(2, 60);
+        let first = request(b"first", 1);
+        assert_eq!(guard.check(&first, 100).unwrap(), None);
+        guard.record(&first, 100).unwrap();
+        assert_eq!(guard.check(&first, 159).unwrap(), Some(100));
+        assert_eq!(guard.check(&first, 160).unwrap(), None);
+        // Same raw_data under another request id is still a replay
+        let rebuilt = TronSignRequest::new(Some(vec![7; 16]), b"first".to_vec(), 1, "m/44'/195'/0'/0/1".to_string(), None, None, None);
+        assert_eq!(guard.check(&rebuilt, 120).unwrap(), Some(100));
+        assert_eq!(guard.check(&request(b"first", 2), 120).unwrap(), None);
+
+        guard.record(&request(b"second", 1), 110).unwrap();
+        guard.record(&request(b"third", 1), 120).unwrap();
+        assert_eq!(guard.check(&first, 130).unwrap(), None);
+        assert_eq!(guard.expire(175), 1);
+
+        let mut guard = ReplayGuard::new(1, 60);
+        tron_replay_guard_set_store(&mut guard, Some(store_record), Some(store_lookup), std::ptr::null_mut());
+        guard.record(&request(b"stored", 2), 200).unwrap();
+        guard.record(&request(b"evicts", 2), 201).unwrap();
+        assert_eq!(guard.check(&request(b"stored", 2), 210).unwrap(), Some(200));
+
+        let mut restarted = ReplayGuard::new(1, 60);
+        let key = replay_key(&request(b"stored", 2)).unwrap();
+        restarted.restore(key, 200);
+        assert_eq!(restarted.check(&request(b"stored", 2), 210).unwrap(), Some(200));
+    }
+}
@@ -0,0 +1,138 @@
+//! Bandwidth and energy a transaction is expected to consume, so the signer
+//! can warn before a call burns a lot of TRX. Bandwidth follows java-tron's
+//! accounting exactly; energy is a rough range for well-known calls only.
+
+#[cfg(feature = "json")]
+use crate::response::{PtrResponse, Response};
+#[cfg(feature = "json")]
+use crate::tron::transaction::parse_request;
+use crate::tron::transaction::{RawTransaction, TRC20_APPROVE, TRC20_TRANSFER, TRC20_TRANSFER_FROM};
+#[cfg(feature = "json")]
+use crate::tron::tron_sign_request::TronSignRequest;
+
+#[cfg(feature = "json")]
+use serde_json::json;
+
+// Space java-tron reserves per contract for the execution result
+const MAX_RESULT_SIZE_IN_TX: u64 = 64;
+// A signature entry: tag, length, 65-byte signature
+const SIGNATURE_ENTRY_LEN: u64 = 67;
+
+/// Energy ranges for common TRC-20 calls, as seen on mainnet: the low end
+/// for plain tokens, the high end for USDT-like contracts under dynamic
+/// energy writing a fresh storage slot
+const KNOWN_ENERGY: [([u8; 4], u64, u64); 3] = [
+    (TRC20_TRANSFER, 13_000, 131_000),
+    (TRC20_APPROVE, 22_000, 50_000),
+    (TRC20_TRANSFER_FROM, 30_000, 145_000),
+];
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ResourceEstimate {
+    pub bandwidth: u64,
+    /// `None` when the transaction calls a function without a known range
+    pub energy: Option<(u64, u64)>,
+    /// Zero for transactions that don't set one
+    pub fee_limit: u64,
+    /// True for smart-contract calls, which may use energy even without a range
+    pub calls_contract: bool,
+}
+
+impl ResourceEstimate {
+    /// Worst-case TRX burn in SUN when the account has no free resources
+    pub fn max_burn(&self, energy_price: u64, bandwidth_price: u64) -> u64 {
+        let energy = self.energy.map_or(0, |(_, max)| max.saturating_mul(energy_price));
+        let energy = match self.fee_limit {
+            0 => energy,
+            fee_limit => energy.min(fee_limit),
+        };
+        energy.saturating_add(self.bandwidth.saturating_mul(bandwidth_price))
+    }
+}
+
+/// Estimate for `raw_data` once it carries `signature_count` signatures
+pub fn estimate_resources(raw_data: &[u8], signature_count: u64) -> Result<ResourceEstimate, String> {
+    let transaction = RawTransaction::parse(raw_data)?;
+    let raw_len = raw_data.len() as u64;
+    let bandwidth = 1 + varint_len(raw_len) + raw_len
+        + signature_count * SIGNATURE_ENTRY_LEN
+        + transaction.contracts.len() as u64 * MAX_RESULT_SIZE_IN_TX;
+
+    let mut energy = None;
+    let mut calls_contract = false;
+    for contract in transaction.contracts.iter() {
+        if let Some(call) = contract.as_trigger_smart_contract()? {
+            calls_contract = true;
+            let known = KNOWN_ENERGY.iter().find(|(selector, _, _)| Some(*selector) == call.get_selector());
+            energy = known.map(|(_, min, max)| (*min, *max));
+        }
+    }
+
+    Ok(ResourceEstimate { bandwidth, energy, fee_limit: transaction.fee_limit, calls_contract })
+}
+
+fn varint_len(value: u64) -> u64 {
+    (64 - value.max(1).leading_zeros() as u64).div_ceil(7)
+}
+
+// ========== FFI Functions ==========
+
+/// JSON `{"bandwidth", "energyMin", "energyMax", "feeLimit", "callsContract", "maxBurn"}`
+/// for a single-signature transaction; prices are in SUN per unit
+#[cfg(feature = "json")]
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_request_estimate_resources(
+    tron_sign_request: &TronSignRequest,
+    energy_price: u64,
+    bandwidth_price: u64,
+) -> PtrResponse {
+    match parse_request(tron_sign_request)
+        .and_then(|_| estimate_resources(tron_sign_request.get_sign_data(), 1))
+    {
+        Ok(estimate) => Response::success_string(
+            json!({
+                "bandwidth": estimate.bandwidth,
+                "energyMin": estimate.energy.map(|(min, _)| min),
+                "energyMax": estimate.energy.map(|(_, max)| max),
+                "feeLimit": estimate.fee_limit,
+                "callsContract": estimate.calls_contract,
+                "maxBurn": estimate.max_burn(energy_price, bandwidth_price),
+            })
+            .to_string(),
+        ),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tron::protobuf::encode::{bytes_field, varint_field};
+
+    #[test]
+    fn test_estimate_trc20_transfer() {
+        let mut call = Vec::new();
+        bytes_field(1, &[0x41; 21], &mut call);
+        bytes_field(2, &[0x42; 21], &mut call);
+        bytes_field(4, &[0xa9, 0x05, 0x9c, 0xbb, 0, 0], &mut call);
+        let mut any = Vec::new();
+        bytes_field(2, &call, &mut any);
+        let mut contract = Vec::new();
+        varint_field(1, 31, &mut contract);
+        bytes_field(2, &any, &mut contract);
+        let mut raw_data = Vec::new();
+        bytes_field(11, &contract, &mut raw_data);
+        varint_field(18, 30_000_000, &mut raw_data);
+
+        let estimate = estimate_resources(&raw_data, 1).unwrap();
+        assert_eq!(estimate.bandwidth, 2 + raw_data.len() as u64 + 67 + 64);
+        assert_eq!(estimate.energy, Some((13_000, 131_000)));
+        assert!(estimate.calls_contract);
+        assert_eq!(estimate.max_burn(420, 1000), 30_000_000 + estimate.bandwidth * 1000);
+        assert_eq!(estimate.max_burn(100, 0), 13_100_000);
+        assert_eq!(varint_len(127), 1);
+        assert_eq!(varint_len(128), 2);
+    }
+}
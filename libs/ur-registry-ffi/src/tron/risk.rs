@@ -0,0 +1,529 @@
+//! Risk scoring: a configurable set of rules runs over a decoded request and
+//! each match contributes a weighted factor. The score is the sum of the
+//! weights, capped at `MAX_RISK_SCORE`, so hot wallets and signers can pick
+//! their own thresholds for warning, confirming twice or refusing.
+//!
+//! Hosts plug in their own checks with `tron_risk_engine_add_custom_rule`.
+
+use crate::response::{PtrResponse, Response};
+use crate::tron::address::{decode_address, encode_address};
+use crate::tron::analyze::{get_approval, is_blind_signing};
+use crate::tron::contracts::ContractRegistry;
+use crate::tron::transaction::{parse_request, RawTransaction, TRANSFER_CONTRACT, TRIGGER_SMART_CONTRACT};
+use crate::tron::tron_sign_request::TronSignRequest;
+use crate::types::{PtrString, PtrVoid};
+use crate::utils::{convert_ptr_string_to_string, parse_ptr_string_to_bytes};
+
+#[cfg(feature = "json")]
+use serde_json::json;
+
+pub const MAX_RISK_SCORE: u32 = 100;
+
+// Rule name of the factor for transactions that don't decode
+const DECODING_RULE: &str = "Decoding";
+
+/// Returns the weight the request earns from the host's check, 0 for none
+pub type RiskRuleCallback = extern "C" fn(tron_sign_request: &TronSignRequest, context: PtrVoid) -> u32;
+
+#[derive(Clone, Debug)]
+pub enum RiskRule {
+    /// Transfers to base58 addresses outside `allowed`
+    DestinationAllowlist { allowed: Vec<String>, weight: u32 },
+    /// Transfers above `max_amount`, in the asset's smallest unit. `token`
+    /// is the base58 TRC-20 contract, or `None` for TRX sent by transfers
+    /// and contract calls.
+    AmountThreshold { token: Option<String>, max_amount: u128, weight: u32 },
+    /// Calls into contracts missing from `registry`, or to a `blocked`
+    /// base58 address
+    ContractReputation { registry: ContractRegistry, blocked: Vec<String>, unknown_weight: u32, blocked_weight: u32 },
+    /// TRC-20 `approve`, weighted higher for unlimited allowances
+    ApprovalPattern { approval_weight: u32, unlimited_weight: u32 },
+    /// Requests the signer can't display, see `is_blind_signing`
+    BlindSigning { registry: ContractRegistry, weight: u32 },
+    Custom { name: String, callback: RiskRuleCallback, context: usize },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RiskFactor {
+    /// Rule that matched, e.g. `ContractReputation`, or a custom rule's name
+    pub rule: String,
+    /// Stable identifier, e.g. `BlockedContract`
+    pub code: String,
+    pub weight: u32,
+    /// Address or amount the factor is about; may be empty
+    pub detail: String,
+    /// Index of the contract the factor is about, `None` for the request
+    pub contract: Option<usize>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RiskReport {
+    pub score: u32,
+    pub factors: Vec<RiskFactor>,
+}
+
+// Whether a big-endian amount is above `max`
+fn exceeds(amount: &[u8], max: u128) -> bool {
+    let split = amount.len().saturating_sub(16);
+    let (high, low) = amount.split_at(split);
+    if high.iter().any(|b| *b != 0) {
+        return true;
+    }
+    low.iter().fold(0u128, |acc, b| acc << 8 | *b as u128) > max
+}
+
+fn factor(rule: &str, code: &str, weight: u32, detail: String) -> RiskFactor {
+    RiskFactor { rule: rule.to_string(), code: code.to_string(), weight, detail, contract: None }
+}
+
+fn contract_factor(rule: &str, code: &str, weight: u32, detail: String, contract: usize) -> RiskFactor {
+    RiskFactor { contract: Some(contract), ..factor(rule, code, weight, detail) }
+}
+
+impl RiskRule {
+    pub fn get_name(&self) -> &str {
+        match self {
+            RiskRule::DestinationAllowlist { .. } => "DestinationAllowlist",
+            RiskRule::AmountThreshold { .. } => "AmountThreshold",
+            RiskRule::ContractReputation { .. } => "ContractReputation",
+            RiskRule::ApprovalPattern { .. } => "ApprovalPattern",
+            RiskRule::BlindSigning { .. } => "BlindSigning",
+            RiskRule::Custom { name, .. } => name,
+        }
+    }
+
+    /// Factors `request` earns; `transaction` is `None` for requests that
+    /// aren't decodable transactions
+    pub fn evaluate(&self, request: &TronSignRequest, transaction: Option<&RawTransaction>) -> Result<Vec<RiskFactor>, String> {
+        let name = self.get_name();
+        let mut factors = Vec::new();
+        let contracts = transaction.map_or(&[][..], |t| t.contracts.as_slice());
+        match self {
+            RiskRule::DestinationAllowlist { allowed, weight } => {
+                for (i, contract) in contracts.iter().enumerate() {
+                    if let Some(transfer) = contract.get_transfer()? {
+                        let to = encode_address(&transfer.to)?;
+                        if !allowed.contains(&to) {
+                            factors.push(contract_factor(name, "DestinationNotAllowed", *weight, to, i));
+                        }
+                    }
+                }
+            }
+            RiskRule::AmountThreshold { token, max_amount, weight } => {
+                let token = token.as_deref().map(decode_address).transpose()?;
+                for (i, contract) in contracts.iter().enumerate() {
+                    let amount = match (&token, contract.contract_type) {
+                        (None, TRANSFER_CONTRACT) => contract.get_transfer()?.map(|t| t.amount),
+                        (None, TRIGGER_SMART_CONTRACT) => {
+                            contract.as_trigger_smart_contract()?.map(|call| call.call_value.to_be_bytes().to_vec())
+                        }
+                        (Some(token), TRIGGER_SMART_CONTRACT) => match contract.as_trigger_smart_contract()? {
+                            Some(call) if &call.contract_address == token => contract.get_transfer()?.map(|t| t.amount),
+                            _ => None,
+                        },
+                        _ => None,
+                    };
+                    if let Some(amount) = amount.filter(|a| exceeds(a, *max_amount)) {
+                        factors.push(contract_factor(name, "AmountAboveThreshold", *weight, hex::encode(amount), i));
+                    }
+                }
+            }
+            RiskRule::ContractReputation { registry, blocked, unknown_weight, blocked_weight } => {
+                for (i, contract) in contracts.iter().enumerate() {
+                    if let Some(call) = contract.as_trigger_smart_contract()? {
+                        let address = encode_address(&call.contract_address)?;
+                        if blocked.contains(&address) {
+                            factors.push(contract_factor(name, "BlockedContract", *blocked_weight, address, i));
+                        } else if registry.get(&call.contract_address).is_none() {
+                            factors.push(contract_factor(name, "UnknownContract", *unknown_weight, address, i));
+                        }
+                    }
+                }
+            }
+            RiskRule::ApprovalPattern { approval_weight, unlimited_weight } => {
+                if transaction.is_some() {
+                    if let Some(approval) = get_approval(request)? {
+                        let (code, weight) = match approval.unlimited {
+                            true => ("UnlimitedApproval", *unlimited_weight),
+                            false => ("Approval", *approval_weight),
+                        };
+                        factors.push(factor(name, code, weight, approval.spender));
+                    }
+                }
+            }
+            RiskRule::BlindSigning { registry, weight } => {
+                if is_blind_signing(request, registry)? {
+                    factors.push(factor(name, "BlindSigning", *weight, String::new()));
+                }
+            }
+            RiskRule::Custom { name, callback, context } => {
+                let weight = callback(request, *context as PtrVoid);
+                if weight != 0 {
+                    factors.push(factor(name, name, weight, String::new()));
+                }
+            }
+        }
+        Ok(factors)
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct RiskEngine {
+    rules: Vec<RiskRule>,
+}
+
+impl RiskEngine {
+    /// Engine with no rules
+    pub fn new() -> Self {
+        RiskEngine::default()
+    }
+
+    /// Rules that need no per-user configuration: blind signing, contract
+    /// reputation against the built-in registry, and approvals
+    pub fn with_default_rules() -> Self {
+        let mut engine = RiskEngine::new();
+        engine.add_rule(RiskRule::BlindSigning { registry: ContractRegistry::builtin(), weight: 40 });
+        engine.add_rule(RiskRule::ContractReputation {
+            registry: ContractRegistry::builtin(),
+            blocked: vec![],
+            unknown_weight: 30,
+            blocked_weight: MAX_RISK_SCORE,
+        });
+        engine.add_rule(RiskRule::ApprovalPattern { approval_weight: 10, unlimited_weight: 50 });
+        engine
+    }
+
+    pub fn add_rule(&mut self, rule: RiskRule) {
+        self.rules.push(rule);
+    }
+
+    pub fn get_rules(&self) -> &[RiskRule] {
+        &self.rules
+    }
+
+    /// Runs every rule, in the order added. A transaction that doesn't
+    /// decode escapes every transaction rule, so it scores the maximum, as
+    /// does a contract a rule fails to read; the other rules still run.
+    pub fn evaluate(&self, request: &TronSignRequest) -> Result<RiskReport, String> {
+        let mut report = RiskReport::default();
+        let transaction = match request.get_data_type() {
+            1 => match parse_request(request) {
+                Ok(transaction) => Some(transaction),
+                Err(e) => {
+                    report.score = MAX_RISK_SCORE;
+                    report.factors.push(factor(DECODING_RULE, "UndecodableTransaction", MAX_RISK_SCORE, e));
+                    None
+                }
+            },
+            _ => None,
+        };
+        for rule in self.rules.iter() {
+            let factors = rule
+                .evaluate(request, transaction.as_ref())
+                .unwrap_or_else(|e| vec![factor(rule.get_name(), "UndecodableContract", MAX_RISK_SCORE, e)]);
+            for factor in factors {
+                if !report.factors.contains(&factor) {
+                    report.score = report.score.saturating_add(factor.weight);
+                    report.factors.push(factor);
+                }
+            }
+        }
+        report.score = report.score.min(MAX_RISK_SCORE);
+        Ok(report)
+    }
+}
+
+fn address_list(addresses: PtrString) -> Result<Vec<String>, String> {
+    let addresses = convert_ptr_string_to_string(addresses)?;
+    let addresses: Vec<String> = addresses.lines().map(str::trim).filter(|s| !s.is_empty()).map(str::to_string).collect();
+    for address in addresses.iter() {
+        decode_address(address)?;
+    }
+    Ok(addresses)
+}
+
+// ========== FFI Functions ==========
+
+/// Engine with the default rules, or none when `with_default_rules` is false
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_risk_engine_new(with_default_rules: bool) -> PtrResponse {
+    let engine = match with_default_rules {
+        true => RiskEngine::with_default_rules(),
+        false => RiskEngine::new(),
+    };
+    Response::success_object(Box::into_raw(Box::new(engine)) as PtrVoid).c_ptr()
+}
+
+/// `allowed` is newline-separated base58 addresses
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_risk_engine_add_destination_allowlist(
+    risk_engine: &mut RiskEngine,
+    allowed: PtrString,
+    weight: u32,
+) -> PtrResponse {
+    match address_list(allowed) {
+        Ok(allowed) => {
+            risk_engine.add_rule(RiskRule::DestinationAllowlist { allowed, weight });
+            Response::success_null()
+        }
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+/// `max_amount` is big-endian hex in the asset's smallest unit; an empty
+/// `token` means TRX
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_risk_engine_add_amount_threshold(
+    risk_engine: &mut RiskEngine,
+    token: PtrString,
+    max_amount: PtrString,
+    weight: u32,
+) -> PtrResponse {
+    let token = match address_list(token).map_err(|e| Response::error(e)) {
+        Ok(v) if v.len() > 1 => return Response::error("Expected a single token address".to_string()).c_ptr(),
+        Ok(v) => v.into_iter().next(),
+        Err(e) => return e.c_ptr(),
+    };
+    let max_amount = match parse_ptr_string_to_bytes(max_amount).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    if max_amount.len() > 16 {
+        return Response::error("Threshold amount exceeds 128 bits".to_string()).c_ptr();
+    }
+    let max_amount = max_amount.iter().fold(0u128, |acc, b| acc << 8 | *b as u128);
+    risk_engine.add_rule(RiskRule::AmountThreshold { token, max_amount, weight });
+    Response::success_null().c_ptr()
+}
+
+/// `blocked` is newline-separated base58 addresses
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_risk_engine_add_contract_reputation(
+    risk_engine: &mut RiskEngine,
+    registry: &ContractRegistry,
+    blocked: PtrString,
+    unknown_weight: u32,
+    blocked_weight: u32,
+) -> PtrResponse {
+    match address_list(blocked) {
+        Ok(blocked) => {
+            risk_engine.add_rule(RiskRule::ContractReputation {
+                registry: registry.clone(),
+                blocked,
+                unknown_weight,
+                blocked_weight,
+            });
+            Response::success_null()
+        }
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_risk_engine_add_approval_pattern(
+    risk_engine: &mut RiskEngine,
+    approval_weight: u32,
+    unlimited_weight: u32,
+) -> PtrResponse {
+    risk_engine.add_rule(RiskRule::ApprovalPattern { approval_weight, unlimited_weight });
+    Response::success_null().c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_risk_engine_add_blind_signing(
+    risk_engine: &mut RiskEngine,
+    registry: &ContractRegistry,
+    weight: u32,
+) -> PtrResponse {
+    risk_engine.add_rule(RiskRule::BlindSigning { registry: registry.clone(), weight });
+    Response::success_null().c_ptr()
+}
+
+/// Adds a host-defined rule; `callback` runs on the evaluating thread with
+/// `context` passed back untouched
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_risk_engine_add_custom_rule(
+    risk_engine: &mut RiskEngine,
+    name: PtrString,
+    callback: RiskRuleCallback,
+    context: PtrVoid,
+) -> PtrResponse {
+    match convert_ptr_string_to_string(name) {
+        Ok(name) if !name.is_empty() => {
+            risk_engine.add_rule(RiskRule::Custom { name, callback, context: context as usize });
+            Response::success_null()
+        }
+        Ok(_) => Response::error("Custom rules need a name".to_string()),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_risk_engine_get_score(risk_engine: &RiskEngine, tron_sign_request: &TronSignRequest) -> PtrResponse {
+    match risk_engine.evaluate(tron_sign_request) {
+        Ok(report) => Response::success_uint32(report.score),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+/// JSON `{"score", "factors": [{"rule", "code", "weight", "detail",
+/// "contract"}]}`, with `contract` null for request-wide factors
+#[cfg(feature = "json")]
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_risk_engine_evaluate(risk_engine: &RiskEngine, tron_sign_request: &TronSignRequest) -> PtrResponse {
+    match risk_engine.evaluate(tron_sign_request) {
+        Ok(report) => {
+            let factors: Vec<_> = report
+                .factors
+                .iter()
+                .map(|f| json!({ "rule": f.rule, "code": f.code, "weight": f.weight, "detail": f.detail, "contract": f.contract }))
+                .collect();
+            Response::success_string(json!({ "score": report.score, "factors": factors }).to_string())
+        }
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tron::protobuf::encode::{bytes_field, varint_field};
+    use crate::tron::transaction::{TRC20_APPROVE, TRC20_TRANSFER};
+
+    const USDT: &str = "TR7NHqjeKQxGTCi8q8ZY4pL8otSzgjLj6t";
+
+    fn request(contract_type: u64, value: &[u8]) -> TronSignRequest {
+        let mut any = Vec::new();
+        bytes_field(2, value, &mut any);
+        let mut contract = Vec::new();
+        varint_field(1, contract_type, &mut contract);
+        bytes_field(2, &any, &mut contract);
+        let mut raw_data = Vec::new();
+        bytes_field(11, &contract, &mut raw_data);
+        TronSignRequest::new_with_derived_request_id(raw_data, 1, "m/44'/195'/0'/0/0".to_string(), None, None, None)
+    }
+
+    fn trc20_call(contract: &str, selector: [u8; 4], amount: u8) -> TronSignRequest {
+        let mut data = selector.to_vec();
+        data.extend_from_slice(&[0; 12]);
+        data.extend_from_slice(&[0xa6; 20]);
+        data.extend_from_slice(&[amount; 32]);
+        let mut call = Vec::new();
+        bytes_field(1, &[0x41; 21], &mut call);
+        bytes_field(2, &decode_address(contract).unwrap(), &mut call);
+        bytes_field(4, &data, &mut call);
+        request(TRIGGER_SMART_CONTRACT, &call)
+    }
+
+    extern "C" fn flag_messages(request: &TronSignRequest, context: PtrVoid) -> u32 {
+        if request.get_data_type() == 2 { unsafe { *(context as *const u32) } } else { 0 }
+    }
+
+    #[test]
+    fn test_risk_score() {
+        let engine = RiskEngine::with_default_rules();
+        let unlimited = engine.evaluate(&trc20_call(USDT, TRC20_APPROVE, 0xff)).unwrap();
+        assert_eq!(unlimited.score, 50);
+        assert_eq!(unlimited.factors[0].code, "UnlimitedApproval");
+        assert_eq!(engine.evaluate(&trc20_call(USDT, TRC20_APPROVE, 0x01)).unwrap().score, 10);
+        let unknown = "TMVQGm1qAQYVdetCeGRRkTWYYrLXuHK2HC";
+        let report = engine.evaluate(&trc20_call(unknown, TRC20_TRANSFER, 0x01)).unwrap();
+        assert_eq!(report.factors.iter().map(|f| f.code.as_str()).collect::<Vec<_>>(), vec!["BlindSigning", "UnknownContract"]);
+        assert_eq!(report.score, 70);
+
+        let mut recipient = vec![0x41];
+        recipient.extend_from_slice(&[0xa6; 20]);
+        let mut engine = RiskEngine::new();
+        engine.add_rule(RiskRule::DestinationAllowlist { allowed: vec![encode_address(&recipient).unwrap()], weight: 20 });
+        engine.add_rule(RiskRule::AmountThreshold { token: Some(USDT.to_string()), max_amount: u128::MAX, weight: 60 });
+        engine.add_rule(RiskRule::ContractReputation {
+            registry: ContractRegistry::default(),
+            blocked: vec![USDT.to_string()],
+            unknown_weight: 0,
+            blocked_weight: 70,
+        });
+        let report = engine.evaluate(&trc20_call(USDT, TRC20_TRANSFER, 0x01)).unwrap();
+        assert_eq!((report.score, report.factors.len()), (MAX_RISK_SCORE, 2));
+        assert_eq!(report.factors[0].code, "AmountAboveThreshold");
+
+        let mut transfer = Vec::new();
+        bytes_field(1, &[0x41; 21], &mut transfer);
+        bytes_field(2, &[0x41; 21], &mut transfer);
+        varint_field(3, 5_000_000, &mut transfer);
+        let trx = request(TRANSFER_CONTRACT, &transfer);
+        let mut engine = RiskEngine::new();
+        engine.add_rule(RiskRule::AmountThreshold { token: None, max_amount: 5_000_000, weight: 25 });
+        assert_eq!(engine.evaluate(&trx).unwrap().score, 0);
+        engine.add_rule(RiskRule::AmountThreshold { token: None, max_amount: 4_999_999, weight: 25 });
+        engine.add_rule(RiskRule::DestinationAllowlist { allowed: vec![], weight: 15 });
+        assert_eq!(engine.evaluate(&trx).unwrap().score, 40);
+
+        let weight = 35u32;
+        engine.add_rule(RiskRule::Custom { name: "Messages".to_string(), callback: flag_messages, context: &weight as *const u32 as usize });
+        let message = TronSignRequest::new_with_derived_request_id(b"hi".to_vec(), 2, "m/44'/195'/0'/0/0".to_string(), None, None, None);
+        let report = engine.evaluate(&message).unwrap();
+        assert_eq!((report.score, report.factors[0].rule.as_str()), (35, "Messages"));
+    }
+
+    #[test]
+    fn test_risk_per_contract_and_undecodable() {
+        let mut transfer = Vec::new();
+        bytes_field(1, &[0x41; 21], &mut transfer);
+        bytes_field(2, &[0x41; 21], &mut transfer);
+        varint_field(3, 5_000_000, &mut transfer);
+        let mut any = Vec::new();
+        bytes_field(2, &transfer, &mut any);
+        let mut contract = Vec::new();
+        varint_field(1, TRANSFER_CONTRACT, &mut contract);
+        bytes_field(2, &any, &mut contract);
+        let mut raw_data = Vec::new();
+        bytes_field(11, &contract, &mut raw_data);
+        bytes_field(11, &contract, &mut raw_data);
+        let twice = TronSignRequest::new_with_derived_request_id(raw_data, 1, "m/44'/195'/0'/0/0".to_string(), None, None, None);
+
+        let mut engine = RiskEngine::new();
+        engine.add_rule(RiskRule::DestinationAllowlist { allowed: vec![], weight: 15 });
+        let report = engine.evaluate(&twice).unwrap();
+        assert_eq!(report.score, 30);
+        assert_eq!(report.factors.iter().map(|f| f.contract).collect::<Vec<_>>(), vec![Some(0), Some(1)]);
+
+        let garbage = TronSignRequest::new_with_derived_request_id(vec![0xff], 1, "m/44'/195'/0'/0/0".to_string(), None, None, None);
+        let report = engine.evaluate(&garbage).unwrap();
+        assert_eq!((report.score, report.factors[0].code.as_str()), (MAX_RISK_SCORE, "UndecodableTransaction"));
+    }
+
+    #[test]
+    fn test_risk_rule_error_scores_maximum() {
+        // Recipient one byte short of an address
+        let mut transfer = Vec::new();
+        bytes_field(1, &[0x41; 21], &mut transfer);
+        bytes_field(2, &[0x41; 20], &mut transfer);
+        varint_field(3, 5_000_000, &mut transfer);
+        let truncated = request(TRANSFER_CONTRACT, &transfer);
+
+        let mut engine = RiskEngine::new();
+        engine.add_rule(RiskRule::DestinationAllowlist { allowed: vec![], weight: 15 });
+        engine.add_rule(RiskRule::AmountThreshold { token: None, max_amount: 1, weight: 25 });
+        let report = engine.evaluate(&truncated).unwrap();
+        assert_eq!(report.score, MAX_RISK_SCORE);
+        let factors: Vec<_> = report.factors.iter().map(|f| (f.rule.as_str(), f.code.as_str())).collect();
+        assert_eq!(
+            factors,
+            vec![("DestinationAllowlist", "UndecodableContract"), ("AmountThreshold", "AmountAboveThreshold")]
+        );
+    }
+}
@@ -0,0 +1,287 @@
+//! Machine-readable description of every TRON registry type's CBOR map:
+//! keys, value types and which are required. Each type declares its
+//! `SCHEMA` next to the key constants its encoder and decoder use, and the
+//! tests check it against them, so validators and docs generated from
+//! `tron_registry_schema` follow the code.
+
+#[cfg(feature = "json")]
+use crate::response::{PtrResponse, Response};
+use crate::tron::{
+    tron_address_book, tron_encrypted_payload, tron_pairing, tron_sign_rejection, tron_sign_request,
+    tron_sign_session, tron_signature, tron_wallet_bundle,
+};
+
+#[cfg(feature = "json")]
+use serde_json::json;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FieldType {
+    Bytes,
+    Text,
+    Uint,
+    Bool,
+    /// `crypto-keypath` (tag 304)
+    KeyPath,
+    /// Map of text keys to text or bytes values
+    Metadata,
+    ArrayOfBytes,
+    ArrayOfText,
+    /// Array of maps described by the field's `fields`
+    ArrayOfMaps,
+    /// Text, or a map described by the field's `fields`
+    TextOrMap,
+}
+
+impl FieldType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FieldType::Bytes => "bytes",
+            FieldType::Text => "text",
+            FieldType::Uint => "uint",
+            FieldType::Bool => "bool",
+            FieldType::KeyPath => "keypath",
+            FieldType::Metadata => "metadata",
+            FieldType::ArrayOfBytes => "array<bytes>",
+            FieldType::ArrayOfText => "array<text>",
+            FieldType::ArrayOfMaps => "array<map>",
+            FieldType::TextOrMap => "text|map",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FieldSchema {
+    pub key: i128,
+    pub name: &'static str,
+    pub field_type: FieldType,
+    pub required: bool,
+    /// Tag the value may be wrapped in, e.g. DEFLATE for sign_data
+    pub tag: Option<u64>,
+    /// Keys of the maps an `ArrayOfMaps` holds
+    pub fields: &'static [FieldSchema],
+}
+
+impl FieldSchema {
+    pub const fn required(key: i128, name: &'static str, field_type: FieldType) -> Self {
+        FieldSchema { key, name, field_type, required: true, tag: None, fields: &[] }
+    }
+
+    pub const fn optional(key: i128, name: &'static str, field_type: FieldType) -> Self {
+        FieldSchema { key, name, field_type, required: false, tag: None, fields: &[] }
+    }
+
+    pub const fn tagged(mut self, tag: u64) -> Self {
+        self.tag = Some(tag);
+        self
+    }
+
+    pub const fn with_fields(mut self, fields: &'static [FieldSchema]) -> Self {
+        self.fields = fields;
+        self
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TypeSchema {
+    pub ur_type: &'static str,
+    /// Tag the top-level map is wrapped in
+    pub tag: Option<u64>,
+    pub fields: &'static [FieldSchema],
+}
+
+/// Every TRON registry type, in `ur_decoder_resolve` order
+pub fn schemas() -> [&'static TypeSchema; 8] {
+    [
+        &tron_sign_request::SCHEMA,
+        &tron_signature::SCHEMA,
+        &tron_encrypted_payload::SCHEMA,
+        &tron_pairing::SCHEMA,
+        &tron_wallet_bundle::SCHEMA,
+        &tron_sign_rejection::SCHEMA,
+        &tron_address_book::SCHEMA,
+        &tron_sign_session::SCHEMA,
+    ]
+}
+
+#[cfg(feature = "json")]
+fn field_json(field: &FieldSchema) -> serde_json::Value {
+    let mut value = json!({
+        "key": field.key as i64,
+        "name": field.name,
+        "type": field.field_type.as_str(),
+        "required": field.required,
+    });
+    if let Some(tag) = field.tag {
+        value["tag"] = json!(tag);
+    }
+    if !field.fields.is_empty() {
+        value["fields"] = json!(field.fields.iter().map(field_json).collect::<Vec<_>>());
+    }
+    value
+}
+
+/// JSON `{"protocol_version", "types": [{"ur_type", "tag", "fields":
+/// [{"key", "name", "type", "required", "tag"?, "fields"?}]}]}`
+#[cfg(feature = "json")]
+pub fn to_json() -> serde_json::Value {
+    let types: Vec<_> = schemas()
+        .iter()
+        .map(|schema| {
+            json!({
+                "ur_type": schema.ur_type,
+                "tag": schema.tag,
+                "fields": schema.fields.iter().map(field_json).collect::<Vec<_>>(),
+            })
+        })
+        .collect();
+    json!({ "protocol_version": crate::tron::version::PROTOCOL_VERSION, "types": types })
+}
+
+// ========== FFI Functions ==========
+
+#[cfg(feature = "json")]
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_registry_schema() -> PtrResponse {
+    Response::success_string(to_json().to_string()).c_ptr()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tron::tron_address_book::{TronAddressBook, TronAddressBookEntry};
+    use crate::tron::tron_encrypted_payload::TronEncryptedPayload;
+    use crate::tron::tron_pairing::TronPairing;
+    use crate::tron::tron_sign_rejection::{RejectionReason, TronSignRejection};
+    use crate::tron::tron_sign_request::TronSignRequest;
+    use crate::tron::tron_sign_session::TronSignSession;
+    use crate::tron::tron_wallet_bundle::{TronBundleAccount, TronWalletBundle};
+
+    use secp256k1::{PublicKey, Secp256k1, SecretKey};
+
+    use serde_cbor::Value;
+
+    const XPUB: &str = "xpub661MyMwAqRbcFtXgS5sYJABqqG9YLmC4Q1Rdap9gSE8NqtwybGhePY2gZ29ESFjqJoCu1Rupje8YtGqsefD265TMg7usUDFdp6W1EGMcet8";
+
+    fn keys(fields: &[FieldSchema]) -> Vec<i128> {
+        fields.iter().map(|f| f.key).collect()
+    }
+
+    // Keys of a map, or of the map a tag wraps, in encoded order
+    fn map_keys(value: &Value) -> Vec<i128> {
+        match value {
+            Value::Tag(_, inner) => map_keys(inner),
+            Value::Map(map) => map
+                .keys()
+                .map(|key| match key {
+                    Value::Integer(key) => *key,
+                    _ => unreachable!(),
+                })
+                .collect(),
+            _ => unreachable!(),
+        }
+    }
+
+    // Keys of the first map in the array at `key`
+    fn first_entry_keys(value: &Value, key: i128) -> Vec<i128> {
+        match value {
+            Value::Map(map) => match &map[&Value::Integer(key)] {
+                Value::Array(entries) => map_keys(&entries[0]),
+                _ => unreachable!(),
+            },
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_schema_matches_codecs() {
+        assert_eq!(keys(tron_sign_request::SCHEMA.fields), tron_sign_request::KNOWN_KEYS.to_vec());
+        assert_eq!(keys(tron_signature::SCHEMA.fields), tron_signature::KNOWN_KEYS.to_vec());
+        assert_eq!(keys(tron_encrypted_payload::SCHEMA.fields), tron_encrypted_payload::KNOWN_KEYS.to_vec());
+        assert_eq!(keys(tron_pairing::SCHEMA.fields), tron_pairing::KNOWN_KEYS.to_vec());
+        assert_eq!(keys(tron_wallet_bundle::SCHEMA.fields), tron_wallet_bundle::KNOWN_KEYS.to_vec());
+        assert_eq!(keys(tron_wallet_bundle::SCHEMA.fields[0].fields), tron_wallet_bundle::ACCOUNT_KNOWN_KEYS.to_vec());
+        assert_eq!(keys(tron_sign_rejection::SCHEMA.fields), tron_sign_rejection::KNOWN_KEYS.to_vec());
+        assert_eq!(keys(tron_address_book::SCHEMA.fields), tron_address_book::KNOWN_KEYS.to_vec());
+        assert_eq!(keys(tron_address_book::SCHEMA.fields[0].fields), tron_address_book::ENTRY_KNOWN_KEYS.to_vec());
+        assert_eq!(keys(tron_sign_session::SCHEMA.fields), tron_sign_session::KNOWN_KEYS.to_vec());
+        for schema in schemas() {
+            let mut unique = keys(schema.fields);
+            unique.sort();
+            unique.dedup();
+            assert_eq!(unique.len(), schema.fields.len(), "{}", schema.ur_type);
+        }
+
+        // Dropping any required key breaks decoding, dropping optional ones doesn't
+        let mut request = TronSignRequest::new(
+            Some(vec![1; 16]),
+            b"hi".to_vec(),
+            2,
+            "m/44'/195'/0'/0/0".to_string(),
+            Some(0x12345678),
+            Some("TXYZ".to_string()),
+            Some("app.example".to_string()),
+        );
+        request.set_salt(Some(vec![2; 16]));
+        let map = match serde_cbor::from_slice(&request.to_bytes().unwrap()).unwrap() {
+            Value::Map(map) => map,
+            _ => unreachable!(),
+        };
+        for field in tron_sign_request::SCHEMA.fields.iter().filter(|f| map.contains_key(&Value::Integer(f.key))) {
+            let mut without = map.clone();
+            without.remove(&Value::Integer(field.key));
+            let decoded = TronSignRequest::try_from(serde_cbor::to_vec(&Value::Map(without)).unwrap());
+            assert_eq!(decoded.is_err(), field.required, "{}", field.name);
+        }
+
+        // Fully populated items encode exactly the known keys
+        let sorted = |keys: &[i128]| {
+            let mut keys = keys.to_vec();
+            keys.sort();
+            keys
+        };
+        let payload = TronEncryptedPayload::seal("tron-sign-request", b"payload", &[1; 32]).unwrap();
+        let payload: Value = serde_cbor::from_slice(&payload.to_bytes().unwrap()).unwrap();
+        assert_eq!(sorted(&map_keys(&payload)), sorted(&tron_encrypted_payload::KNOWN_KEYS));
+
+        let public_key = PublicKey::from_secret_key(&Secp256k1::new(), &SecretKey::from_slice(&[1; 32]).unwrap());
+        let pairing = TronPairing::new(vec![1; 16], Some("Signer".to_string()), public_key.serialize().to_vec(), vec!["mac".to_string()]).unwrap();
+        let pairing: Value = serde_cbor::from_slice(&pairing.to_bytes().unwrap()).unwrap();
+        assert_eq!(sorted(&map_keys(&pairing)), sorted(&tron_pairing::KNOWN_KEYS));
+
+        let mut bundle = TronWalletBundle::new(Some("Keystone".to_string()), Some(0x12345678));
+        let mut account = TronBundleAccount::new(
+            XPUB.to_string(),
+            "m/44'/195'/0'".to_string(),
+            Some("Main".to_string()),
+            Some("m/44'/195'/0'/0/*".to_string()),
+        )
+        .unwrap();
+        account.set_wallet_id(Some(vec![0xaa; 8]));
+        bundle.add_account(account);
+        let bundle: Value = serde_cbor::from_slice(&bundle.to_bytes().unwrap()).unwrap();
+        assert_eq!(sorted(&map_keys(&bundle)), sorted(&tron_wallet_bundle::KNOWN_KEYS));
+        assert_eq!(sorted(&first_entry_keys(&bundle, 1)), sorted(&tron_wallet_bundle::ACCOUNT_KNOWN_KEYS));
+
+        let mut rejection = TronSignRejection::new(vec![1; 16], RejectionReason::UserDeclined, Some("Declined".to_string()));
+        rejection.set_version(Some(1));
+        let rejection: Value = serde_cbor::from_slice(&rejection.to_bytes().unwrap()).unwrap();
+        assert_eq!(sorted(&map_keys(&rejection)), sorted(&tron_sign_rejection::KNOWN_KEYS));
+
+        let mut book = TronAddressBook::new();
+        book.add_entry(
+            TronAddressBookEntry::new("TR7NHqjeKQxGTCi8q8ZY4pL8otSzgjLj6t", "USDT".to_string(), Some("Tether".to_string())).unwrap(),
+        );
+        let book: Value = serde_cbor::from_slice(&book.to_bytes().unwrap()).unwrap();
+        assert_eq!(sorted(&map_keys(&book)), sorted(&tron_address_book::KNOWN_KEYS));
+        assert_eq!(sorted(&first_entry_keys(&book, 1)), sorted(&tron_address_book::ENTRY_KNOWN_KEYS));
+
+        let mut session = TronSignSession::new(Some(vec![7; 16]), Some("Approve then swap".to_string()), true).unwrap();
+        session.add_request(request.clone()).unwrap();
+        let session: Value = serde_cbor::from_slice(&session.to_bytes().unwrap()).unwrap();
+        assert_eq!(sorted(&map_keys(&session)), sorted(&tron_sign_session::KNOWN_KEYS));
+
+        #[cfg(feature = "json")]
+        assert_eq!(to_json()["types"][0]["fields"][1]["tag"], tron_sign_request::DEFLATE_TAG);
+    }
+}
@@ -0,0 +1,103 @@
+//! Round trip through the whole library, for bindings to check that the
+//! native library was linked and packaged correctly. It signs with the
+//! software signer, so it only ships with the `test-signer` feature.
+
+use crate::response::{PtrResponse, Response};
+use crate::tron::test_signer::TestSigner;
+use crate::tron::tron_sign_request::{TronSignRequest, TRON_SIGN_REQUEST_TYPE};
+use crate::tron::tron_signature::{recover_address, TronSignature};
+use crate::ur_encoder::UREncoder;
+
+use ur::Decoder;
+
+// Well-known throwaway key; nothing signed with it carries value
+const SELFTEST_KEY: [u8; 32] = [1; 32];
+// Small enough to force a multi-part UR
+const SELFTEST_FRAGMENT_LENGTH: usize = 32;
+
+/// Names of the steps that passed, or the failing step and its error
+pub fn run_selftest() -> Result<Vec<&'static str>, String> {
+    let mut passed = Vec::new();
+
+    let signer = TestSigner::new(&SELFTEST_KEY)?;
+    let address = signer.get_address()?;
+    let request = TronSignRequest::new_with_derived_request_id(
+        // raw_data with ref_block_bytes and expiration only
+        vec![0x0a, 0x02, 0x07, 0x90, 0x40, 0x80, 0xd0, 0xdb, 0xc3, 0xf7, 0x31],
+        1,
+        "m/44'/195'/0'/0/0".to_string(),
+        None,
+        Some(address.clone()),
+        Some("selftest".to_string()),
+    );
+    let derived = match request.is_request_id_derived() {
+        true => Ok(()),
+        false => Err("request id was not derived"),
+    };
+    step(&mut passed, "construct", derived)?;
+
+    let encoded = step(&mut passed, "encode", request.to_bytes())?;
+    let parts = step(&mut passed, "ur", encode_parts(&encoded))?;
+    let decoded = step(&mut passed, "decode", decode_parts(&parts, &encoded))?;
+    let signature = step(&mut passed, "sign", signer.sign(&decoded))?;
+    step(&mut passed, "verify", verify(&request, &signature, &address))?;
+
+    Ok(passed)
+}
+
+fn step<T, E: ToString>(passed: &mut Vec<&'static str>, name: &'static str, result: Result<T, E>) -> Result<T, String> {
+    let value = result.map_err(|e| format!("{} failed: {}", name, e.to_string()))?;
+    passed.push(name);
+    Ok(value)
+}
+
+fn encode_parts(encoded: &[u8]) -> Result<Vec<String>, String> {
+    let mut encoder = UREncoder::new(encoded, SELFTEST_FRAGMENT_LENGTH, TRON_SIGN_REQUEST_TYPE)?;
+    (0..encoder.fragment_count()).map(|_| encoder.next_part()).collect()
+}
+
+fn decode_parts(parts: &[String], encoded: &[u8]) -> Result<TronSignRequest, String> {
+    let mut decoder = Decoder::default();
+    for part in parts.iter() {
+        decoder.receive(part).map_err(|e| e.to_string())?;
+    }
+    let message = decoder.message().map_err(|e| e.to_string())?.ok_or("UR is incomplete")?;
+    if message != encoded {
+        return Err("UR payload differs from the encoded request".to_string());
+    }
+    Ok(TronSignRequest::try_from(message)?)
+}
+
+fn verify(request: &TronSignRequest, signature: &TronSignature, address: &str) -> Result<(), String> {
+    let signature = TronSignature::try_from(signature.to_bytes()?)?;
+    if signature.get_request_id() != request.get_request_id() {
+        return Err("request id mismatch".to_string());
+    }
+    if recover_address(request, &signature)? != address {
+        return Err("signature recovers to another address".to_string());
+    }
+    Ok(())
+}
+
+// ========== FFI Functions ==========
+
+/// Passed steps joined by commas, or an error naming the failing step
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn ur_registry_selftest() -> PtrResponse {
+    match run_selftest() {
+        Ok(passed) => Response::success_string(passed.join(",")),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_selftest_passes() {
+        assert_eq!(run_selftest().unwrap(), vec!["construct", "encode", "ur", "decode", "sign", "verify"]);
+    }
+}
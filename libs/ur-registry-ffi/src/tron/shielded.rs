@@ -0,0 +1,130 @@
+//! ShieldedTransferContract recognition. Only the transparent side is
+//! visible: amounts and counterparties inside the shielded pool are
+//! encrypted, so signers can't fully inspect these transactions.
+
+#[cfg(feature = "json")]
+use crate::response::{PtrResponse, Response};
+use crate::tron::address::encode_address;
+use crate::tron::protobuf::{find_field, parse_fields};
+use crate::tron::transaction::{parse_request, Contract, SHIELDED_TRANSFER_CONTRACT};
+use crate::tron::tron_sign_request::TronSignRequest;
+
+#[cfg(feature = "json")]
+use serde_json::json;
+
+// ShieldedTransferContract
+const SHIELDED_FROM_ADDRESS: u32 = 1;
+const SHIELDED_FROM_AMOUNT: u32 = 2;
+const SHIELDED_SPEND_DESCRIPTION: u32 = 3;
+const SHIELDED_RECEIVE_DESCRIPTION: u32 = 5;
+const SHIELDED_TO_AMOUNT: u32 = 6;
+const SHIELDED_TO_ADDRESS: u32 = 7;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ShieldedTransfer {
+    /// Base58 address paying into the pool, for shielding transfers
+    pub transparent_from: Option<String>,
+    /// SUN paid into the pool
+    pub from_amount: u64,
+    /// Base58 address paid out of the pool, for unshielding transfers
+    pub transparent_to: Option<String>,
+    /// SUN paid out of the pool
+    pub to_amount: u64,
+    /// Shielded notes spent and created; their values aren't visible
+    pub spend_count: usize,
+    pub receive_count: usize,
+}
+
+impl ShieldedTransfer {
+    pub fn from_contract(contract: &Contract) -> Result<Option<Self>, String> {
+        if contract.contract_type != SHIELDED_TRANSFER_CONTRACT {
+            return Ok(None);
+        }
+        let fields = parse_fields(&contract.value)?;
+        let address = |number| {
+            find_field(&fields, number)
+                .and_then(|v| v.as_bytes())
+                .filter(|a| !a.is_empty())
+                .map(encode_address)
+                .transpose()
+        };
+        let varint = |number| find_field(&fields, number).and_then(|v| v.as_varint()).unwrap_or_default();
+        let count = |number| fields.iter().filter(|(n, _)| *n == number).count();
+        Ok(Some(ShieldedTransfer {
+            transparent_from: address(SHIELDED_FROM_ADDRESS)?,
+            from_amount: varint(SHIELDED_FROM_AMOUNT),
+            transparent_to: address(SHIELDED_TO_ADDRESS)?,
+            to_amount: varint(SHIELDED_TO_AMOUNT),
+            spend_count: count(SHIELDED_SPEND_DESCRIPTION),
+            receive_count: count(SHIELDED_RECEIVE_DESCRIPTION),
+        }))
+    }
+
+    /// Whether any value moves inside the pool, where it can't be checked
+    pub fn has_shielded_parts(&self) -> bool {
+        self.spend_count > 0 || self.receive_count > 0
+    }
+}
+
+/// The shielded transfer a transaction request makes, if any
+pub fn get_shielded_transfer(request: &TronSignRequest) -> Result<Option<ShieldedTransfer>, String> {
+    for contract in parse_request(request)?.contracts.iter() {
+        if let Some(transfer) = ShieldedTransfer::from_contract(contract)? {
+            return Ok(Some(transfer));
+        }
+    }
+    Ok(None)
+}
+
+// ========== FFI Functions ==========
+
+/// JSON with the transparent parts and note counts, or `NOT_PRESENT` when
+/// the request isn't shielded. `fully_inspectable` is false whenever notes
+/// are spent or created.
+#[cfg(feature = "json")]
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_request_get_shielded_transfer(tron_sign_request: &TronSignRequest) -> PtrResponse {
+    match get_shielded_transfer(tron_sign_request) {
+        Ok(transfer) => transfer.map_or(Response::not_present(), |t| {
+            Response::success_string(
+                json!({
+                    "transparent_from": t.transparent_from,
+                    "from_amount": t.from_amount,
+                    "transparent_to": t.transparent_to,
+                    "to_amount": t.to_amount,
+                    "spend_count": t.spend_count,
+                    "receive_count": t.receive_count,
+                    "fully_inspectable": !t.has_shielded_parts(),
+                })
+                .to_string(),
+            )
+        }),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tron::protobuf::encode::{bytes_field, varint_field};
+
+    #[test]
+    fn test_shielded_transfer() {
+        let mut value = Vec::new();
+        bytes_field(SHIELDED_FROM_ADDRESS, &[0x41; 21], &mut value);
+        varint_field(SHIELDED_FROM_AMOUNT, 5_000_000, &mut value);
+        bytes_field(SHIELDED_RECEIVE_DESCRIPTION, &[0xaa; 16], &mut value);
+        bytes_field(SHIELDED_RECEIVE_DESCRIPTION, &[0xbb; 16], &mut value);
+        let contract = Contract { contract_type: SHIELDED_TRANSFER_CONTRACT, value, ..Default::default() };
+
+        let transfer = ShieldedTransfer::from_contract(&contract).unwrap().unwrap();
+        assert_eq!(transfer.transparent_from, Some(encode_address(&[0x41; 21]).unwrap()));
+        assert_eq!(transfer.from_amount, 5_000_000);
+        assert_eq!(transfer.transparent_to, None);
+        assert_eq!((transfer.spend_count, transfer.receive_count), (0, 2));
+        assert!(transfer.has_shielded_parts());
+        assert_eq!(ShieldedTransfer::from_contract(&Contract::default()).unwrap(), None);
+    }
+}
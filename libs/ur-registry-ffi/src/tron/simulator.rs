@@ -0,0 +1,286 @@
+//! Headless stand-in for the cold wallet: scans the UR parts of a
+//! `tron-sign-request`, decides per `SimulatorPolicy`, and answers with the
+//! UR parts of a `tron-signature` or `tron-sign-rejection`.
+
+use crate::response::{PtrResponse, Response};
+#[cfg(feature = "protobuf")]
+use crate::tron::analyze::{check_multiple_contracts, is_blind_signing, AnalyzerConfig};
+#[cfg(feature = "protobuf")]
+use crate::tron::contracts::ContractRegistry;
+use crate::tron::telemetry::{self, EventKind};
+use crate::tron::test_signer::TestSigner;
+use crate::tron::tron_sign_rejection::{RejectionReason, TronSignRejection, TRON_SIGN_REJECTION_TYPE};
+use crate::tron::tron_sign_request::{TronSignRequest, TRON_SIGN_REQUEST_TYPE};
+use crate::tron::tron_signature::TRON_SIGNATURE_TYPE;
+use crate::tron::validate::MAX_SIGN_DATA_LEN;
+use crate::types::{PtrString, PtrVoid};
+use crate::ur_encoder::UREncoder;
+use crate::utils::{convert_ptr_string_to_string, parse_ptr_string_to_bytes};
+
+use ur::bytewords::{self, Style};
+use ur::Decoder;
+
+// Same fragment length the FFI encoders use
+const MAX_FRAGMENT_LENGTH: usize = 400;
+
+#[derive(Clone, Debug)]
+pub struct SimulatorPolicy {
+    /// Decline everything, as if the user pressed reject
+    pub decline_all: bool,
+    /// Data types the simulated device signs; others are unsupported
+    pub allowed_data_types: Vec<u32>,
+    /// Larger sign_data is refused as a policy violation
+    pub max_sign_data_len: usize,
+    /// Refuse requests the device couldn't display
+    #[cfg(feature = "protobuf")]
+    pub refuse_blind_signing: bool,
+    /// Refuse transactions with more than one contract
+    #[cfg(feature = "protobuf")]
+    pub refuse_multiple_contracts: bool,
+}
+
+impl Default for SimulatorPolicy {
+    fn default() -> Self {
+        SimulatorPolicy {
+            decline_all: false,
+            allowed_data_types: vec![1, 2],
+            max_sign_data_len: MAX_SIGN_DATA_LEN,
+            #[cfg(feature = "protobuf")]
+            refuse_blind_signing: false,
+            #[cfg(feature = "protobuf")]
+            refuse_multiple_contracts: false,
+        }
+    }
+}
+
+impl SimulatorPolicy {
+    /// `None` approves the request
+    pub fn check(&self, request: &TronSignRequest) -> Option<(RejectionReason, String)> {
+        if self.decline_all {
+            return Some((RejectionReason::UserDeclined, "Declined by policy".to_string()));
+        }
+        if !self.allowed_data_types.contains(&request.get_data_type()) {
+            return Some((
+                RejectionReason::UnsupportedRequest,
+                format!("Data type {} is not allowed", request.get_data_type()),
+            ));
+        }
+        if request.get_sign_data().len() > self.max_sign_data_len {
+            return Some((
+                RejectionReason::PolicyViolation,
+                format!("sign_data exceeds {} bytes", self.max_sign_data_len),
+            ));
+        }
+        #[cfg(feature = "protobuf")]
+        if self.refuse_blind_signing && is_blind_signing(request, &ContractRegistry::builtin()).unwrap_or(true) {
+            return Some((RejectionReason::PolicyViolation, "Blind signing is disabled".to_string()));
+        }
+        #[cfg(feature = "protobuf")]
+        if self.refuse_multiple_contracts {
+            let config = AnalyzerConfig { refuse_multiple_contracts: true, ..Default::default() };
+            if let Err(e) = check_multiple_contracts(request, &config) {
+                return Some((RejectionReason::PolicyViolation, e));
+            }
+        }
+        None
+    }
+}
+
+pub struct Simulator {
+    signer: TestSigner,
+    policy: SimulatorPolicy,
+    decoder: Decoder,
+    // Body of a single-part request, which the fountain decoder doesn't take
+    single_part: Option<Vec<u8>>,
+}
+
+impl Simulator {
+    pub fn new(private_key: &[u8], policy: SimulatorPolicy) -> Result<Self, String> {
+        Ok(Simulator {
+            signer: TestSigner::new(private_key)?,
+            policy,
+            decoder: Decoder::default(),
+            single_part: None,
+        })
+    }
+
+    pub fn get_policy_mut(&mut self) -> &mut SimulatorPolicy {
+        &mut self.policy
+    }
+
+    /// Feeds one scanned part; true once the request is complete
+    pub fn receive_part(&mut self, part: &str) -> Result<bool, String> {
+        let part = part.to_lowercase();
+        let segments: Vec<&str> = part.strip_prefix("ur:").ok_or("Not a UR part")?.split('/').collect();
+        if segments[0] != TRON_SIGN_REQUEST_TYPE {
+            return Err(format!("Expected {}, got {}", TRON_SIGN_REQUEST_TYPE, segments[0]));
+        }
+        if let [_, body] = segments[..] {
+            self.single_part = Some(bytewords::decode(body, &Style::Minimal).map_err(|e| e.to_string())?);
+            return Ok(true);
+        }
+        self.decoder.receive(&part).map_err(|e| e.to_string())?;
+        Ok(self.decoder.complete())
+    }
+
+    /// UR parts of the answer to the received request: every fragment once,
+    /// enough for the host to decode it. Resets the simulator for the next request.
+    pub fn respond(&mut self) -> Result<Vec<String>, String> {
+        let message = match self.single_part.take() {
+            Some(message) => message,
+            None => self.decoder.message().map_err(|e| e.to_string())?.ok_or("Request is not complete yet")?,
+        };
+        self.decoder = Decoder::default();
+
+        let request = TronSignRequest::try_from(message)?;
+        let request_id = request.get_request_id().cloned().unwrap_or_default();
+        let (reason, message) = match self.policy.check(&request) {
+            Some((reason, message)) => {
+                telemetry::emit(EventKind::PolicyRejection, "simulator", &format!("{:?}", reason), &message);
+                (reason, message)
+            }
+            None => match self.signer.sign(&request) {
+                Ok(signature) => return encode_parts(&signature.to_bytes()?, TRON_SIGNATURE_TYPE),
+                Err(e) => (RejectionReason::InvalidRequest, e),
+            },
+        };
+
+        let rejection = TronSignRejection::new(request_id, reason, Some(message));
+        encode_parts(&rejection.to_bytes()?, TRON_SIGN_REJECTION_TYPE)
+    }
+}
+
+fn encode_parts(message: &[u8], ur_type: &str) -> Result<Vec<String>, String> {
+    let mut encoder = UREncoder::new(message, MAX_FRAGMENT_LENGTH, ur_type)?;
+    (0..encoder.fragment_count()).map(|_| encoder.next_part()).collect()
+}
+
+// ========== FFI Functions ==========
+
+/// Simulator signing with a hex private key under the default policy
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_simulator_new(private_key: PtrString) -> PtrResponse {
+    let private_key = match parse_ptr_string_to_bytes(private_key).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    match Simulator::new(&private_key, SimulatorPolicy::default()) {
+        Ok(simulator) => Response::success_object(Box::into_raw(Box::new(simulator)) as PtrVoid),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_simulator_set_decline_all(simulator: &mut Simulator, decline_all: bool) -> PtrResponse {
+    simulator.get_policy_mut().decline_all = decline_all;
+    Response::success_null().c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_simulator_set_max_sign_data_len(simulator: &mut Simulator, max_len: u32) -> PtrResponse {
+    simulator.get_policy_mut().max_sign_data_len = max_len as usize;
+    Response::success_null().c_ptr()
+}
+
+#[cfg(feature = "protobuf")]
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_simulator_set_refuse_blind_signing(simulator: &mut Simulator, refuse: bool) -> PtrResponse {
+    simulator.get_policy_mut().refuse_blind_signing = refuse;
+    Response::success_null().c_ptr()
+}
+
+#[cfg(feature = "protobuf")]
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_simulator_set_refuse_multiple_contracts(simulator: &mut Simulator, refuse: bool) -> PtrResponse {
+    simulator.get_policy_mut().refuse_multiple_contracts = refuse;
+    Response::success_null().c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_simulator_receive_part(simulator: &mut Simulator, part: PtrString) -> PtrResponse {
+    let part = match convert_ptr_string_to_string(part).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    match simulator.receive_part(&part) {
+        Ok(complete) => Response::success_boolean(complete),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+/// Response parts joined by newlines
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_simulator_respond(simulator: &mut Simulator) -> PtrResponse {
+    match simulator.respond() {
+        Ok(parts) => Response::success_string(parts.join("\n")),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tron::tron_signature::recover_address;
+    use crate::tron::tron_signature::TronSignature;
+
+    fn request_parts(request: &TronSignRequest) -> Vec<String> {
+        encode_parts(&request.to_bytes().unwrap(), TRON_SIGN_REQUEST_TYPE).unwrap()
+    }
+
+    fn decode_parts(parts: &[String]) -> Vec<u8> {
+        if let [part] = parts {
+            let body = part.rsplit('/').next().unwrap();
+            return bytewords::decode(body, &Style::Minimal).unwrap();
+        }
+        let mut decoder = Decoder::default();
+        for part in parts {
+            decoder.receive(part).unwrap();
+        }
+        decoder.message().unwrap().unwrap()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut simulator = Simulator::new(&[1; 32], SimulatorPolicy::default()).unwrap();
+        let request = TronSignRequest::new_with_derived_request_id(
+            vec![0x0a; 1200], 1, "m/44'/195'/0'/0/0".to_string(), None, None, None,
+        );
+        let parts = request_parts(&request);
+        assert!(parts.len() > 1);
+        for (i, part) in parts.iter().enumerate() {
+            assert_eq!(simulator.receive_part(part).unwrap(), i == parts.len() - 1);
+        }
+
+        let response = simulator.respond().unwrap();
+        assert!(response[0].starts_with("ur:tron-signature/"));
+        let signature = TronSignature::try_from(decode_parts(&response)).unwrap();
+        assert_eq!(signature.get_request_id(), request.get_request_id());
+        assert_eq!(
+            recover_address(&request, &signature).unwrap(),
+            TestSigner::new(&[1; 32]).unwrap().get_address().unwrap()
+        );
+
+        simulator.get_policy_mut().decline_all = true;
+        let request = TronSignRequest::new_with_derived_request_id(
+            vec![0x0a; 16], 1, "m/44'/195'/0'/0/0".to_string(), None, None, None,
+        );
+        let parts = request_parts(&request);
+        assert_eq!(parts.len(), 1);
+        assert!(simulator.receive_part(&parts[0]).unwrap());
+        let response = simulator.respond().unwrap();
+        assert!(response[0].starts_with("ur:tron-sign-rejection/"));
+        let rejection = TronSignRejection::try_from(decode_parts(&response)).unwrap();
+        assert_eq!(rejection.get_reason(), Some(RejectionReason::UserDeclined));
+        assert!(simulator.receive_part("ur:tron-signature/abc").is_err());
+    }
+}
@@ -0,0 +1,289 @@
+//! One-line plain-text summary of a sign request, e.g. "Send 250 TRX to
+//! TAbc…wxyz, fee limit 10 TRX, expires in 9 min", so signers with small
+//! displays share the same phrasing instead of each building their own.
+//!
+//! Each part is a catalog message bindings can translate; the text is its
+//! English rendering. The locale only picks the decimal separator, `de-DE`
+//! giving "1,5 TRX"; without one the request's display hints choose it,
+//! and they pick whether TRX amounts show in TRX or SUN. Token symbols and
+//! decimals come from the contract registry, never the request.
+
+use crate::response::{PtrResponse, Response};
+use crate::tron::address::{encode_address, SIMILARITY_PREFIX_LEN, SIMILARITY_SUFFIX_LEN};
+use crate::tron::amount::format_units;
+#[cfg(feature = "json")]
+use crate::tron::catalog::to_json;
+use crate::tron::catalog::{render_english, CatalogMessage};
+use crate::tron::contracts::{ContractRegistry, KnownContract};
+use crate::tron::display_hints::{localize_amount, CurrencyDisplay, DisplayHints};
+use crate::tron::transaction::{parse_request, Contract, ContractKind, Transfer};
+use crate::tron::tron_sign_request::{DataType, TronSignRequest};
+use crate::types::PtrString;
+use crate::utils::convert_ptr_string_to_string;
+
+#[cfg(feature = "json")]
+use serde_json::json;
+
+/// "TAbc…wxyz", the characters wallets show of an address
+pub fn shorten_address(address: &str) -> String {
+    if address.len() <= SIMILARITY_PREFIX_LEN + SIMILARITY_SUFFIX_LEN + 1 {
+        return address.to_string();
+    }
+    let start = address.get(..SIMILARITY_PREFIX_LEN);
+    let end = address.get(address.len() - SIMILARITY_SUFFIX_LEN..);
+    match start.zip(end) {
+        Some((start, end)) => format!("{}…{}", start, end),
+        None => address.to_string(),
+    }
+}
+
+fn short_address(address: &[u8]) -> String {
+    encode_address(address).map_or_else(|_| hex::encode(address), |a| shorten_address(&a))
+}
+
+// Time left until `expiration` (ms), from `now` (seconds)
+fn describe_expiration(expiration: u64, now: u64) -> CatalogMessage {
+    match (expiration / 1000).saturating_sub(now) {
+        0 => CatalogMessage::new("summary.expired"),
+        left if left < 60 => CatalogMessage::new("summary.expires_in_seconds").with_arg("count", left),
+        left if left < 3600 => CatalogMessage::new("summary.expires_in_minutes").with_arg("count", left / 60),
+        left => CatalogMessage::new("summary.expires_in_hours").with_arg("count", left / 3600),
+    }
+}
+
+// TRC-20 amount with the symbol and decimals the registry has for the
+// contract. Request metadata can name any token, so unregistered contracts
+// show raw units and the contract instead.
+fn describe_token_transfer(token: &[u8], transfer: &Transfer, registry: &ContractRegistry) -> Result<CatalogMessage, String> {
+    let message = match registry.get(token) {
+        Some(KnownContract { name, decimals: Some(decimals), .. }) => CatalogMessage::new("summary.send")
+            .with_arg("amount", format_units(&transfer.amount, *decimals)?)
+            .with_arg("symbol", name),
+        known => {
+            let label = registry.get_label(token)?;
+            CatalogMessage::new("summary.send_token_units_of")
+                .with_arg("units", format_units(&transfer.amount, 0)?)
+                .with_arg("token", if known.is_some() { label } else { shorten_address(&label) })
+        }
+    };
+    Ok(message.with_arg("to", short_address(&transfer.to)))
+}
+
+fn describe_contract(
+    contract: &Contract,
+    registry: &ContractRegistry,
+    currency: CurrencyDisplay,
+) -> Result<CatalogMessage, String> {
+    let kind = contract.get_kind();
+    if let Some(transfer) = contract.get_transfer()? {
+        return match kind {
+            ContractKind::Transfer => {
+                let (amount, symbol) = currency.format_sun(&transfer.amount)?;
+                Ok(CatalogMessage::new("summary.send")
+                    .with_arg("amount", amount)
+                    .with_arg("symbol", symbol)
+                    .with_arg("to", short_address(&transfer.to)))
+            }
+            ContractKind::TransferAsset => Ok(CatalogMessage::new("summary.send_token_units")
+                .with_arg("units", format_units(&transfer.amount, 0)?)
+                .with_arg("to", short_address(&transfer.to))),
+            _ => {
+                let call = contract.as_trigger_smart_contract()?.unwrap_or_default();
+                describe_token_transfer(&call.contract_address, &transfer, registry)
+            }
+        };
+    }
+    if let Some(call) = contract.as_trigger_smart_contract()? {
+        let known = registry.get(&call.contract_address);
+        let function = call.get_selector().and_then(|s| known?.get_function(&s)).map(|f| f.get_name().to_string());
+        let target = registry.get_label(&call.contract_address)?;
+        let target = match known {
+            Some(_) => target,
+            None => shorten_address(&target),
+        };
+        return Ok(match function {
+            Some(function) => CatalogMessage::new("summary.call_function").with_arg("function", function),
+            None => CatalogMessage::new("summary.call"),
+        }
+        .with_arg("contract", target));
+    }
+    if let Some(account) = contract.get_created_account()? {
+        return Ok(CatalogMessage::new("summary.activate_account").with_arg("address", short_address(&account)));
+    }
+    Ok(CatalogMessage::new(&format!("contract.{}", kind.as_str().unwrap_or("Other"))))
+}
+
+/// Parts of the summary of `request` as catalog messages, amounts written
+/// with a '.' in the unit the request hints; `now` is Unix seconds, or 0 to
+/// leave out the expiration
+pub fn summary_messages(request: &TronSignRequest, now: u64) -> Result<Vec<CatalogMessage>, String> {
+    let currency = DisplayHints::from_request(request).currency;
+    let mut parts = Vec::new();
+    match DataType::from_u32(request.get_data_type())? {
+        DataType::Message => parts.push(CatalogMessage::new("summary.sign_message")),
+        DataType::TypedData => parts.push(CatalogMessage::new("summary.sign_typed_data")),
+        DataType::Transaction => {
+            let transaction = parse_request(request)?;
+            let registry = ContractRegistry::builtin();
+            parts.push(match transaction.contracts.first() {
+                Some(contract) => describe_contract(contract, &registry, currency)?,
+                None => CatalogMessage::new("summary.empty_transaction"),
+            });
+            if transaction.contracts.len() > 1 {
+                parts.push(CatalogMessage::new("summary.more_contracts").with_arg("count", transaction.contracts.len() - 1));
+            }
+            if transaction.fee_limit > 0 {
+                let (amount, symbol) = currency.format_sun(&transaction.fee_limit.to_be_bytes())?;
+                parts.push(CatalogMessage::new("summary.fee_limit").with_arg("amount", amount).with_arg("symbol", symbol));
+            }
+            if now > 0 && transaction.expiration > 0 {
+                parts.push(describe_expiration(transaction.expiration, now));
+            }
+        }
+    }
+    if let Some(origin) = request.get_origin() {
+        parts.push(CatalogMessage::new("summary.origin").with_arg("origin", origin));
+    }
+    Ok(parts)
+}
+
+/// English summary of `request`; `now` is Unix seconds, or 0 to leave out
+/// the expiration, and `locale` a BCP 47 tag such as `en-US`, or empty for
+/// the request's hint
+pub fn summarize(request: &TronSignRequest, now: u64, locale: &str) -> Result<String, String> {
+    let hints = DisplayHints::from_request(request);
+    let locale = hints.effective_locale(locale);
+    let mut parts = Vec::new();
+    for mut message in summary_messages(request, now)? {
+        if let Some(amount) = message.args.get_mut("amount") {
+            *amount = localize_amount(amount, locale);
+        }
+        parts.push(render_english(&message)?);
+    }
+    Ok(parts.join(", "))
+}
+
+// ========== FFI Functions ==========
+
+/// `locale_hint` is a BCP 47 tag, empty for the request's; `now` is Unix
+/// seconds, or 0 to leave out the expiration
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_request_summary(tron_sign_request: &TronSignRequest, locale_hint: PtrString, now: u64) -> PtrResponse {
+    let locale_hint = match convert_ptr_string_to_string(locale_hint).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    match summarize(tron_sign_request, now, &locale_hint) {
+        Ok(summary) => Response::success_string(summary),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+/// JSON array of the summary parts as `{"key", "args"}` catalog messages,
+/// for bindings rendering their own translations
+#[cfg(feature = "json")]
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_request_summary_messages(tron_sign_request: &TronSignRequest, now: u64) -> PtrResponse {
+    match summary_messages(tron_sign_request, now) {
+        Ok(messages) => Response::success_string(json!(messages.iter().map(to_json).collect::<Vec<_>>()).to_string()),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tron::protobuf::encode::{bytes_field, varint_field};
+    use crate::tron::amount::{TOKEN_DECIMALS_KEY, TOKEN_SYMBOL_KEY};
+    use crate::tron::transaction::{TRANSFER_CONTRACT, TRC20_TRANSFER, TRIGGER_SMART_CONTRACT};
+    use crate::tron::tron_sign_request::MetadataValue;
+
+    fn token_transfer_request(token: &[u8]) -> TronSignRequest {
+        let mut data = TRC20_TRANSFER.to_vec();
+        data.extend_from_slice(&[0; 12]);
+        data.extend_from_slice(&[0x41; 20]);
+        data.extend_from_slice(&[0; 29]);
+        data.extend_from_slice(&[0x0f, 0x42, 0x40]);
+        let mut call = Vec::new();
+        bytes_field(1, &[0x41; 21], &mut call);
+        bytes_field(2, token, &mut call);
+        bytes_field(4, &data, &mut call);
+        let mut any = Vec::new();
+        bytes_field(2, &call, &mut any);
+        let mut contract = Vec::new();
+        varint_field(1, TRIGGER_SMART_CONTRACT, &mut contract);
+        bytes_field(2, &any, &mut contract);
+        let mut raw_data = Vec::new();
+        bytes_field(11, &contract, &mut raw_data);
+        let mut request = TronSignRequest::new_with_derived_request_id(raw_data, 1, "m/44'/195'/0'/0/0".to_string(), None, None, None);
+        request.set_metadata(TOKEN_SYMBOL_KEY.to_string(), MetadataValue::Text("USDT".to_string()));
+        request.set_metadata(TOKEN_DECIMALS_KEY.to_string(), MetadataValue::Text("6".to_string()));
+        request
+    }
+
+    #[test]
+    fn test_summary() {
+        let mut value = Vec::new();
+        bytes_field(1, &[0x41; 21], &mut value);
+        bytes_field(2, &[0x41; 21], &mut value);
+        varint_field(3, 250_500_000, &mut value);
+        let mut any = Vec::new();
+        bytes_field(2, &value, &mut any);
+        let mut contract = Vec::new();
+        varint_field(1, TRANSFER_CONTRACT, &mut contract);
+        bytes_field(2, &any, &mut contract);
+        let mut raw_data = Vec::new();
+        varint_field(8, 1_700_000_600_000, &mut raw_data);
+        bytes_field(11, &contract, &mut raw_data);
+        varint_field(18, 10_000_000, &mut raw_data);
+        let request = TronSignRequest::new_with_derived_request_id(raw_data, 1, "m/44'/195'/0'/0/0".to_string(), None, None, None);
+
+        let to = shorten_address(&encode_address(&[0x41; 21]).unwrap());
+        assert_eq!(to.chars().count(), SIMILARITY_PREFIX_LEN + 1 + SIMILARITY_SUFFIX_LEN);
+        assert_eq!(
+            summarize(&request, 1_700_000_050, "en-US").unwrap(),
+            format!("Send 250.5 TRX to {}, fee limit 10 TRX, expires in 9 min", to)
+        );
+        assert_eq!(
+            summarize(&request, 0, "de_DE").unwrap(),
+            format!("Send 250,5 TRX to {}, fee limit 10 TRX", to)
+        );
+        assert!(summarize(&request, 1_700_000_600, "").unwrap().ends_with("expired"));
+        let mut hinted = request.clone();
+        DisplayHints { locale: Some("fr-FR".to_string()), currency: CurrencyDisplay::Sun }.apply(&mut hinted);
+        assert_eq!(
+            summarize(&hinted, 0, "").unwrap(),
+            format!("Send 250500000 SUN to {}, fee limit 10000000 SUN", to)
+        );
+        assert!(summarize(&hinted, 0, "en").unwrap().starts_with("Send 250500000 SUN"));
+        let messages = summary_messages(&request, 0).unwrap();
+        assert_eq!(messages[0].key, "summary.send");
+        assert_eq!(messages[0].args["amount"], "250.5");
+        assert_eq!(messages[0].args["symbol"], "TRX");
+
+        let message = TronSignRequest::new(None, b"hi".to_vec(), 2, "m/44'/195'/0'/0/0".to_string(), None, None, Some("app.example".to_string()));
+        assert_eq!(summarize(&message, 0, "").unwrap(), "Sign message, from app.example");
+    }
+
+    #[test]
+    fn test_token_metadata_is_not_trusted() {
+        let to = shorten_address(&encode_address(&[0x41; 21]).unwrap());
+        let usdt = crate::tron::address::decode_address("TR7NHqjeKQxGTCi8q8ZY4pL8otSzgjLj6t").unwrap();
+        assert_eq!(summarize(&token_transfer_request(&usdt), 0, "").unwrap(), format!("Send 1 USDT to {}", to));
+
+        // Metadata claims USDT, but the call goes to another contract
+        let fake = [0x41, 0x42, 0x43, 0x44, 0x45, 0x46, 0x47, 0x48, 0x49, 0x4a, 0x4b, 0x4c, 0x4d, 0x4e, 0x4f, 0x50, 0x51, 0x52, 0x53, 0x54, 0x55];
+        let fake_label = shorten_address(&encode_address(&fake).unwrap());
+        assert_eq!(
+            summarize(&token_transfer_request(&fake), 0, "").unwrap(),
+            format!("Send 1000000 units of {} to {}", fake_label, to)
+        );
+        let messages = summary_messages(&token_transfer_request(&fake), 0).unwrap();
+        assert_eq!(messages[0].key, "summary.send_token_units_of");
+        assert!(!messages[0].args.values().any(|arg| arg.contains("USDT")));
+    }
+}
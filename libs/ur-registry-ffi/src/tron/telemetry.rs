@@ -0,0 +1,117 @@
+//! Optional process-wide hook receiving structured event records (decode
+//! failures, analyzer warnings, policy rejections), so deployments can feed
+//! signer-side telemetry into their monitoring. Nothing is recorded while
+//! no callback is set.
+
+use crate::response::{PtrResponse, Response};
+use crate::tron::decode::DecodeError;
+use crate::types::PtrVoid;
+
+use std::ffi::CString;
+use std::os::raw::c_char;
+use std::sync::Mutex;
+
+// Code for decode failures whose error carries no variant
+const INVALID_PAYLOAD: &str = "InvalidPayload";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EventKind {
+    DecodeFailure = 1,
+    ValidationWarning = 2,
+    PolicyRejection = 3,
+}
+
+/// One event, valid only for the duration of the callback: copy the
+/// strings out before returning
+#[repr(C)]
+pub struct TelemetryEvent {
+    pub kind: u32,
+    /// UR type or component the event comes from, e.g. `tron-sign-request`
+    pub source: *const c_char,
+    /// Stable identifier, e.g. `MissingField` or `UnlimitedApproval`
+    pub code: *const c_char,
+    /// Human-readable detail; may be empty
+    pub detail: *const c_char,
+}
+
+pub type TelemetryCallback = extern "C" fn(event: *const TelemetryEvent, context: PtrVoid);
+
+#[derive(Clone, Copy)]
+struct Hook {
+    callback: TelemetryCallback,
+    // The caller's pointer, opaque to us
+    context: usize,
+}
+
+static HOOK: Mutex<Option<Hook>> = Mutex::new(None);
+
+fn c_string(s: &str) -> CString {
+    CString::new(s.replace('\0', "")).unwrap()
+}
+
+/// Reports an event to the callback, if one is set. The callback runs
+/// outside the lock, so it may itself replace the hook.
+pub fn emit(kind: EventKind, source: &str, code: &str, detail: &str) {
+    let hook = match *HOOK.lock().unwrap_or_else(|e| e.into_inner()) {
+        Some(hook) => hook,
+        None => return,
+    };
+    let (source, code, detail) = (c_string(source), c_string(code), c_string(detail));
+    let event = TelemetryEvent {
+        kind: kind as u32,
+        source: source.as_ptr(),
+        code: code.as_ptr(),
+        detail: detail.as_ptr(),
+    };
+    (hook.callback)(&event, hook.context as PtrVoid);
+}
+
+pub fn decode_failure(source: &str, error: &DecodeError) {
+    emit(EventKind::DecodeFailure, source, error.code(), &error.to_string());
+}
+
+/// For payloads whose decoders report plain strings
+pub fn decode_failure_message(source: &str, error: &str) {
+    emit(EventKind::DecodeFailure, source, INVALID_PAYLOAD, error);
+}
+
+// ========== FFI Functions ==========
+
+/// Sets the process-wide callback, or clears it for a null `callback`.
+/// `context` is passed back untouched and must outlive the registration;
+/// the callback may run on any thread that calls into the library.
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_set_telemetry_callback(callback: Option<TelemetryCallback>, context: PtrVoid) -> PtrResponse {
+    *HOOK.lock().unwrap_or_else(|e| e.into_inner()) = callback.map(|callback| Hook { callback, context: context as usize });
+    Response::success_null().c_ptr()
+}
+
+#[cfg(all(test, feature = "ffi"))]
+mod tests {
+    use super::*;
+    use std::ffi::CStr;
+
+    static EVENTS: Mutex<Vec<(u32, String, String)>> = Mutex::new(Vec::new());
+
+    extern "C" fn record(event: *const TelemetryEvent, _context: PtrVoid) {
+        let event = unsafe { &*event };
+        let text = |p| unsafe { CStr::from_ptr(p) }.to_string_lossy().into_owned();
+        EVENTS.lock().unwrap().push((event.kind, text(event.source), text(event.code)));
+    }
+
+    #[test]
+    fn test_telemetry_callback() {
+        tron_set_telemetry_callback(Some(record), std::ptr::null_mut());
+        decode_failure("tron-signature", &DecodeError::MissingField("signature"));
+        emit(EventKind::PolicyRejection, "analyzer", "BlindSigning", "nul\0byte");
+        tron_set_telemetry_callback(None, std::ptr::null_mut());
+        emit(EventKind::ValidationWarning, "analyzer", "HighFeeLimit", "");
+
+        // Other tests may emit while the hook is set
+        let events = EVENTS.lock().unwrap();
+        assert!(events.contains(&(1, "tron-signature".to_string(), "MissingField".to_string())));
+        assert!(events.contains(&(3, "analyzer".to_string(), "BlindSigning".to_string())));
+        assert!(!events.iter().any(|(_, _, code)| code == "HighFeeLimit"));
+    }
+}
@@ -0,0 +1,129 @@
+//! Software signer standing in for the cold wallet in integration tests
+//! and demo apps. Keys live in host memory: never ship this in a wallet.
+//! It is only built for tests and with the `test-signer` feature, which
+//! also exports its FFI functions.
+
+#[cfg(feature = "test-signer")]
+use crate::response::{PtrResponse, Response};
+use crate::tron::address::{encode_address, public_key_to_address};
+use crate::tron::tron_sign_request::TronSignRequest;
+#[cfg(any(test, feature = "test-signer"))]
+use crate::tron::tron_signature::recover_address;
+use crate::tron::tron_signature::{salt_proof_hash, signing_hash, TronSignature, RECOVERY_ID_OFFSET};
+#[cfg(feature = "test-signer")]
+use crate::types::{PtrString, PtrVoid};
+#[cfg(feature = "test-signer")]
+use crate::utils::parse_ptr_string_to_bytes;
+
+use secp256k1::{Message, PublicKey, Secp256k1, SecretKey};
+
+pub struct TestSigner {
+    secret_key: SecretKey,
+}
+
+impl TestSigner {
+    pub fn new(private_key: &[u8]) -> Result<Self, String> {
+        let secret_key = SecretKey::from_slice(private_key).map_err(|e| format!("Invalid private key: {}", e))?;
+        Ok(TestSigner { secret_key })
+    }
+
+    pub fn get_public_key(&self) -> Vec<u8> {
+        PublicKey::from_secret_key(&Secp256k1::signing_only(), &self.secret_key)
+            .serialize()
+            .to_vec()
+    }
+
+    pub fn get_address(&self) -> Result<String, String> {
+        encode_address(&public_key_to_address(&self.get_public_key())?)
+    }
+
+    /// 65-byte `r || s || v` signature answering `request`. Refuses requests
+    /// addressed to a different account, as a device would.
+    pub fn sign(&self, request: &TronSignRequest) -> Result<TronSignature, String> {
+        if let Some(address) = request.get_address() {
+            if *address != self.get_address()? {
+                return Err(format!("Request is for {}, not this signer", address));
+            }
+        }
+        let mut signature = TronSignature::new(request.get_request_id().cloned(), self.sign_digest(&signing_hash(request)?)?);
+        signature.set_public_key(Some(self.get_public_key()))?;
+        if request.get_salt().is_some() {
+            signature.set_salt(request.get_salt().cloned());
+            signature.set_salt_proof(Some(self.sign_digest(&salt_proof_hash(request)?)?));
+        }
+        Ok(signature)
+    }
+
+    // 65-byte `r || s || v` signature over `digest`
+    fn sign_digest(&self, digest: &[u8; 32]) -> Result<Vec<u8>, String> {
+        let message = Message::from_slice(digest).map_err(|e| e.to_string())?;
+        let (recovery_id, compact) = Secp256k1::signing_only()
+            .sign_ecdsa_recoverable(&message, &self.secret_key)
+            .serialize_compact();
+        let mut signature = compact.to_vec();
+        signature.push(recovery_id.to_i32() as u8 + RECOVERY_ID_OFFSET);
+        Ok(signature)
+    }
+}
+
+// ========== FFI Functions ==========
+
+/// Signs with a hex private key; returns a TronSignature object
+#[cfg(feature = "test-signer")]
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_test_signer_sign(tron_sign_request: &TronSignRequest, private_key: PtrString) -> PtrResponse {
+    let private_key = match parse_ptr_string_to_bytes(private_key).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    match TestSigner::new(&private_key).and_then(|signer| signer.sign(tron_sign_request)) {
+        Ok(signature) => Response::success_object(Box::into_raw(Box::new(signature)) as PtrVoid),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(feature = "test-signer")]
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_test_signer_recover_address(
+    tron_sign_request: &TronSignRequest,
+    tron_signature: &TronSignature,
+) -> PtrResponse {
+    match recover_address(tron_sign_request, tron_signature) {
+        Ok(address) => Response::success_string(address),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sign_and_recover() {
+        let signer = TestSigner::new(&[1; 32]).unwrap();
+        for data_type in [1, 2] {
+            let request = TronSignRequest::new_with_derived_request_id(
+                vec![0x0a, 0x02, 0x07, 0x90],
+                data_type,
+                "m/44'/195'/0'/0/0".to_string(),
+                None,
+                Some(signer.get_address().unwrap()),
+                None,
+            );
+            let signature = signer.sign(&request).unwrap();
+            assert_eq!(signature.get_request_id(), request.get_request_id());
+            assert_eq!(signature.get_signature().len(), 65);
+            assert_eq!(recover_address(&request, &signature).unwrap(), signer.get_address().unwrap());
+        }
+
+        let other = TronSignRequest::new(
+            None, vec![1], 1, "m/44'/195'/0'/0/0".to_string(), None,
+            Some("TMVQGm1qAQYVdetCeGRRkTWYYrLXuHK2HC".to_string()), None,
+        );
+        assert!(signer.sign(&other).is_err());
+    }
+}
@@ -0,0 +1,725 @@
+//! Decoded `Transaction.raw` (the sign_data of transaction requests), for
+//! signer display and checks. Field numbers follow TRON's core/Tron.proto.
+
+use crate::response::{PtrResponse, Response};
+use crate::tron::address::{decode_address, encode_address, validate_address, ADDRESS_PREFIX};
+use crate::tron::protobuf::{encode, find_field, parse_fields};
+use crate::tron::tron_sign_request::{DataType, TronSignRequest};
+use crate::types::PtrString;
+use crate::utils::{convert_ptr_string_to_string, parse_ptr_string_to_bytes};
+
+#[cfg(feature = "json")]
+use serde_json::json;
+use sha2::{Digest, Sha256};
+
+// Transaction.raw
+const REF_BLOCK_BYTES: u32 = 1;
+const REF_BLOCK_NUM: u32 = 3;
+const REF_BLOCK_HASH: u32 = 4;
+const EXPIRATION: u32 = 8;
+const DATA: u32 = 10;
+const CONTRACT: u32 = 11;
+const TIMESTAMP: u32 = 14;
+const FEE_LIMIT: u32 = 18;
+
+// ref_block_bytes are bytes 6..8 of the big-endian block number,
+// ref_block_hash bytes 8..16 of the block id
+const REF_BLOCK_BYTES_RANGE: std::ops::Range<usize> = 6..8;
+const REF_BLOCK_HASH_RANGE: std::ops::Range<usize> = 8..16;
+const BLOCK_HASH_LEN: usize = 32;
+
+// BlockHeader.raw
+const BLOCK_NUMBER: u32 = 7;
+
+// Transaction.Contract and its google.protobuf.Any parameter
+const CONTRACT_TYPE: u32 = 1;
+const CONTRACT_PARAMETER: u32 = 2;
+const CONTRACT_PERMISSION_ID: u32 = 5;
+const ANY_TYPE_URL: u32 = 1;
+const ANY_VALUE: u32 = 2;
+// Field of the signing account in every contract message
+const CONTRACT_OWNER_ADDRESS: u32 = 1;
+
+// TriggerSmartContract
+const TRIGGER_OWNER_ADDRESS: u32 = 1;
+const TRIGGER_CONTRACT_ADDRESS: u32 = 2;
+const TRIGGER_CALL_VALUE: u32 = 3;
+const TRIGGER_DATA: u32 = 4;
+
+// Transaction.Contract.ContractType values
+pub const ACCOUNT_CREATE_CONTRACT: u64 = 0;
+pub const TRANSFER_CONTRACT: u64 = 1;
+pub const TRANSFER_ASSET_CONTRACT: u64 = 2;
+pub const WITHDRAW_BALANCE_CONTRACT: u64 = 13;
+pub const PROPOSAL_CREATE_CONTRACT: u64 = 16;
+pub const PROPOSAL_APPROVE_CONTRACT: u64 = 17;
+pub const PROPOSAL_DELETE_CONTRACT: u64 = 18;
+pub const TRIGGER_SMART_CONTRACT: u64 = 31;
+pub const ACCOUNT_PERMISSION_UPDATE_CONTRACT: u64 = 46;
+pub const SHIELDED_TRANSFER_CONTRACT: u64 = 51;
+
+// AccountCreateContract
+const ACCOUNT_CREATE_OWNER_ADDRESS: u32 = 1;
+const ACCOUNT_CREATE_ACCOUNT_ADDRESS: u32 = 2;
+const ACCOUNT_CREATE_TYPE_URL: &str = "type.googleapis.com/protocol.AccountCreateContract";
+
+// TransferContract and TransferAssetContract
+const TRANSFER_TO_ADDRESS: u32 = 2;
+const TRANSFER_AMOUNT: u32 = 3;
+const TRANSFER_ASSET_TO_ADDRESS: u32 = 3;
+const TRANSFER_ASSET_AMOUNT: u32 = 4;
+
+// TRC-20 function selectors
+pub const TRC20_TRANSFER: [u8; 4] = [0xa9, 0x05, 0x9c, 0xbb];
+pub const TRC20_APPROVE: [u8; 4] = [0x09, 0x5e, 0xa7, 0xb3];
+pub const TRC20_TRANSFER_FROM: [u8; 4] = [0x23, 0xb8, 0x72, 0xdd];
+
+const ABI_WORD_LEN: usize = 32;
+
+/// What a contract does, for signer screens to pick a layout
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ContractKind {
+    /// Activates a new address (AccountCreateContract)
+    AccountCreate,
+    Transfer,
+    TransferAsset,
+    /// Claims the voting rewards of an account (WithdrawBalanceContract)
+    WithdrawBalance,
+    ProposalCreate,
+    ProposalApprove,
+    ProposalDelete,
+    TriggerSmartContract,
+    AccountPermissionUpdate,
+    /// z-TRON transfer; only its transparent side can be shown
+    ShieldedTransfer,
+    Other(u64),
+}
+
+impl ContractKind {
+    pub fn from_contract_type(contract_type: u64) -> Self {
+        match contract_type {
+            ACCOUNT_CREATE_CONTRACT => ContractKind::AccountCreate,
+            TRANSFER_CONTRACT => ContractKind::Transfer,
+            TRANSFER_ASSET_CONTRACT => ContractKind::TransferAsset,
+            WITHDRAW_BALANCE_CONTRACT => ContractKind::WithdrawBalance,
+            PROPOSAL_CREATE_CONTRACT => ContractKind::ProposalCreate,
+            PROPOSAL_APPROVE_CONTRACT => ContractKind::ProposalApprove,
+            PROPOSAL_DELETE_CONTRACT => ContractKind::ProposalDelete,
+            TRIGGER_SMART_CONTRACT => ContractKind::TriggerSmartContract,
+            ACCOUNT_PERMISSION_UPDATE_CONTRACT => ContractKind::AccountPermissionUpdate,
+            SHIELDED_TRANSFER_CONTRACT => ContractKind::ShieldedTransfer,
+            other => ContractKind::Other(other),
+        }
+    }
+
+    /// Stable identifier; `Other` has none
+    pub fn as_str(&self) -> Option<&'static str> {
+        Some(match self {
+            ContractKind::AccountCreate => "AccountCreate",
+            ContractKind::Transfer => "Transfer",
+            ContractKind::TransferAsset => "TransferAsset",
+            ContractKind::WithdrawBalance => "WithdrawBalance",
+            ContractKind::ProposalCreate => "ProposalCreate",
+            ContractKind::ProposalApprove => "ProposalApprove",
+            ContractKind::ProposalDelete => "ProposalDelete",
+            ContractKind::TriggerSmartContract => "TriggerSmartContract",
+            ContractKind::AccountPermissionUpdate => "AccountPermissionUpdate",
+            ContractKind::ShieldedTransfer => "ShieldedTransfer",
+            ContractKind::Other(_) => return None,
+        })
+    }
+
+    /// Kind `as_str` names, with "Other" for the unnamed ones
+    pub fn from_name(name: &str) -> Option<Self> {
+        if name == "Other" {
+            return Some(ContractKind::Other(0));
+        }
+        [
+            ACCOUNT_CREATE_CONTRACT,
+            TRANSFER_CONTRACT,
+            TRANSFER_ASSET_CONTRACT,
+            WITHDRAW_BALANCE_CONTRACT,
+            PROPOSAL_CREATE_CONTRACT,
+            PROPOSAL_APPROVE_CONTRACT,
+            PROPOSAL_DELETE_CONTRACT,
+            TRIGGER_SMART_CONTRACT,
+            ACCOUNT_PERMISSION_UPDATE_CONTRACT,
+            SHIELDED_TRANSFER_CONTRACT,
+        ]
+        .into_iter()
+        .map(ContractKind::from_contract_type)
+        .find(|kind| kind.as_str() == Some(name))
+    }
+
+    /// Short English title, e.g. "Claim rewards"
+    pub fn get_title(&self) -> &'static str {
+        match self {
+            ContractKind::AccountCreate => "Activate account",
+            ContractKind::Transfer => "Send TRX",
+            ContractKind::TransferAsset => "Send token",
+            ContractKind::WithdrawBalance => "Claim rewards",
+            ContractKind::ProposalCreate => "Create proposal",
+            ContractKind::ProposalApprove => "Vote on proposal",
+            ContractKind::ProposalDelete => "Delete proposal",
+            ContractKind::TriggerSmartContract => "Contract call",
+            ContractKind::AccountPermissionUpdate => "Change account permissions",
+            ContractKind::ShieldedTransfer => "Shielded transfer",
+            ContractKind::Other(_) => "Unknown transaction",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Contract {
+    pub contract_type: u64,
+    pub type_url: String,
+    /// Serialized contract message, e.g. a `TransferContract`
+    pub value: Vec<u8>,
+    pub permission_id: u64,
+}
+
+impl Contract {
+    pub fn get_kind(&self) -> ContractKind {
+        ContractKind::from_contract_type(self.contract_type)
+    }
+
+    /// The contract call, for `TriggerSmartContract` contracts
+    pub fn as_trigger_smart_contract(&self) -> Result<Option<TriggerSmartContract>, String> {
+        if self.contract_type != TRIGGER_SMART_CONTRACT {
+            return Ok(None);
+        }
+        let fields = parse_fields(&self.value)?;
+        let bytes = |number| find_field(&fields, number).and_then(|v| v.as_bytes()).unwrap_or_default().to_vec();
+        Ok(Some(TriggerSmartContract {
+            owner_address: bytes(TRIGGER_OWNER_ADDRESS),
+            contract_address: bytes(TRIGGER_CONTRACT_ADDRESS),
+            call_value: find_field(&fields, TRIGGER_CALL_VALUE).and_then(|v| v.as_varint()).unwrap_or_default(),
+            data: bytes(TRIGGER_DATA),
+        }))
+    }
+
+    /// 21-byte address of the account the contract acts for
+    pub fn get_owner_address(&self) -> Result<Option<Vec<u8>>, String> {
+        let fields = parse_fields(&self.value)?;
+        Ok(find_field(&fields, CONTRACT_OWNER_ADDRESS).and_then(|v| v.as_bytes()).map(<[u8]>::to_vec))
+    }
+
+    /// 21-byte address an AccountCreateContract activates
+    pub fn get_created_account(&self) -> Result<Option<Vec<u8>>, String> {
+        if self.contract_type != ACCOUNT_CREATE_CONTRACT {
+            return Ok(None);
+        }
+        let fields = parse_fields(&self.value)?;
+        Ok(find_field(&fields, ACCOUNT_CREATE_ACCOUNT_ADDRESS).and_then(|v| v.as_bytes()).map(<[u8]>::to_vec))
+    }
+
+    /// The transfer, for TRX, TRC-10 and TRC-20 transfers
+    pub fn get_transfer(&self) -> Result<Option<Transfer>, String> {
+        let (to, amount) = match self.contract_type {
+            TRANSFER_CONTRACT => (TRANSFER_TO_ADDRESS, TRANSFER_AMOUNT),
+            TRANSFER_ASSET_CONTRACT => (TRANSFER_ASSET_TO_ADDRESS, TRANSFER_ASSET_AMOUNT),
+            _ => {
+                return Ok(self.as_trigger_smart_contract()?.and_then(|call| {
+                    let (to, amount) = match call.get_selector()? {
+                        TRC20_TRANSFER => (0, 1),
+                        TRC20_TRANSFER_FROM => (1, 2),
+                        _ => return None,
+                    };
+                    Some(Transfer { to: call.get_address_argument(to)?, amount: call.get_argument(amount)?.to_vec() })
+                }))
+            }
+        };
+        let fields = parse_fields(&self.value)?;
+        let to = find_field(&fields, to).and_then(|v| v.as_bytes()).map(<[u8]>::to_vec);
+        let amount = find_field(&fields, amount).and_then(|v| v.as_varint()).unwrap_or_default();
+        Ok(to.map(|to| Transfer { to, amount: amount.to_be_bytes().to_vec() }))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Transfer {
+    /// 21-byte recipient address
+    pub to: Vec<u8>,
+    /// Big-endian, in the asset's smallest unit
+    pub amount: Vec<u8>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TriggerSmartContract {
+    pub owner_address: Vec<u8>,
+    pub contract_address: Vec<u8>,
+    /// SUN sent along with the call
+    pub call_value: u64,
+    /// ABI-encoded call: 4-byte selector, then arguments
+    pub data: Vec<u8>,
+}
+
+impl TriggerSmartContract {
+    pub fn get_selector(&self) -> Option<[u8; 4]> {
+        self.data.get(..4).map(|s| s.try_into().unwrap())
+    }
+
+    /// ABI word `index` of the arguments
+    pub fn get_argument(&self, index: usize) -> Option<&[u8]> {
+        let start = 4 + index * ABI_WORD_LEN;
+        self.data.get(start..start + ABI_WORD_LEN)
+    }
+
+    /// Address argument `index` as a 21-byte TRON address
+    pub fn get_address_argument(&self, index: usize) -> Option<Vec<u8>> {
+        let word = self.get_argument(index)?;
+        if word[..12].iter().any(|b| *b != 0) {
+            return None;
+        }
+        let mut address = vec![ADDRESS_PREFIX];
+        address.extend_from_slice(&word[12..]);
+        Some(address)
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RawTransaction {
+    pub ref_block_bytes: Vec<u8>,
+    pub ref_block_num: u64,
+    pub ref_block_hash: Vec<u8>,
+    pub expiration: u64,
+    /// The memo
+    pub data: Option<Vec<u8>>,
+    pub contracts: Vec<Contract>,
+    pub timestamp: u64,
+    pub fee_limit: u64,
+}
+
+impl RawTransaction {
+    pub fn parse(raw_data: &[u8]) -> Result<Self, String> {
+        let fields = parse_fields(raw_data)?;
+        let bytes = |number| find_field(&fields, number).and_then(|v| v.as_bytes()).map(<[u8]>::to_vec);
+        let varint = |number| find_field(&fields, number).and_then(|v| v.as_varint()).unwrap_or_default();
+
+        let contracts = fields
+            .iter()
+            .filter(|(number, _)| *number == CONTRACT)
+            .map(|(_, value)| parse_contract(value.as_bytes().ok_or("Contract is not a message")?))
+            .collect::<Result<Vec<_>, String>>()?;
+
+        Ok(RawTransaction {
+            ref_block_bytes: bytes(REF_BLOCK_BYTES).unwrap_or_default(),
+            ref_block_num: varint(REF_BLOCK_NUM),
+            ref_block_hash: bytes(REF_BLOCK_HASH).unwrap_or_default(),
+            expiration: varint(EXPIRATION),
+            data: bytes(DATA),
+            contracts,
+            timestamp: varint(TIMESTAMP),
+            fee_limit: varint(FEE_LIMIT),
+        })
+    }
+
+    /// More than one contract entry: nodes execute all of them, while
+    /// wallets commonly show only the first
+    pub fn has_multiple_contracts(&self) -> bool {
+        self.contracts.len() > 1
+    }
+
+    /// The memo as text, or `0x`-prefixed hex when it isn't printable UTF-8
+    pub fn get_memo(&self) -> Option<String> {
+        self.data.as_ref().map(|data| match std::str::from_utf8(data) {
+            Ok(text) if text.chars().all(|c| !c.is_control() || c == '\n') => text.to_string(),
+            _ => format!("0x{}", hex::encode(data)),
+        })
+    }
+}
+
+fn parse_contract(data: &[u8]) -> Result<Contract, String> {
+    let fields = parse_fields(data)?;
+    let parameter = find_field(&fields, CONTRACT_PARAMETER).and_then(|v| v.as_bytes()).unwrap_or_default();
+    let parameter = parse_fields(parameter)?;
+    Ok(Contract {
+        contract_type: find_field(&fields, CONTRACT_TYPE).and_then(|v| v.as_varint()).unwrap_or_default(),
+        type_url: find_field(&parameter, ANY_TYPE_URL).and_then(|v| v.as_str()).unwrap_or_default().to_string(),
+        value: find_field(&parameter, ANY_VALUE).and_then(|v| v.as_bytes()).unwrap_or_default().to_vec(),
+        permission_id: find_field(&fields, CONTRACT_PERMISSION_ID).and_then(|v| v.as_varint()).unwrap_or_default(),
+    })
+}
+
+/// Decoded sign_data of a transaction request
+pub fn parse_request(request: &TronSignRequest) -> Result<RawTransaction, String> {
+    match DataType::from_u32(request.get_data_type())? {
+        DataType::Transaction => RawTransaction::parse(request.get_sign_data()),
+        _ => Err("Request is not a transaction".to_string()),
+    }
+}
+
+/// `raw_data` with its memo replaced, or removed for `None`. Other fields
+/// keep their order; the memo goes where protobuf serializers put it.
+pub fn set_memo(raw_data: &[u8], memo: Option<&[u8]>) -> Result<Vec<u8>, String> {
+    let mut out = Vec::with_capacity(raw_data.len() + memo.map_or(0, |m| m.len() + 4));
+    let mut memo = memo;
+    for (number, value) in parse_fields(raw_data)? {
+        if number == DATA {
+            continue;
+        }
+        if number > DATA {
+            if let Some(memo) = memo.take() {
+                encode::bytes_field(DATA, memo, &mut out);
+            }
+        }
+        encode::field(number, &value, &mut out);
+    }
+    if let Some(memo) = memo {
+        encode::bytes_field(DATA, memo, &mut out);
+    }
+    Ok(out)
+}
+
+/// Block reference and validity window for building transactions; times
+/// are in milliseconds
+#[derive(Clone, Debug, PartialEq)]
+pub struct TransactionHeader {
+    pub ref_block_bytes: Vec<u8>,
+    pub ref_block_hash: Vec<u8>,
+    pub expiration: u64,
+    pub timestamp: u64,
+}
+
+/// raw_data of a transaction with a single contract of `contract_type`
+fn build_raw_transaction(header: &TransactionHeader, contract_type: u64, type_url: &str, value: &[u8]) -> Vec<u8> {
+    let mut any = Vec::new();
+    encode::bytes_field(ANY_TYPE_URL, type_url.as_bytes(), &mut any);
+    encode::bytes_field(ANY_VALUE, value, &mut any);
+    let mut contract = Vec::new();
+    encode::varint_field(CONTRACT_TYPE, contract_type, &mut contract);
+    encode::bytes_field(CONTRACT_PARAMETER, &any, &mut contract);
+
+    let mut raw_data = Vec::new();
+    encode::bytes_field(REF_BLOCK_BYTES, &header.ref_block_bytes, &mut raw_data);
+    encode::bytes_field(REF_BLOCK_HASH, &header.ref_block_hash, &mut raw_data);
+    encode::varint_field(EXPIRATION, header.expiration, &mut raw_data);
+    encode::bytes_field(CONTRACT, &contract, &mut raw_data);
+    encode::varint_field(TIMESTAMP, header.timestamp, &mut raw_data);
+    raw_data
+}
+
+/// raw_data of an AccountCreateContract in which `owner` activates
+/// `account`; both are 21-byte addresses
+pub fn build_account_create(header: &TransactionHeader, owner: &[u8], account: &[u8]) -> Result<Vec<u8>, String> {
+    validate_address(owner)?;
+    validate_address(account)?;
+    if header.ref_block_bytes.len() != REF_BLOCK_BYTES_RANGE.len() || header.ref_block_hash.len() != REF_BLOCK_HASH_RANGE.len() {
+        return Err("Invalid block reference".to_string());
+    }
+    let mut value = Vec::new();
+    encode::bytes_field(ACCOUNT_CREATE_OWNER_ADDRESS, owner, &mut value);
+    encode::bytes_field(ACCOUNT_CREATE_ACCOUNT_ADDRESS, account, &mut value);
+    Ok(build_raw_transaction(header, ACCOUNT_CREATE_CONTRACT, ACCOUNT_CREATE_TYPE_URL, &value))
+}
+
+/// Whether the transaction references the block `block_number` with id
+/// `block_hash`, so an offline signer can catch stale or forged references
+pub fn validate_ref_block(raw_data: &[u8], block_hash: &[u8], block_number: u64) -> Result<bool, String> {
+    if block_hash.len() != BLOCK_HASH_LEN {
+        return Err(format!("Block hash must be {} bytes, got {}", BLOCK_HASH_LEN, block_hash.len()));
+    }
+    let transaction = RawTransaction::parse(raw_data)?;
+    if transaction.ref_block_bytes.is_empty() || transaction.ref_block_hash.is_empty() {
+        return Err("Transaction has no block reference".to_string());
+    }
+    Ok(transaction.ref_block_bytes == block_number.to_be_bytes()[REF_BLOCK_BYTES_RANGE]
+        && transaction.ref_block_hash == block_hash[REF_BLOCK_HASH_RANGE])
+}
+
+/// Block id and number of a serialized `BlockHeader.raw`: sha256 of the
+/// header with its first 8 bytes replaced by the big-endian block number
+pub fn block_id(header: &[u8]) -> Result<([u8; 32], u64), String> {
+    let fields = parse_fields(header)?;
+    let number = find_field(&fields, BLOCK_NUMBER).and_then(|v| v.as_varint()).ok_or("Block header has no number")?;
+    let mut id: [u8; 32] = Sha256::digest(header).into();
+    id[..8].copy_from_slice(&number.to_be_bytes());
+    Ok((id, number))
+}
+
+/// Checks the transaction against the block header the request carries
+pub fn validate_embedded_block(request: &TronSignRequest) -> Result<bool, String> {
+    let header = request.get_block_header().ok_or("Request carries no block header")?;
+    let (id, number) = block_id(header)?;
+    let transaction = parse_request(request)?;
+    Ok(transaction.ref_block_bytes == number.to_be_bytes()[REF_BLOCK_BYTES_RANGE]
+        && transaction.ref_block_hash == id[REF_BLOCK_HASH_RANGE])
+}
+
+// ========== FFI Functions ==========
+
+/// Kind of the first contract, e.g. "WithdrawBalance", or `NOT_PRESENT`
+/// when it has no stable name
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_request_get_contract_kind(tron_sign_request: &TronSignRequest) -> PtrResponse {
+    match parse_request(tron_sign_request) {
+        Ok(transaction) => transaction
+            .contracts
+            .first()
+            .and_then(|c| c.get_kind().as_str())
+            .map_or(Response::not_present(), |kind| Response::success_string(kind.to_string())),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+/// English title of the first contract, e.g. "Claim rewards"
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_request_get_contract_title(tron_sign_request: &TronSignRequest) -> PtrResponse {
+    match parse_request(tron_sign_request) {
+        Ok(transaction) => match transaction.contracts.first() {
+            Some(contract) => Response::success_string(contract.get_kind().get_title().to_string()),
+            None => Response::error("Transaction has no contract".to_string()),
+        },
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_request_get_contract_count(tron_sign_request: &TronSignRequest) -> PtrResponse {
+    match parse_request(tron_sign_request) {
+        Ok(transaction) => Response::success_uint32(transaction.contracts.len() as u32),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+/// JSON `{"type", "kind", "title", "type_url", "value", "permission_id"}` of
+/// contract `index`, with the value in hex
+#[cfg(feature = "json")]
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_request_get_contract(tron_sign_request: &TronSignRequest, index: u32) -> PtrResponse {
+    match parse_request(tron_sign_request) {
+        Ok(transaction) => match transaction.contracts.get(index as usize) {
+            Some(contract) => Response::success_string(
+                json!({
+                    "type": contract.contract_type,
+                    "kind": contract.get_kind().as_str(),
+                    "title": contract.get_kind().get_title(),
+                    "type_url": contract.type_url,
+                    "value": hex::encode(&contract.value),
+                    "permission_id": contract.permission_id,
+                })
+                .to_string(),
+            ),
+            None => Response::error(format!("No contract at index {}", index)),
+        },
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+/// Base58 address the request activates, or `NOT_PRESENT` for other
+/// transactions
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_request_get_created_account(tron_sign_request: &TronSignRequest) -> PtrResponse {
+    let created = parse_request(tron_sign_request).and_then(|transaction| {
+        match transaction.contracts.first().map(Contract::get_created_account).transpose()?.flatten() {
+            Some(account) => encode_address(&account).map(Some),
+            None => Ok(None),
+        }
+    });
+    match created {
+        Ok(account) => account.map_or(Response::not_present(), Response::success_string),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+/// Hex raw_data activating base58 `account` from `owner`. The block
+/// reference is hex; times are in milliseconds.
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_transaction_build_account_create(
+    owner: PtrString,
+    account: PtrString,
+    ref_block_bytes: PtrString,
+    ref_block_hash: PtrString,
+    expiration: u64,
+    timestamp: u64,
+) -> PtrResponse {
+    let owner = match convert_ptr_string_to_string(owner).and_then(|a| decode_address(&a)).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    let account = match convert_ptr_string_to_string(account).and_then(|a| decode_address(&a)).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    let ref_block_bytes = match parse_ptr_string_to_bytes(ref_block_bytes).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    let ref_block_hash = match parse_ptr_string_to_bytes(ref_block_hash).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    let header = TransactionHeader { ref_block_bytes, ref_block_hash, expiration, timestamp };
+    match build_account_create(&header, &owner, &account) {
+        Ok(raw_data) => Response::success_string(hex::encode(raw_data)),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_request_get_memo(tron_sign_request: &TronSignRequest) -> PtrResponse {
+    match parse_request(tron_sign_request) {
+        Ok(transaction) => transaction.get_memo().map_or(Response::not_present(), Response::success_string),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+/// Hex raw_data with the UTF-8 `memo` set; an empty memo removes it
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_transaction_set_memo(raw_data: PtrString, memo: PtrString) -> PtrResponse {
+    let raw_data = match parse_ptr_string_to_bytes(raw_data).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    let memo = match convert_ptr_string_to_string(memo).map_err(|e| Response::error(e)) {
+        Ok(v) => Some(v).filter(|s| !s.is_empty()),
+        Err(e) => return e.c_ptr(),
+    };
+    match set_memo(&raw_data, memo.as_deref().map(str::as_bytes)) {
+        Ok(raw_data) => Response::success_string(hex::encode(raw_data)),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_transaction_validate_ref_block(raw_data: PtrString, block_hash: PtrString, block_number: u64) -> PtrResponse {
+    let raw_data = match parse_ptr_string_to_bytes(raw_data).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    let block_hash = match parse_ptr_string_to_bytes(block_hash).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    match validate_ref_block(&raw_data, &block_hash, block_number) {
+        Ok(valid) => Response::success_boolean(valid),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_request_validate_block_header(tron_sign_request: &TronSignRequest) -> PtrResponse {
+    match validate_embedded_block(tron_sign_request) {
+        Ok(valid) => Response::success_boolean(valid),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tron::protobuf::encode::{bytes_field, varint_field};
+
+    #[test]
+    fn test_parse_and_memo() {
+        let mut any = Vec::new();
+        bytes_field(ANY_TYPE_URL, b"type.googleapis.com/protocol.TransferContract", &mut any);
+        bytes_field(ANY_VALUE, &[0x18, 0x0a], &mut any);
+        let mut contract = Vec::new();
+        varint_field(CONTRACT_TYPE, 1, &mut contract);
+        bytes_field(CONTRACT_PARAMETER, &any, &mut contract);
+        let mut raw_data = Vec::new();
+        bytes_field(REF_BLOCK_BYTES, &[0x07, 0x90], &mut raw_data);
+        bytes_field(REF_BLOCK_HASH, &[0xe1; 8], &mut raw_data);
+        varint_field(EXPIRATION, 1_700_000_060_000, &mut raw_data);
+        bytes_field(CONTRACT, &contract, &mut raw_data);
+        varint_field(TIMESTAMP, 1_700_000_000_000, &mut raw_data);
+
+        let transaction = RawTransaction::parse(&raw_data).unwrap();
+        assert_eq!(transaction.ref_block_bytes, vec![0x07, 0x90]);
+        assert_eq!(transaction.expiration, 1_700_000_060_000);
+        assert_eq!(transaction.contracts.len(), 1);
+        assert!(!transaction.has_multiple_contracts());
+        assert_eq!(transaction.contracts[0].contract_type, 1);
+        assert_eq!(transaction.contracts[0].value, vec![0x18, 0x0a]);
+        assert_eq!(transaction.contracts[0].get_kind(), ContractKind::Transfer);
+        assert_eq!(ContractKind::from_contract_type(WITHDRAW_BALANCE_CONTRACT).get_title(), "Claim rewards");
+        assert_eq!(ContractKind::from_contract_type(99).as_str(), None);
+
+        let header = TransactionHeader {
+            ref_block_bytes: vec![0x07, 0x90],
+            ref_block_hash: vec![0xe1; 8],
+            expiration: 1_700_000_060_000,
+            timestamp: 1_700_000_000_000,
+        };
+        let account: Vec<u8> = std::iter::once(ADDRESS_PREFIX).chain([0x42; 20]).collect();
+        let raw_data = build_account_create(&header, &[0x41; 21], &account).unwrap();
+        let transaction = RawTransaction::parse(&raw_data).unwrap();
+        assert_eq!(transaction.contracts[0].get_kind(), ContractKind::AccountCreate);
+        assert_eq!(transaction.contracts[0].type_url, ACCOUNT_CREATE_TYPE_URL);
+        assert_eq!(transaction.contracts[0].get_created_account().unwrap(), Some(account));
+        assert_eq!(transaction.expiration, header.expiration);
+        assert!(build_account_create(&header, &[0x41; 21], &[0x42; 21]).is_err());
+
+        let mut hidden = raw_data.clone();
+        bytes_field(CONTRACT, &contract, &mut hidden);
+        let transaction = RawTransaction::parse(&hidden).unwrap();
+        assert!(transaction.has_multiple_contracts());
+        assert_eq!(transaction.contracts[1].get_kind(), ContractKind::Transfer);
+        assert_eq!(transaction.get_memo(), None);
+
+        let with_memo = set_memo(&raw_data, Some(b"deposit 42")).unwrap();
+        let transaction = RawTransaction::parse(&with_memo).unwrap();
+        assert_eq!(transaction.get_memo().as_deref(), Some("deposit 42"));
+        let fields: Vec<u32> = parse_fields(&with_memo).unwrap().iter().map(|(n, _)| *n).collect();
+        assert_eq!(fields, vec![REF_BLOCK_BYTES, REF_BLOCK_HASH, EXPIRATION, DATA, CONTRACT, TIMESTAMP]);
+
+        let binary = set_memo(&with_memo, Some(&[0xff, 0x00])).unwrap();
+        assert_eq!(RawTransaction::parse(&binary).unwrap().get_memo().as_deref(), Some("0xff00"));
+        assert_eq!(set_memo(&with_memo, None).unwrap(), raw_data);
+    }
+
+    #[test]
+    fn test_validate_ref_block() {
+        let block_number: u64 = 0x0345_0790;
+        let mut block_hash = vec![0; 8];
+        block_hash.extend_from_slice(&[0xe1; 8]);
+        block_hash.extend_from_slice(&[0x55; 16]);
+        let mut raw_data = Vec::new();
+        bytes_field(REF_BLOCK_BYTES, &[0x07, 0x90], &mut raw_data);
+        bytes_field(REF_BLOCK_HASH, &[0xe1; 8], &mut raw_data);
+
+        assert!(validate_ref_block(&raw_data, &block_hash, block_number).unwrap());
+        assert!(!validate_ref_block(&raw_data, &block_hash, block_number + 1).unwrap());
+        block_hash[8] = 0;
+        assert!(!validate_ref_block(&raw_data, &block_hash, block_number).unwrap());
+        assert!(validate_ref_block(&raw_data, &block_hash[..16], block_number).is_err());
+        assert!(validate_ref_block(&[], &block_hash, block_number).is_err());
+
+        let mut header = Vec::new();
+        varint_field(1, 1_700_000_000_000, &mut header);
+        varint_field(BLOCK_NUMBER, block_number, &mut header);
+        let (id, number) = block_id(&header).unwrap();
+        assert_eq!(number, block_number);
+        let mut raw_data = Vec::new();
+        bytes_field(REF_BLOCK_BYTES, &[0x07, 0x90], &mut raw_data);
+        bytes_field(REF_BLOCK_HASH, &id[8..16], &mut raw_data);
+        let mut request = TronSignRequest::new_with_derived_request_id(
+            raw_data, 1, "m/44'/195'/0'/0/0".to_string(), None, None, None,
+        );
+        assert!(validate_embedded_block(&request).is_err());
+        request.set_block_header(Some(header));
+        let request = TronSignRequest::try_from(request.to_bytes().unwrap()).unwrap();
+        assert!(validate_embedded_block(&request).unwrap());
+    }
+}
@@ -0,0 +1,293 @@
+//! Tamper-evident audit logs of what was requested and signed. Each entry
+//! digests (request CBOR, response CBOR, timestamp), and the transcript
+//! chains entry digests into a head hash: editing, dropping or reordering
+//! any entry changes the head. Custody systems anchor the head somewhere
+//! the log's writer can't rewrite and compare it on audit.
+//!
+//! Digests are sha256 over a domain tag and length-prefixed fields, so no
+//! two distinct inputs share an encoding.
+
+use crate::response::{PtrResponse, Response};
+use crate::tron::decode::decode_map;
+use crate::tron::tron_sign_request::TronSignRequest;
+use crate::tron::tron_signature::TronSignature;
+use crate::types::{PtrString, PtrVoid};
+use crate::utils::parse_ptr_string_to_bytes;
+
+use serde_cbor::Value;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+
+const ENTRY_TAG: &[u8] = b"tron-transcript-entry-v1";
+const CHAIN_TAG: &[u8] = b"tron-transcript-chain-v1";
+
+// CBOR map keys for a serialized Transcript
+const ENTRIES: i128 = 1;
+const HEAD: i128 = 2;
+
+// CBOR map keys for each entry
+const REQUEST: i128 = 1;
+const RESPONSE: i128 = 2;
+const TIMESTAMP: i128 = 3;
+
+fn length_prefixed(hasher: &mut Sha256, field: &[u8]) {
+    hasher.update((field.len() as u64).to_be_bytes());
+    hasher.update(field);
+}
+
+/// Canonical digest of one exchange; `timestamp` is in the host's unit,
+/// typically Unix seconds
+pub fn transcript_digest(request: &[u8], response: &[u8], timestamp: u64) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    length_prefixed(&mut hasher, ENTRY_TAG);
+    length_prefixed(&mut hasher, request);
+    length_prefixed(&mut hasher, response);
+    hasher.update(timestamp.to_be_bytes());
+    hasher.finalize().into()
+}
+
+fn chain(head: &[u8; 32], digest: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    length_prefixed(&mut hasher, CHAIN_TAG);
+    hasher.update(head);
+    hasher.update(digest);
+    hasher.finalize().into()
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TranscriptEntry {
+    pub request: Vec<u8>,
+    pub response: Vec<u8>,
+    pub timestamp: u64,
+}
+
+impl TranscriptEntry {
+    pub fn get_digest(&self) -> [u8; 32] {
+        transcript_digest(&self.request, &self.response, self.timestamp)
+    }
+}
+
+/// Append-only log; the head of an empty transcript is all zeroes
+#[derive(Clone, Debug, Default)]
+pub struct Transcript {
+    entries: Vec<TranscriptEntry>,
+    head: [u8; 32],
+}
+
+impl Transcript {
+    pub fn new() -> Self {
+        Transcript::default()
+    }
+
+    /// Appends an exchange and returns the new head. Timestamps may repeat
+    /// but not go backwards.
+    pub fn append(&mut self, request: Vec<u8>, response: Vec<u8>, timestamp: u64) -> Result<[u8; 32], String> {
+        if self.entries.last().is_some_and(|last| last.timestamp > timestamp) {
+            return Err("Transcript timestamps can't go backwards".to_string());
+        }
+        let entry = TranscriptEntry { request, response, timestamp };
+        self.head = chain(&self.head, &entry.get_digest());
+        self.entries.push(entry);
+        Ok(self.head)
+    }
+
+    pub fn append_signature(&mut self, request: &TronSignRequest, signature: &TronSignature, timestamp: u64) -> Result<[u8; 32], String> {
+        self.append(request.to_bytes()?, signature.to_bytes()?, timestamp)
+    }
+
+    pub fn get_entries(&self) -> &Vec<TranscriptEntry> {
+        &self.entries
+    }
+
+    pub fn get_head(&self) -> [u8; 32] {
+        self.head
+    }
+
+    /// Serialize to CBOR bytes, head included
+    pub fn to_bytes(&self) -> Result<Vec<u8>, String> {
+        let entries = self
+            .entries
+            .iter()
+            .map(|entry| {
+                let mut map: BTreeMap<Value, Value> = BTreeMap::new();
+                map.insert(Value::Integer(REQUEST), Value::Bytes(entry.request.clone()));
+                map.insert(Value::Integer(RESPONSE), Value::Bytes(entry.response.clone()));
+                map.insert(Value::Integer(TIMESTAMP), Value::Integer(entry.timestamp as i128));
+                Value::Map(map)
+            })
+            .collect();
+        let mut map: BTreeMap<Value, Value> = BTreeMap::new();
+        map.insert(Value::Integer(ENTRIES), Value::Array(entries));
+        map.insert(Value::Integer(HEAD), Value::Bytes(self.head.to_vec()));
+        serde_cbor::to_vec(&Value::Map(map)).map_err(|e| e.to_string())
+    }
+}
+
+/// Replays the entries and fails unless they chain to the stored head
+impl TryFrom<Vec<u8>> for Transcript {
+    type Error = String;
+
+    fn try_from(data: Vec<u8>) -> Result<Self, Self::Error> {
+        let map = decode_map(&data)?;
+        let bytes = |map: &BTreeMap<Value, Value>, key, name: &str| match map.get(&Value::Integer(key)) {
+            Some(Value::Bytes(b)) => Ok(b.clone()),
+            _ => Err(format!("Missing {}", name)),
+        };
+
+        let mut transcript = Transcript::new();
+        match map.get(&Value::Integer(ENTRIES)) {
+            Some(Value::Array(entries)) => {
+                for entry in entries {
+                    let entry = match entry {
+                        Value::Map(entry) => entry,
+                        _ => return Err("Expected transcript entry map".to_string()),
+                    };
+                    let timestamp = match entry.get(&Value::Integer(TIMESTAMP)) {
+                        Some(Value::Integer(i)) => u64::try_from(*i).map_err(|_| "Invalid timestamp".to_string())?,
+                        _ => return Err("Missing timestamp".to_string()),
+                    };
+                    transcript.append(bytes(entry, REQUEST, "request")?, bytes(entry, RESPONSE, "response")?, timestamp)?;
+                }
+            }
+            _ => return Err("Missing transcript entries".to_string()),
+        }
+        if bytes(&map, HEAD, "transcript head")? != transcript.head {
+            return Err("Transcript entries don't match its head".to_string());
+        }
+        Ok(transcript)
+    }
+}
+
+// ========== FFI Functions ==========
+
+/// Hex digest of one signed exchange
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_transcript_digest(
+    tron_sign_request: &TronSignRequest,
+    tron_signature: &TronSignature,
+    timestamp: u64,
+) -> PtrResponse {
+    match (tron_sign_request.to_bytes(), tron_signature.to_bytes()) {
+        (Ok(request), Ok(signature)) => Response::success_string(hex::encode(transcript_digest(&request, &signature, timestamp))),
+        (Err(e), _) | (_, Err(e)) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_transcript_new() -> PtrResponse {
+    Response::success_object(Box::into_raw(Box::new(Transcript::new())) as PtrVoid).c_ptr()
+}
+
+/// Appends a signed exchange and returns the new head in hex
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_transcript_append_signature(
+    transcript: &mut Transcript,
+    tron_sign_request: &TronSignRequest,
+    tron_signature: &TronSignature,
+    timestamp: u64,
+) -> PtrResponse {
+    match transcript.append_signature(tron_sign_request, tron_signature, timestamp) {
+        Ok(head) => Response::success_string(hex::encode(head)),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+/// Appends an exchange given as hex CBOR, e.g. a request and the
+/// `tron-sign-rejection` answering it
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_transcript_append(
+    transcript: &mut Transcript,
+    request: PtrString,
+    response: PtrString,
+    timestamp: u64,
+) -> PtrResponse {
+    let request = match parse_ptr_string_to_bytes(request).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    let response = match parse_ptr_string_to_bytes(response).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    match transcript.append(request, response, timestamp) {
+        Ok(head) => Response::success_string(hex::encode(head)),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_transcript_get_head(transcript: &Transcript) -> PtrResponse {
+    Response::success_string(hex::encode(transcript.get_head())).c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_transcript_get_entries_len(transcript: &Transcript) -> PtrResponse {
+    Response::success_uint32(transcript.get_entries().len() as u32).c_ptr()
+}
+
+/// Hex CBOR for persisting the log
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_transcript_to_bytes(transcript: &Transcript) -> PtrResponse {
+    match transcript.to_bytes() {
+        Ok(bytes) => Response::success_string(hex::encode(bytes)),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+/// Loads a persisted log, failing when its entries don't chain to its head
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_transcript_from_bytes(bytes: PtrString) -> PtrResponse {
+    let bytes = match parse_ptr_string_to_bytes(bytes).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    match Transcript::try_from(bytes) {
+        Ok(transcript) => Response::success_object(Box::into_raw(Box::new(transcript)) as PtrVoid),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transcript_chain() {
+        let request = TronSignRequest::new_with_derived_request_id(
+            vec![0x0a, 0x02], 1, "m/44'/195'/0'/0/0".to_string(), None, None, None,
+        );
+        let signature = TronSignature::new(request.get_request_id().cloned(), vec![1; 65]);
+        let digest = transcript_digest(&request.to_bytes().unwrap(), &signature.to_bytes().unwrap(), 1_700_000_000);
+        assert_ne!(digest, transcript_digest(&request.to_bytes().unwrap(), &signature.to_bytes().unwrap(), 1_700_000_001));
+        // Moving bytes between fields changes the digest
+        assert_ne!(transcript_digest(b"ab", b"c", 0), transcript_digest(b"a", b"bc", 0));
+
+        let mut transcript = Transcript::new();
+        assert_eq!(transcript.get_head(), [0; 32]);
+        let first = transcript.append_signature(&request, &signature, 1_700_000_000).unwrap();
+        let second = transcript.append(vec![0xa0], vec![0xa1, 1, 2], 1_700_000_000).unwrap();
+        assert_ne!(first, second);
+        assert!(transcript.append(vec![0xa0], vec![0xa0], 1).is_err());
+        assert_eq!(transcript.get_entries()[0].get_digest(), digest);
+
+        let bytes = transcript.to_bytes().unwrap();
+        assert_eq!(Transcript::try_from(bytes.clone()).unwrap().get_head(), second);
+        let position = bytes.windows(3).position(|w| w == [0xa1, 1, 2]).unwrap();
+        let mut tampered = bytes.clone();
+        tampered[position + 2] = 3;
+        assert_eq!(Transcript::try_from(tampered).err().unwrap(), "Transcript entries don't match its head");
+    }
+}
@@ -0,0 +1,117 @@
+//! Single-string transport `<ur-type>:<base64url(cbor)>` for deep links and
+//! clipboard transfers between apps on the same phone, where there's no QR
+//! to scan. Base64url is unpadded and decoded strictly: anything but its
+//! canonical encoding is rejected.
+
+use crate::response::{PtrResponse, Response};
+use crate::types::PtrString;
+#[cfg(feature = "ffi")]
+use crate::ur_decoder::resolve;
+use crate::utils::convert_ptr_string_to_string;
+
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+pub fn base64url_encode(data: &[u8]) -> String {
+    let mut encoded = String::with_capacity((data.len() * 4).div_ceil(3));
+    for chunk in data.chunks(3) {
+        let bits = chunk.iter().enumerate().fold(0u32, |acc, (i, b)| acc | (*b as u32) << (16 - 8 * i));
+        for i in 0..=chunk.len() {
+            encoded.push(ALPHABET[(bits >> (18 - 6 * i) & 0x3f) as usize] as char);
+        }
+    }
+    encoded
+}
+
+pub fn base64url_decode(encoded: &str) -> Result<Vec<u8>, String> {
+    if encoded.len() % 4 == 1 {
+        return Err("Invalid base64url length".to_string());
+    }
+    let mut data = Vec::with_capacity(encoded.len() * 3 / 4);
+    for chunk in encoded.as_bytes().chunks(4) {
+        let mut bits = 0u32;
+        for (i, c) in chunk.iter().enumerate() {
+            let value = ALPHABET.iter().position(|a| a == c).ok_or("Invalid base64url character")?;
+            bits |= (value as u32) << (18 - 6 * i);
+        }
+        let bytes = bits.to_be_bytes();
+        let length = chunk.len() - 1;
+        // Canonical encodings leave the unused low bits zero
+        if bits & (0xffffff >> (8 * length)) != 0 {
+            return Err("Non-canonical base64url".to_string());
+        }
+        data.extend_from_slice(&bytes[1..=length]);
+    }
+    Ok(data)
+}
+
+pub fn encode(ur_type: &str, cbor: &[u8]) -> String {
+    format!("{}:{}", ur_type, base64url_encode(cbor))
+}
+
+/// UR type and CBOR of a transport string
+pub fn decode(transport: &str) -> Result<(String, Vec<u8>), String> {
+    let (ur_type, payload) = transport.trim().split_once(':').ok_or("Not a transport string")?;
+    if ur_type.is_empty() || payload.is_empty() {
+        return Err("Not a transport string".to_string());
+    }
+    Ok((ur_type.to_lowercase(), base64url_decode(payload)?))
+}
+
+/// CBOR of a transport string that must carry `ur_type`
+pub fn decode_as(ur_type: &str, transport: &str) -> Result<Vec<u8>, String> {
+    match decode(transport)? {
+        (t, cbor) if t == ur_type => Ok(cbor),
+        (t, _) => Err(format!("Expected {}, got {}", ur_type, t)),
+    }
+}
+
+// ========== FFI Functions ==========
+
+/// The registry object a transport string carries, whatever its type, as
+/// `ur_decoder_resolve` returns it
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_transport_string_resolve(transport: PtrString) -> PtrResponse {
+    let transport = match convert_ptr_string_to_string(transport).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    match decode(&transport) {
+        Ok((ur_type, cbor)) => resolve(&ur_type, cbor),
+        Err(e) => Response::error(e).c_ptr(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tron::tron_sign_request::TronSignRequest;
+    use crate::tron::tron_signature::TronSignature;
+
+    #[test]
+    fn test_base64url() {
+        // RFC 4648 test vectors, unpadded
+        for (data, encoded) in [("", ""), ("f", "Zg"), ("fo", "Zm8"), ("foo", "Zm9v"), ("foob", "Zm9vYg"), ("fooba", "Zm9vYmE"), ("foobar", "Zm9vYmFy")] {
+            assert_eq!(base64url_encode(data.as_bytes()), encoded);
+            assert_eq!(base64url_decode(encoded).unwrap(), data.as_bytes());
+        }
+        assert_eq!(base64url_encode(&[0xfb, 0xff]), "-_8");
+        assert!(base64url_decode("Zh").is_err());
+        assert!(base64url_decode("Zm9v=").is_err());
+        assert!(base64url_decode("Z").is_err());
+
+        let transport = encode("tron-signature", &[0xa1, 0x01, 0x02]);
+        assert_eq!(transport, "tron-signature:oQEC");
+        assert_eq!(decode_as("tron-signature", &transport).unwrap(), vec![0xa1, 0x01, 0x02]);
+        assert!(decode_as("tron-sign-request", &transport).is_err());
+        assert!(decode("oQEC").is_err());
+
+        let request = TronSignRequest::new_with_derived_request_id(
+            vec![0x0a, 0x02], 1, "m/44'/195'/0'/0/0".to_string(), None, None, None,
+        );
+        let transport = request.to_transport_string().unwrap();
+        assert!(transport.starts_with("tron-sign-request:"));
+        assert_eq!(TronSignRequest::from_transport_string(&transport).unwrap().get_request_id(), request.get_request_id());
+        assert!(TronSignature::from_transport_string(&transport).is_err());
+    }
+}
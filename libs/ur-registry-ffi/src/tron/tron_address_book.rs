@@ -0,0 +1,365 @@
+use crate::response::{PtrResponse, Response};
+use crate::tron::address::{decode_address, encode_address};
+use crate::tron::decode::{decode_map, get_bytes, get_text, invalid_value, DecodeError};
+use crate::tron::schema::{FieldSchema, FieldType, TypeSchema};
+use crate::tron::telemetry;
+#[cfg(feature = "protobuf")]
+use crate::tron::transaction::parse_request;
+#[cfg(feature = "protobuf")]
+use crate::tron::tron_sign_request::TronSignRequest;
+use crate::types::{PtrString, PtrVoid};
+use crate::ur_encoder::UREncoder;
+use crate::utils::convert_ptr_string_to_string;
+
+use serde_cbor::Value;
+use std::collections::BTreeMap;
+
+// CBOR map keys for TronAddressBook
+const ENTRIES: i128 = 1;
+// Every key the codec reads and writes, checked against SCHEMA
+#[cfg(test)]
+pub(crate) const KNOWN_KEYS: [i128; 1] = [ENTRIES];
+
+// CBOR map keys for each entry
+const ADDRESS: i128 = 1;
+const LABEL: i128 = 2;
+const NOTE: i128 = 3;
+// Keys of each entry, checked against SCHEMA
+#[cfg(test)]
+pub(crate) const ENTRY_KNOWN_KEYS: [i128; 3] = [ADDRESS, LABEL, NOTE];
+
+// UR Type for contacts exported from a hot wallet
+pub const TRON_ADDRESS_BOOK_TYPE: &str = "tron-address-book";
+
+pub(crate) const SCHEMA: TypeSchema = TypeSchema {
+    ur_type: TRON_ADDRESS_BOOK_TYPE,
+    tag: None,
+    fields: &[FieldSchema::required(ENTRIES, "entries", FieldType::ArrayOfMaps).with_fields(&[
+        FieldSchema::required(ADDRESS, "address", FieldType::Bytes),
+        FieldSchema::required(LABEL, "label", FieldType::Text),
+        FieldSchema::optional(NOTE, "note", FieldType::Text),
+    ])],
+};
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TronAddressBookEntry {
+    // 21-byte address
+    address: Vec<u8>,
+    label: String,
+    note: Option<String>,
+}
+
+impl TronAddressBookEntry {
+    /// `address` is base58
+    pub fn new(address: &str, label: String, note: Option<String>) -> Result<Self, String> {
+        if label.is_empty() {
+            return Err("Contacts need a label".to_string());
+        }
+        Ok(TronAddressBookEntry {
+            address: decode_address(address)?,
+            label,
+            note,
+        })
+    }
+
+    pub fn get_address(&self) -> Result<String, String> {
+        encode_address(&self.address)
+    }
+
+    pub fn get_label(&self) -> &str {
+        &self.label
+    }
+
+    pub fn get_note(&self) -> Option<&String> {
+        self.note.as_ref()
+    }
+
+    /// "Alice (saved contact)", for review screens
+    pub fn get_display_label(&self) -> String {
+        format!("{} (saved contact)", self.label)
+    }
+
+    fn to_cbor(&self) -> Value {
+        let mut map: BTreeMap<Value, Value> = BTreeMap::new();
+        map.insert(Value::Integer(ADDRESS), Value::Bytes(self.address.clone()));
+        map.insert(Value::Integer(LABEL), Value::Text(self.label.clone()));
+        if let Some(ref note) = self.note {
+            map.insert(Value::Integer(NOTE), Value::Text(note.clone()));
+        }
+        Value::Map(map)
+    }
+
+    fn from_cbor(value: &Value) -> Result<Self, DecodeError> {
+        let map = match value {
+            Value::Map(map) => map,
+            _ => return Err(DecodeError::InvalidType { field: "contact", expected: "map" }),
+        };
+        let address = get_bytes(map, ADDRESS, "contact address")?.ok_or(DecodeError::MissingField("contact address"))?;
+        let address = encode_address(&address).map_err(|e| invalid_value("contact address", e))?;
+        let label = get_text(map, LABEL, "contact label")?.ok_or(DecodeError::MissingField("contact label"))?;
+        let note = get_text(map, NOTE, "contact note")?;
+        TronAddressBookEntry::new(&address, label, note).map_err(|e| invalid_value("contact", e))
+    }
+}
+
+/// A user's contacts, sent from the hot wallet so the signer can name known
+/// destinations during review
+#[derive(Clone, Debug, Default)]
+pub struct TronAddressBook {
+    entries: Vec<TronAddressBookEntry>,
+}
+
+impl TronAddressBook {
+    pub fn new() -> Self {
+        TronAddressBook::default()
+    }
+
+    /// Adds a contact, replacing any saved under the same address
+    pub fn add_entry(&mut self, entry: TronAddressBookEntry) {
+        self.entries.retain(|e| e.address != entry.address);
+        self.entries.push(entry);
+    }
+
+    pub fn get_entries(&self) -> &Vec<TronAddressBookEntry> {
+        &self.entries
+    }
+
+    /// Contact saved under the base58 `address`
+    pub fn find(&self, address: &str) -> Option<&TronAddressBookEntry> {
+        let address = decode_address(address).ok()?;
+        self.entries.iter().find(|e| e.address == address)
+    }
+
+    /// Contact label of the first transfer recipient in a transaction
+    /// request, if the recipient is saved
+    #[cfg(feature = "protobuf")]
+    pub fn get_recipient_label(&self, request: &TronSignRequest) -> Result<Option<String>, String> {
+        for contract in parse_request(request)?.contracts.iter() {
+            if let Some(transfer) = contract.get_transfer()? {
+                let entry = self.entries.iter().find(|e| e.address == transfer.to);
+                return Ok(entry.map(TronAddressBookEntry::get_display_label));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Serialize to CBOR bytes
+    pub fn to_bytes(&self) -> Result<Vec<u8>, String> {
+        let mut map: BTreeMap<Value, Value> = BTreeMap::new();
+
+        let entries = self.entries.iter().map(TronAddressBookEntry::to_cbor).collect();
+        map.insert(Value::Integer(ENTRIES), Value::Array(entries));
+
+        serde_cbor::to_vec(&Value::Map(map))
+            .map_err(|e| e.to_string())
+    }
+}
+
+impl TryFrom<Vec<u8>> for TronAddressBook {
+    type Error = DecodeError;
+
+    fn try_from(data: Vec<u8>) -> Result<Self, Self::Error> {
+        let map = decode_map(&data)?;
+
+        let entries = match map.get(&Value::Integer(ENTRIES)) {
+            Some(Value::Array(arr)) => arr
+                .iter()
+                .map(TronAddressBookEntry::from_cbor)
+                .collect::<Result<Vec<TronAddressBookEntry>, DecodeError>>()?,
+            Some(_) => return Err(DecodeError::InvalidType { field: "contacts", expected: "array" }),
+            None => return Err(DecodeError::MissingField("contacts")),
+        };
+        // Two labels for one address would let the payload pick which the
+        // signer shows
+        for (i, entry) in entries.iter().enumerate() {
+            if entries[..i].iter().any(|e| e.address == entry.address) {
+                return Err(invalid_value("contacts", format!("{} is listed twice", hex::encode(&entry.address))));
+            }
+        }
+
+        Ok(TronAddressBook { entries })
+    }
+}
+
+// ========== FFI Functions ==========
+
+pub fn resolve(data: Vec<u8>) -> PtrResponse {
+    match TronAddressBook::try_from(data) {
+        Ok(result) => Response::success_object(Box::into_raw(Box::new(result)) as PtrVoid).c_ptr(),
+        Err(error) => {
+            telemetry::decode_failure(TRON_ADDRESS_BOOK_TYPE, &error);
+            Response::error(error.to_string()).c_ptr()
+        }
+    }
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_address_book_new() -> PtrResponse {
+    Response::success_object(Box::into_raw(Box::new(TronAddressBook::new())) as PtrVoid).c_ptr()
+}
+
+/// An empty `note` is treated as absent
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_address_book_add_entry(
+    tron_address_book: &mut TronAddressBook,
+    address: PtrString,
+    label: PtrString,
+    note: PtrString,
+) -> PtrResponse {
+    let address = match convert_ptr_string_to_string(address).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    let label = match convert_ptr_string_to_string(label).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    let note = match convert_ptr_string_to_string(note).map_err(|e| Response::error(e)) {
+        Ok(v) => Some(v).filter(|s| !s.is_empty()),
+        Err(e) => return e.c_ptr(),
+    };
+    match TronAddressBookEntry::new(address.trim(), label, note) {
+        Ok(entry) => {
+            tron_address_book.add_entry(entry);
+            Response::success_null()
+        }
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_address_book_get_ur_encoder(tron_address_book: &TronAddressBook) -> PtrResponse {
+    match tron_address_book.to_bytes() {
+        Ok(message) => match UREncoder::new(message.as_slice(), 400, TRON_ADDRESS_BOOK_TYPE) {
+            Ok(ur_encoder) => Response::success_object(Box::into_raw(Box::new(ur_encoder)) as PtrVoid),
+            Err(e) => Response::error(e),
+        },
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_address_book_get_entries_len(tron_address_book: &TronAddressBook) -> PtrResponse {
+    Response::success_uint32(tron_address_book.get_entries().len() as u32).c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_address_book_get_entry(tron_address_book: &TronAddressBook, index: u32) -> PtrResponse {
+    match tron_address_book.get_entries().get(index as usize) {
+        Some(entry) => Response::success_object(Box::into_raw(Box::new(entry.clone())) as PtrVoid).c_ptr(),
+        None => Response::error(format!("No contact for index {} was found", index)).c_ptr(),
+    }
+}
+
+/// "Alice (saved contact)" for a saved `address`, `NOT_PRESENT` otherwise
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_address_book_get_display_label(tron_address_book: &TronAddressBook, address: PtrString) -> PtrResponse {
+    let address = match convert_ptr_string_to_string(address).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    tron_address_book
+        .find(address.trim())
+        .map_or(Response::not_present(), |e| Response::success_string(e.get_display_label()))
+        .c_ptr()
+}
+
+/// Contact label of the request's recipient, `NOT_PRESENT` when it isn't
+/// saved or the request transfers nothing
+#[cfg(feature = "protobuf")]
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_request_get_recipient_contact(
+    tron_sign_request: &TronSignRequest,
+    tron_address_book: &TronAddressBook,
+) -> PtrResponse {
+    match tron_address_book.get_recipient_label(tron_sign_request) {
+        Ok(label) => label.map_or(Response::not_present(), Response::success_string),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_address_book_entry_get_address(tron_address_book_entry: &TronAddressBookEntry) -> PtrResponse {
+    match tron_address_book_entry.get_address() {
+        Ok(address) => Response::success_string(address),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_address_book_entry_get_label(tron_address_book_entry: &TronAddressBookEntry) -> PtrResponse {
+    Response::success_string(tron_address_book_entry.get_label().to_string()).c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_address_book_entry_get_note(tron_address_book_entry: &TronAddressBookEntry) -> PtrResponse {
+    tron_address_book_entry
+        .get_note()
+        .map_or(Response::not_present(), |v| Response::success_string(v.clone()))
+        .c_ptr()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALICE: &str = "TR7NHqjeKQxGTCi8q8ZY4pL8otSzgjLj6t";
+    const BOB: &str = "TMVQGm1qAQYVdetCeGRRkTWYYrLXuHK2HC";
+
+    #[test]
+    fn test_address_book_round_trip() {
+        let mut book = TronAddressBook::new();
+        book.add_entry(TronAddressBookEntry::new(ALICE, "Alice".to_string(), Some("Rent".to_string())).unwrap());
+        book.add_entry(TronAddressBookEntry::new(BOB, "Bob".to_string(), None).unwrap());
+        book.add_entry(TronAddressBookEntry::new(BOB, "Bob (new)".to_string(), None).unwrap());
+
+        let decoded = TronAddressBook::try_from(book.to_bytes().unwrap()).unwrap();
+        assert_eq!(decoded.get_entries(), book.get_entries());
+        assert_eq!(decoded.get_entries().len(), 2);
+        assert_eq!(decoded.get_entries()[0].get_address().unwrap(), ALICE);
+        assert_eq!(decoded.get_entries()[0].get_note().unwrap(), "Rent");
+        assert_eq!(decoded.find(ALICE).unwrap().get_display_label(), "Alice (saved contact)");
+        assert_eq!(decoded.find(BOB).unwrap().get_label(), "Bob (new)");
+        assert!(decoded.find("not-an-address").is_none());
+
+        assert!(TronAddressBookEntry::new("not-an-address", "Carol".to_string(), None).is_err());
+        assert!(TronAddressBookEntry::new(ALICE, String::new(), None).is_err());
+    }
+
+    #[test]
+    fn test_address_book_rejects_malformed() {
+        let book = |entries: Vec<Value>| {
+            let mut map = BTreeMap::new();
+            map.insert(Value::Integer(ENTRIES), Value::Array(entries));
+            TronAddressBook::try_from(serde_cbor::to_vec(&Value::Map(map)).unwrap())
+        };
+        let alice = TronAddressBookEntry::new(ALICE, "Alice".to_string(), None).unwrap();
+        let mallory = TronAddressBookEntry::new(ALICE, "Mallory".to_string(), None).unwrap();
+        assert_eq!(book(vec![alice.to_cbor(), mallory.to_cbor()]).unwrap_err().code(), "InvalidValue");
+
+        let mut entry = match alice.to_cbor() {
+            Value::Map(map) => map,
+            _ => unreachable!(),
+        };
+        entry.insert(Value::Integer(NOTE), Value::Integer(1));
+        assert_eq!(
+            book(vec![Value::Map(entry)]).unwrap_err(),
+            DecodeError::InvalidType { field: "contact note", expected: "text" }
+        );
+        assert_eq!(book(vec![Value::Text(ALICE.to_string())]).unwrap_err().code(), "InvalidType");
+    }
+}
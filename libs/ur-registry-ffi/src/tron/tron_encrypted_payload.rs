@@ -0,0 +1,251 @@
+use crate::response::{PtrResponse, Response};
+use crate::tron::decode::decode_value;
+use crate::tron::schema::{FieldSchema, FieldType, TypeSchema};
+use crate::tron::telemetry;
+use crate::types::{PtrString, PtrVoid};
+use crate::ur_encoder::UREncoder;
+use crate::utils::{convert_ptr_string_to_string, parse_ptr_string_to_bytes};
+
+use chacha20poly1305::aead::{Aead, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, KeyInit, Nonce};
+use serde_cbor::Value;
+use std::collections::BTreeMap;
+
+// CBOR map keys for TronEncryptedPayload
+const PAYLOAD_TYPE: i128 = 1;
+const NONCE: i128 = 2;
+const CIPHERTEXT: i128 = 3;
+// Every key the codec reads and writes, checked against SCHEMA
+#[cfg(test)]
+pub(crate) const KNOWN_KEYS: [i128; 3] = [PAYLOAD_TYPE, NONCE, CIPHERTEXT];
+
+// CBOR tag wrapping the envelope map
+const ENVELOPE_TAG: u64 = 51196;
+
+const KEY_LEN: usize = 32;
+const NONCE_LEN: usize = 12;
+
+// UR Type for an encrypted TRON payload
+pub const TRON_ENCRYPTED_PAYLOAD_TYPE: &str = "tron-encrypted-payload";
+
+pub(crate) const SCHEMA: TypeSchema = TypeSchema {
+    ur_type: TRON_ENCRYPTED_PAYLOAD_TYPE,
+    tag: Some(ENVELOPE_TAG),
+    fields: &[
+        FieldSchema::required(PAYLOAD_TYPE, "payload_type", FieldType::Text),
+        FieldSchema::required(NONCE, "nonce", FieldType::Bytes),
+        FieldSchema::required(CIPHERTEXT, "ciphertext", FieldType::Bytes),
+    ],
+};
+
+/// ChaCha20-Poly1305 envelope around the CBOR of another registry item.
+/// The key is agreed out-of-band; the inner UR type is bound as associated data.
+#[derive(Clone, Debug, Default)]
+pub struct TronEncryptedPayload {
+    payload_type: String,
+    nonce: Vec<u8>,
+    ciphertext: Vec<u8>,
+}
+
+impl TronEncryptedPayload {
+    pub fn seal(payload_type: &str, plaintext: &[u8], key: &[u8]) -> Result<Self, String> {
+        let mut nonce = [0u8; NONCE_LEN];
+        getrandom::getrandom(&mut nonce).map_err(|e| e.to_string())?;
+        let ciphertext = cipher(key)?
+            .encrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: plaintext,
+                    aad: payload_type.as_bytes(),
+                },
+            )
+            .map_err(|_| "Failed to encrypt payload".to_string())?;
+        Ok(TronEncryptedPayload {
+            payload_type: payload_type.to_string(),
+            nonce: nonce.to_vec(),
+            ciphertext,
+        })
+    }
+
+    pub fn open(&self, key: &[u8]) -> Result<Vec<u8>, String> {
+        cipher(key)?
+            .decrypt(
+                Nonce::from_slice(&self.nonce),
+                Payload {
+                    msg: &self.ciphertext,
+                    aad: self.payload_type.as_bytes(),
+                },
+            )
+            .map_err(|_| "Failed to decrypt payload: wrong key or tampered data".to_string())
+    }
+
+    pub fn get_payload_type(&self) -> &str {
+        &self.payload_type
+    }
+
+    /// Serialize to CBOR bytes
+    pub fn to_bytes(&self) -> Result<Vec<u8>, String> {
+        let mut map: BTreeMap<Value, Value> = BTreeMap::new();
+        map.insert(Value::Integer(PAYLOAD_TYPE), Value::Text(self.payload_type.clone()));
+        map.insert(Value::Integer(NONCE), Value::Bytes(self.nonce.clone()));
+        map.insert(Value::Integer(CIPHERTEXT), Value::Bytes(self.ciphertext.clone()));
+
+        serde_cbor::to_vec(&Value::Tag(ENVELOPE_TAG, Box::new(Value::Map(map))))
+            .map_err(|e| e.to_string())
+    }
+}
+
+fn cipher(key: &[u8]) -> Result<ChaCha20Poly1305, String> {
+    if key.len() != KEY_LEN {
+        return Err(format!("Encryption key must be {} bytes", KEY_LEN));
+    }
+    Ok(ChaCha20Poly1305::new(Key::from_slice(key)))
+}
+
+impl TryFrom<Vec<u8>> for TronEncryptedPayload {
+    type Error = String;
+
+    fn try_from(data: Vec<u8>) -> Result<Self, Self::Error> {
+        let cbor_value = decode_value(&data)?;
+
+        if let Value::Tag(ENVELOPE_TAG, inner) = cbor_value {
+            if let Value::Map(map) = *inner {
+                let payload_type = map.get(&Value::Integer(PAYLOAD_TYPE))
+                    .and_then(|v| if let Value::Text(s) = v { Some(s.clone()) } else { None })
+                    .ok_or("Missing payload type")?;
+
+                let nonce = map.get(&Value::Integer(NONCE))
+                    .and_then(|v| if let Value::Bytes(b) = v { Some(b.clone()) } else { None })
+                    .filter(|b| b.len() == NONCE_LEN)
+                    .ok_or("Missing or invalid nonce")?;
+
+                let ciphertext = map.get(&Value::Integer(CIPHERTEXT))
+                    .and_then(|v| if let Value::Bytes(b) = v { Some(b.clone()) } else { None })
+                    .ok_or("Missing ciphertext")?;
+
+                return Ok(TronEncryptedPayload {
+                    payload_type,
+                    nonce,
+                    ciphertext,
+                });
+            }
+        }
+        Err("Expected tagged encrypted envelope".to_string())
+    }
+}
+
+// ========== FFI Functions ==========
+
+pub fn resolve(data: Vec<u8>) -> PtrResponse {
+    match TronEncryptedPayload::try_from(data) {
+        Ok(result) => Response::success_object(Box::into_raw(Box::new(result)) as PtrVoid).c_ptr(),
+        Err(error) => {
+            telemetry::decode_failure_message(TRON_ENCRYPTED_PAYLOAD_TYPE, &error);
+            Response::error(error.to_string()).c_ptr()
+        }
+    }
+}
+
+pub fn seal_ur_encoder(payload_type: &str, plaintext: &[u8], key: &[u8]) -> PtrResponse {
+    let message = match TronEncryptedPayload::seal(payload_type, plaintext, key).and_then(|p| p.to_bytes()) {
+        Ok(v) => v,
+        Err(e) => return Response::error(e).c_ptr(),
+    };
+    match UREncoder::new(message.as_slice(), 400, TRON_ENCRYPTED_PAYLOAD_TYPE) {
+        Ok(ur_encoder) => Response::success_object(Box::into_raw(Box::new(ur_encoder)) as PtrVoid).c_ptr(),
+        Err(e) => Response::error(e).c_ptr(),
+    }
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_encrypted_payload_seal(
+    payload_type: PtrString,
+    payload: PtrString,
+    key: PtrString,
+) -> PtrResponse {
+    let payload_type = match convert_ptr_string_to_string(payload_type).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    let payload = match parse_ptr_string_to_bytes(payload).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    let key = match parse_ptr_string_to_bytes(key).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    match TronEncryptedPayload::seal(&payload_type, &payload, &key) {
+        Ok(result) => Response::success_object(Box::into_raw(Box::new(result)) as PtrVoid),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_encrypted_payload_get_ur_encoder(tron_encrypted_payload: &TronEncryptedPayload) -> PtrResponse {
+    match tron_encrypted_payload.to_bytes() {
+        Ok(message) => match UREncoder::new(message.as_slice(), 400, TRON_ENCRYPTED_PAYLOAD_TYPE) {
+            Ok(ur_encoder) => Response::success_object(Box::into_raw(Box::new(ur_encoder)) as PtrVoid),
+            Err(e) => Response::error(e),
+        },
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_encrypted_payload_get_payload_type(tron_encrypted_payload: &TronEncryptedPayload) -> PtrResponse {
+    Response::success_string(tron_encrypted_payload.get_payload_type().to_string()).c_ptr()
+}
+
+/// Decrypts and resolves the inner registry item, as `ur_decoder_resolve` would
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_encrypted_payload_open(
+    tron_encrypted_payload: &TronEncryptedPayload,
+    key: PtrString,
+) -> PtrResponse {
+    let key = match parse_ptr_string_to_bytes(key).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    match tron_encrypted_payload.open(&key) {
+        Ok(plaintext) => crate::ur_decoder::resolve(tron_encrypted_payload.get_payload_type(), plaintext),
+        Err(e) => Response::error(e).c_ptr(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seal_and_open() {
+        let key = [7u8; KEY_LEN];
+        let sealed = TronEncryptedPayload::seal("tron-sign-request", b"payload", &key).unwrap();
+        let decoded = TronEncryptedPayload::try_from(sealed.to_bytes().unwrap()).unwrap();
+        assert_eq!(decoded.get_payload_type(), "tron-sign-request");
+        assert_eq!(decoded.open(&key).unwrap(), b"payload".to_vec());
+        assert!(decoded.open(&[8u8; KEY_LEN]).is_err());
+
+        let mut retyped = decoded.clone();
+        retyped.payload_type = "tron-signature".to_string();
+        assert!(retyped.open(&key).is_err());
+    }
+
+    #[test]
+    fn test_rejects_duplicate_keys() {
+        let sealed = TronEncryptedPayload::seal("tron-sign-request", b"payload", &[7u8; KEY_LEN]).unwrap();
+        // The tag head, then the map; a second ciphertext follows the first
+        let tag_len = serde_cbor::to_vec(&Value::Tag(ENVELOPE_TAG, Box::new(Value::Null))).unwrap().len() - 1;
+        let mut duplicated = sealed.to_bytes().unwrap();
+        duplicated[tag_len] += 1;
+        duplicated.extend(serde_cbor::to_vec(&Value::Integer(CIPHERTEXT)).unwrap());
+        duplicated.extend(serde_cbor::to_vec(&Value::Bytes(vec![0; 32])).unwrap());
+        assert!(TronEncryptedPayload::try_from(duplicated).unwrap_err().starts_with("Duplicate CBOR map key"));
+    }
+}
@@ -0,0 +1,325 @@
+use crate::response::{PtrResponse, Response};
+use crate::tron::decode::decode_map;
+use crate::tron::schema::{FieldSchema, FieldType, TypeSchema};
+use crate::tron::telemetry;
+use crate::types::{PtrString, PtrVoid};
+use crate::ur_encoder::UREncoder;
+use crate::utils::{convert_ptr_string_to_string, parse_ptr_string_to_bytes};
+
+use secp256k1::ecdh::SharedSecret;
+use secp256k1::{PublicKey, SecretKey};
+use serde_cbor::Value;
+use std::collections::BTreeMap;
+
+// CBOR map keys for TronPairing
+const DEVICE_ID: i128 = 1;
+const NAME: i128 = 2;
+const PUBLIC_KEY: i128 = 3;
+const FEATURES: i128 = 4;
+// Every key the codec reads and writes, checked against SCHEMA
+#[cfg(test)]
+pub(crate) const KNOWN_KEYS: [i128; 4] = [DEVICE_ID, NAME, PUBLIC_KEY, FEATURES];
+
+// UR Type for TRON device pairing
+pub const TRON_PAIRING_TYPE: &str = "tron-pairing";
+
+pub(crate) const SCHEMA: TypeSchema = TypeSchema {
+    ur_type: TRON_PAIRING_TYPE,
+    tag: None,
+    fields: &[
+        FieldSchema::required(DEVICE_ID, "device_id", FieldType::Bytes),
+        FieldSchema::optional(NAME, "name", FieldType::Text),
+        FieldSchema::required(PUBLIC_KEY, "public_key", FieldType::Bytes),
+        FieldSchema::optional(FEATURES, "features", FieldType::ArrayOfText),
+    ],
+};
+
+/// Exchanged once when a hot wallet and a signer are first linked.
+/// The public keys feed an ECDH agreement for the MAC/encryption secrets.
+#[derive(Clone, Debug, Default)]
+pub struct TronPairing {
+    device_id: Vec<u8>,
+    name: Option<String>,
+    public_key: Vec<u8>,
+    features: Vec<String>,
+}
+
+impl TronPairing {
+    pub fn new(
+        device_id: Vec<u8>,
+        name: Option<String>,
+        public_key: Vec<u8>,
+        features: Vec<String>,
+    ) -> Result<Self, String> {
+        PublicKey::from_slice(&public_key).map_err(|e| format!("Invalid public key: {}", e))?;
+        Ok(TronPairing {
+            device_id,
+            name,
+            public_key,
+            features,
+        })
+    }
+
+    pub fn get_device_id(&self) -> &Vec<u8> {
+        &self.device_id
+    }
+
+    pub fn get_name(&self) -> Option<&String> {
+        self.name.as_ref()
+    }
+
+    pub fn get_public_key(&self) -> &Vec<u8> {
+        &self.public_key
+    }
+
+    pub fn get_features(&self) -> &Vec<String> {
+        &self.features
+    }
+
+    pub fn supports_feature(&self, feature: &str) -> bool {
+        self.features.iter().any(|f| f == feature)
+    }
+
+    /// 32-byte pairing secret agreed with the peer: SHA256 of the ECDH point
+    pub fn derive_shared_secret(&self, private_key: &[u8]) -> Result<Vec<u8>, String> {
+        let secret_key = SecretKey::from_slice(private_key).map_err(|e| format!("Invalid private key: {}", e))?;
+        let public_key = PublicKey::from_slice(&self.public_key).map_err(|e| format!("Invalid public key: {}", e))?;
+        Ok(SharedSecret::new(&public_key, &secret_key).secret_bytes().to_vec())
+    }
+
+    /// Serialize to CBOR bytes
+    pub fn to_bytes(&self) -> Result<Vec<u8>, String> {
+        let mut map: BTreeMap<Value, Value> = BTreeMap::new();
+
+        map.insert(Value::Integer(DEVICE_ID), Value::Bytes(self.device_id.clone()));
+
+        if let Some(ref name) = self.name {
+            map.insert(Value::Integer(NAME), Value::Text(name.clone()));
+        }
+
+        map.insert(Value::Integer(PUBLIC_KEY), Value::Bytes(self.public_key.clone()));
+
+        if !self.features.is_empty() {
+            let features = self.features.iter().map(|f| Value::Text(f.clone())).collect();
+            map.insert(Value::Integer(FEATURES), Value::Array(features));
+        }
+
+        serde_cbor::to_vec(&Value::Map(map))
+            .map_err(|e| e.to_string())
+    }
+}
+
+impl TryFrom<Vec<u8>> for TronPairing {
+    type Error = String;
+
+    fn try_from(data: Vec<u8>) -> Result<Self, Self::Error> {
+        let map = decode_map(&data)?;
+
+        let device_id = map.get(&Value::Integer(DEVICE_ID))
+            .and_then(|v| if let Value::Bytes(b) = v { Some(b.clone()) } else { None })
+            .ok_or("Missing device id")?;
+
+        let name = map.get(&Value::Integer(NAME))
+            .and_then(|v| if let Value::Text(s) = v { Some(s.clone()) } else { None });
+
+        let public_key = map.get(&Value::Integer(PUBLIC_KEY))
+            .and_then(|v| if let Value::Bytes(b) = v { Some(b.clone()) } else { None })
+            .ok_or("Missing public key")?;
+
+        let features = match map.get(&Value::Integer(FEATURES)) {
+            Some(Value::Array(arr)) => arr
+                .iter()
+                .map(|f| if let Value::Text(s) = f { Ok(s.clone()) } else { Err("Invalid feature".to_string()) })
+                .collect::<Result<Vec<String>, String>>()?,
+            Some(_) => return Err("Invalid features".to_string()),
+            None => vec![],
+        };
+
+        TronPairing::new(device_id, name, public_key, features)
+    }
+}
+
+// ========== FFI Functions ==========
+
+pub fn resolve(data: Vec<u8>) -> PtrResponse {
+    match TronPairing::try_from(data) {
+        Ok(result) => Response::success_object(Box::into_raw(Box::new(result)) as PtrVoid).c_ptr(),
+        Err(error) => {
+            telemetry::decode_failure_message(TRON_PAIRING_TYPE, &error);
+            Response::error(error.to_string()).c_ptr()
+        }
+    }
+}
+
+/// `features` is a JSON array of strings, e.g. `["mac", "encryption"]`
+#[cfg(feature = "json")]
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_pairing_construct(
+    device_id: PtrString,
+    name: PtrString,
+    public_key: PtrString,
+    features: PtrString,
+) -> PtrResponse {
+    let device_id = match parse_ptr_string_to_bytes(device_id).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    let name = match convert_ptr_string_to_string(name).map_err(|e| Response::error(e)) {
+        Ok(v) => Some(v).filter(|s| !s.is_empty()),
+        Err(e) => return e.c_ptr(),
+    };
+    let public_key = match parse_ptr_string_to_bytes(public_key).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    let features = match convert_ptr_string_to_string(features)
+        .and_then(|v| serde_json::from_str::<Vec<String>>(&v).map_err(|e| format!("features is invalid: {}", e)))
+        .map_err(|e| Response::error(e))
+    {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+
+    match TronPairing::new(device_id, name, public_key, features) {
+        Ok(pairing) => Response::success_object(Box::into_raw(Box::new(pairing)) as PtrVoid),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_pairing_get_ur_encoder(tron_pairing: &TronPairing) -> PtrResponse {
+    match tron_pairing.to_bytes() {
+        Ok(message) => match UREncoder::new(message.as_slice(), 400, TRON_PAIRING_TYPE) {
+            Ok(ur_encoder) => Response::success_object(Box::into_raw(Box::new(ur_encoder)) as PtrVoid),
+            Err(e) => Response::error(e),
+        },
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_pairing_get_device_id(tron_pairing: &TronPairing) -> PtrResponse {
+    Response::success_string(hex::encode(tron_pairing.get_device_id())).c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_pairing_get_name(tron_pairing: &TronPairing) -> PtrResponse {
+    tron_pairing
+        .get_name()
+        .map_or(Response::not_present(), |v| Response::success_string(v.clone()))
+        .c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_pairing_get_public_key(tron_pairing: &TronPairing) -> PtrResponse {
+    Response::success_string(hex::encode(tron_pairing.get_public_key())).c_ptr()
+}
+
+#[cfg(feature = "json")]
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_pairing_get_features(tron_pairing: &TronPairing) -> PtrResponse {
+    match serde_json::to_string(tron_pairing.get_features()) {
+        Ok(v) => Response::success_string(v),
+        Err(e) => Response::error(e.to_string()),
+    }
+    .c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_pairing_supports_feature(tron_pairing: &TronPairing, feature: PtrString) -> PtrResponse {
+    match convert_ptr_string_to_string(feature) {
+        Ok(v) => Response::success_boolean(tron_pairing.supports_feature(&v)),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+/// Pairing secret (hex) from the peer's pairing record and our own private key
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_pairing_derive_shared_secret(tron_pairing: &TronPairing, private_key: PtrString) -> PtrResponse {
+    let private_key = match parse_ptr_string_to_bytes(private_key).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    match tron_pairing.derive_shared_secret(&private_key) {
+        Ok(secret) => Response::success_string(hex::encode(secret)),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use secp256k1::Secp256k1;
+
+    fn public_key(secret: &[u8; 32]) -> Vec<u8> {
+        let secret_key = SecretKey::from_slice(secret).unwrap();
+        PublicKey::from_secret_key(&Secp256k1::new(), &secret_key).serialize().to_vec()
+    }
+
+    #[test]
+    fn test_pairing_round_trip() {
+        let pairing = TronPairing::new(
+            vec![1; 16],
+            Some("Keystone".to_string()),
+            public_key(&[1; 32]),
+            vec!["mac".to_string(), "encryption".to_string()],
+        )
+        .unwrap();
+        let decoded = TronPairing::try_from(pairing.to_bytes().unwrap()).unwrap();
+        assert_eq!(decoded.get_device_id(), &vec![1; 16]);
+        assert_eq!(decoded.get_name().unwrap(), "Keystone");
+        assert_eq!(decoded.get_public_key(), &public_key(&[1; 32]));
+        assert!(decoded.supports_feature("encryption"));
+        assert!(!decoded.supports_feature("sessions"));
+
+        let bare = TronPairing::new(vec![2; 16], None, public_key(&[2; 32]), vec![]).unwrap();
+        let decoded = TronPairing::try_from(bare.to_bytes().unwrap()).unwrap();
+        assert_eq!((decoded.get_name(), decoded.get_features().len()), (None, 0));
+
+        // Both sides agree on the secret
+        let secret = pairing.derive_shared_secret(&[2; 32]).unwrap();
+        assert_eq!(secret.len(), 32);
+        assert_eq!(bare.derive_shared_secret(&[1; 32]).unwrap(), secret);
+        assert!(pairing.derive_shared_secret(&[0; 32]).is_err());
+    }
+
+    #[test]
+    fn test_pairing_rejects_malformed() {
+        assert!(TronPairing::new(vec![1; 16], None, vec![5; 33], vec![]).is_err());
+
+        let pairing = TronPairing::new(vec![1; 16], None, public_key(&[1; 32]), vec!["mac".to_string()]).unwrap();
+        let map = match serde_cbor::from_slice(&pairing.to_bytes().unwrap()).unwrap() {
+            Value::Map(map) => map,
+            _ => unreachable!(),
+        };
+        let decode = |edit: &dyn Fn(&mut BTreeMap<Value, Value>)| {
+            let mut map = map.clone();
+            edit(&mut map);
+            TronPairing::try_from(serde_cbor::to_vec(&Value::Map(map)).unwrap())
+        };
+        assert!(decode(&|_| {}).is_ok());
+        assert_eq!(decode(&|m| drop(m.remove(&Value::Integer(DEVICE_ID)))).unwrap_err(), "Missing device id");
+        assert_eq!(decode(&|m| drop(m.remove(&Value::Integer(PUBLIC_KEY)))).unwrap_err(), "Missing public key");
+        assert!(decode(&|m| drop(m.insert(Value::Integer(PUBLIC_KEY), Value::Bytes(vec![0; 33]))))
+            .unwrap_err()
+            .starts_with("Invalid public key"));
+        assert_eq!(decode(&|m| drop(m.insert(Value::Integer(FEATURES), Value::Text("mac".to_string())))).unwrap_err(), "Invalid features");
+        assert_eq!(
+            decode(&|m| drop(m.insert(Value::Integer(FEATURES), Value::Array(vec![Value::Integer(1)])))).unwrap_err(),
+            "Invalid feature"
+        );
+        assert!(TronPairing::try_from(vec![0xff]).is_err());
+    }
+}
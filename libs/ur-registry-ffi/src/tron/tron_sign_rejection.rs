@@ -0,0 +1,288 @@
+use crate::response::{PtrResponse, Response};
+use crate::tron::decode::decode_map;
+use crate::tron::schema::{FieldSchema, FieldType, TypeSchema};
+use crate::tron::telemetry;
+use crate::tron::version::{decode_version, encode_version};
+use crate::types::{PtrString, PtrVoid};
+use crate::ur_encoder::UREncoder;
+use crate::utils::{convert_ptr_string_to_string, parse_ptr_string_to_bytes};
+
+use serde_cbor::Value;
+use std::collections::BTreeMap;
+
+// CBOR map keys for TronSignRejection
+const REQUEST_ID: i128 = 1;
+const REASON: i128 = 2;
+const MESSAGE: i128 = 3;
+const VERSION: i128 = 4;
+// Every key the codec reads and writes, checked against SCHEMA
+#[cfg(test)]
+pub(crate) const KNOWN_KEYS: [i128; 4] = [REQUEST_ID, REASON, MESSAGE, VERSION];
+
+// UR Type for a declined TRON sign request
+pub const TRON_SIGN_REJECTION_TYPE: &str = "tron-sign-rejection";
+
+pub(crate) const SCHEMA: TypeSchema = TypeSchema {
+    ur_type: TRON_SIGN_REJECTION_TYPE,
+    tag: None,
+    fields: &[
+        FieldSchema::required(REQUEST_ID, "request_id", FieldType::Bytes),
+        FieldSchema::required(REASON, "reason", FieldType::Uint),
+        FieldSchema::optional(MESSAGE, "message", FieldType::Text),
+        FieldSchema::optional(VERSION, "version", FieldType::Uint),
+    ],
+};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RejectionReason {
+    UserDeclined = 1,
+    PolicyViolation = 2,
+    UnsupportedRequest = 3,
+    InvalidRequest = 4,
+}
+
+impl RejectionReason {
+    pub fn from_u32(value: u32) -> Result<Self, String> {
+        match value {
+            1 => Ok(RejectionReason::UserDeclined),
+            2 => Ok(RejectionReason::PolicyViolation),
+            3 => Ok(RejectionReason::UnsupportedRequest),
+            4 => Ok(RejectionReason::InvalidRequest),
+            _ => Err(format!("Invalid rejection reason: {}", value)),
+        }
+    }
+
+    pub fn to_u32(self) -> u32 {
+        self as u32
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct TronSignRejection {
+    request_id: Vec<u8>,
+    reason: u32,  // see RejectionReason; unknown codes are kept as-is
+    message: Option<String>,
+    version: Option<u32>,
+}
+
+impl TronSignRejection {
+    pub fn new(request_id: Vec<u8>, reason: RejectionReason, message: Option<String>) -> Self {
+        TronSignRejection {
+            request_id,
+            reason: reason.to_u32(),
+            message,
+            version: None,
+        }
+    }
+
+    pub fn get_request_id(&self) -> &Vec<u8> {
+        &self.request_id
+    }
+
+    pub fn get_reason_code(&self) -> u32 {
+        self.reason
+    }
+
+    /// `None` for codes introduced after this build
+    pub fn get_reason(&self) -> Option<RejectionReason> {
+        RejectionReason::from_u32(self.reason).ok()
+    }
+
+    pub fn get_message(&self) -> Option<&String> {
+        self.message.as_ref()
+    }
+
+    pub fn get_version(&self) -> Option<u32> {
+        self.version
+    }
+
+    pub fn set_version(&mut self, version: Option<u32>) {
+        self.version = version;
+    }
+
+    /// Serialize to CBOR bytes
+    pub fn to_bytes(&self) -> Result<Vec<u8>, String> {
+        let mut map: BTreeMap<Value, Value> = BTreeMap::new();
+
+        map.insert(Value::Integer(REQUEST_ID), Value::Bytes(self.request_id.clone()));
+        map.insert(Value::Integer(REASON), Value::Integer(self.reason as i128));
+
+        if let Some(ref message) = self.message {
+            map.insert(Value::Integer(MESSAGE), Value::Text(message.clone()));
+        }
+
+        if let Some(version) = self.version {
+            map.insert(Value::Integer(VERSION), encode_version(version));
+        }
+
+        serde_cbor::to_vec(&Value::Map(map))
+            .map_err(|e| e.to_string())
+    }
+}
+
+impl TryFrom<Vec<u8>> for TronSignRejection {
+    type Error = String;
+
+    fn try_from(data: Vec<u8>) -> Result<Self, Self::Error> {
+        let map = decode_map(&data)?;
+
+        let version = decode_version(map.get(&Value::Integer(VERSION)))?;
+
+        let request_id = map.get(&Value::Integer(REQUEST_ID))
+            .and_then(|v| if let Value::Bytes(b) = v { Some(b.clone()) } else { None })
+            .ok_or("Missing request id")?;
+
+        let reason = map.get(&Value::Integer(REASON))
+            .and_then(|v| if let Value::Integer(i) = v { u32::try_from(*i).ok() } else { None })
+            .ok_or("Missing rejection reason")?;
+
+        let message = map.get(&Value::Integer(MESSAGE))
+            .and_then(|v| if let Value::Text(s) = v { Some(s.clone()) } else { None });
+
+        Ok(TronSignRejection {
+            request_id,
+            reason,
+            message,
+            version,
+        })
+    }
+}
+
+// ========== FFI Functions ==========
+
+pub fn resolve(data: Vec<u8>) -> PtrResponse {
+    match TronSignRejection::try_from(data) {
+        Ok(result) => Response::success_object(Box::into_raw(Box::new(result)) as PtrVoid).c_ptr(),
+        Err(error) => {
+            telemetry::decode_failure_message(TRON_SIGN_REJECTION_TYPE, &error);
+            Response::error(error.to_string()).c_ptr()
+        }
+    }
+}
+
+/// `message` may be empty
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_rejection_construct(request_id: PtrString, reason: u32, message: PtrString) -> PtrResponse {
+    let request_id = match parse_ptr_string_to_bytes(request_id).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    let reason = match RejectionReason::from_u32(reason).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    let message = match convert_ptr_string_to_string(message).map_err(|e| Response::error(e)) {
+        Ok(v) => Some(v).filter(|s| !s.is_empty()),
+        Err(e) => return e.c_ptr(),
+    };
+    let rejection = TronSignRejection::new(request_id, reason, message);
+    Response::success_object(Box::into_raw(Box::new(rejection)) as PtrVoid).c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_rejection_get_ur_encoder(tron_sign_rejection: &TronSignRejection) -> PtrResponse {
+    match tron_sign_rejection.to_bytes() {
+        Ok(message) => match UREncoder::new(message.as_slice(), 400, TRON_SIGN_REJECTION_TYPE) {
+            Ok(ur_encoder) => Response::success_object(Box::into_raw(Box::new(ur_encoder)) as PtrVoid),
+            Err(e) => Response::error(e),
+        },
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_rejection_get_request_id(tron_sign_rejection: &TronSignRejection) -> PtrResponse {
+    Response::success_string(hex::encode(tron_sign_rejection.get_request_id())).c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_rejection_get_reason(tron_sign_rejection: &TronSignRejection) -> PtrResponse {
+    Response::success_uint32(tron_sign_rejection.get_reason_code()).c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_rejection_is_known_reason(tron_sign_rejection: &TronSignRejection) -> PtrResponse {
+    Response::success_boolean(tron_sign_rejection.get_reason().is_some()).c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_rejection_get_message(tron_sign_rejection: &TronSignRejection) -> PtrResponse {
+    tron_sign_rejection
+        .get_message()
+        .map_or(Response::not_present(), |v| Response::success_string(v.clone()))
+        .c_ptr()
+}
+
+/// `version` of 0 clears the field
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_rejection_set_version(tron_sign_rejection: &mut TronSignRejection, version: u32) -> PtrResponse {
+    tron_sign_rejection.set_version(Some(version).filter(|v| *v != 0));
+    Response::success_null().c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_rejection_get_version(tron_sign_rejection: &TronSignRejection) -> PtrResponse {
+    tron_sign_rejection
+        .get_version()
+        .map_or(Response::not_present(), Response::success_uint32)
+        .c_ptr()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tron::version::PROTOCOL_VERSION;
+
+    #[test]
+    fn test_rejection_round_trip() {
+        let mut rejection =
+            TronSignRejection::new(vec![7; 16], RejectionReason::PolicyViolation, Some("Daily limit".to_string()));
+        rejection.set_version(Some(PROTOCOL_VERSION));
+        let decoded = TronSignRejection::try_from(rejection.to_bytes().unwrap()).unwrap();
+        assert_eq!(decoded.get_request_id(), &vec![7; 16]);
+        assert_eq!(decoded.get_reason(), Some(RejectionReason::PolicyViolation));
+        assert_eq!(decoded.get_message().map(String::as_str), Some("Daily limit"));
+        assert_eq!(decoded.get_version(), Some(PROTOCOL_VERSION));
+
+        let bare = TronSignRejection::new(vec![1; 16], RejectionReason::UserDeclined, None);
+        let decoded = TronSignRejection::try_from(bare.to_bytes().unwrap()).unwrap();
+        assert_eq!(decoded.get_message(), None);
+        assert_eq!(decoded.get_version(), None);
+    }
+
+    #[test]
+    fn test_rejection_keeps_unknown_reasons() {
+        let mut map = BTreeMap::new();
+        map.insert(Value::Integer(REQUEST_ID), Value::Bytes(vec![1; 16]));
+        map.insert(Value::Integer(REASON), Value::Integer(9));
+        let decoded = TronSignRejection::try_from(serde_cbor::to_vec(&Value::Map(map)).unwrap()).unwrap();
+        assert_eq!(decoded.get_reason_code(), 9);
+        assert_eq!(decoded.get_reason(), None);
+        assert_eq!(TronSignRejection::try_from(decoded.to_bytes().unwrap()).unwrap().get_reason_code(), 9);
+        assert!(RejectionReason::from_u32(9).is_err());
+    }
+
+    #[test]
+    fn test_rejection_rejects_malformed() {
+        let encode = |entries: Vec<(i128, Value)>| {
+            let map = entries.into_iter().map(|(k, v)| (Value::Integer(k), v)).collect();
+            serde_cbor::to_vec(&Value::Map(map)).unwrap()
+        };
+        let missing_id = encode(vec![(REASON, Value::Integer(1))]);
+        assert_eq!(TronSignRejection::try_from(missing_id).unwrap_err(), "Missing request id");
+        let missing_reason = encode(vec![(REQUEST_ID, Value::Bytes(vec![1; 16]))]);
+        assert_eq!(TronSignRejection::try_from(missing_reason).unwrap_err(), "Missing rejection reason");
+        let negative_reason = encode(vec![(REQUEST_ID, Value::Bytes(vec![1; 16])), (REASON, Value::Integer(-1))]);
+        assert!(TronSignRejection::try_from(negative_reason).is_err());
+        assert!(TronSignRejection::try_from(serde_cbor::to_vec(&Value::Array(vec![])).unwrap()).is_err());
+    }
+}
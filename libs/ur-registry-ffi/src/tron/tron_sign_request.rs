@@ -1,22 +1,93 @@
 use crate::response::{PtrResponse, Response};
+use crate::tron::address::decode_address;
+use crate::tron::tron_encrypted_payload::seal_ur_encoder;
+use crate::tron::decode::{decode_map, get_bytes, get_text, get_u32, invalid_value, unknown_entries, DecodeError};
+use crate::hex_codec;
+use crate::tron::keypath::{normalize_path, KeyPath};
+use crate::tron::origin::{self, Origin};
+use crate::tron::schema::{FieldSchema, FieldType, TypeSchema};
+use crate::tron::telemetry;
+#[cfg(feature = "protobuf")]
+use crate::tron::transaction::parse_request;
+use crate::tron::transport;
+#[cfg(feature = "json")]
+use crate::tron::typed_data::check_request as check_typed_data;
+use crate::tron::version::{decode_version, encode_version};
 use crate::types::{PtrString, PtrVoid};
 use crate::utils::{convert_ptr_string_to_string, parse_ptr_string_to_bytes};
+use crate::ur_encoder::UREncoder;
 
-use serde::{Deserialize, Serialize};
 use serde_cbor::Value;
+use flate2::read::{DeflateDecoder, DeflateEncoder};
+use flate2::Compression;
+use hmac::{Hmac, Mac};
+#[cfg(feature = "json")]
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::sync::OnceLock;
 use std::collections::BTreeMap;
+use std::io::Read;
+use std::os::raw::c_char;
+use ur::bytewords::{self, Style};
+use uuid::Uuid;
 
 // CBOR map keys for TronSignRequest
-const REQUEST_ID: i128 = 1;
-const SIGN_DATA: i128 = 2;
-const DATA_TYPE: i128 = 3;
-const DERIVATION_PATH: i128 = 4;
-const ADDRESS: i128 = 5;
-const ORIGIN: i128 = 6;
+pub(crate) const REQUEST_ID: i128 = 1;
+pub(crate) const SIGN_DATA: i128 = 2;
+pub(crate) const DATA_TYPE: i128 = 3;
+pub(crate) const DERIVATION_PATH: i128 = 4;
+pub(crate) const ADDRESS: i128 = 5;
+pub(crate) const ORIGIN: i128 = 6;
+pub(crate) const METADATA: i128 = 7;
+pub(crate) const MAC: i128 = 8;
+pub(crate) const VERSION: i128 = 9;
+pub(crate) const WALLET_ID: i128 = 10;
+pub(crate) const BLOCK_HEADER: i128 = 11;
+pub(crate) const FEE_PAYER: i128 = 12;
+pub(crate) const SALT: i128 = 13;
+pub(crate) const KNOWN_KEYS: [i128; 13] = [
+    REQUEST_ID, SIGN_DATA, DATA_TYPE, DERIVATION_PATH, ADDRESS, ORIGIN, METADATA, MAC, VERSION, WALLET_ID,
+    BLOCK_HEADER, FEE_PAYER, SALT,
+];
+
+// Random bytes `generate_salt` draws
+pub const SALT_LEN: usize = 16;
+
+// CBOR tag marking DEFLATE-compressed sign_data
+pub(crate) const DEFLATE_TAG: u64 = 51195;
+// Upper bound for inflated sign_data, guards against decompression bombs
+const MAX_INFLATED_LEN: u64 = 16 * 1024 * 1024;
+
+// Digest bytes, and so words, `fingerprint` shows: 64 bits, out of reach
+// of an attacker tweaking a request offline until its words match
+pub const FINGERPRINT_LEN: usize = 8;
 
 // UR Type for TRON sign request
 pub const TRON_SIGN_REQUEST_TYPE: &str = "tron-sign-request";
 
+pub(crate) const SCHEMA: TypeSchema = TypeSchema {
+    ur_type: TRON_SIGN_REQUEST_TYPE,
+    tag: None,
+    fields: &[
+        FieldSchema::optional(REQUEST_ID, "request_id", FieldType::Bytes),
+        FieldSchema::required(SIGN_DATA, "sign_data", FieldType::Bytes).tagged(DEFLATE_TAG),
+        FieldSchema::optional(DATA_TYPE, "data_type", FieldType::Uint),
+        FieldSchema::optional(DERIVATION_PATH, "derivation_path", FieldType::KeyPath),
+        FieldSchema::optional(ADDRESS, "address", FieldType::Text),
+        FieldSchema::optional(ORIGIN, "origin", FieldType::TextOrMap).with_fields(origin::FIELDS),
+        FieldSchema::optional(METADATA, "metadata", FieldType::Metadata),
+        FieldSchema::optional(MAC, "mac", FieldType::Bytes),
+        FieldSchema::optional(VERSION, "version", FieldType::Uint),
+        FieldSchema::optional(WALLET_ID, "wallet_id", FieldType::Bytes),
+        FieldSchema::optional(BLOCK_HEADER, "block_header", FieldType::Bytes),
+        FieldSchema::optional(FEE_PAYER, "fee_payer", FieldType::Text),
+        FieldSchema::optional(SALT, "salt", FieldType::Bytes),
+    ],
+};
+
+// UUID v5 namespace for request ids derived from request content
+const REQUEST_ID_NAMESPACE: Uuid = Uuid::from_u128(0x5f1b_7c2e_3d4a_4e8b_9a61_2c0d_e7f3_b495);
+
 #[derive(Clone, Debug)]
 pub enum DataType {
     Transaction = 1,
@@ -43,15 +114,47 @@ impl DataType {
     }
 }
 
+/// Vendor metadata value, kept as-is across decode/encode
+#[derive(Clone, Debug, PartialEq)]
+pub enum MetadataValue {
+    Text(String),
+    Bytes(Vec<u8>),
+}
+
 #[derive(Clone, Debug, Default)]
 pub struct TronSignRequest {
+    // Unused while `id_derived`
     request_id: Option<Vec<u8>>,
+    // Set by `new_with_derived_request_id`: the id follows the content and
+    // is derived into `derived_id` on first read, again after appends
+    id_derived: bool,
+    derived_id: OnceLock<Vec<u8>>,
     sign_data: Vec<u8>,
     data_type: u32,  // 1=Transaction, 2=Message, 3=TypedData
     derivation_path: String,
     xfp: Option<u32>,
     address: Option<String>,
-    origin: Option<String>,
+    origin: Option<Origin>,
+    metadata: BTreeMap<String, MetadataValue>,
+    compress: bool,
+    mac: Option<Vec<u8>>,
+    version: Option<u32>,
+    // Picks the seed on devices holding several (e.g. passphrase wallets)
+    wallet_id: Option<Vec<u8>>,
+    // Serialized `BlockHeader.raw` of the block the transaction references
+    block_header: Option<Vec<u8>>,
+    // Base58 account that pays the fees and broadcasts, when it isn't the
+    // owner (fee delegation, exchange hot wallets)
+    fee_payer: Option<String>,
+    // Random per-instance value the signature must echo and prove
+    salt: Option<Vec<u8>>,
+    // Top-level keys of the decoded payload, unknown ones included; reset by
+    // every setter
+    present_keys: Option<Vec<Value>>,
+    // Entries with keys this build doesn't know, re-emitted by `to_bytes`
+    unknown: BTreeMap<Value, Value>,
+    // Output of `to_bytes`, reset by every setter
+    encoded: OnceLock<Vec<u8>>,
 }
 
 impl TronSignRequest {
@@ -64,6 +167,8 @@ impl TronSignRequest {
         address: Option<String>,
         origin: Option<String>,
     ) -> Self {
+        // Unparseable paths are kept as-is and rejected when encoding
+        let derivation_path = normalize_path(&derivation_path).unwrap_or(derivation_path);
         TronSignRequest {
             request_id,
             sign_data,
@@ -71,18 +176,67 @@ impl TronSignRequest {
             derivation_path,
             xfp,
             address,
-            origin,
+            origin: origin.map(Origin::from_text),
+            ..Default::default()
+        }
+    }
+
+    /// Same as `new`, but with the request id derived from (sign_data, path, data_type)
+    pub fn new_with_derived_request_id(
+        sign_data: Vec<u8>,
+        data_type: u32,
+        derivation_path: String,
+        xfp: Option<u32>,
+        address: Option<String>,
+        origin: Option<String>,
+    ) -> Self {
+        TronSignRequest {
+            id_derived: true,
+            ..TronSignRequest::new(None, sign_data, data_type, derivation_path, xfp, address, origin)
         }
     }
 
+    // Every setter calls this: the cached encoding and the decoded key
+    // list no longer describe the request, and the MAC no longer covers it
+    fn invalidate(&mut self) {
+        self.encoded.take();
+        self.present_keys = None;
+        self.mac = None;
+    }
+
     pub fn get_request_id(&self) -> Option<&Vec<u8>> {
+        if self.id_derived {
+            return Some(self.derived_id.get_or_init(|| derive_request_id(&self.sign_data, &self.derivation_path, self.data_type)));
+        }
         self.request_id.as_ref()
     }
 
+    /// Whether the request id matches the one derived from this request's content
+    pub fn is_request_id_derived(&self) -> bool {
+        self.id_derived
+            || self.request_id.as_ref().is_some_and(|id| {
+                *id == derive_request_id(&self.sign_data, &self.derivation_path, self.data_type)
+            })
+    }
+
     pub fn get_sign_data(&self) -> &Vec<u8> {
         &self.sign_data
     }
 
+    /// Extends sign_data by `chunk`, for payloads fed in pieces. The id of
+    /// a request made with `new_with_derived_request_id` follows the
+    /// content, derived once when next read; any other is left as is.
+    /// Fails past the size signers accept.
+    pub fn append_sign_data(&mut self, chunk: &[u8]) -> Result<(), String> {
+        if (self.sign_data.len() + chunk.len()) as u64 > MAX_INFLATED_LEN {
+            return Err(format!("sign_data would exceed {} bytes", MAX_INFLATED_LEN));
+        }
+        self.sign_data.extend_from_slice(chunk);
+        self.derived_id.take();
+        self.invalidate();
+        Ok(())
+    }
+
     pub fn get_data_type(&self) -> u32 {
         self.data_type
     }
@@ -91,23 +245,252 @@ impl TronSignRequest {
         &self.derivation_path
     }
 
+    pub fn get_xfp(&self) -> Option<u32> {
+        self.xfp
+    }
+
     pub fn get_address(&self) -> Option<&String> {
         self.address.as_ref()
     }
 
-    pub fn get_origin(&self) -> Option<&String> {
+    pub fn get_origin(&self) -> Option<&Origin> {
         self.origin.as_ref()
     }
 
+    /// Replaces the origin; one with nothing set clears it
+    pub fn set_origin(&mut self, origin: Option<Origin>) {
+        self.origin = origin.filter(|o| !o.is_empty());
+        self.invalidate();
+    }
+
+    pub fn get_metadata(&self) -> &BTreeMap<String, MetadataValue> {
+        &self.metadata
+    }
+
+    pub fn set_metadata(&mut self, key: String, value: MetadataValue) {
+        self.metadata.insert(key, value);
+        self.invalidate();
+    }
+
+    pub fn remove_metadata(&mut self, key: &str) -> Option<MetadataValue> {
+        let removed = self.metadata.remove(key);
+        if removed.is_some() {
+            self.invalidate();
+        }
+        removed
+    }
+
+    pub fn is_compress(&self) -> bool {
+        self.compress
+    }
+
+    /// DEFLATE sign_data when encoding; skipped if it would not shrink the payload
+    pub fn set_compress(&mut self, compress: bool) {
+        self.compress = compress;
+        self.invalidate();
+    }
+
+    pub fn get_version(&self) -> Option<u32> {
+        self.version
+    }
+
+    pub fn set_version(&mut self, version: Option<u32>) {
+        self.version = version;
+        self.invalidate();
+    }
+
+    pub fn get_wallet_id(&self) -> Option<&Vec<u8>> {
+        self.wallet_id.as_ref()
+    }
+
+    pub fn set_wallet_id(&mut self, wallet_id: Option<Vec<u8>>) {
+        self.wallet_id = wallet_id;
+        self.invalidate();
+    }
+
+    pub fn get_block_header(&self) -> Option<&Vec<u8>> {
+        self.block_header.as_ref()
+    }
+
+    pub fn set_block_header(&mut self, block_header: Option<Vec<u8>>) {
+        self.block_header = block_header;
+        self.invalidate();
+    }
+
+    pub fn get_fee_payer(&self) -> Option<&String> {
+        self.fee_payer.as_ref()
+    }
+
+    /// `fee_payer` is a base58 address
+    pub fn set_fee_payer(&mut self, fee_payer: Option<String>) -> Result<(), String> {
+        if let Some(ref fee_payer) = fee_payer {
+            decode_address(fee_payer)?;
+        }
+        self.fee_payer = fee_payer;
+        self.invalidate();
+        Ok(())
+    }
+
+    pub fn get_salt(&self) -> Option<&Vec<u8>> {
+        self.salt.as_ref()
+    }
+
+    pub fn set_salt(&mut self, salt: Option<Vec<u8>>) {
+        self.salt = salt;
+        self.invalidate();
+    }
+
+    /// Sets a fresh random salt of `SALT_LEN` bytes and returns it
+    pub fn generate_salt(&mut self) -> Result<Vec<u8>, String> {
+        let mut salt = vec![0u8; SALT_LEN];
+        getrandom::getrandom(&mut salt).map_err(|e| e.to_string())?;
+        self.set_salt(Some(salt.clone()));
+        Ok(salt)
+    }
+
+    /// Whether another account than the transaction's owner submits it.
+    /// Fails when a fee payer is set on a request that isn't a decodable
+    /// transaction, since the owner can't be told.
+    #[cfg(feature = "protobuf")]
+    pub fn is_fee_delegated(&self) -> Result<bool, String> {
+        let fee_payer = match self.fee_payer {
+            Some(ref fee_payer) => decode_address(fee_payer)?,
+            None => return Ok(false),
+        };
+        let transaction = parse_request(self)?;
+        if transaction.contracts.is_empty() {
+            return Err("Transaction has no contract".to_string());
+        }
+        for contract in &transaction.contracts {
+            if contract.get_owner_address()?.as_ref() != Some(&fee_payer) {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Keys present in the decoded payload, or that `to_bytes` would emit
+    /// for a request built locally or edited since
+    pub fn get_present_keys(&self) -> Result<Vec<Value>, String> {
+        match self.present_keys {
+            Some(ref keys) => Ok(keys.clone()),
+            None => {
+                let mut keys: Vec<Value> = self.to_cbor_map(self.compress)?.into_keys().collect();
+                if self.mac.is_some() {
+                    keys.push(Value::Integer(MAC));
+                }
+                Ok(keys)
+            }
+        }
+    }
+
+    pub fn get_unknown_fields(&self) -> &BTreeMap<Value, Value> {
+        &self.unknown
+    }
+
+    pub fn has_field(&self, key: i128) -> Result<bool, String> {
+        Ok(self.get_present_keys()?.contains(&Value::Integer(key)))
+    }
+
+    pub fn get_mac(&self) -> Option<&Vec<u8>> {
+        self.mac.as_ref()
+    }
+
+    /// Authenticate the request with the pairing secret shared with the
+    /// signer. Call it last: every other setter drops the MAC.
+    pub fn set_mac(&mut self, secret: &[u8]) -> Result<(), String> {
+        let mac = self.compute_mac(secret)?;
+        self.invalidate();
+        self.mac = Some(mac);
+        Ok(())
+    }
+
+    /// False when the MAC is missing or was not produced with this secret
+    pub fn verify_mac(&self, secret: &[u8]) -> Result<bool, String> {
+        match self.mac {
+            Some(ref mac) => Ok(self.mac_state(secret)?.verify_slice(mac).is_ok()),
+            None => Ok(false),
+        }
+    }
+
+    fn compute_mac(&self, secret: &[u8]) -> Result<Vec<u8>, String> {
+        Ok(self.mac_state(secret)?.finalize().into_bytes().to_vec())
+    }
+
+    /// MAC key omitted and sign_data uncompressed, so it does not depend on
+    /// the sender's DEFLATE output
+    fn canonical_body(&self) -> Result<Vec<u8>, String> {
+        serde_cbor::to_vec(&Value::Map(self.to_cbor_map(false)?)).map_err(|e| e.to_string())
+    }
+
+    /// HMAC-SHA256 over the canonical body
+    fn mac_state(&self, secret: &[u8]) -> Result<Hmac<Sha256>, String> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret).map_err(|e| e.to_string())?;
+        mac.update(&self.canonical_body()?);
+        Ok(mac)
+    }
+
+    /// Bytewords of the first `FINGERPRINT_LEN` bytes of the canonical
+    /// body's SHA-256, e.g. "able acid also apex cash draw echo fact", for
+    /// the hot and cold screens to show side by side so a swapped QR stands
+    /// out
+    pub fn fingerprint(&self) -> Result<String, String> {
+        let digest = Sha256::digest(self.canonical_body()?);
+        // `encode` appends four checksum words, only the digest's are kept
+        let words = bytewords::encode(&digest[..FINGERPRINT_LEN], &Style::Standard);
+        Ok(words.split(' ').take(FINGERPRINT_LEN).collect::<Vec<_>>().join(" "))
+    }
+
     /// Serialize to CBOR bytes
     pub fn to_bytes(&self) -> Result<Vec<u8>, String> {
+        self.encoded_bytes().map(|bytes| bytes.to_vec())
+    }
+
+    /// `tron-sign-request:<base64url(cbor)>`, for same-device handoffs
+    pub fn to_transport_string(&self) -> Result<String, String> {
+        Ok(transport::encode(TRON_SIGN_REQUEST_TYPE, qr_payload(self)?))
+    }
+
+    /// QR parts of the request; the encoder is an `Iterator` of frames
+    pub fn to_ur_encoder(&self, max_fragment_length: usize) -> Result<UREncoder, String> {
+        UREncoder::new(qr_payload(self)?, max_fragment_length, TRON_SIGN_REQUEST_TYPE)
+    }
+
+    pub fn from_transport_string(transport: &str) -> Result<Self, String> {
+        let cbor = transport::decode_as(TRON_SIGN_REQUEST_TYPE, transport)?;
+        TronSignRequest::try_from(cbor).map_err(|e| e.to_string())
+    }
+
+    /// Same as `to_bytes`, without copying; encoded once until a setter runs
+    pub fn encoded_bytes(&self) -> Result<&[u8], String> {
+        if let Some(bytes) = self.encoded.get() {
+            return Ok(bytes);
+        }
+
+        let mut map = self.to_cbor_map(self.compress)?;
+
+        if let Some(ref mac) = self.mac {
+            map.insert(Value::Integer(MAC), Value::Bytes(mac.clone()));
+        }
+
+        let bytes = serde_cbor::to_vec(&Value::Map(map))
+            .map_err(|e| e.to_string())?;
+        Ok(self.encoded.get_or_init(|| bytes))
+    }
+
+    fn to_cbor_map(&self, compress: bool) -> Result<BTreeMap<Value, Value>, String> {
         let mut map: BTreeMap<Value, Value> = BTreeMap::new();
 
-        if let Some(ref id) = self.request_id {
+        if let Some(id) = self.get_request_id() {
             map.insert(Value::Integer(REQUEST_ID), Value::Bytes(id.clone()));
         }
 
-        map.insert(Value::Integer(SIGN_DATA), Value::Bytes(self.sign_data.clone()));
+        let sign_data = if compress {
+            compress_sign_data(&self.sign_data)?
+        } else {
+            Value::Bytes(self.sign_data.clone())
+        };
+        map.insert(Value::Integer(SIGN_DATA), sign_data);
         map.insert(Value::Integer(DATA_TYPE), Value::Integer(self.data_type as i128));
 
         // Encode derivation path as CBOR
@@ -119,111 +502,188 @@ impl TronSignRequest {
         }
 
         if let Some(ref origin) = self.origin {
-            map.insert(Value::Integer(ORIGIN), Value::Text(origin.clone()));
+            map.insert(Value::Integer(ORIGIN), origin.to_cbor());
         }
 
-        serde_cbor::to_vec(&Value::Map(map))
-            .map_err(|e| e.to_string())
-    }
-}
+        if !self.metadata.is_empty() {
+            let metadata = self
+                .metadata
+                .iter()
+                .map(|(k, v)| {
+                    let value = match v {
+                        MetadataValue::Text(t) => Value::Text(t.clone()),
+                        MetadataValue::Bytes(b) => Value::Bytes(b.clone()),
+                    };
+                    (Value::Text(k.clone()), value)
+                })
+                .collect();
+            map.insert(Value::Integer(METADATA), Value::Map(metadata));
+        }
 
-/// Encode BIP44 derivation path to CBOR
-fn encode_derivation_path(path: &str, xfp: Option<u32>) -> Result<Value, String> {
-    // Parse path like "m/44'/195'/0'/0/0"
-    let parts: Vec<&str> = path.trim_start_matches("m/").split('/').collect();
-    let mut components: Vec<Value> = Vec::new();
+        if let Some(version) = self.version {
+            map.insert(Value::Integer(VERSION), encode_version(version));
+        }
 
-    for part in parts {
-        let hardened = part.ends_with('\'');
-        let index: u32 = part.trim_end_matches('\'').parse()
-            .map_err(|_| format!("Invalid path component: {}", part))?;
-        
-        // CBOR array: [index, hardened]
-        components.push(Value::Array(vec![
-            Value::Integer(index as i128),
-            Value::Bool(hardened),
-        ]));
-    }
+        if let Some(ref wallet_id) = self.wallet_id {
+            map.insert(Value::Integer(WALLET_ID), Value::Bytes(wallet_id.clone()));
+        }
+
+        if let Some(ref block_header) = self.block_header {
+            map.insert(Value::Integer(BLOCK_HEADER), Value::Bytes(block_header.clone()));
+        }
 
-    let mut map: BTreeMap<Value, Value> = BTreeMap::new();
-    map.insert(Value::Integer(1), Value::Array(components));
-    
-    if let Some(fingerprint) = xfp {
-        map.insert(Value::Integer(2), Value::Integer(fingerprint as i128));
+        if let Some(ref fee_payer) = self.fee_payer {
+            map.insert(Value::Integer(FEE_PAYER), Value::Text(fee_payer.clone()));
+        }
+
+        if let Some(ref salt) = self.salt {
+            map.insert(Value::Integer(SALT), Value::Bytes(salt.clone()));
+        }
+
+        for (key, value) in self.unknown.iter() {
+            map.entry(key.clone()).or_insert_with(|| value.clone());
+        }
+
+        Ok(map)
     }
+}
 
-    Ok(Value::Map(map))
+/// Deterministic request id: a UUID v5 over data_type, path and sign_data,
+/// so the same logical request always maps to the same id
+pub fn derive_request_id(sign_data: &[u8], derivation_path: &str, data_type: u32) -> Vec<u8> {
+    let mut name = Vec::with_capacity(8 + derivation_path.len() + sign_data.len());
+    name.extend_from_slice(&data_type.to_be_bytes());
+    name.extend_from_slice(&(derivation_path.len() as u32).to_be_bytes());
+    name.extend_from_slice(derivation_path.as_bytes());
+    name.extend_from_slice(sign_data);
+    Uuid::new_v5(&REQUEST_ID_NAMESPACE, &name).as_bytes().to_vec()
+}
+
+/// Encode BIP44 derivation path to CBOR
+pub(crate) fn encode_derivation_path(path: &str, xfp: Option<u32>) -> Result<Value, String> {
+    let mut path = KeyPath::parse(path)?;
+    path.set_source_fingerprint(xfp);
+    Ok(path.to_cbor())
 }
 
 impl TryFrom<Vec<u8>> for TronSignRequest {
-    type Error = String;
+    type Error = DecodeError;
 
     fn try_from(data: Vec<u8>) -> Result<Self, Self::Error> {
-        let cbor_value: Value = serde_cbor::from_slice(&data)
-            .map_err(|e| format!("Failed to decode CBOR: {}", e))?;
-
-        if let Value::Map(map) = cbor_value {
-            let request_id = map.get(&Value::Integer(REQUEST_ID))
-                .and_then(|v| if let Value::Bytes(b) = v { Some(b.clone()) } else { None });
-
-            let sign_data = map.get(&Value::Integer(SIGN_DATA))
-                .and_then(|v| if let Value::Bytes(b) = v { Some(b.clone()) } else { None })
-                .ok_or("Missing sign_data")?;
-
-            let data_type = map.get(&Value::Integer(DATA_TYPE))
-                .and_then(|v| if let Value::Integer(i) = v { Some(*i as u32) } else { None })
-                .unwrap_or(1);
-
-            let derivation_path = decode_derivation_path(
-                map.get(&Value::Integer(DERIVATION_PATH))
-            )?;
-
-            let address = map.get(&Value::Integer(ADDRESS))
-                .and_then(|v| if let Value::Text(s) = v { Some(s.clone()) } else { None });
-
-            let origin = map.get(&Value::Integer(ORIGIN))
-                .and_then(|v| if let Value::Text(s) = v { Some(s.clone()) } else { None });
-
-            Ok(TronSignRequest {
-                request_id,
-                sign_data,
-                data_type,
-                derivation_path,
-                xfp: None,  // Will be decoded from path if present
-                address,
-                origin,
-            })
-        } else {
-            Err("Expected CBOR map".to_string())
+        let map = decode_map(&data)?;
+
+        let version = decode_version(map.get(&Value::Integer(VERSION)))?;
+        let present_keys = map.keys().cloned().collect();
+
+        let request_id = get_bytes(&map, REQUEST_ID, "request id")?;
+
+        let (sign_data, compress) = match map.get(&Value::Integer(SIGN_DATA)) {
+            Some(Value::Bytes(b)) => (b.clone(), false),
+            Some(Value::Tag(DEFLATE_TAG, inner)) => match inner.as_ref() {
+                Value::Bytes(b) => (inflate_sign_data(b)?, true),
+                _ => return Err(DecodeError::InvalidType { field: "sign_data", expected: "bytes" }),
+            },
+            Some(_) => return Err(DecodeError::InvalidType { field: "sign_data", expected: "bytes" }),
+            None => return Err(DecodeError::MissingField("sign_data")),
+        };
+
+        let data_type = get_u32(&map, DATA_TYPE, "data type")?.unwrap_or(1);
+
+        let (derivation_path, xfp) = match map.get(&Value::Integer(DERIVATION_PATH)) {
+            Some(path) => {
+                let path = KeyPath::from_cbor(path)?;
+                (path.to_string(), path.get_source_fingerprint())
+            }
+            None => (decode_derivation_path(None)?, None),
+        };
+
+        let address = get_text(&map, ADDRESS, "address")?;
+
+        let origin = map.get(&Value::Integer(ORIGIN)).map(Origin::from_cbor).transpose()?;
+
+        let metadata = decode_metadata(map.get(&Value::Integer(METADATA)))?;
+
+        let mac = get_bytes(&map, MAC, "mac")?;
+
+        let wallet_id = get_bytes(&map, WALLET_ID, "wallet id")?;
+
+        let block_header = get_bytes(&map, BLOCK_HEADER, "block header")?;
+
+        let fee_payer = get_text(&map, FEE_PAYER, "fee payer")?;
+        if let Some(ref fee_payer) = fee_payer {
+            decode_address(fee_payer).map_err(|e| invalid_value("fee payer", e))?;
         }
+
+        let salt = get_bytes(&map, SALT, "salt")?;
+
+        Ok(TronSignRequest {
+            request_id,
+            id_derived: false,
+            derived_id: OnceLock::new(),
+            sign_data,
+            data_type,
+            derivation_path,
+            xfp,
+            address,
+            origin,
+            metadata,
+            compress,
+            mac,
+            version,
+            wallet_id,
+            block_header,
+            fee_payer,
+            salt,
+            present_keys: Some(present_keys),
+            unknown: unknown_entries(&map, &KNOWN_KEYS),
+            encoded: OnceLock::new(),
+        })
     }
 }
 
-fn decode_derivation_path(value: Option<&Value>) -> Result<String, String> {
+pub(crate) fn decode_derivation_path(value: Option<&Value>) -> Result<String, DecodeError> {
     match value {
-        Some(Value::Map(map)) => {
-            let components = map.get(&Value::Integer(1))
-                .and_then(|v| if let Value::Array(arr) = v { Some(arr) } else { None })
-                .ok_or("Missing path components")?;
-
-            let mut path = String::from("m");
-            for component in components {
-                if let Value::Array(arr) = component {
-                    if arr.len() >= 2 {
-                        let index = if let Value::Integer(i) = &arr[0] { *i as u32 } else { 0 };
-                        let hardened = if let Value::Bool(b) = &arr[1] { *b } else { false };
-                        
-                        path.push('/');
-                        path.push_str(&index.to_string());
-                        if hardened {
-                            path.push('\'');
-                        }
-                    }
-                }
-            }
-            Ok(path)
-        }
-        _ => Ok("m/44'/195'/0'/0/0".to_string()), // Default TRON path
+        Some(value) => Ok(KeyPath::from_cbor(value)?.to_string()),
+        None => Ok("m/44'/195'/0'/0/0".to_string()), // Default TRON path
+    }
+}
+
+fn compress_sign_data(sign_data: &[u8]) -> Result<Value, String> {
+    let mut compressed = Vec::new();
+    DeflateEncoder::new(sign_data, Compression::best())
+        .read_to_end(&mut compressed)
+        .map_err(|e| format!("Failed to compress sign_data: {}", e))?;
+    if compressed.len() < sign_data.len() {
+        Ok(Value::Tag(DEFLATE_TAG, Box::new(Value::Bytes(compressed))))
+    } else {
+        Ok(Value::Bytes(sign_data.to_vec()))
+    }
+}
+
+pub(crate) fn inflate_sign_data(compressed: &[u8]) -> Result<Vec<u8>, DecodeError> {
+    let mut sign_data = Vec::new();
+    DeflateDecoder::new(compressed)
+        .take(MAX_INFLATED_LEN + 1)
+        .read_to_end(&mut sign_data)
+        .map_err(|e| invalid_value("sign_data", format!("failed to decompress: {}", e)))?;
+    if sign_data.len() as u64 > MAX_INFLATED_LEN {
+        return Err(invalid_value("sign_data", "compressed data exceeds size limit"));
+    }
+    Ok(sign_data)
+}
+
+fn decode_metadata(value: Option<&Value>) -> Result<BTreeMap<String, MetadataValue>, DecodeError> {
+    match value {
+        None => Ok(BTreeMap::new()),
+        Some(Value::Map(map)) => map
+            .iter()
+            .map(|(k, v)| match (k, v) {
+                (Value::Text(k), Value::Text(t)) => Ok((k.clone(), MetadataValue::Text(t.clone()))),
+                (Value::Text(k), Value::Bytes(b)) => Ok((k.clone(), MetadataValue::Bytes(b.clone()))),
+                _ => Err(DecodeError::InvalidType { field: "metadata entry", expected: "text key with text or bytes value" }),
+            })
+            .collect(),
+        Some(_) => Err(DecodeError::InvalidType { field: "metadata", expected: "map" }),
     }
 }
 
@@ -232,15 +692,20 @@ fn decode_derivation_path(value: Option<&Value>) -> Result<String, String> {
 pub fn resolve(data: Vec<u8>) -> PtrResponse {
     match TronSignRequest::try_from(data) {
         Ok(result) => Response::success_object(Box::into_raw(Box::new(result)) as PtrVoid).c_ptr(),
-        Err(error) => Response::error(error.to_string()).c_ptr(),
+        Err(error) => {
+            telemetry::decode_failure(TRON_SIGN_REQUEST_TYPE, &error);
+            Response::error(error.to_string()).c_ptr()
+        }
     }
 }
 
+#[cfg(feature = "ffi")]
 #[no_mangle]
 pub extern "C" fn tron_sign_request_new() -> PtrResponse {
     Response::success_object(Box::into_raw(Box::new(TronSignRequest::default())) as PtrVoid).c_ptr()
 }
 
+#[cfg(feature = "ffi")]
 #[no_mangle]
 pub extern "C" fn tron_sign_request_construct(
     request_id: PtrString,
@@ -284,35 +749,802 @@ pub extern "C" fn tron_sign_request_construct(
     Response::success_object(Box::into_raw(Box::new(request)) as PtrVoid).c_ptr()
 }
 
+#[cfg(feature = "ffi")]
 #[no_mangle]
-pub extern "C" fn tron_sign_request_get_ur_encoder(tron_sign_request: &mut TronSignRequest) -> PtrResponse {
-    match tron_sign_request.to_bytes() {
-        Ok(message) => {
-            let ur_encoder = ur::Encoder::new(
-                message.as_slice(),
-                400,
-                TRON_SIGN_REQUEST_TYPE,
-            )
-            .unwrap();
-            Response::success_object(Box::into_raw(Box::new(ur_encoder)) as PtrVoid).c_ptr()
-        }
+pub extern "C" fn tron_sign_request_construct_with_derived_id(
+    sign_data: PtrString,
+    path: PtrString,
+    xfp: u32,
+    address: PtrString,
+    origin: PtrString,
+    data_type: u32,
+) -> PtrResponse {
+    let sign_data = match parse_ptr_string_to_bytes(sign_data).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    let path = match convert_ptr_string_to_string(path).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    let address = match convert_ptr_string_to_string(address).map_err(|e| Response::error(e)) {
+        Ok(v) => Some(v).filter(|s| !s.is_empty()),
+        Err(e) => return e.c_ptr(),
+    };
+    let origin = match convert_ptr_string_to_string(origin).map_err(|e| Response::error(e)) {
+        Ok(v) => Some(v).filter(|s| !s.is_empty()),
+        Err(e) => return e.c_ptr(),
+    };
+
+    let request = TronSignRequest::new_with_derived_request_id(
+        sign_data,
+        data_type,
+        path,
+        Some(xfp),
+        address,
+        origin,
+    );
+    Response::success_object(Box::into_raw(Box::new(request)) as PtrVoid).c_ptr()
+}
+
+/// Payload for a QR code; typed data is validated first so malformed dApp
+/// payloads fail here rather than on the signer
+fn qr_payload(request: &TronSignRequest) -> Result<&[u8], String> {
+    #[cfg(feature = "json")]
+    check_typed_data(request)?;
+    request.encoded_bytes()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_request_get_ur_encoder(tron_sign_request: &TronSignRequest) -> PtrResponse {
+    match tron_sign_request.to_ur_encoder(400) {
+        Ok(ur_encoder) => Response::success_object(Box::into_raw(Box::new(ur_encoder)) as PtrVoid).c_ptr(),
         Err(e) => Response::error(e).c_ptr(),
     }
 }
 
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_request_to_transport_string(tron_sign_request: &TronSignRequest) -> PtrResponse {
+    match tron_sign_request.to_transport_string() {
+        Ok(transport) => Response::success_string(transport),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(feature = "ffi")]
 #[no_mangle]
-pub extern "C" fn tron_sign_request_get_request_id(tron_sign_request: &mut TronSignRequest) -> PtrResponse {
-    tron_sign_request.get_request_id().map_or(Response::success_null().c_ptr(), |id| {
+pub extern "C" fn tron_sign_request_from_transport_string(transport: PtrString) -> PtrResponse {
+    let transport = match convert_ptr_string_to_string(transport).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    match TronSignRequest::from_transport_string(&transport) {
+        Ok(request) => Response::success_object(Box::into_raw(Box::new(request)) as PtrVoid),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+/// UR encoder emitting uppercase parts of at most `capacity` characters, for
+/// QR codes in alphanumeric mode; `capacity` is the alphanumeric character
+/// count the chosen QR version and error correction level hold
+#[cfg(feature = "qr")]
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_request_get_alphanumeric_ur_encoder(
+    tron_sign_request: &TronSignRequest,
+    capacity: u32,
+) -> PtrResponse {
+    match qr_payload(tron_sign_request)
+        .and_then(|message| UREncoder::new_alphanumeric(message, capacity as usize, TRON_SIGN_REQUEST_TYPE))
+    {
+        Ok(ur_encoder) => Response::success_object(Box::into_raw(Box::new(ur_encoder)) as PtrVoid),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+/// UR encoder for this request wrapped in a `tron-encrypted-payload` envelope
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_request_get_encrypted_ur_encoder(
+    tron_sign_request: &TronSignRequest,
+    key: PtrString,
+) -> PtrResponse {
+    let key = match parse_ptr_string_to_bytes(key).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    match qr_payload(tron_sign_request) {
+        Ok(message) => seal_ur_encoder(TRON_SIGN_REQUEST_TYPE, message, &key),
+        Err(e) => Response::error(e).c_ptr(),
+    }
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_request_get_request_id(tron_sign_request: &TronSignRequest) -> PtrResponse {
+    tron_sign_request.get_request_id().map_or(Response::not_present().c_ptr(), |id| {
         Response::success_string(hex::encode(id)).c_ptr()
     })
 }
 
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_request_get_sign_data(tron_sign_request: &TronSignRequest) -> PtrResponse {
+    Response::success_string(hex_codec::encode(tron_sign_request.get_sign_data())).c_ptr()
+}
+
+/// Writes sign_data as NUL-terminated hex to `buffer` when `capacity`
+/// bytes suffice, and returns the size needed: call with a null buffer to
+/// size it, then again to fill it
+#[cfg(feature = "ffi")]
 #[no_mangle]
-pub extern "C" fn tron_sign_request_get_sign_data(tron_sign_request: &mut TronSignRequest) -> PtrResponse {
-    Response::success_string(hex::encode(tron_sign_request.get_sign_data())).c_ptr()
+pub extern "C" fn tron_sign_request_get_sign_data_into(
+    tron_sign_request: &TronSignRequest,
+    buffer: *mut c_char,
+    capacity: usize,
+) -> usize {
+    hex_codec::write_c_hex(tron_sign_request.get_sign_data(), buffer, capacity)
 }
 
+/// Appends the hex `chunk` to sign_data, so bindings whose bridges cap
+/// message sizes can pass a large payload in pieces. Every chunk must hold
+/// whole bytes, i.e. an even number of digits.
+#[cfg(feature = "ffi")]
 #[no_mangle]
-pub extern "C" fn tron_sign_request_get_derivation_path(tron_sign_request: &mut TronSignRequest) -> PtrResponse {
+pub extern "C" fn tron_sign_request_append_sign_data(tron_sign_request: &mut TronSignRequest, chunk: PtrString) -> PtrResponse {
+    let chunk = match parse_ptr_string_to_bytes(chunk).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    match tron_sign_request.append_sign_data(&chunk) {
+        Ok(()) => Response::success_null(),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_request_get_derivation_path(tron_sign_request: &TronSignRequest) -> PtrResponse {
     Response::success_string(tron_sign_request.get_derivation_path().to_string()).c_ptr()
 }
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_request_get_xfp(tron_sign_request: &TronSignRequest) -> PtrResponse {
+    tron_sign_request
+        .get_xfp()
+        .map_or(Response::not_present(), Response::success_uint32)
+        .c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_request_is_request_id_derived(tron_sign_request: &TronSignRequest) -> PtrResponse {
+    Response::success_boolean(tron_sign_request.is_request_id_derived()).c_ptr()
+}
+
+/// Eight space-separated bytewords both devices should show identically
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_request_fingerprint(tron_sign_request: &TronSignRequest) -> PtrResponse {
+    match tron_sign_request.fingerprint() {
+        Ok(fingerprint) => Response::success_string(fingerprint),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_request_set_metadata_text(
+    tron_sign_request: &mut TronSignRequest,
+    key: PtrString,
+    value: PtrString,
+) -> PtrResponse {
+    let key = match convert_ptr_string_to_string(key).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    let value = match convert_ptr_string_to_string(value).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    tron_sign_request.set_metadata(key, MetadataValue::Text(value));
+    Response::success_null().c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_request_set_metadata_bytes(
+    tron_sign_request: &mut TronSignRequest,
+    key: PtrString,
+    value: PtrString,
+) -> PtrResponse {
+    let key = match convert_ptr_string_to_string(key).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    let value = match parse_ptr_string_to_bytes(value).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    tron_sign_request.set_metadata(key, MetadataValue::Bytes(value));
+    Response::success_null().c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_request_set_compress(tron_sign_request: &mut TronSignRequest, compress: bool) -> PtrResponse {
+    tron_sign_request.set_compress(compress);
+    Response::success_null().c_ptr()
+}
+
+/// `version` of 0 clears the field
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_request_set_version(tron_sign_request: &mut TronSignRequest, version: u32) -> PtrResponse {
+    tron_sign_request.set_version(Some(version).filter(|v| *v != 0));
+    Response::success_null().c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_request_get_version(tron_sign_request: &TronSignRequest) -> PtrResponse {
+    tron_sign_request
+        .get_version()
+        .map_or(Response::not_present(), Response::success_uint32)
+        .c_ptr()
+}
+
+/// `wallet_id` is hex; an empty string clears the field
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_request_set_wallet_id(tron_sign_request: &mut TronSignRequest, wallet_id: PtrString) -> PtrResponse {
+    let wallet_id = match parse_ptr_string_to_bytes(wallet_id).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    tron_sign_request.set_wallet_id(Some(wallet_id).filter(|id| !id.is_empty()));
+    Response::success_null().c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_request_get_wallet_id(tron_sign_request: &TronSignRequest) -> PtrResponse {
+    tron_sign_request
+        .get_wallet_id()
+        .map_or(Response::not_present(), |id| Response::success_string(hex::encode(id)))
+        .c_ptr()
+}
+
+/// `block_header` is hex; an empty string clears the field
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_request_set_block_header(tron_sign_request: &mut TronSignRequest, block_header: PtrString) -> PtrResponse {
+    let block_header = match parse_ptr_string_to_bytes(block_header).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    tron_sign_request.set_block_header(Some(block_header).filter(|header| !header.is_empty()));
+    Response::success_null().c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_request_get_block_header(tron_sign_request: &TronSignRequest) -> PtrResponse {
+    tron_sign_request
+        .get_block_header()
+        .map_or(Response::not_present(), |header| Response::success_string(hex::encode(header)))
+        .c_ptr()
+}
+
+/// `fee_payer` is a base58 address; an empty string clears the field
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_request_set_fee_payer(tron_sign_request: &mut TronSignRequest, fee_payer: PtrString) -> PtrResponse {
+    let fee_payer = match convert_ptr_string_to_string(fee_payer).map_err(|e| Response::error(e)) {
+        Ok(v) => Some(v).filter(|s| !s.is_empty()),
+        Err(e) => return e.c_ptr(),
+    };
+    match tron_sign_request.set_fee_payer(fee_payer) {
+        Ok(()) => Response::success_null(),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_request_get_fee_payer(tron_sign_request: &TronSignRequest) -> PtrResponse {
+    tron_sign_request
+        .get_fee_payer()
+        .map_or(Response::not_present(), |fee_payer| Response::success_string(fee_payer.clone()))
+        .c_ptr()
+}
+
+#[cfg(feature = "protobuf")]
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_request_is_fee_delegated(tron_sign_request: &TronSignRequest) -> PtrResponse {
+    match tron_sign_request.is_fee_delegated() {
+        Ok(delegated) => Response::success_boolean(delegated),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+/// Empty strings leave a part unset; all three empty clears the origin
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_request_set_origin(
+    tron_sign_request: &mut TronSignRequest,
+    app: PtrString,
+    dapp_url: PtrString,
+    device: PtrString,
+) -> PtrResponse {
+    let mut parts = Vec::with_capacity(3);
+    for part in [app, dapp_url, device] {
+        match convert_ptr_string_to_string(part).map_err(|e| Response::error(e)) {
+            Ok(v) => parts.push(Some(v).filter(|s| !s.is_empty())),
+            Err(e) => return e.c_ptr(),
+        }
+    }
+    let device = parts.pop().flatten();
+    let dapp_url = parts.pop().flatten();
+    let app = parts.pop().flatten();
+    tron_sign_request.set_origin(Some(Origin::new(app, dapp_url, device)));
+    Response::success_null().c_ptr()
+}
+
+/// The whole origin as one display string, e.g. "TronLink via
+/// https://sunswap.com on Pixel 8"
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_request_get_origin(tron_sign_request: &TronSignRequest) -> PtrResponse {
+    tron_sign_request
+        .get_origin()
+        .map_or(Response::not_present(), |origin| Response::success_string(origin.to_string()))
+        .c_ptr()
+}
+
+fn origin_part(tron_sign_request: &TronSignRequest, part: fn(&Origin) -> &Option<String>) -> PtrResponse {
+    match tron_sign_request.get_origin().and_then(|origin| part(origin).clone()) {
+        Some(value) => Response::success_string(value),
+        None => Response::not_present(),
+    }
+    .c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_request_get_origin_app(tron_sign_request: &TronSignRequest) -> PtrResponse {
+    origin_part(tron_sign_request, |origin| &origin.app)
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_request_get_origin_dapp_url(tron_sign_request: &TronSignRequest) -> PtrResponse {
+    origin_part(tron_sign_request, |origin| &origin.dapp_url)
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_request_get_origin_device(tron_sign_request: &TronSignRequest) -> PtrResponse {
+    origin_part(tron_sign_request, |origin| &origin.device)
+}
+
+/// `salt` is hex; an empty string clears the field
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_request_set_salt(tron_sign_request: &mut TronSignRequest, salt: PtrString) -> PtrResponse {
+    let salt = match parse_ptr_string_to_bytes(salt).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    tron_sign_request.set_salt(Some(salt).filter(|salt| !salt.is_empty()));
+    Response::success_null().c_ptr()
+}
+
+/// Sets a fresh random salt and returns it as hex
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_request_generate_salt(tron_sign_request: &mut TronSignRequest) -> PtrResponse {
+    match tron_sign_request.generate_salt() {
+        Ok(salt) => Response::success_string(hex::encode(salt)),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_request_get_salt(tron_sign_request: &TronSignRequest) -> PtrResponse {
+    tron_sign_request
+        .get_salt()
+        .map_or(Response::not_present(), |salt| Response::success_string(hex::encode(salt)))
+        .c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_request_set_mac(tron_sign_request: &mut TronSignRequest, secret: PtrString) -> PtrResponse {
+    let secret = match parse_ptr_string_to_bytes(secret).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    match tron_sign_request.set_mac(&secret) {
+        Ok(_) => Response::success_null(),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_request_verify_mac(tron_sign_request: &TronSignRequest, secret: PtrString) -> PtrResponse {
+    let secret = match parse_ptr_string_to_bytes(secret).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    match tron_sign_request.verify_mac(&secret) {
+        Ok(valid) => Response::success_boolean(valid),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_request_get_mac(tron_sign_request: &TronSignRequest) -> PtrResponse {
+    tron_sign_request.get_mac().map_or(Response::not_present().c_ptr(), |mac| {
+        Response::success_string(hex::encode(mac)).c_ptr()
+    })
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_request_has_field(tron_sign_request: &TronSignRequest, key: u32) -> PtrResponse {
+    match tron_sign_request.has_field(key as i128) {
+        Ok(present) => Response::success_boolean(present),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+/// Present top-level keys as a JSON array; integer keys as numbers, anything else as its debug form
+#[cfg(feature = "json")]
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_request_get_present_fields(tron_sign_request: &TronSignRequest) -> PtrResponse {
+    match tron_sign_request.get_present_keys() {
+        Ok(keys) => {
+            let keys: Vec<serde_json::Value> = keys
+                .iter()
+                .map(|k| match k {
+                    Value::Integer(i) => json!(*i as i64),
+                    Value::Text(t) => json!(t),
+                    other => json!(format!("{:?}", other)),
+                })
+                .collect();
+            Response::success_string(serde_json::Value::Array(keys).to_string())
+        }
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+/// Metadata as JSON: `{"key": {"type": "text" | "bytes", "value": "..."}}`, bytes hex encoded
+#[cfg(feature = "json")]
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_request_get_metadata(tron_sign_request: &TronSignRequest) -> PtrResponse {
+    let metadata: serde_json::Map<String, serde_json::Value> = tron_sign_request
+        .get_metadata()
+        .iter()
+        .map(|(k, v)| {
+            let entry = match v {
+                MetadataValue::Text(t) => json!({"type": "text", "value": t}),
+                MetadataValue::Bytes(b) => json!({"type": "bytes", "value": hex::encode(b)}),
+            };
+            (k.clone(), entry)
+        })
+        .collect();
+    Response::success_string(serde_json::Value::Object(metadata).to_string()).c_ptr()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "protobuf")]
+    use crate::tron::transaction::{build_account_create, TransactionHeader};
+
+    // Whether a malformed fixture failed the way it should
+    type ErrorCheck = fn(&DecodeError) -> bool;
+
+    fn sample_request() -> TronSignRequest {
+        TronSignRequest::new(
+            Some(hex::decode("9b1deb4d3b7d4bad9bdd2b0d7b3dcb6d").unwrap()),
+            hex::decode("0a0207902208e1b9de559665c6714080c49789bb2c5aae01").unwrap(),
+            1,
+            "m/44'/195'/0'/0/0".to_string(),
+            None,
+            Some("TXYZopYRdj2D9XRtbG411XZZ3kM5VkAeBf".to_string()),
+            Some("TRON MultiSig Wallet".to_string()),
+        )
+    }
+
+    fn fixture(name: &str) -> Vec<u8> {
+        let path = format!("{}/fixtures/tron_sign_request/{}.hex", env!("CARGO_MANIFEST_DIR"), name);
+        hex::decode(std::fs::read_to_string(path).unwrap().trim()).unwrap()
+    }
+
+    #[test]
+    fn test_malformed_fixtures() {
+        assert!(TronSignRequest::try_from(fixture("valid")).is_ok());
+
+        let cases: Vec<(&str, ErrorCheck)> = vec![
+            ("truncated", |e| matches!(e, DecodeError::InvalidCbor(_))),
+            ("not_a_map", |e| *e == DecodeError::ExpectedMap),
+            ("duplicate_keys", |e| matches!(e, DecodeError::DuplicateKey(_))),
+            ("trailing_bytes", |e| matches!(e, DecodeError::TrailingBytes(_))),
+            ("missing_sign_data", |e| *e == DecodeError::MissingField("sign_data")),
+            ("sign_data_text", |e| matches!(e, DecodeError::InvalidType { field: "sign_data", .. })),
+            ("sign_data_bad_deflate", |e| matches!(e, DecodeError::InvalidValue { field: "sign_data", .. })),
+            ("request_id_uint", |e| matches!(e, DecodeError::InvalidType { field: "request id", .. })),
+            ("data_type_negative", |e| matches!(e, DecodeError::InvalidValue { field: "data type", .. })),
+            ("data_type_text", |e| matches!(e, DecodeError::InvalidType { field: "data type", .. })),
+            ("path_not_map", |e| matches!(e, DecodeError::InvalidType { field: "derivation path", .. })),
+            ("path_index_negative", |e| matches!(e, DecodeError::InvalidValue { field: "derivation path", .. })),
+            ("path_index_hardened_range", |e| matches!(e, DecodeError::InvalidValue { field: "derivation path", .. })),
+            ("address_bytes", |e| matches!(e, DecodeError::InvalidType { field: "address", .. })),
+            ("metadata_int_key", |e| matches!(e, DecodeError::InvalidType { field: "metadata entry", .. })),
+            ("version_newer", |e| *e == DecodeError::UnsupportedVersion(2)),
+        ];
+        for (name, expected) in cases {
+            let error = TronSignRequest::try_from(fixture(name)).unwrap_err();
+            assert!(expected(&error), "{}: {:?}", name, error);
+        }
+    }
+
+    #[test]
+    fn test_metadata_round_trip() {
+        let mut request = sample_request();
+        request.set_metadata("vendor".to_string(), MetadataValue::Text("acme".to_string()));
+        request.set_metadata("blob".to_string(), MetadataValue::Bytes(vec![0xde, 0xad]));
+
+        let cbor = request.to_bytes().unwrap();
+        let decoded = TronSignRequest::try_from(cbor.clone()).unwrap();
+        assert_eq!(decoded.get_metadata(), request.get_metadata());
+        assert_eq!(decoded.to_bytes().unwrap(), cbor);
+    }
+
+    #[test]
+    fn test_present_fields() {
+        let request = sample_request();
+        assert!(request.has_field(ADDRESS).unwrap());
+        assert!(!request.has_field(MAC).unwrap());
+
+        let mut map = request.to_cbor_map(false).unwrap();
+        map.remove(&Value::Integer(ADDRESS));
+        map.insert(Value::Integer(42), Value::Bool(true));
+        let decoded = TronSignRequest::try_from(serde_cbor::to_vec(&Value::Map(map)).unwrap()).unwrap();
+        assert!(!decoded.has_field(ADDRESS).unwrap());
+        assert!(decoded.has_field(42).unwrap());
+
+        let mut edited = decoded.clone();
+        edited.set_fee_payer(Some("TXYZopYRdj2D9XRtbG411XZZ3kM5VkAeBf".to_string())).unwrap();
+        assert!(edited.has_field(FEE_PAYER).unwrap());
+        assert!(edited.has_field(42).unwrap());
+        let mut edited = decoded;
+        edited.set_salt(Some(vec![1; SALT_LEN]));
+        assert!(edited.has_field(SALT).unwrap());
+    }
+
+    #[test]
+    fn test_append_sign_data() {
+        let request = sample_request();
+        let mut chunked = TronSignRequest::default();
+        for chunk in request.get_sign_data().chunks(3) {
+            chunked.append_sign_data(chunk).unwrap();
+            assert_eq!(TronSignRequest::try_from(chunked.to_bytes().unwrap()).unwrap().get_sign_data(), chunked.get_sign_data());
+        }
+        assert_eq!(chunked.get_sign_data(), request.get_sign_data());
+        assert!(chunked.append_sign_data(&vec![0; MAX_INFLATED_LEN as usize]).is_err());
+
+        // A derived id follows the content, a caller's id stays
+        let path = "m/44'/195'/0'/0/0".to_string();
+        let mut derived = TronSignRequest::new_with_derived_request_id(b"he".to_vec(), 2, path.clone(), None, None, None);
+        let first = derived.get_request_id().cloned();
+        derived.append_sign_data(b"l").unwrap();
+        assert_ne!(derived.get_request_id().cloned(), first);
+        derived.append_sign_data(b"lo").unwrap();
+        assert!(derived.is_request_id_derived());
+        let whole = TronSignRequest::new_with_derived_request_id(b"hello".to_vec(), 2, path, None, None, None);
+        assert_eq!(derived.get_request_id(), whole.get_request_id());
+        let decoded = TronSignRequest::try_from(derived.to_bytes().unwrap()).unwrap();
+        assert_eq!(decoded.get_request_id(), whole.get_request_id());
+        let mut fixed = sample_request();
+        let id = fixed.get_request_id().cloned();
+        fixed.append_sign_data(b"!").unwrap();
+        assert_eq!(fixed.get_request_id().cloned(), id);
+    }
+
+    #[test]
+    fn test_encoded_cache_invalidated_by_setters() {
+        let mut request = sample_request();
+        let plain = request.encoded_bytes().unwrap().to_vec();
+        assert_eq!(request.encoded_bytes().unwrap().as_ptr(), request.encoded_bytes().unwrap().as_ptr());
+
+        request.set_metadata("vendor".to_string(), MetadataValue::Text("acme".to_string()));
+        let with_metadata = request.to_bytes().unwrap();
+        assert_ne!(with_metadata, plain);
+        assert!(TronSignRequest::try_from(with_metadata).unwrap().get_metadata().contains_key("vendor"));
+
+        // Getters take shared references, so readers on several threads may
+        // race to fill the cache
+        let request = sample_request();
+        let encoded: Vec<Vec<u8>> = std::thread::scope(|s| {
+            let readers: Vec<_> = (0..4).map(|_| s.spawn(|| request.to_bytes().unwrap())).collect();
+            readers.into_iter().map(|r| r.join().unwrap()).collect()
+        });
+        assert!(encoded.iter().all(|e| *e == encoded[0]));
+    }
+
+    #[test]
+    fn test_unknown_keys_preserved() {
+        let mut map = sample_request().to_cbor_map(false).unwrap();
+        map.insert(Value::Integer(42), Value::Text("from the future".to_string()));
+        map.insert(Value::Text("x-vendor".to_string()), Value::Bool(true));
+        let mut decoded = TronSignRequest::try_from(serde_cbor::to_vec(&Value::Map(map)).unwrap()).unwrap();
+        assert_eq!(decoded.get_unknown_fields().len(), 2);
+
+        decoded.set_metadata("vendor".to_string(), MetadataValue::Text("acme".to_string()));
+        let reencoded = decode_map(&decoded.to_bytes().unwrap()).unwrap();
+        assert_eq!(reencoded.get(&Value::Integer(42)), Some(&Value::Text("from the future".to_string())));
+        assert_eq!(reencoded.get(&Value::Text("x-vendor".to_string())), Some(&Value::Bool(true)));
+    }
+
+    #[test]
+    fn test_compressed_sign_data() {
+        let mut request = sample_request();
+        let sign_data = [0x0a, 0x02, 0x07, 0x90].repeat(256);
+        request.sign_data = sign_data.clone();
+        let plain = request.to_bytes().unwrap();
+
+        request.set_compress(true);
+        let compressed = request.to_bytes().unwrap();
+        assert!(compressed.len() < plain.len());
+
+        let decoded = TronSignRequest::try_from(compressed).unwrap();
+        assert!(decoded.is_compress());
+        assert_eq!(decoded.get_sign_data(), &sign_data);
+    }
+
+    #[test]
+    fn test_mac() {
+        let secret = [0x42u8; 32];
+        let mut request = sample_request();
+        request.set_compress(true);
+        request.set_mac(&secret).unwrap();
+
+        let decoded = TronSignRequest::try_from(request.to_bytes().unwrap()).unwrap();
+        assert!(decoded.verify_mac(&secret).unwrap());
+        assert!(!decoded.verify_mac(&[0x43u8; 32]).unwrap());
+
+        let mut tampered = decoded.clone();
+        tampered.origin = Some(Origin::from_text("Evil dApp"));
+        assert!(!tampered.verify_mac(&secret).unwrap());
+        assert!(!sample_request().verify_mac(&secret).unwrap());
+
+        // An edit through a setter drops the MAC rather than leaving a stale one
+        let mut edited = decoded;
+        edited.set_origin(Some(Origin::from_text("Evil dApp")));
+        assert_eq!(edited.get_mac(), None);
+        assert!(!TronSignRequest::try_from(edited.to_bytes().unwrap()).unwrap().has_field(MAC).unwrap());
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        let mut request = sample_request();
+        let fingerprint = request.fingerprint().unwrap();
+        assert_eq!(fingerprint.split(' ').count(), FINGERPRINT_LEN);
+
+        // The cold side sees the same words whatever the wire encoding
+        request.set_compress(true);
+        request.set_mac(&[0x42; 32]).unwrap();
+        let decoded = TronSignRequest::try_from(request.to_bytes().unwrap()).unwrap();
+        assert_eq!(decoded.fingerprint().unwrap(), fingerprint);
+
+        request.origin = Some(Origin::from_text("Evil dApp"));
+        assert_ne!(request.fingerprint().unwrap(), fingerprint);
+    }
+
+    #[test]
+    fn test_path_spellings_normalized() {
+        let canonical = TronSignRequest::new_with_derived_request_id(
+            vec![1, 2, 3], 1, "m/44'/195'/0'/0/0".to_string(), None, None, None,
+        );
+        for path in ["44'/195'/0'/0/0", "m/44h/195h/0h/0/0", "m/44'/195h/0'/0/0"] {
+            let request = TronSignRequest::new_with_derived_request_id(
+                vec![1, 2, 3], 1, path.to_string(), None, None, None,
+            );
+            assert_eq!(request.get_derivation_path(), "m/44'/195'/0'/0/0");
+            assert_eq!(request.get_request_id(), canonical.get_request_id());
+            assert_eq!(request.to_bytes().unwrap(), canonical.to_bytes().unwrap());
+        }
+    }
+
+    #[test]
+    fn test_xfp_round_trip() {
+        let request = TronSignRequest::new(
+            None, vec![1, 2, 3], 1, "m/44'/195'/0'/0/0".to_string(), Some(0x12345678), None, None,
+        );
+        let decoded = TronSignRequest::try_from(request.to_bytes().unwrap()).unwrap();
+        assert_eq!(decoded.get_xfp(), Some(0x12345678));
+        assert_eq!(decoded.get_wallet_id(), None);
+
+        let mut request = request;
+        request.set_wallet_id(Some(vec![0xaa; 8]));
+        let decoded = TronSignRequest::try_from(request.to_bytes().unwrap()).unwrap();
+        assert_eq!(decoded.get_wallet_id(), Some(&vec![0xaa; 8]));
+        assert_eq!(decoded.to_bytes().unwrap(), request.to_bytes().unwrap());
+    }
+
+    #[test]
+    fn test_fee_payer() {
+        let owner = "TXYZopYRdj2D9XRtbG411XZZ3kM5VkAeBf";
+        let other = "TR7NHqjeKQxGTCi8q8ZY4pL8otSzgjLj6t";
+        let mut request = sample_request();
+        request.set_fee_payer(Some(other.to_string())).unwrap();
+        let decoded = TronSignRequest::try_from(request.to_bytes().unwrap()).unwrap();
+        assert_eq!(decoded.get_fee_payer().map(String::as_str), Some(other));
+        request.set_fee_payer(Some(owner.to_string())).unwrap();
+
+        assert!(request.set_fee_payer(Some("not an address".to_string())).is_err());
+        assert_eq!(request.get_fee_payer().map(String::as_str), Some(owner));
+        let mut map = request.to_cbor_map(false).unwrap();
+        map.insert(Value::Integer(FEE_PAYER), Value::Text("TXYZ".to_string()));
+        let invalid = TronSignRequest::try_from(serde_cbor::to_vec(&Value::Map(map)).unwrap());
+        assert_eq!(invalid.unwrap_err().code(), "InvalidValue");
+    }
+
+    #[cfg(feature = "protobuf")]
+    #[test]
+    fn test_fee_delegation() {
+        let owner = "TXYZopYRdj2D9XRtbG411XZZ3kM5VkAeBf";
+        let header = TransactionHeader {
+            ref_block_bytes: vec![0x12, 0x34],
+            ref_block_hash: vec![0xab; 8],
+            expiration: 1_700_000_060_000,
+            timestamp: 1_700_000_000_000,
+        };
+        let raw_data = build_account_create(&header, &decode_address(owner).unwrap(), &[0x41; 21]).unwrap();
+        // The address hint names someone else; only the owner counts
+        let other = "TR7NHqjeKQxGTCi8q8ZY4pL8otSzgjLj6t";
+        let mut request = TronSignRequest::new(None, raw_data, 1, "m/44'/195'/0'/0/0".to_string(), None, Some(other.to_string()), None);
+        assert!(!request.is_fee_delegated().unwrap());
+
+        request.set_fee_payer(Some(other.to_string())).unwrap();
+        let decoded = TronSignRequest::try_from(request.to_bytes().unwrap()).unwrap();
+        assert!(decoded.is_fee_delegated().unwrap());
+        request.set_fee_payer(Some(owner.to_string())).unwrap();
+        assert!(!request.is_fee_delegated().unwrap());
+        assert!(sample_request().is_fee_delegated().is_ok_and(|delegated| !delegated));
+    }
+
+    #[test]
+    fn test_salt() {
+        let mut request = TronSignRequest::new(None, vec![1, 2, 3], 1, "m/44'/195'/0'/0/0".to_string(), None, None, None);
+        let salt = request.generate_salt().unwrap();
+        assert_eq!(salt.len(), SALT_LEN);
+        let decoded = TronSignRequest::try_from(request.to_bytes().unwrap()).unwrap();
+        assert_eq!(decoded.get_salt(), Some(&salt));
+        assert_ne!(request.generate_salt().unwrap(), salt);
+    }
+}
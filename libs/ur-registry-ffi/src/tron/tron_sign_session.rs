@@ -0,0 +1,391 @@
+use crate::response::{PtrResponse, Response};
+use crate::tron::decode::{decode_map, get_bool, get_bytes, get_text, invalid_value, DecodeError};
+use crate::tron::schema::{FieldSchema, FieldType, TypeSchema};
+use crate::tron::telemetry;
+use crate::tron::tron_sign_request::TronSignRequest;
+use crate::tron::tron_signature::TronSignature;
+use crate::types::{PtrString, PtrVoid};
+use crate::ur_encoder::UREncoder;
+use crate::utils::{convert_ptr_string_to_string, parse_ptr_string_to_bytes};
+
+use serde_cbor::Value;
+use std::collections::BTreeMap;
+
+// CBOR map keys for TronSignSession
+const SESSION_ID: i128 = 1;
+const DESCRIPTION: i128 = 2;
+const REQUESTS: i128 = 3;
+const ORDERED: i128 = 4;
+// Every key the codec reads and writes, checked against SCHEMA
+#[cfg(test)]
+pub(crate) const KNOWN_KEYS: [i128; 4] = [SESSION_ID, DESCRIPTION, REQUESTS, ORDERED];
+
+const SESSION_ID_LEN: usize = 16;
+
+// UR Type for several sign requests that belong together
+pub const TRON_SIGN_SESSION_TYPE: &str = "tron-sign-session";
+
+pub(crate) const SCHEMA: TypeSchema = TypeSchema {
+    ur_type: TRON_SIGN_SESSION_TYPE,
+    tag: None,
+    fields: &[
+        FieldSchema::required(SESSION_ID, "session_id", FieldType::Bytes),
+        FieldSchema::optional(DESCRIPTION, "description", FieldType::Text),
+        // Each an encoded tron-sign-request
+        FieldSchema::required(REQUESTS, "requests", FieldType::ArrayOfBytes),
+        FieldSchema::optional(ORDERED, "ordered", FieldType::Bool),
+    ],
+};
+
+/// Requests grouped under one session id, e.g. "approve then swap". Each
+/// member is a full `tron-sign-request` and needs a request id, which its
+/// signature is matched by. Which members are signed is tracked locally
+/// and isn't encoded.
+#[derive(Clone, Debug, Default)]
+pub struct TronSignSession {
+    session_id: Vec<u8>,
+    description: Option<String>,
+    requests: Vec<TronSignRequest>,
+    // Members must be signed in order
+    ordered: bool,
+    signatures: Vec<Option<TronSignature>>,
+}
+
+impl TronSignSession {
+    /// `session_id` of `None` picks a random one
+    pub fn new(session_id: Option<Vec<u8>>, description: Option<String>, ordered: bool) -> Result<Self, String> {
+        let session_id = match session_id {
+            Some(id) => id,
+            None => {
+                let mut id = vec![0u8; SESSION_ID_LEN];
+                getrandom::getrandom(&mut id).map_err(|e| e.to_string())?;
+                id
+            }
+        };
+        if session_id.is_empty() {
+            return Err("Session id is empty".to_string());
+        }
+        Ok(TronSignSession {
+            session_id,
+            description,
+            ordered,
+            ..Default::default()
+        })
+    }
+
+    pub fn add_request(&mut self, request: TronSignRequest) -> Result<(), String> {
+        let request_id = request.get_request_id().ok_or("Session members need a request id")?;
+        if self.index_of(request_id).is_some() {
+            return Err(format!("Request {} is already in the session", hex::encode(request_id)));
+        }
+        self.requests.push(request);
+        self.signatures.push(None);
+        Ok(())
+    }
+
+    pub fn get_session_id(&self) -> &Vec<u8> {
+        &self.session_id
+    }
+
+    pub fn get_description(&self) -> Option<&String> {
+        self.description.as_ref()
+    }
+
+    pub fn is_ordered(&self) -> bool {
+        self.ordered
+    }
+
+    pub fn get_requests(&self) -> &Vec<TronSignRequest> {
+        &self.requests
+    }
+
+    fn index_of(&self, request_id: &[u8]) -> Option<usize> {
+        self.requests.iter().position(|r| r.get_request_id().map(Vec::as_slice) == Some(request_id))
+    }
+
+    /// Records the signature and returns the member it answers. In ordered
+    /// sessions, every earlier member must be signed first.
+    pub fn mark_signed(&mut self, signature: &TronSignature) -> Result<usize, String> {
+        let request_id = signature.get_request_id().ok_or("No request id supplied")?;
+        let index = self
+            .index_of(request_id)
+            .ok_or(format!("Request {} is not in the session", hex::encode(request_id)))?;
+        if self.signatures[index].is_some() {
+            return Err(format!("Request {} is already signed", hex::encode(request_id)));
+        }
+        if self.ordered && self.get_next_unsigned().is_some_and(|next| next < index) {
+            return Err(format!("Request {} is signed out of order", hex::encode(request_id)));
+        }
+        if !signature.check_salt(&self.requests[index]) {
+            return Err(format!("Signature doesn't prove the salt of request {}", hex::encode(request_id)));
+        }
+        self.signatures[index] = Some(signature.clone());
+        Ok(index)
+    }
+
+    pub fn get_signature(&self, index: usize) -> Option<&TronSignature> {
+        self.signatures.get(index).and_then(Option::as_ref)
+    }
+
+    /// First member still waiting for a signature
+    pub fn get_next_unsigned(&self) -> Option<usize> {
+        self.signatures.iter().position(Option::is_none)
+    }
+
+    pub fn is_complete(&self) -> bool {
+        !self.requests.is_empty() && self.get_next_unsigned().is_none()
+    }
+
+    /// Serialize to CBOR bytes
+    pub fn to_bytes(&self) -> Result<Vec<u8>, String> {
+        let mut map: BTreeMap<Value, Value> = BTreeMap::new();
+
+        map.insert(Value::Integer(SESSION_ID), Value::Bytes(self.session_id.clone()));
+
+        if let Some(ref description) = self.description {
+            map.insert(Value::Integer(DESCRIPTION), Value::Text(description.clone()));
+        }
+
+        let requests = self
+            .requests
+            .iter()
+            .map(|r| r.to_bytes().map(Value::Bytes))
+            .collect::<Result<Vec<Value>, String>>()?;
+        map.insert(Value::Integer(REQUESTS), Value::Array(requests));
+
+        if self.ordered {
+            map.insert(Value::Integer(ORDERED), Value::Bool(true));
+        }
+
+        serde_cbor::to_vec(&Value::Map(map))
+            .map_err(|e| e.to_string())
+    }
+}
+
+impl TryFrom<Vec<u8>> for TronSignSession {
+    type Error = DecodeError;
+
+    fn try_from(data: Vec<u8>) -> Result<Self, Self::Error> {
+        let map = decode_map(&data)?;
+
+        let session_id = get_bytes(&map, SESSION_ID, "session id")?.ok_or(DecodeError::MissingField("session id"))?;
+
+        let description = get_text(&map, DESCRIPTION, "description")?;
+
+        let ordered = get_bool(&map, ORDERED, "ordered")?.unwrap_or(false);
+
+        let mut session =
+            TronSignSession::new(Some(session_id), description, ordered).map_err(|e| invalid_value("session id", e))?;
+        match map.get(&Value::Integer(REQUESTS)) {
+            Some(Value::Array(arr)) => {
+                for request in arr {
+                    let request = match request {
+                        Value::Bytes(b) => TronSignRequest::try_from(b.clone())?,
+                        _ => return Err(DecodeError::InvalidType { field: "session request", expected: "bytes" }),
+                    };
+                    session.add_request(request).map_err(|e| invalid_value("session requests", e))?;
+                }
+            }
+            Some(_) => return Err(DecodeError::InvalidType { field: "session requests", expected: "array" }),
+            None => return Err(DecodeError::MissingField("session requests")),
+        }
+        Ok(session)
+    }
+}
+
+// ========== FFI Functions ==========
+
+pub fn resolve(data: Vec<u8>) -> PtrResponse {
+    match TronSignSession::try_from(data) {
+        Ok(result) => Response::success_object(Box::into_raw(Box::new(result)) as PtrVoid).c_ptr(),
+        Err(error) => {
+            telemetry::decode_failure(TRON_SIGN_SESSION_TYPE, &error);
+            Response::error(error.to_string()).c_ptr()
+        }
+    }
+}
+
+/// An empty `session_id` picks a random one; an empty `description` is
+/// treated as absent
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_session_new(session_id: PtrString, description: PtrString, ordered: bool) -> PtrResponse {
+    let session_id = match parse_ptr_string_to_bytes(session_id).map_err(|e| Response::error(e)) {
+        Ok(v) => Some(v).filter(|id| !id.is_empty()),
+        Err(e) => return e.c_ptr(),
+    };
+    let description = match convert_ptr_string_to_string(description).map_err(|e| Response::error(e)) {
+        Ok(v) => Some(v).filter(|s| !s.is_empty()),
+        Err(e) => return e.c_ptr(),
+    };
+    match TronSignSession::new(session_id, description, ordered) {
+        Ok(session) => Response::success_object(Box::into_raw(Box::new(session)) as PtrVoid),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_session_add_request(
+    tron_sign_session: &mut TronSignSession,
+    tron_sign_request: &TronSignRequest,
+) -> PtrResponse {
+    match tron_sign_session.add_request(tron_sign_request.clone()) {
+        Ok(_) => Response::success_null(),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_session_get_ur_encoder(tron_sign_session: &TronSignSession) -> PtrResponse {
+    match tron_sign_session.to_bytes() {
+        Ok(message) => match UREncoder::new(message.as_slice(), 400, TRON_SIGN_SESSION_TYPE) {
+            Ok(ur_encoder) => Response::success_object(Box::into_raw(Box::new(ur_encoder)) as PtrVoid),
+            Err(e) => Response::error(e),
+        },
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_session_get_session_id(tron_sign_session: &TronSignSession) -> PtrResponse {
+    Response::success_string(hex::encode(tron_sign_session.get_session_id())).c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_session_get_description(tron_sign_session: &TronSignSession) -> PtrResponse {
+    tron_sign_session
+        .get_description()
+        .map_or(Response::not_present(), |v| Response::success_string(v.clone()))
+        .c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_session_is_ordered(tron_sign_session: &TronSignSession) -> PtrResponse {
+    Response::success_boolean(tron_sign_session.is_ordered()).c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_session_get_requests_len(tron_sign_session: &TronSignSession) -> PtrResponse {
+    Response::success_uint32(tron_sign_session.get_requests().len() as u32).c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_session_get_request(tron_sign_session: &TronSignSession, index: u32) -> PtrResponse {
+    match tron_sign_session.get_requests().get(index as usize) {
+        Some(request) => Response::success_object(Box::into_raw(Box::new(request.clone())) as PtrVoid).c_ptr(),
+        None => Response::error(format!("No request for index {} was found", index)).c_ptr(),
+    }
+}
+
+/// Index of the member `tron_signature` answers
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_session_mark_signed(
+    tron_sign_session: &mut TronSignSession,
+    tron_signature: &TronSignature,
+) -> PtrResponse {
+    match tron_sign_session.mark_signed(tron_signature) {
+        Ok(index) => Response::success_uint32(index as u32),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_session_is_signed(tron_sign_session: &TronSignSession, index: u32) -> PtrResponse {
+    Response::success_boolean(tron_sign_session.get_signature(index as usize).is_some()).c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_session_get_signature(tron_sign_session: &TronSignSession, index: u32) -> PtrResponse {
+    tron_sign_session
+        .get_signature(index as usize)
+        .map_or(Response::not_present(), |s| Response::success_object(Box::into_raw(Box::new(s.clone())) as PtrVoid))
+        .c_ptr()
+}
+
+/// Index of the first unsigned member, `NOT_PRESENT` once all are signed
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_session_get_next_unsigned(tron_sign_session: &TronSignSession) -> PtrResponse {
+    tron_sign_session
+        .get_next_unsigned()
+        .map_or(Response::not_present(), |i| Response::success_uint32(i as u32))
+        .c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_session_is_complete(tron_sign_session: &TronSignSession) -> PtrResponse {
+    Response::success_boolean(tron_sign_session.is_complete()).c_ptr()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(sign_data: &[u8]) -> TronSignRequest {
+        TronSignRequest::new_with_derived_request_id(sign_data.to_vec(), 1, "m/44'/195'/0'/0/0".to_string(), None, None, None)
+    }
+
+    #[test]
+    fn test_session_round_trip() {
+        let mut session = TronSignSession::new(Some(vec![7; 16]), Some("Approve then swap".to_string()), true).unwrap();
+        let (approve, swap) = (request(b"approve"), request(b"swap"));
+        session.add_request(approve.clone()).unwrap();
+        session.add_request(swap.clone()).unwrap();
+        assert!(session.add_request(approve.clone()).is_err());
+        assert!(session.add_request(TronSignRequest::new(None, vec![1], 1, "m/44'/195'/0'/0/0".to_string(), None, None, None)).is_err());
+
+        let mut decoded = TronSignSession::try_from(session.to_bytes().unwrap()).unwrap();
+        assert_eq!(decoded.get_session_id(), &vec![7; 16]);
+        assert_eq!(decoded.get_description().unwrap(), "Approve then swap");
+        assert!(decoded.is_ordered());
+        assert_eq!(decoded.get_requests()[1].get_request_id(), swap.get_request_id());
+
+        let sign = |request: &TronSignRequest| TronSignature::new(request.get_request_id().cloned(), vec![1; 65]);
+        assert_eq!(decoded.get_next_unsigned(), Some(0));
+        assert!(decoded.mark_signed(&sign(&swap)).unwrap_err().contains("out of order"));
+        assert_eq!(decoded.mark_signed(&sign(&approve)).unwrap(), 0);
+        assert!(!decoded.is_complete());
+        assert_eq!(decoded.mark_signed(&sign(&swap)).unwrap(), 1);
+        assert!(decoded.is_complete());
+        let replaced = TronSignature::new(approve.get_request_id().cloned(), vec![2; 65]);
+        assert!(decoded.mark_signed(&replaced).unwrap_err().contains("already signed"));
+        assert_eq!(decoded.get_signature(0).unwrap().get_signature(), &vec![1; 65]);
+        assert!(decoded.mark_signed(&sign(&request(b"other"))).is_err());
+
+        let random = TronSignSession::new(None, None, false).unwrap();
+        assert_eq!(random.get_session_id().len(), SESSION_ID_LEN);
+        assert!(!random.is_complete());
+    }
+
+    #[test]
+    fn test_session_rejects_malformed() {
+        let session = TronSignSession::new(Some(vec![7; 16]), None, false).unwrap();
+        let mut map = match serde_cbor::from_slice(&session.to_bytes().unwrap()).unwrap() {
+            Value::Map(map) => map,
+            _ => unreachable!(),
+        };
+        map.insert(Value::Integer(ORDERED), Value::Integer(1));
+        let decoded = TronSignSession::try_from(serde_cbor::to_vec(&Value::Map(map.clone())).unwrap());
+        assert_eq!(decoded.unwrap_err(), DecodeError::InvalidType { field: "ordered", expected: "bool" });
+
+        map.remove(&Value::Integer(ORDERED));
+        map.insert(Value::Integer(DESCRIPTION), Value::Bytes(vec![1]));
+        let decoded = TronSignSession::try_from(serde_cbor::to_vec(&Value::Map(map)).unwrap());
+        assert_eq!(decoded.unwrap_err(), DecodeError::InvalidType { field: "description", expected: "text" });
+    }
+}
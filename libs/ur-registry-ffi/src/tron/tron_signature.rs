@@ -1,20 +1,111 @@
 use crate::response::{PtrResponse, Response};
-use crate::types::PtrVoid;
+use crate::tron::address::{encode_address, public_key_to_address};
+use crate::tron::schema::{FieldSchema, FieldType, TypeSchema};
+use crate::tron::tron_sign_request::{DataType, TronSignRequest};
+use crate::tron::decode::{decode_map, get_bytes, unknown_entries, DecodeError};
+use crate::tron::telemetry;
+use crate::tron::transport;
+use crate::tron::version::{decode_version, encode_version};
+use crate::types::{PtrString, PtrVoid};
+use crate::utils::{convert_ptr_string_to_string, parse_ptr_string_to_bytes};
 
+use secp256k1::{Message, PublicKey, Secp256k1};
+
+use secp256k1::ecdsa::{RecoverableSignature, RecoveryId, Signature};
 use serde_cbor::Value;
+use sha2::{Digest, Sha256};
+use sha3::Keccak256;
 use std::collections::BTreeMap;
+use ur::bytewords::{self, Style};
 
 // CBOR map keys for TronSignature
 const REQUEST_ID: i128 = 1;
 const SIGNATURE: i128 = 2;
+const VERSION: i128 = 3;
+const PUBLIC_KEY: i128 = 4;
+const SALT: i128 = 5;
+const SALT_PROOF: i128 = 6;
+pub(crate) const KNOWN_KEYS: [i128; 6] = [REQUEST_ID, SIGNATURE, VERSION, PUBLIC_KEY, SALT, SALT_PROOF];
+
+// r || s || v
+const RECOVERABLE_SIGNATURE_LEN: usize = 65;
+const COMPACT_SIGNATURE_LEN: usize = 64;
+const COMPONENT_LEN: usize = 32;
+// Recovery id offset in the trailing `v` byte, as TronWeb emits it
+pub(crate) const RECOVERY_ID_OFFSET: u8 = 27;
+
+// v is either the raw recovery id or offset by 27
+fn flip_recovery_id(v: u8) -> u8 {
+    match v {
+        27 | 28 => 55 - v,
+        _ => v ^ 1,
+    }
+}
 
 // UR Type for TRON signature
 pub const TRON_SIGNATURE_TYPE: &str = "tron-signature";
 
+pub(crate) const SCHEMA: TypeSchema = TypeSchema {
+    ur_type: TRON_SIGNATURE_TYPE,
+    tag: None,
+    fields: &[
+        FieldSchema::optional(REQUEST_ID, "request_id", FieldType::Bytes),
+        FieldSchema::required(SIGNATURE, "signature", FieldType::Bytes),
+        FieldSchema::optional(VERSION, "version", FieldType::Uint),
+        FieldSchema::optional(PUBLIC_KEY, "public_key", FieldType::Bytes),
+        FieldSchema::optional(SALT, "salt", FieldType::Bytes),
+        FieldSchema::optional(SALT_PROOF, "salt_proof", FieldType::Bytes),
+    ],
+};
+
+// TIP-191 prefix for signed messages
+const MESSAGE_PREFIX: &[u8] = b"\x19TRON Signed Message:\n";
+// Leads the message a salt proof signs
+const SALT_PROOF_DOMAIN: &[u8] = b"tron-sign-request salt:";
+
+fn message_hash(message: &[u8]) -> [u8; 32] {
+    let mut hasher = Keccak256::new();
+    hasher.update(MESSAGE_PREFIX);
+    hasher.update(message.len().to_string().as_bytes());
+    hasher.update(message);
+    hasher.finalize().into()
+}
+
+/// Digest the device signs: the transaction id (sha256 of raw_data) for
+/// transactions, the TIP-191 hash for messages
+pub fn signing_hash(request: &TronSignRequest) -> Result<[u8; 32], String> {
+    let sign_data = request.get_sign_data();
+    match DataType::from_u32(request.get_data_type())? {
+        DataType::Transaction => Ok(Sha256::digest(sign_data).into()),
+        DataType::Message => Ok(message_hash(sign_data)),
+        DataType::TypedData => Err("TypedData signing hashes are not supported".to_string()),
+    }
+}
+
+/// Digest a salt proof signs: the TIP-191 hash of the domain, the request's
+/// salt and its signing hash. Signed with the key that signs the request,
+/// it ties the signature to this salted instance; as a message hash it
+/// can't pass for a transaction id.
+pub fn salt_proof_hash(request: &TronSignRequest) -> Result<[u8; 32], String> {
+    let salt = request.get_salt().ok_or("Request has no salt")?;
+    let mut message = SALT_PROOF_DOMAIN.to_vec();
+    message.extend_from_slice(salt);
+    message.extend_from_slice(&signing_hash(request)?);
+    Ok(message_hash(&message))
+}
 #[derive(Clone, Debug, Default)]
 pub struct TronSignature {
     request_id: Option<Vec<u8>>,
     signature: Vec<u8>,
+    version: Option<u32>,
+    // SEC1 key of the signing account, compressed or not
+    public_key: Option<Vec<u8>>,
+    // The request's salt, echoed
+    salt: Option<Vec<u8>>,
+    // 65-byte signature over `salt_proof_hash` by the signing key
+    salt_proof: Option<Vec<u8>>,
+    // Entries with keys this build doesn't know, re-emitted by `to_bytes`
+    unknown: BTreeMap<Value, Value>,
 }
 
 impl TronSignature {
@@ -22,6 +113,7 @@ impl TronSignature {
         TronSignature {
             request_id,
             signature,
+            ..Default::default()
         }
     }
 
@@ -33,6 +125,151 @@ impl TronSignature {
         &self.signature
     }
 
+    pub fn get_version(&self) -> Option<u32> {
+        self.version
+    }
+
+    pub fn set_version(&mut self, version: Option<u32>) {
+        self.version = version;
+    }
+
+    pub fn get_public_key(&self) -> Option<&Vec<u8>> {
+        self.public_key.as_ref()
+    }
+
+    pub fn set_public_key(&mut self, public_key: Option<Vec<u8>>) -> Result<(), String> {
+        if let Some(ref key) = public_key {
+            PublicKey::from_slice(key).map_err(|e| format!("Invalid public key: {}", e))?;
+        }
+        self.public_key = public_key;
+        Ok(())
+    }
+
+    pub fn get_salt(&self) -> Option<&Vec<u8>> {
+        self.salt.as_ref()
+    }
+
+    pub fn set_salt(&mut self, salt: Option<Vec<u8>>) {
+        self.salt = salt;
+    }
+
+    pub fn get_salt_proof(&self) -> Option<&Vec<u8>> {
+        self.salt_proof.as_ref()
+    }
+
+    pub fn set_salt_proof(&mut self, salt_proof: Option<Vec<u8>>) {
+        self.salt_proof = salt_proof;
+    }
+
+    /// Whether the signature answers this instance of `request`: it echoes
+    /// the salt and carries a salt proof made by the key the signature
+    /// recovers to, so an echo copied onto an older signature fails. Always
+    /// true for unsalted requests.
+    pub fn check_salt(&self, request: &TronSignRequest) -> bool {
+        let salt = match request.get_salt() {
+            Some(salt) => salt,
+            None => return true,
+        };
+        let proof = match (&self.salt, &self.salt_proof) {
+            (Some(echoed), Some(proof)) if echoed == salt => proof,
+            _ => return false,
+        };
+        let prover = salt_proof_hash(request).and_then(|digest| recover_signer(&digest, proof));
+        matches!((prover, recover_address(request, self)), (Ok(prover), Ok(signer)) if prover == signer)
+    }
+
+    /// Base58 address of the carried public key
+    pub fn get_signer_address(&self) -> Result<Option<String>, String> {
+        self.public_key
+            .as_ref()
+            .map(|key| public_key_to_address(key).and_then(|address| encode_address(&address)))
+            .transpose()
+    }
+
+    /// Whether the signer is the account the request was addressed to;
+    /// `None` when either side doesn't say
+    pub fn check_signer(&self, request: &TronSignRequest) -> Result<Option<bool>, String> {
+        match (self.get_signer_address()?, request.get_address()) {
+            (Some(signer), Some(expected)) => Ok(Some(signer == *expected)),
+            _ => Ok(None),
+        }
+    }
+
+    /// Builds the 65-byte form; `recovery_id` may be 0/1 or 27/28 and is
+    /// stored offset by 27
+    pub fn from_components(request_id: Option<Vec<u8>>, r: &[u8], s: &[u8], recovery_id: u8) -> Result<Self, String> {
+        if r.len() != COMPONENT_LEN || s.len() != COMPONENT_LEN {
+            return Err(format!("r and s must be {} bytes each", COMPONENT_LEN));
+        }
+        let v = match recovery_id {
+            0 | 1 => recovery_id + RECOVERY_ID_OFFSET,
+            27 | 28 => recovery_id,
+            _ => return Err(format!("Invalid recovery id: {}", recovery_id)),
+        };
+        let mut signature = Vec::with_capacity(RECOVERABLE_SIGNATURE_LEN);
+        signature.extend_from_slice(r);
+        signature.extend_from_slice(s);
+        signature.push(v);
+        Ok(TronSignature::new(request_id, signature))
+    }
+
+    fn component(&self, index: usize) -> Result<&[u8], String> {
+        match self.signature.len() {
+            RECOVERABLE_SIGNATURE_LEN | COMPACT_SIGNATURE_LEN => Ok(&self.signature[index * COMPONENT_LEN..(index + 1) * COMPONENT_LEN]),
+            len => Err(format!("Expected a 64 or 65-byte signature, got {}", len)),
+        }
+    }
+
+    pub fn get_r(&self) -> Result<&[u8], String> {
+        self.component(0)
+    }
+
+    pub fn get_s(&self) -> Result<&[u8], String> {
+        self.component(1)
+    }
+
+    /// Recovery id as 0 or 1, whichever way the signer encoded v
+    pub fn get_recovery_id(&self) -> Result<u8, String> {
+        if self.signature.len() != RECOVERABLE_SIGNATURE_LEN {
+            return Err(format!("Expected a {}-byte signature, got {}", RECOVERABLE_SIGNATURE_LEN, self.signature.len()));
+        }
+        match self.signature[COMPACT_SIGNATURE_LEN] {
+            v @ (0 | 1) => Ok(v),
+            v @ (27 | 28) => Ok(v - RECOVERY_ID_OFFSET),
+            v => Err(format!("Invalid recovery id: {}", v)),
+        }
+    }
+
+    fn compact(&self) -> Result<Signature, String> {
+        self.component(0)?;
+        Signature::from_compact(&self.signature[..COMPACT_SIGNATURE_LEN]).map_err(|e| format!("Invalid signature: {}", e))
+    }
+
+    /// Whether s is in the lower half of the curve order. High-s signatures
+    /// are valid but malleable, and some nodes reject them.
+    pub fn is_low_s(&self) -> Result<bool, String> {
+        let signature = self.compact()?;
+        let mut normalized = signature;
+        normalized.normalize_s();
+        Ok(normalized == signature)
+    }
+
+    /// Rewrites a high-s signature to its low-s twin, flipping the recovery
+    /// id so it still recovers the same key; true when it changed
+    pub fn normalize_s(&mut self) -> Result<bool, String> {
+        if self.is_low_s()? {
+            return Ok(false);
+        }
+        let mut signature = self.compact()?;
+        signature.normalize_s();
+        self.signature[..COMPACT_SIGNATURE_LEN].copy_from_slice(&signature.serialize_compact());
+        // Negating s flips the parity of R's y coordinate
+        if let Some(v) = self.signature.get_mut(COMPACT_SIGNATURE_LEN) {
+            *v = flip_recovery_id(*v);
+        }
+        Ok(true)
+    }
+
     /// Serialize to CBOR bytes
     pub fn to_bytes(&self) -> Result<Vec<u8>, String> {
         let mut map: BTreeMap<Value, Value> = BTreeMap::new();
@@ -43,54 +280,504 @@ impl TronSignature {
 
         map.insert(Value::Integer(SIGNATURE), Value::Bytes(self.signature.clone()));
 
+        if let Some(version) = self.version {
+            map.insert(Value::Integer(VERSION), encode_version(version));
+        }
+
+        if let Some(ref public_key) = self.public_key {
+            map.insert(Value::Integer(PUBLIC_KEY), Value::Bytes(public_key.clone()));
+        }
+
+        if let Some(ref salt) = self.salt {
+            map.insert(Value::Integer(SALT), Value::Bytes(salt.clone()));
+        }
+
+        if let Some(ref salt_proof) = self.salt_proof {
+            map.insert(Value::Integer(SALT_PROOF), Value::Bytes(salt_proof.clone()));
+        }
+
+        for (key, value) in self.unknown.iter() {
+            map.entry(key.clone()).or_insert_with(|| value.clone());
+        }
+
         serde_cbor::to_vec(&Value::Map(map))
             .map_err(|e| e.to_string())
     }
+
+    /// `tron-signature:<base64url(cbor)>`, for same-device handoffs
+    pub fn to_transport_string(&self) -> Result<String, String> {
+        Ok(transport::encode(TRON_SIGNATURE_TYPE, &self.to_bytes()?))
+    }
+
+    pub fn from_transport_string(transport: &str) -> Result<Self, String> {
+        let cbor = transport::decode_as(TRON_SIGNATURE_TYPE, transport)?;
+        TronSignature::try_from(cbor).map_err(|e| e.to_string())
+    }
+
+    /// Parses a scanned single-part `ur:tron-signature/<bytewords>`, either
+    /// case; multi-part scans go through a `ur_decoder` instead
+    pub fn from_ur_string(ur: &str) -> Result<Self, String> {
+        let ur = ur.trim().to_lowercase();
+        let (ur_type, body) = ur.strip_prefix("ur:").and_then(|rest| rest.split_once('/')).ok_or("Not a UR")?;
+        if ur_type != TRON_SIGNATURE_TYPE {
+            return Err(format!("Expected a {} UR, got {}", TRON_SIGNATURE_TYPE, ur_type));
+        }
+        if body.contains('/') {
+            return Err("Multi-part URs need a decoder".to_string());
+        }
+        let cbor = bytewords::decode(body, &Style::Minimal).map_err(|e| e.to_string())?;
+        TronSignature::try_from(cbor).map_err(|e| {
+            telemetry::decode_failure(TRON_SIGNATURE_TYPE, &e);
+            e.to_string()
+        })
+    }
 }
 
 impl TryFrom<Vec<u8>> for TronSignature {
-    type Error = String;
+    type Error = DecodeError;
 
     fn try_from(data: Vec<u8>) -> Result<Self, Self::Error> {
-        let cbor_value: Value = serde_cbor::from_slice(&data)
-            .map_err(|e| format!("Failed to decode CBOR: {}", e))?;
+        let map = decode_map(&data)?;
 
-        if let Value::Map(map) = cbor_value {
-            let request_id = map.get(&Value::Integer(REQUEST_ID))
-                .and_then(|v| if let Value::Bytes(b) = v { Some(b.clone()) } else { None });
+        let version = decode_version(map.get(&Value::Integer(VERSION)))?;
 
-            let signature = map.get(&Value::Integer(SIGNATURE))
-                .and_then(|v| if let Value::Bytes(b) = v { Some(b.clone()) } else { None })
-                .ok_or("Missing signature")?;
+        let request_id = get_bytes(&map, REQUEST_ID, "request id")?;
 
-            Ok(TronSignature {
-                request_id,
-                signature,
-            })
-        } else {
-            Err("Expected CBOR map".to_string())
-        }
+        let signature = get_bytes(&map, SIGNATURE, "signature")?
+            .ok_or(DecodeError::MissingField("signature"))?;
+
+        let public_key = get_bytes(&map, PUBLIC_KEY, "public key")?;
+
+        let salt = get_bytes(&map, SALT, "salt")?;
+
+        let salt_proof = get_bytes(&map, SALT_PROOF, "salt proof")?;
+
+        Ok(TronSignature {
+            request_id,
+            signature,
+            version,
+            public_key,
+            salt,
+            salt_proof,
+            unknown: unknown_entries(&map, &KNOWN_KEYS),
+        })
     }
 }
 
+// Base58 address of the key that made the 65-byte `signature` over `digest`
+fn recover_signer(digest: &[u8; 32], signature: &[u8]) -> Result<String, String> {
+    if signature.len() != RECOVERABLE_SIGNATURE_LEN {
+        return Err(format!("Expected a {}-byte signature, got {}", RECOVERABLE_SIGNATURE_LEN, signature.len()));
+    }
+    let v = signature[COMPACT_SIGNATURE_LEN];
+    let recovery_id = RecoveryId::from_i32(v.checked_sub(RECOVERY_ID_OFFSET).unwrap_or(v) as i32)
+        .map_err(|e| format!("Invalid recovery id: {}", e))?;
+    let signature = RecoverableSignature::from_compact(&signature[..COMPACT_SIGNATURE_LEN], recovery_id)
+        .map_err(|e| e.to_string())?;
+    let message = Message::from_slice(digest).map_err(|e| e.to_string())?;
+    let public_key = Secp256k1::verification_only()
+        .recover_ecdsa(&message, &signature)
+        .map_err(|e| e.to_string())?;
+    encode_address(&public_key_to_address(&public_key.serialize())?)
+}
+
+/// Base58 address of the key that produced `signature` over `request`
+pub fn recover_address(request: &TronSignRequest, signature: &TronSignature) -> Result<String, String> {
+    recover_signer(&signing_hash(request)?, signature.get_signature())
+}
+
 // ========== FFI Functions ==========
 
 pub fn resolve(data: Vec<u8>) -> PtrResponse {
     match TronSignature::try_from(data) {
         Ok(result) => Response::success_object(Box::into_raw(Box::new(result)) as PtrVoid).c_ptr(),
-        Err(error) => Response::error(error.to_string()).c_ptr(),
+        Err(error) => {
+            telemetry::decode_failure(TRON_SIGNATURE_TYPE, &error);
+            Response::error(error.to_string()).c_ptr()
+        }
+    }
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_signature_to_transport_string(tron_signature: &TronSignature) -> PtrResponse {
+    match tron_signature.to_transport_string() {
+        Ok(transport) => Response::success_string(transport),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_signature_from_transport_string(transport: PtrString) -> PtrResponse {
+    let transport = match convert_ptr_string_to_string(transport).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    match TronSignature::from_transport_string(&transport) {
+        Ok(signature) => Response::success_object(Box::into_raw(Box::new(signature)) as PtrVoid),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+/// Strips the scheme, checks the type, bytewords-decodes and resolves a
+/// scanned `ur:tron-signature/…` in one call
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_signature_from_ur_string(ur: PtrString) -> PtrResponse {
+    let ur = match convert_ptr_string_to_string(ur).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    match TronSignature::from_ur_string(&ur) {
+        Ok(signature) => Response::success_object(Box::into_raw(Box::new(signature)) as PtrVoid),
+        Err(e) => Response::error(e),
     }
+    .c_ptr()
 }
 
+#[cfg(feature = "ffi")]
 #[no_mangle]
-pub extern "C" fn tron_signature_get_signature(tron_signature: &mut TronSignature) -> PtrResponse {
+pub extern "C" fn tron_signature_get_signature(tron_signature: &TronSignature) -> PtrResponse {
     Response::success_string(hex::encode(tron_signature.get_signature())).c_ptr()
 }
 
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_signature_get_request_id(tron_signature: &TronSignature) -> PtrResponse {
+    tron_signature
+        .get_request_id()
+        .map_or(Response::not_present(), |id| Response::success_string(hex::encode(id)))
+        .c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_signature_get_version(tron_signature: &TronSignature) -> PtrResponse {
+    tron_signature
+        .get_version()
+        .map_or(Response::not_present(), Response::success_uint32)
+        .c_ptr()
+}
+
+/// `NOT_PRESENT` when the signer didn't include its key
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_signature_get_public_key(tron_signature: &TronSignature) -> PtrResponse {
+    tron_signature
+        .get_public_key()
+        .map_or(Response::not_present(), |key| Response::success_string(hex::encode(key)))
+        .c_ptr()
+}
+
+/// An empty string clears the key
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_signature_set_public_key(tron_signature: &mut TronSignature, public_key: PtrString) -> PtrResponse {
+    let public_key = match parse_ptr_string_to_bytes(public_key).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    match tron_signature.set_public_key(Some(public_key).filter(|key| !key.is_empty())) {
+        Ok(()) => Response::success_null(),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_signature_get_signer_address(tron_signature: &TronSignature) -> PtrResponse {
+    match tron_signature.get_signer_address() {
+        Ok(address) => address.map_or(Response::not_present(), Response::success_string),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+/// `NOT_PRESENT` when the request has no address or the signature no public key
+#[cfg(feature = "ffi")]
 #[no_mangle]
-pub extern "C" fn tron_signature_get_request_id(tron_signature: &mut TronSignature) -> PtrResponse {
-    match tron_signature.get_request_id() {
-        Some(v) => Response::success_string(hex::encode(v)).c_ptr(),
-        None => Response::error(format!("No request id supplied")).c_ptr()
+pub extern "C" fn tron_signature_check_signer(
+    tron_signature: &TronSignature,
+    tron_sign_request: &TronSignRequest,
+) -> PtrResponse {
+    match tron_signature.check_signer(tron_sign_request) {
+        Ok(matches) => matches.map_or(Response::not_present(), Response::success_boolean),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+/// `salt` is hex; an empty string clears the field
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_signature_set_salt(tron_signature: &mut TronSignature, salt: PtrString) -> PtrResponse {
+    let salt = match parse_ptr_string_to_bytes(salt).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    tron_signature.set_salt(Some(salt).filter(|salt| !salt.is_empty()));
+    Response::success_null().c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_signature_get_salt(tron_signature: &TronSignature) -> PtrResponse {
+    tron_signature
+        .get_salt()
+        .map_or(Response::not_present(), |salt| Response::success_string(hex::encode(salt)))
+        .c_ptr()
+}
+
+/// `salt_proof` is hex; an empty string clears the field
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_signature_set_salt_proof(tron_signature: &mut TronSignature, salt_proof: PtrString) -> PtrResponse {
+    let salt_proof = match parse_ptr_string_to_bytes(salt_proof).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    tron_signature.set_salt_proof(Some(salt_proof).filter(|proof| !proof.is_empty()));
+    Response::success_null().c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_signature_get_salt_proof(tron_signature: &TronSignature) -> PtrResponse {
+    tron_signature
+        .get_salt_proof()
+        .map_or(Response::not_present(), |proof| Response::success_string(hex::encode(proof)))
+        .c_ptr()
+}
+
+/// Hex digest a signer signs with the request's key to prove the salt
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_signature_salt_proof_hash(tron_sign_request: &TronSignRequest) -> PtrResponse {
+    match salt_proof_hash(tron_sign_request) {
+        Ok(digest) => Response::success_string(hex::encode(digest)),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_signature_check_salt(tron_signature: &TronSignature, tron_sign_request: &TronSignRequest) -> PtrResponse {
+    Response::success_boolean(tron_signature.check_salt(tron_sign_request)).c_ptr()
+}
+
+/// `request_id` is optional hex; `recovery_id` may be 0/1 or 27/28
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_signature_from_components(request_id: PtrString, r: PtrString, s: PtrString, recovery_id: u32) -> PtrResponse {
+    let request_id = match parse_ptr_string_to_bytes(request_id).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    let r = match parse_ptr_string_to_bytes(r).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    let s = match parse_ptr_string_to_bytes(s).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    let request_id = Some(request_id).filter(|id| !id.is_empty());
+    let recovery_id = match u8::try_from(recovery_id) {
+        Ok(v) => v,
+        Err(_) => return Response::error(format!("Invalid recovery id: {}", recovery_id)).c_ptr(),
+    };
+    match TronSignature::from_components(request_id, &r, &s, recovery_id) {
+        Ok(signature) => Response::success_object(Box::into_raw(Box::new(signature)) as PtrVoid),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_signature_get_r(tron_signature: &TronSignature) -> PtrResponse {
+    match tron_signature.get_r() {
+        Ok(r) => Response::success_string(hex::encode(r)),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_signature_get_s(tron_signature: &TronSignature) -> PtrResponse {
+    match tron_signature.get_s() {
+        Ok(s) => Response::success_string(hex::encode(s)),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+/// 0 or 1
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_signature_get_recovery_id(tron_signature: &TronSignature) -> PtrResponse {
+    match tron_signature.get_recovery_id() {
+        Ok(v) => Response::success_uint32(v as u32),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_signature_is_low_s(tron_signature: &TronSignature) -> PtrResponse {
+    match tron_signature.is_low_s() {
+        Ok(low_s) => Response::success_boolean(low_s),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+/// Normalizes in place; returns whether the signature changed
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_signature_normalize_s(tron_signature: &mut TronSignature) -> PtrResponse {
+    match tron_signature.normalize_s() {
+        Ok(changed) => Response::success_boolean(changed),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Whether a malformed fixture failed the way it should
+    type ErrorCheck = fn(&DecodeError) -> bool;
+
+    fn fixture(name: &str) -> Vec<u8> {
+        let path = format!("{}/fixtures/tron_signature/{}.hex", env!("CARGO_MANIFEST_DIR"), name);
+        hex::decode(std::fs::read_to_string(path).unwrap().trim()).unwrap()
+    }
+
+    #[test]
+    fn test_malformed_fixtures() {
+        let cases: Vec<(&str, ErrorCheck)> = vec![
+            ("truncated", |e| matches!(e, DecodeError::InvalidCbor(_))),
+            ("trailing_bytes", |e| matches!(e, DecodeError::TrailingBytes(_))),
+            ("missing_signature", |e| *e == DecodeError::MissingField("signature")),
+            ("signature_text", |e| matches!(e, DecodeError::InvalidType { field: "signature", .. })),
+            ("request_id_text", |e| matches!(e, DecodeError::InvalidType { field: "request id", .. })),
+        ];
+        for (name, expected) in cases {
+            let error = TronSignature::try_from(fixture(name)).unwrap_err();
+            assert!(expected(&error), "{}: {:?}", name, error);
+        }
+    }
+
+    #[test]
+    fn test_signature_components() {
+        use crate::tron::test_signer::TestSigner;
+
+        // secp256k1 curve order
+        let order = hex::decode("fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364141").unwrap();
+        let signer = TestSigner::new(&[1; 32]).unwrap();
+        let request = TronSignRequest::new_with_derived_request_id(
+            vec![0x0a, 0x02], 1, "m/44'/195'/0'/0/0".to_string(), None, None, None,
+        );
+        let low = signer.sign(&request).unwrap();
+        assert!(low.is_low_s().unwrap());
+
+        // s' = n - s, with v flipped
+        let mut high_bytes = low.get_signature().clone();
+        let mut borrow = 0i16;
+        for i in (0..32).rev() {
+            let diff = order[i] as i16 - high_bytes[32 + i] as i16 - borrow;
+            high_bytes[32 + i] = diff.rem_euclid(256) as u8;
+            borrow = (diff < 0) as i16;
+        }
+        high_bytes[64] = flip_recovery_id(high_bytes[64]);
+        let mut high = TronSignature::new(low.get_request_id().cloned(), high_bytes);
+        assert!(!high.is_low_s().unwrap());
+        assert_eq!(recover_address(&request, &high).unwrap(), signer.get_address().unwrap());
+
+        assert!(high.normalize_s().unwrap());
+        assert_eq!(high.get_signature(), low.get_signature());
+        assert!(!high.normalize_s().unwrap());
+
+        let assembled = TronSignature::from_components(None, low.get_r().unwrap(), low.get_s().unwrap(), low.get_recovery_id().unwrap()).unwrap();
+        assert_eq!(assembled.get_signature(), low.get_signature());
+        assert!(TronSignature::from_components(None, &[0; 31], &[0; 32], 0).is_err());
+        assert!(TronSignature::from_components(None, &[0; 32], &[0; 32], 2).is_err());
+
+        // The signer's public key survives the round trip and names the account
+        let decoded = TronSignature::try_from(low.to_bytes().unwrap()).unwrap();
+        assert_eq!(decoded.get_public_key(), Some(&signer.get_public_key()));
+        assert_eq!(decoded.get_signer_address().unwrap(), Some(signer.get_address().unwrap()));
+        let addressed = TronSignRequest::new_with_derived_request_id(
+            vec![0x0a, 0x02], 1, "m/44'/195'/0'/0/0".to_string(), None, Some(encode_address(&[0x41; 21]).unwrap()), None,
+        );
+        assert_eq!(decoded.check_signer(&addressed).unwrap(), Some(false));
+        assert_eq!(assembled.check_signer(&addressed).unwrap(), None);
+        assert!(assembled.clone().set_public_key(Some(vec![5; 33])).is_err());
+        assert!(TronSignature::new(None, vec![1; 10]).is_low_s().is_err());
+    }
+
+    #[test]
+    fn test_salt_proof() {
+        use crate::tron::test_signer::TestSigner;
+        let signer = TestSigner::new(&[1; 32]).unwrap();
+        let mut request = TronSignRequest::new(Some(vec![1; 16]), vec![0x0a, 0x02], 1, "m/44'/195'/0'/0/0".to_string(), None, None, None);
+        let unsalted = signer.sign(&request).unwrap();
+        assert!(unsalted.check_salt(&request));
+
+        request.generate_salt().unwrap();
+        let signature = TronSignature::try_from(signer.sign(&request).unwrap().to_bytes().unwrap()).unwrap();
+        assert_eq!(signature.get_salt(), request.get_salt());
+        assert!(signature.check_salt(&request));
+
+        // A replayed signature with the public salt copied on
+        let mut replayed = unsalted.clone();
+        replayed.set_salt(request.get_salt().cloned());
+        assert!(!replayed.check_salt(&request));
+        replayed.set_salt_proof(TestSigner::new(&[2; 32]).unwrap().sign(&request).unwrap().get_salt_proof().cloned());
+        assert!(!replayed.check_salt(&request));
+
+        // Proofs don't carry over to a fresh salt
+        request.generate_salt().unwrap();
+        let mut stale = signature.clone();
+        stale.set_salt(request.get_salt().cloned());
+        assert!(!stale.check_salt(&request));
+    }
+
+    #[test]
+    fn test_from_ur_string() {
+        let signature = TronSignature::new(Some(vec![1; 16]), vec![7; 65]);
+        let ur = ur::ur::encode(&signature.to_bytes().unwrap(), TRON_SIGNATURE_TYPE);
+        let parsed = TronSignature::from_ur_string(&format!(" {} ", ur.to_uppercase())).unwrap();
+        assert_eq!(parsed.get_signature(), signature.get_signature());
+        assert_eq!(parsed.get_request_id(), signature.get_request_id());
+
+        let request = ur::ur::encode(&[0xa0], "tron-sign-request");
+        assert!(TronSignature::from_ur_string(&request).unwrap_err().contains("tron-sign-request"));
+        assert!(TronSignature::from_ur_string("ur:tron-signature/1-2/lpad").is_err());
+        assert!(TronSignature::from_ur_string("tron-signature:abc").is_err());
+    }
+
+    #[cfg(feature = "ffi")]
+    #[test]
+    fn test_absent_fields_not_present() {
+        use crate::response::{NOT_PRESENT, SUCCESS};
+
+        let signature = TronSignature::new(None, vec![1; 65]);
+        for response in [tron_signature_get_request_id(&signature), tron_signature_get_public_key(&signature)] {
+            let response = Response::from_ptr(response);
+            assert_eq!(response.status_code, NOT_PRESENT);
+            assert!(response.error_message.is_null());
+        }
+        let signature = TronSignature::new(Some(vec![7; 16]), vec![1; 65]);
+        assert_eq!(Response::from_ptr(tron_signature_get_request_id(&signature)).status_code, SUCCESS);
     }
 }
@@ -0,0 +1,438 @@
+use crate::response::{PtrResponse, Response};
+use crate::tron::decode::decode_map;
+use crate::tron::schema::{FieldSchema, FieldType, TypeSchema};
+use crate::tron::telemetry;
+use crate::tron::tron_sign_request::{decode_derivation_path, encode_derivation_path};
+use crate::types::{PtrString, PtrVoid};
+use crate::ur_encoder::UREncoder;
+use crate::utils::{convert_ptr_string_to_string, parse_ptr_string_to_bytes};
+
+use serde_cbor::Value;
+use std::collections::BTreeMap;
+
+// CBOR map keys for TronWalletBundle
+const ACCOUNTS: i128 = 1;
+const DEVICE: i128 = 2;
+const MASTER_FINGERPRINT: i128 = 3;
+// Every key the codec reads and writes, checked against SCHEMA
+#[cfg(test)]
+pub(crate) const KNOWN_KEYS: [i128; 3] = [ACCOUNTS, DEVICE, MASTER_FINGERPRINT];
+
+// CBOR map keys for each bundled account
+const XPUB: i128 = 1;
+const PATH: i128 = 2;
+const LABEL: i128 = 3;
+const TEMPLATE: i128 = 4;
+const WALLET_ID: i128 = 5;
+// Keys of each account, checked against SCHEMA
+#[cfg(test)]
+pub(crate) const ACCOUNT_KNOWN_KEYS: [i128; 5] = [XPUB, PATH, LABEL, TEMPLATE, WALLET_ID];
+
+// Serialized BIP32 extended key length
+const XPUB_LEN: usize = 78;
+
+// UR Type for a TRON watch-only wallet bundle
+pub const TRON_WALLET_BUNDLE_TYPE: &str = "tron-wallet-bundle";
+
+pub(crate) const SCHEMA: TypeSchema = TypeSchema {
+    ur_type: TRON_WALLET_BUNDLE_TYPE,
+    tag: None,
+    fields: &[
+        FieldSchema::required(ACCOUNTS, "accounts", FieldType::ArrayOfMaps).with_fields(&[
+            FieldSchema::required(XPUB, "xpub", FieldType::Text),
+            FieldSchema::required(PATH, "path", FieldType::KeyPath),
+            FieldSchema::optional(LABEL, "label", FieldType::Text),
+            FieldSchema::optional(TEMPLATE, "template", FieldType::Text),
+            FieldSchema::optional(WALLET_ID, "wallet_id", FieldType::Bytes),
+        ]),
+        FieldSchema::optional(DEVICE, "device", FieldType::Text),
+        FieldSchema::optional(MASTER_FINGERPRINT, "master_fingerprint", FieldType::Uint),
+    ],
+};
+
+#[derive(Clone, Debug, Default)]
+pub struct TronBundleAccount {
+    xpub: String,
+    path: String,
+    label: Option<String>,
+    template: Option<String>,
+    wallet_id: Option<Vec<u8>>,
+}
+
+impl TronBundleAccount {
+    pub fn new(xpub: String, path: String, label: Option<String>, template: Option<String>) -> Result<Self, String> {
+        let decoded = bs58::decode(&xpub)
+            .with_check(None)
+            .into_vec()
+            .map_err(|e| format!("Invalid xpub: {}", e))?;
+        if decoded.len() != XPUB_LEN {
+            return Err("Invalid xpub length".to_string());
+        }
+        // Validates the path up front rather than at encode time
+        encode_derivation_path(&path, None)?;
+        Ok(TronBundleAccount {
+            xpub,
+            path,
+            label,
+            template,
+            wallet_id: None,
+        })
+    }
+
+    pub fn get_xpub(&self) -> &str {
+        &self.xpub
+    }
+
+    pub fn get_path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn get_label(&self) -> Option<&String> {
+        self.label.as_ref()
+    }
+
+    /// Address derivation template below the account, e.g. `m/44'/195'/0'/0/*`
+    pub fn get_template(&self) -> Option<&String> {
+        self.template.as_ref()
+    }
+
+    /// Seed the account belongs to, for devices holding several
+    pub fn get_wallet_id(&self) -> Option<&Vec<u8>> {
+        self.wallet_id.as_ref()
+    }
+
+    pub fn set_wallet_id(&mut self, wallet_id: Option<Vec<u8>>) {
+        self.wallet_id = wallet_id;
+    }
+
+    fn to_cbor(&self) -> Result<Value, String> {
+        let mut map: BTreeMap<Value, Value> = BTreeMap::new();
+        map.insert(Value::Integer(XPUB), Value::Text(self.xpub.clone()));
+        map.insert(Value::Integer(PATH), encode_derivation_path(&self.path, None)?);
+        if let Some(ref label) = self.label {
+            map.insert(Value::Integer(LABEL), Value::Text(label.clone()));
+        }
+        if let Some(ref template) = self.template {
+            map.insert(Value::Integer(TEMPLATE), Value::Text(template.clone()));
+        }
+        if let Some(ref wallet_id) = self.wallet_id {
+            map.insert(Value::Integer(WALLET_ID), Value::Bytes(wallet_id.clone()));
+        }
+        Ok(Value::Map(map))
+    }
+
+    fn from_cbor(value: &Value) -> Result<Self, String> {
+        if let Value::Map(map) = value {
+            let xpub = map.get(&Value::Integer(XPUB))
+                .and_then(|v| if let Value::Text(s) = v { Some(s.clone()) } else { None })
+                .ok_or("Missing xpub")?;
+
+            let path = match map.get(&Value::Integer(PATH)) {
+                Some(v) => decode_derivation_path(Some(v))?,
+                None => return Err("Missing account path".to_string()),
+            };
+
+            let label = map.get(&Value::Integer(LABEL))
+                .and_then(|v| if let Value::Text(s) = v { Some(s.clone()) } else { None });
+
+            let template = map.get(&Value::Integer(TEMPLATE))
+                .and_then(|v| if let Value::Text(s) = v { Some(s.clone()) } else { None });
+
+            let wallet_id = map.get(&Value::Integer(WALLET_ID))
+                .and_then(|v| if let Value::Bytes(b) = v { Some(b.clone()) } else { None });
+
+            let mut account = TronBundleAccount::new(xpub, path, label, template)?;
+            account.set_wallet_id(wallet_id);
+            Ok(account)
+        } else {
+            Err("Expected account map".to_string())
+        }
+    }
+}
+
+/// Several watch-only accounts exported in one (multi-part) QR
+#[derive(Clone, Debug, Default)]
+pub struct TronWalletBundle {
+    accounts: Vec<TronBundleAccount>,
+    device: Option<String>,
+    master_fingerprint: Option<u32>,
+}
+
+impl TronWalletBundle {
+    pub fn new(device: Option<String>, master_fingerprint: Option<u32>) -> Self {
+        TronWalletBundle {
+            accounts: vec![],
+            device,
+            master_fingerprint,
+        }
+    }
+
+    pub fn add_account(&mut self, account: TronBundleAccount) {
+        self.accounts.push(account);
+    }
+
+    pub fn get_accounts(&self) -> &Vec<TronBundleAccount> {
+        &self.accounts
+    }
+
+    pub fn get_device(&self) -> Option<&String> {
+        self.device.as_ref()
+    }
+
+    pub fn get_master_fingerprint(&self) -> Option<u32> {
+        self.master_fingerprint
+    }
+
+    /// Serialize to CBOR bytes
+    pub fn to_bytes(&self) -> Result<Vec<u8>, String> {
+        let mut map: BTreeMap<Value, Value> = BTreeMap::new();
+
+        let accounts = self
+            .accounts
+            .iter()
+            .map(|a| a.to_cbor())
+            .collect::<Result<Vec<Value>, String>>()?;
+        map.insert(Value::Integer(ACCOUNTS), Value::Array(accounts));
+
+        if let Some(ref device) = self.device {
+            map.insert(Value::Integer(DEVICE), Value::Text(device.clone()));
+        }
+
+        if let Some(fingerprint) = self.master_fingerprint {
+            map.insert(Value::Integer(MASTER_FINGERPRINT), Value::Integer(fingerprint as i128));
+        }
+
+        serde_cbor::to_vec(&Value::Map(map))
+            .map_err(|e| e.to_string())
+    }
+}
+
+impl TryFrom<Vec<u8>> for TronWalletBundle {
+    type Error = String;
+
+    fn try_from(data: Vec<u8>) -> Result<Self, Self::Error> {
+        let map = decode_map(&data)?;
+
+        let accounts = match map.get(&Value::Integer(ACCOUNTS)) {
+            Some(Value::Array(arr)) => arr
+                .iter()
+                .map(TronBundleAccount::from_cbor)
+                .collect::<Result<Vec<TronBundleAccount>, String>>()?,
+            _ => return Err("Missing accounts".to_string()),
+        };
+
+        let device = map.get(&Value::Integer(DEVICE))
+            .and_then(|v| if let Value::Text(s) = v { Some(s.clone()) } else { None });
+
+        let master_fingerprint = map.get(&Value::Integer(MASTER_FINGERPRINT))
+            .and_then(|v| if let Value::Integer(i) = v { u32::try_from(*i).ok() } else { None });
+
+        Ok(TronWalletBundle {
+            accounts,
+            device,
+            master_fingerprint,
+        })
+    }
+}
+
+// ========== FFI Functions ==========
+
+pub fn resolve(data: Vec<u8>) -> PtrResponse {
+    match TronWalletBundle::try_from(data) {
+        Ok(result) => Response::success_object(Box::into_raw(Box::new(result)) as PtrVoid).c_ptr(),
+        Err(error) => {
+            telemetry::decode_failure_message(TRON_WALLET_BUNDLE_TYPE, &error);
+            Response::error(error.to_string()).c_ptr()
+        }
+    }
+}
+
+/// `master_fingerprint` of 0 is treated as absent
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_wallet_bundle_new(device: PtrString, master_fingerprint: u32) -> PtrResponse {
+    let device = match convert_ptr_string_to_string(device).map_err(|e| Response::error(e)) {
+        Ok(v) => Some(v).filter(|s| !s.is_empty()),
+        Err(e) => return e.c_ptr(),
+    };
+    let bundle = TronWalletBundle::new(device, Some(master_fingerprint).filter(|f| *f != 0));
+    Response::success_object(Box::into_raw(Box::new(bundle)) as PtrVoid).c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_wallet_bundle_add_account(
+    tron_wallet_bundle: &mut TronWalletBundle,
+    xpub: PtrString,
+    path: PtrString,
+    label: PtrString,
+    template: PtrString,
+) -> PtrResponse {
+    let xpub = match convert_ptr_string_to_string(xpub).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    let path = match convert_ptr_string_to_string(path).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    let label = match convert_ptr_string_to_string(label).map_err(|e| Response::error(e)) {
+        Ok(v) => Some(v).filter(|s| !s.is_empty()),
+        Err(e) => return e.c_ptr(),
+    };
+    let template = match convert_ptr_string_to_string(template).map_err(|e| Response::error(e)) {
+        Ok(v) => Some(v).filter(|s| !s.is_empty()),
+        Err(e) => return e.c_ptr(),
+    };
+    match TronBundleAccount::new(xpub, path, label, template) {
+        Ok(account) => {
+            tron_wallet_bundle.add_account(account);
+            Response::success_null()
+        }
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_wallet_bundle_get_ur_encoder(tron_wallet_bundle: &TronWalletBundle) -> PtrResponse {
+    match tron_wallet_bundle.to_bytes() {
+        Ok(message) => match UREncoder::new(message.as_slice(), 400, TRON_WALLET_BUNDLE_TYPE) {
+            Ok(ur_encoder) => Response::success_object(Box::into_raw(Box::new(ur_encoder)) as PtrVoid),
+            Err(e) => Response::error(e),
+        },
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_wallet_bundle_get_device(tron_wallet_bundle: &TronWalletBundle) -> PtrResponse {
+    tron_wallet_bundle
+        .get_device()
+        .map_or(Response::not_present(), |v| Response::success_string(v.clone()))
+        .c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_wallet_bundle_get_master_fingerprint(tron_wallet_bundle: &TronWalletBundle) -> PtrResponse {
+    tron_wallet_bundle
+        .get_master_fingerprint()
+        .map_or(Response::not_present(), |v| Response::success_string(hex::encode(v.to_be_bytes())))
+        .c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_wallet_bundle_get_accounts_len(tron_wallet_bundle: &TronWalletBundle) -> PtrResponse {
+    Response::success_uint32(tron_wallet_bundle.get_accounts().len() as u32).c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_wallet_bundle_get_account(tron_wallet_bundle: &TronWalletBundle, index: u32) -> PtrResponse {
+    match tron_wallet_bundle.get_accounts().get(index as usize) {
+        Some(account) => {
+            Response::success_object(Box::into_raw(Box::new(account.clone())) as PtrVoid).c_ptr()
+        }
+        None => Response::error(format!("No account for index {} was found", index)).c_ptr(),
+    }
+}
+
+/// `wallet_id` is hex; an empty string clears the field
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_wallet_bundle_set_account_wallet_id(
+    tron_wallet_bundle: &mut TronWalletBundle,
+    index: u32,
+    wallet_id: PtrString,
+) -> PtrResponse {
+    let wallet_id = match parse_ptr_string_to_bytes(wallet_id).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    match tron_wallet_bundle.accounts.get_mut(index as usize) {
+        Some(account) => {
+            account.set_wallet_id(Some(wallet_id).filter(|id| !id.is_empty()));
+            Response::success_null().c_ptr()
+        }
+        None => Response::error(format!("No account for index {} was found", index)).c_ptr(),
+    }
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_bundle_account_get_xpub(tron_bundle_account: &TronBundleAccount) -> PtrResponse {
+    Response::success_string(tron_bundle_account.get_xpub().to_string()).c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_bundle_account_get_path(tron_bundle_account: &TronBundleAccount) -> PtrResponse {
+    Response::success_string(tron_bundle_account.get_path().to_string()).c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_bundle_account_get_label(tron_bundle_account: &TronBundleAccount) -> PtrResponse {
+    tron_bundle_account
+        .get_label()
+        .map_or(Response::not_present(), |v| Response::success_string(v.clone()))
+        .c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_bundle_account_get_template(tron_bundle_account: &TronBundleAccount) -> PtrResponse {
+    tron_bundle_account
+        .get_template()
+        .map_or(Response::not_present(), |v| Response::success_string(v.clone()))
+        .c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_bundle_account_get_wallet_id(tron_bundle_account: &TronBundleAccount) -> PtrResponse {
+    tron_bundle_account
+        .get_wallet_id()
+        .map_or(Response::not_present(), |v| Response::success_string(hex::encode(v)))
+        .c_ptr()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // BIP32 test vector 1, master key
+    const XPUB: &str = "xpub661MyMwAqRbcFtXgS5sYJABqqG9YLmC4Q1Rdap9gSE8NqtwybGhePY2gZ29ESFjqJoCu1Rupje8YtGqsefD265TMg7usUDFdp6W1EGMcet8";
+
+    #[test]
+    fn test_bundle_round_trip() {
+        let mut bundle = TronWalletBundle::new(Some("Keystone".to_string()), Some(0x12345678));
+        bundle.add_account(TronBundleAccount::new(
+            XPUB.to_string(),
+            "m/44'/195'/0'".to_string(),
+            Some("Main".to_string()),
+            Some("m/44'/195'/0'/0/*".to_string()),
+        ).unwrap());
+        let mut passphrase_account = TronBundleAccount::new(XPUB.to_string(), "m/44'/195'/1'".to_string(), None, None).unwrap();
+        passphrase_account.set_wallet_id(Some(vec![0xaa; 8]));
+        bundle.add_account(passphrase_account);
+
+        let decoded = TronWalletBundle::try_from(bundle.to_bytes().unwrap()).unwrap();
+        assert_eq!(decoded.get_device().unwrap(), "Keystone");
+        assert_eq!(decoded.get_master_fingerprint(), Some(0x12345678));
+        assert_eq!(decoded.get_accounts().len(), 2);
+        assert_eq!(decoded.get_accounts()[0].get_label().unwrap(), "Main");
+        assert_eq!(decoded.get_accounts()[0].get_template().unwrap(), "m/44'/195'/0'/0/*");
+        assert_eq!(decoded.get_accounts()[1].get_path(), "m/44'/195'/1'");
+        assert!(decoded.get_accounts()[1].get_label().is_none());
+        assert!(decoded.get_accounts()[0].get_wallet_id().is_none());
+        assert_eq!(decoded.get_accounts()[1].get_wallet_id(), Some(&vec![0xaa; 8]));
+
+        assert!(TronBundleAccount::new("not-an-xpub".to_string(), "m/44'/195'/0'".to_string(), None, None).is_err());
+    }
+}
@@ -0,0 +1,208 @@
+//! TronLink offline-signing JSON, the TronWeb transaction object a watch-only
+//! TronLink exports and its cold wallet signs:
+//!
+//! ```text
+//! {"txID": "<hex>", "raw_data": {...}, "raw_data_hex": "<hex>", "visible": false, "signature": ["<hex>"]}
+//! ```
+//!
+//! Only `raw_data_hex` is signed; `raw_data` is carried along untouched.
+
+use crate::response::{PtrResponse, Response};
+use crate::tron::address::{decode_address, encode_address};
+use crate::tron::tron_sign_request::{DataType, TronSignRequest};
+use crate::tron::tron_signature::TronSignature;
+use crate::types::{PtrString, PtrVoid};
+use crate::utils::convert_ptr_string_to_string;
+
+use serde_json::{json, Map, Value};
+use sha2::{Digest, Sha256};
+
+const DEFAULT_PATH: &str = "m/44'/195'/0'/0/0";
+
+fn parse_transaction(transaction: &str) -> Result<Map<String, Value>, String> {
+    match serde_json::from_str(transaction).map_err(|e| format!("Invalid TronLink JSON: {}", e))? {
+        Value::Object(map) => Ok(map),
+        _ => Err("TronLink JSON must be an object".to_string()),
+    }
+}
+
+/// raw_data_hex, checked against txID when one is present
+fn raw_data(transaction: &Map<String, Value>) -> Result<Vec<u8>, String> {
+    let raw_data = transaction.get("raw_data_hex").and_then(|v| v.as_str()).ok_or("Missing raw_data_hex")?;
+    let raw_data = hex::decode(raw_data).map_err(|e| format!("Invalid raw_data_hex: {}", e))?;
+    if let Some(tx_id) = transaction.get("txID").and_then(|v| v.as_str()) {
+        if !tx_id.eq_ignore_ascii_case(&hex::encode(Sha256::digest(&raw_data))) {
+            return Err("txID does not match raw_data_hex".to_string());
+        }
+    }
+    Ok(raw_data)
+}
+
+/// Owner of the first contract, hex (`41…`) or base58 when `visible`
+fn owner_address(transaction: &Map<String, Value>) -> Result<Option<String>, String> {
+    let owner = transaction
+        .get("raw_data")
+        .and_then(|v| v.pointer("/contract/0/parameter/value/owner_address"))
+        .and_then(|v| v.as_str());
+    match owner {
+        Some(owner) if owner.starts_with('T') => decode_address(owner).map(|_| Some(owner.to_string())),
+        Some(owner) => {
+            let bytes = hex::decode(owner).map_err(|e| format!("Invalid owner_address: {}", e))?;
+            encode_address(&bytes).map(Some)
+        }
+        None => Ok(None),
+    }
+}
+
+/// Transaction request for a TronLink unsigned transaction. `path` defaults
+/// to the first TRON account; the address is taken from the first contract.
+pub fn request_from_tronlink(transaction: &str, path: Option<String>, xfp: Option<u32>) -> Result<TronSignRequest, String> {
+    let transaction = parse_transaction(transaction)?;
+    let sign_data = raw_data(&transaction)?;
+    let address = owner_address(&transaction)?;
+    let path = path.unwrap_or_else(|| DEFAULT_PATH.to_string());
+    Ok(TronSignRequest::new_with_derived_request_id(
+        sign_data,
+        DataType::Transaction.to_u32(),
+        path,
+        xfp,
+        address,
+        Some("TronLink".to_string()),
+    ))
+}
+
+/// Unsigned TronLink transaction for a transaction request. `raw_data` can't
+/// be rebuilt without decoding the protobuf, so only the hex form is set.
+pub fn request_to_tronlink(request: &TronSignRequest) -> Result<String, String> {
+    if !matches!(DataType::from_u32(request.get_data_type())?, DataType::Transaction) {
+        return Err("Only transaction requests have a TronLink form".to_string());
+    }
+    let sign_data = request.get_sign_data();
+    let transaction = json!({
+        "txID": hex::encode(Sha256::digest(sign_data)),
+        "raw_data_hex": hex::encode(sign_data),
+        "visible": false,
+    });
+    Ok(transaction.to_string())
+}
+
+/// `transaction` with `signature` appended, as TronLink broadcasts it
+pub fn signature_to_tronlink(transaction: &str, signature: &TronSignature) -> Result<String, String> {
+    let mut transaction = parse_transaction(transaction)?;
+    raw_data(&transaction)?;
+    let signatures = transaction
+        .entry("signature")
+        .or_insert_with(|| Value::Array(vec![]))
+        .as_array_mut()
+        .ok_or("signature must be an array")?;
+    signatures.push(Value::String(hex::encode(signature.get_signature())));
+    Ok(Value::Object(transaction).to_string())
+}
+
+/// Last signature of a signed TronLink transaction, as the answer to `request`
+pub fn signature_from_tronlink(transaction: &str, request: &TronSignRequest) -> Result<TronSignature, String> {
+    let transaction = parse_transaction(transaction)?;
+    if raw_data(&transaction)? != *request.get_sign_data() {
+        return Err("Transaction does not match the request".to_string());
+    }
+    let signature = transaction
+        .get("signature")
+        .and_then(|v| v.as_array())
+        .and_then(|signatures| signatures.last())
+        .and_then(|v| v.as_str())
+        .ok_or("Transaction is not signed")?;
+    let signature = hex::decode(signature).map_err(|e| format!("Invalid signature: {}", e))?;
+    // TronLink never saw the salt, so the signature can't prove it
+    Ok(TronSignature::new(request.get_request_id().cloned(), signature))
+}
+
+// ========== FFI Functions ==========
+
+/// Empty path uses the default; xfp 0 means none
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_request_from_tronlink(transaction: PtrString, path: PtrString, xfp: u32) -> PtrResponse {
+    let transaction = match convert_ptr_string_to_string(transaction).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    let path = match convert_ptr_string_to_string(path).map_err(|e| Response::error(e)) {
+        Ok(v) => Some(v).filter(|s| !s.is_empty()),
+        Err(e) => return e.c_ptr(),
+    };
+    match request_from_tronlink(&transaction, path, Some(xfp).filter(|x| *x != 0)) {
+        Ok(request) => Response::success_object(Box::into_raw(Box::new(request)) as PtrVoid),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_request_to_tronlink(tron_sign_request: &TronSignRequest) -> PtrResponse {
+    match request_to_tronlink(tron_sign_request) {
+        Ok(transaction) => Response::success_string(transaction),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_signature_to_tronlink(tron_signature: &TronSignature, transaction: PtrString) -> PtrResponse {
+    let transaction = match convert_ptr_string_to_string(transaction).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    match signature_to_tronlink(&transaction, tron_signature) {
+        Ok(transaction) => Response::success_string(transaction),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_signature_from_tronlink(transaction: PtrString, tron_sign_request: &TronSignRequest) -> PtrResponse {
+    let transaction = match convert_ptr_string_to_string(transaction).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    match signature_from_tronlink(&transaction, tron_sign_request) {
+        Ok(signature) => Response::success_object(Box::into_raw(Box::new(signature)) as PtrVoid),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let raw_data = "0a0207902208e1b9de559665c6714080c49789bb2c5a65080112610a2d747970652e676f6f676c65617069732e636f6d2f70726f746f636f6c2e5472616e73666572436f6e747261637412300a1541a614f803b6fd780986a42c78ec9c7f77e6ded13c121541d4e6d1b2f1a2a3a4a5a6a7a8a9aaabacadaeafb0180a70ffa89489bb2c";
+        let transaction = json!({
+            "txID": hex::encode(Sha256::digest(hex::decode(raw_data).unwrap())),
+            "raw_data": {"contract": [{"parameter": {"value": {"owner_address": "41a614f803b6fd780986a42c78ec9c7f77e6ded13c"}}}]},
+            "raw_data_hex": raw_data,
+            "visible": false,
+        })
+        .to_string();
+
+        let request = request_from_tronlink(&transaction, None, Some(0x12345678)).unwrap();
+        assert_eq!(hex::encode(request.get_sign_data()), raw_data);
+        assert_eq!(request.get_address().map(String::as_str), Some("TR7NHqjeKQxGTCi8q8ZY4pL8otSzgjLj6t"));
+        assert!(request_to_tronlink(&request).unwrap().contains(raw_data));
+
+        let signature = TronSignature::new(request.get_request_id().cloned(), vec![7; 65]);
+        let signed = signature_to_tronlink(&transaction, &signature).unwrap();
+        assert!(signed.contains("owner_address"));
+        let decoded = signature_from_tronlink(&signed, &request).unwrap();
+        assert_eq!(decoded.get_signature(), signature.get_signature());
+        assert_eq!(decoded.get_request_id(), request.get_request_id());
+
+        let tampered = transaction.replace("\"txID\":\"", "\"txID\":\"00");
+        assert!(request_from_tronlink(&tampered, None, None).is_err());
+    }
+}
@@ -0,0 +1,160 @@
+//! Structural validation of TIP-712 typed data (the EIP-712 JSON layout),
+//! so malformed dApp payloads fail when the QR is generated rather than on
+//! the signer.
+
+use crate::response::{PtrResponse, Response};
+use crate::tron::tron_sign_request::{DataType, TronSignRequest};
+
+use serde_json::{json, Map, Value};
+use std::fmt;
+
+const DOMAIN_TYPE: &str = "EIP712Domain";
+
+/// Why typed data was rejected, and where: `path` is a dotted location such
+/// as `types.Mail[1].type`
+#[derive(Clone, Debug, PartialEq)]
+pub struct TypedDataError {
+    pub path: String,
+    pub kind: TypedDataErrorKind,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum TypedDataErrorKind {
+    InvalidJson(String),
+    MissingField,
+    InvalidType { expected: &'static str },
+    /// A member type that is neither a Solidity type nor declared in `types`
+    UnknownType(String),
+}
+
+impl fmt::Display for TypedDataError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            TypedDataErrorKind::InvalidJson(e) => write!(f, "Invalid typed data JSON: {}", e),
+            TypedDataErrorKind::MissingField => write!(f, "Missing {}", self.path),
+            TypedDataErrorKind::InvalidType { expected } => write!(f, "Invalid {}: expected {}", self.path, expected),
+            TypedDataErrorKind::UnknownType(t) => write!(f, "Unknown type {} at {}", t, self.path),
+        }
+    }
+}
+
+impl From<TypedDataError> for String {
+    fn from(e: TypedDataError) -> Self {
+        e.to_string()
+    }
+}
+
+fn error(path: impl Into<String>, kind: TypedDataErrorKind) -> TypedDataError {
+    TypedDataError { path: path.into(), kind }
+}
+
+fn field<'a>(object: &'a Map<String, Value>, name: &str) -> Result<&'a Value, TypedDataError> {
+    object.get(name).ok_or_else(|| error(name, TypedDataErrorKind::MissingField))
+}
+
+fn object<'a>(value: &'a Value, path: &str) -> Result<&'a Map<String, Value>, TypedDataError> {
+    value.as_object().ok_or_else(|| error(path, TypedDataErrorKind::InvalidType { expected: "object" }))
+}
+
+fn string<'a>(value: Option<&'a Value>, path: &str) -> Result<&'a str, TypedDataError> {
+    match value {
+        Some(Value::String(s)) => Ok(s),
+        Some(_) => Err(error(path, TypedDataErrorKind::InvalidType { expected: "string" })),
+        None => Err(error(path, TypedDataErrorKind::MissingField)),
+    }
+}
+
+fn is_solidity_type(t: &str) -> bool {
+    let sized = |prefix: &str, max: u32, step: u32| {
+        t.strip_prefix(prefix)
+            .map(|size| size.is_empty() || size.parse::<u32>().is_ok_and(|n| n > 0 && n <= max && n % step == 0))
+            .unwrap_or(false)
+    };
+    matches!(t, "address" | "bool" | "string" | "bytes" | "trcToken")
+        || sized("uint", 256, 8)
+        || sized("int", 256, 8)
+        || (t != "bytes" && sized("bytes", 32, 1))
+}
+
+/// Checks the `types`, `primaryType`, `domain` and `message` members and
+/// that every referenced type is declared. Values aren't type-checked.
+pub fn validate_typed_data(data: &[u8]) -> Result<(), TypedDataError> {
+    let value: Value = serde_json::from_slice(data).map_err(|e| error("", TypedDataErrorKind::InvalidJson(e.to_string())))?;
+    let root = object(&value, "typed data")?;
+    let types = object(field(root, "types")?, "types")?;
+    if !types.contains_key(DOMAIN_TYPE) {
+        return Err(error(format!("types.{}", DOMAIN_TYPE), TypedDataErrorKind::MissingField));
+    }
+    for (name, members) in types.iter() {
+        let path = format!("types.{}", name);
+        let members = members.as_array().ok_or_else(|| error(&path, TypedDataErrorKind::InvalidType { expected: "array" }))?;
+        for (i, member) in members.iter().enumerate() {
+            let path = format!("{}[{}]", path, i);
+            let member = object(member, &path)?;
+            string(member.get("name"), &format!("{}.name", path))?;
+            let member_type = string(member.get("type"), &format!("{}.type", path))?;
+            // Arrays declare their element type before the brackets
+            let base = member_type.split('[').next().unwrap_or_default();
+            if !is_solidity_type(base) && !types.contains_key(base) {
+                return Err(error(format!("{}.type", path), TypedDataErrorKind::UnknownType(member_type.to_string())));
+            }
+        }
+    }
+    let primary_type = string(root.get("primaryType"), "primaryType")?;
+    if !types.contains_key(primary_type) {
+        return Err(error("primaryType", TypedDataErrorKind::UnknownType(primary_type.to_string())));
+    }
+    object(field(root, "domain")?, "domain")?;
+    object(field(root, "message")?, "message")?;
+    Ok(())
+}
+
+/// Validates the sign_data of TypedData requests; other requests pass
+pub fn check_request(request: &TronSignRequest) -> Result<(), TypedDataError> {
+    match DataType::from_u32(request.get_data_type()) {
+        Ok(DataType::TypedData) => validate_typed_data(request.get_sign_data()),
+        _ => Ok(()),
+    }
+}
+
+// ========== FFI Functions ==========
+
+/// Null when valid, otherwise JSON `{"path", "error"}`
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_request_validate_typed_data(tron_sign_request: &TronSignRequest) -> PtrResponse {
+    match check_request(tron_sign_request) {
+        Ok(()) => Response::success_null(),
+        Err(e) => Response::success_string(json!({ "path": e.path, "error": e.to_string() }).to_string()),
+    }
+    .c_ptr()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_typed_data() {
+        let mut typed_data = json!({
+            "types": {
+                "EIP712Domain": [{"name": "name", "type": "string"}, {"name": "chainId", "type": "uint256"}],
+                "Mail": [{"name": "to", "type": "address"}, {"name": "tags", "type": "bytes32[]"}, {"name": "reply", "type": "Mail"}],
+            },
+            "primaryType": "Mail",
+            "domain": {"name": "dApp", "chainId": 728126428},
+            "message": {"to": "TMVQGm1qAQYVdetCeGRRkTWYYrLXuHK2HC"},
+        });
+        assert_eq!(validate_typed_data(typed_data.to_string().as_bytes()), Ok(()));
+
+        typed_data["types"]["Mail"][1]["type"] = json!("uint7");
+        let e = validate_typed_data(typed_data.to_string().as_bytes()).unwrap_err();
+        assert_eq!(e.path, "types.Mail[1].type");
+        assert_eq!(e.kind, TypedDataErrorKind::UnknownType("uint7".to_string()));
+
+        typed_data["types"]["Mail"][1]["type"] = json!("bytes32");
+        typed_data.as_object_mut().unwrap().remove("domain");
+        assert_eq!(validate_typed_data(typed_data.to_string().as_bytes()).unwrap_err().path, "domain");
+        assert!(matches!(validate_typed_data(b"{").unwrap_err().kind, TypedDataErrorKind::InvalidJson(_)));
+    }
+}
@@ -0,0 +1,314 @@
+//! Dry run of every check a signer applies, so wallets find problems before
+//! generating QR frames. Each check reports on its own; a request is valid
+//! when none fails. Warnings are for review screens, not for refusing.
+//!
+//! `validate` runs on a request object; `validate_cbor` runs on the bytes a
+//! signer receives, so the CBOR check covers what is actually on the wire.
+
+use crate::crypto_address::COIN_TYPE_TRON;
+use crate::response::{PtrResponse, Response};
+use crate::tron::address::decode_address;
+#[cfg(feature = "protobuf")]
+use crate::tron::analyze::is_blind_signing;
+#[cfg(feature = "protobuf")]
+use crate::tron::contracts::ContractRegistry;
+use crate::tron::keypath::{KeyPath, PathComponent};
+#[cfg(feature = "protobuf")]
+use crate::tron::transaction::parse_request;
+use crate::tron::tron_sign_request::{DataType, TronSignRequest};
+#[cfg(feature = "json")]
+use crate::types::PtrString;
+#[cfg(feature = "json")]
+use crate::utils::parse_ptr_string_to_bytes;
+
+#[cfg(feature = "json")]
+use serde_json::json;
+
+/// Larger sign_data is refused; also the simulator's default limit
+pub const MAX_SIGN_DATA_LEN: usize = 64 * 1024;
+
+const BIP44_PURPOSE: u32 = 44;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CheckStatus {
+    Pass,
+    Warn,
+    Fail,
+    /// Doesn't apply, e.g. expiration for a message
+    Skipped,
+}
+
+impl CheckStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CheckStatus::Pass => "Pass",
+            CheckStatus::Warn => "Warn",
+            CheckStatus::Fail => "Fail",
+            CheckStatus::Skipped => "Skipped",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CheckResult {
+    /// Stable identifier, e.g. `PathPolicy`
+    pub check: &'static str,
+    pub status: CheckStatus,
+    /// Why it didn't pass; empty otherwise
+    pub detail: String,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ValidationReport {
+    pub checks: Vec<CheckResult>,
+}
+
+impl ValidationReport {
+    pub fn is_valid(&self) -> bool {
+        !self.checks.iter().any(|c| c.status == CheckStatus::Fail)
+    }
+
+    pub fn get(&self, check: &str) -> Option<&CheckResult> {
+        self.checks.iter().find(|c| c.check == check)
+    }
+
+    fn push(&mut self, check: &'static str, (status, detail): Outcome) {
+        self.checks.push(CheckResult { check, status, detail });
+    }
+}
+
+// Status and detail of one check
+type Outcome = (CheckStatus, String);
+
+fn pass() -> Outcome {
+    (CheckStatus::Pass, String::new())
+}
+
+fn outcome(result: Result<Outcome, String>) -> Outcome {
+    result.unwrap_or_else(|detail| (CheckStatus::Fail, detail))
+}
+
+// Strictly decodes the bytes, as the signer will
+fn check_cbor(cbor: &[u8]) -> Result<TronSignRequest, String> {
+    Ok(TronSignRequest::try_from(cbor.to_vec())?)
+}
+
+fn check_data_type(request: &TronSignRequest) -> Result<Outcome, String> {
+    DataType::from_u32(request.get_data_type()).map(|_| pass())
+}
+
+// Paths under m/44'/195' of another shape than
+// m/44'/195'/account'/change/index only warn
+fn check_path(request: &TronSignRequest) -> Result<Outcome, String> {
+    let path = KeyPath::parse(request.get_derivation_path())?;
+    let hardened = |index| PathComponent { index, hardened: true };
+    match path.get_components().as_slice() {
+        [purpose, coin, account, change, index]
+            if *purpose == hardened(BIP44_PURPOSE)
+                && *coin == hardened(COIN_TYPE_TRON)
+                && account.hardened
+                && !change.hardened
+                && !index.hardened =>
+        {
+            Ok(pass())
+        }
+        [purpose, coin, ..] if *purpose == hardened(BIP44_PURPOSE) && *coin == hardened(COIN_TYPE_TRON) => {
+            Ok((CheckStatus::Warn, "Path isn't m/44'/195'/account'/change/index".to_string()))
+        }
+        _ => Err("Path is outside m/44'/195'".to_string()),
+    }
+}
+
+fn check_address(request: &TronSignRequest) -> Result<Outcome, String> {
+    let addresses: Vec<&String> = request.get_address().into_iter().chain(request.get_fee_payer()).collect();
+    if addresses.is_empty() {
+        return Ok((CheckStatus::Skipped, String::new()));
+    }
+    for address in addresses {
+        decode_address(address)?;
+    }
+    Ok(pass())
+}
+
+fn check_size(request: &TronSignRequest) -> Result<Outcome, String> {
+    match request.get_sign_data().len() {
+        0 => Err("sign_data is empty".to_string()),
+        len if len > MAX_SIGN_DATA_LEN => Err(format!("sign_data exceeds {} bytes", MAX_SIGN_DATA_LEN)),
+        _ => Ok(pass()),
+    }
+}
+
+// `now` is Unix seconds, 0 to skip; transaction expirations are in ms
+#[cfg(feature = "protobuf")]
+fn check_expiration(request: &TronSignRequest, now: u64) -> Result<Outcome, String> {
+    if now == 0 || request.get_data_type() != DataType::Transaction.to_u32() {
+        return Ok((CheckStatus::Skipped, String::new()));
+    }
+    match parse_request(request).map(|t| t.expiration) {
+        Err(e) => Err(format!("Transaction doesn't decode: {}", e)),
+        Ok(0) => Ok((CheckStatus::Skipped, String::new())),
+        Ok(expiration) if expiration / 1000 <= now => Err(format!("Transaction expired at {}", expiration / 1000)),
+        Ok(_) => Ok(pass()),
+    }
+}
+
+#[cfg(not(feature = "protobuf"))]
+fn check_expiration(_request: &TronSignRequest, _now: u64) -> Result<Outcome, String> {
+    Ok((CheckStatus::Skipped, String::new()))
+}
+
+// Fails for transactions that don't decode; warns when the signer can't
+// show what is signed
+#[cfg(feature = "protobuf")]
+fn check_decodable(request: &TronSignRequest) -> Result<Outcome, String> {
+    if request.get_data_type() == DataType::Transaction.to_u32() {
+        parse_request(request).map_err(|e| format!("Transaction doesn't decode: {}", e))?;
+    }
+    match is_blind_signing(request, &ContractRegistry::builtin())? {
+        true => Ok((CheckStatus::Warn, "sign_data can't be shown to the user".to_string())),
+        false => Ok(pass()),
+    }
+}
+
+#[cfg(not(feature = "protobuf"))]
+fn check_decodable(_request: &TronSignRequest) -> Result<Outcome, String> {
+    Ok((CheckStatus::Skipped, String::new()))
+}
+
+/// Runs every check; `now` is Unix seconds, or 0 to skip expiration. The
+/// CBOR check decodes the request's own encoding.
+pub fn validate(request: &TronSignRequest, now: u64) -> ValidationReport {
+    match request.to_bytes() {
+        Ok(cbor) => validate_cbor(&cbor, now),
+        Err(e) => {
+            let mut report = ValidationReport::default();
+            report.push("Cbor", (CheckStatus::Fail, e));
+            report
+        }
+    }
+}
+
+/// Runs every check on the encoded request `cbor`; the other checks only
+/// run when it decodes
+pub fn validate_cbor(cbor: &[u8], now: u64) -> ValidationReport {
+    let mut report = ValidationReport::default();
+    let request = match check_cbor(cbor) {
+        Ok(request) => request,
+        Err(e) => {
+            report.push("Cbor", (CheckStatus::Fail, e));
+            return report;
+        }
+    };
+    report.push("Cbor", pass());
+    report.push("DataType", outcome(check_data_type(&request)));
+    report.push("PathPolicy", outcome(check_path(&request)));
+    report.push("AddressChecksum", outcome(check_address(&request)));
+    report.push("Expiration", outcome(check_expiration(&request, now)));
+    report.push("Size", outcome(check_size(&request)));
+    report.push("Decodable", outcome(check_decodable(&request)));
+    report
+}
+
+#[cfg(feature = "json")]
+fn report_json(report: &ValidationReport) -> String {
+    let checks: Vec<_> = report
+        .checks
+        .iter()
+        .map(|c| json!({ "check": c.check, "status": c.status.as_str(), "detail": c.detail }))
+        .collect();
+    json!({ "valid": report.is_valid(), "checks": checks }).to_string()
+}
+
+// ========== FFI Functions ==========
+
+/// JSON `{"valid", "checks": [{"check", "status", "detail"}]}`; `now` is
+/// Unix seconds, or 0 to skip the expiration check
+#[cfg(feature = "json")]
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_request_validate(tron_sign_request: &TronSignRequest, now: u64) -> PtrResponse {
+    Response::success_string(report_json(&validate(tron_sign_request, now))).c_ptr()
+}
+
+/// As `tron_sign_request_validate`, on hex CBOR as received
+#[cfg(feature = "json")]
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_request_validate_cbor(cbor: PtrString, now: u64) -> PtrResponse {
+    match parse_ptr_string_to_bytes(cbor) {
+        Ok(cbor) => Response::success_string(report_json(&validate_cbor(&cbor, now))),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_sign_request_is_valid(tron_sign_request: &TronSignRequest, now: u64) -> PtrResponse {
+    Response::success_boolean(validate(tron_sign_request, now).is_valid()).c_ptr()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(sign_data: Vec<u8>, data_type: u32, path: &str) -> TronSignRequest {
+        TronSignRequest::new_with_derived_request_id(sign_data, data_type, path.to_string(), None, None, None)
+    }
+
+    #[test]
+    fn test_validate() {
+        let report = validate(&request(b"Sign in".to_vec(), 2, "m/44'/195'/0'/0/0"), 1_700_000_000);
+        assert!(report.is_valid());
+        assert!(report.checks.iter().all(|c| c.status != CheckStatus::Warn));
+        assert_eq!(report.get("AddressChecksum").unwrap().status, CheckStatus::Skipped);
+        assert_eq!(report.get("Expiration").unwrap().status, CheckStatus::Skipped);
+
+        let account = validate(&request(b"Sign in".to_vec(), 2, "m/44'/195'/0'"), 0);
+        assert!(account.is_valid());
+        assert_eq!(account.get("PathPolicy").unwrap().status, CheckStatus::Warn);
+        let bitcoin = validate(&request(b"Sign in".to_vec(), 2, "m/44'/0'/0'/0/0"), 0);
+        assert_eq!(bitcoin.get("PathPolicy").unwrap().detail, "Path is outside m/44'/195'");
+
+        let bad = TronSignRequest::new(None, vec![], 9, "m/44'/195'/0'/0/0".to_string(), None, Some("TXYZ".to_string()), None);
+        let report = validate(&bad, 0);
+        assert!(!report.is_valid());
+        for check in ["DataType", "AddressChecksum", "Size"] {
+            assert_eq!(report.get(check).unwrap().status, CheckStatus::Fail, "{}", check);
+        }
+        assert_eq!(report.get("Size").unwrap().detail, "sign_data is empty");
+
+        #[cfg(feature = "protobuf")]
+        {
+            use crate::tron::transaction::{build_account_create, TransactionHeader};
+            let header = TransactionHeader {
+                ref_block_bytes: vec![0; 2],
+                ref_block_hash: vec![0; 8],
+                expiration: 1_700_000_060_000,
+                timestamp: 1_700_000_000_000,
+            };
+            let raw_data = build_account_create(&header, &[0x41; 21], &[0x41; 21]).unwrap();
+            let transaction = request(raw_data, 1, "m/44'/195'/0'/0/0");
+            assert_eq!(validate(&transaction, 1_700_000_000).get("Expiration").unwrap().status, CheckStatus::Pass);
+            assert!(!validate(&transaction, 1_700_000_060).is_valid());
+            let garbage = validate(&request(vec![0xff], 1, "m/44'/195'/0'/0/0"), 1_700_000_000);
+            assert!(!garbage.is_valid());
+            assert_eq!(garbage.get("Decodable").unwrap().status, CheckStatus::Fail);
+            assert_eq!(garbage.get("Expiration").unwrap().status, CheckStatus::Fail);
+        }
+    }
+
+    #[test]
+    fn test_validate_cbor() {
+        let cbor = request(b"Sign in".to_vec(), 2, "m/44'/195'/0'/0/0").to_bytes().unwrap();
+        assert!(validate_cbor(&cbor, 0).is_valid());
+
+        // Trailing bytes after the map
+        let mut trailing = cbor.clone();
+        trailing.push(0);
+        let report = validate_cbor(&trailing, 0);
+        assert_eq!(report.checks.len(), 1);
+        assert_eq!(report.get("Cbor").unwrap().status, CheckStatus::Fail);
+        assert!(!validate_cbor(&[0xa1, 0x01], 0).is_valid());
+    }
+}
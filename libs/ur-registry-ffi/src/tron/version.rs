@@ -0,0 +1,78 @@
+use crate::response::{PtrResponse, Response};
+use crate::tron::decode::{invalid_value, DecodeError};
+
+use serde_cbor::Value;
+
+/// Highest protocol version this build understands. Payloads without a
+/// version field predate versioning and are treated as version 1.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Version both sides understand, given the peer's highest version, or
+/// None when they share none: versions start at 1
+pub fn negotiate_version(peer_version: u32) -> Option<u32> {
+    match peer_version {
+        0 => None,
+        _ => Some(peer_version.min(PROTOCOL_VERSION)),
+    }
+}
+
+pub(crate) fn encode_version(version: u32) -> Value {
+    Value::Integer(version as i128)
+}
+
+/// Rejects payloads claiming a version newer than this build instead of
+/// decoding them with fields it may misinterpret
+pub(crate) fn decode_version(value: Option<&Value>) -> Result<Option<u32>, DecodeError> {
+    let version = match value {
+        None => return Ok(None),
+        Some(Value::Integer(i)) => u32::try_from(*i).map_err(|_| invalid_value("protocol version", i.to_string()))?,
+        Some(_) => return Err(DecodeError::InvalidType { field: "protocol version", expected: "unsigned integer" }),
+    };
+    if version == 0 {
+        return Err(invalid_value("protocol version", "0"));
+    }
+    if version > PROTOCOL_VERSION {
+        return Err(DecodeError::UnsupportedVersion(version));
+    }
+    Ok(Some(version))
+}
+
+// ========== FFI Functions ==========
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_protocol_version() -> PtrResponse {
+    Response::success_uint32(PROTOCOL_VERSION).c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn tron_protocol_negotiate(peer_version: u32) -> PtrResponse {
+    match negotiate_version(peer_version) {
+        Some(version) => Response::success_uint32(version).c_ptr(),
+        None => Response::not_present().c_ptr(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_version() {
+        assert_eq!(decode_version(None).unwrap(), None);
+        assert_eq!(decode_version(Some(&encode_version(1))).unwrap(), Some(1));
+        assert_eq!(
+            decode_version(Some(&encode_version(PROTOCOL_VERSION + 1))),
+            Err(DecodeError::UnsupportedVersion(PROTOCOL_VERSION + 1))
+        );
+        assert!(decode_version(Some(&Value::Integer(-1))).is_err());
+    }
+
+    #[test]
+    fn test_negotiate_version() {
+        assert_eq!(negotiate_version(7), Some(PROTOCOL_VERSION));
+        assert_eq!(negotiate_version(1), Some(1));
+        assert_eq!(negotiate_version(0), None);
+    }
+}
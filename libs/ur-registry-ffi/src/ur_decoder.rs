@@ -1,33 +1,319 @@
-use crate::response::{PtrResponse, Response, Value};
+use crate::binary_part;
+use crate::registry_item::RegistryItem;
+use crate::response::{PtrResponse, Response, Value, ERROR, SUCCESS};
+use crate::hex_codec;
 use crate::types::PtrString;
-use hex::encode;
+use crate::ur_encoder::short_code;
+use crate::utils::{convert_ptr_string_to_string, parse_ptr_string_to_bytes};
+use crc::{Crc, CRC_32_ISO_HDLC};
+use sha2::{Digest, Sha256};
+use std::cell::RefCell;
 use std::ffi::{c_void, CStr};
 use ur::Decoder;
 
+// `ReceiveSlot::error_code` values
+pub const RECEIVE_OK: u32 = 0;
+pub const RECEIVE_INVALID_UTF8: u32 = 1;
+pub const RECEIVE_INVALID_PART: u32 = 2;
+pub const RECEIVE_TYPE_NOT_ALLOWED: u32 = 3;
+pub const RECEIVE_SESSION_EXPIRED: u32 = 4;
+
+/// Why a part was refused
+#[derive(Clone, Debug, PartialEq)]
+pub enum ReceiveError {
+    InvalidUtf8,
+    InvalidPart(String),
+    /// The part's UR type is outside the decoder's allowlist
+    TypeNotAllowed(String),
+    /// No part arrived within the inactivity timeout; the parts collected
+    /// so far were dropped
+    SessionExpired,
+}
+
+impl std::fmt::Display for ReceiveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReceiveError::InvalidUtf8 => write!(f, "UR part is not valid UTF-8"),
+            ReceiveError::InvalidPart(e) => write!(f, "Invalid UR part: {}", e),
+            ReceiveError::TypeNotAllowed(t) => write!(f, "UR type {} is not allowed by this decoder", t),
+            ReceiveError::SessionExpired => write!(f, "Scan session expired, start again"),
+        }
+    }
+}
+
+impl ReceiveError {
+    /// `ReceiveSlot::error_code` value
+    pub fn code(&self) -> u32 {
+        match self {
+            ReceiveError::InvalidUtf8 => RECEIVE_INVALID_UTF8,
+            ReceiveError::InvalidPart(_) => RECEIVE_INVALID_PART,
+            ReceiveError::TypeNotAllowed(_) => RECEIVE_TYPE_NOT_ALLOWED,
+            ReceiveError::SessionExpired => RECEIVE_SESSION_EXPIRED,
+        }
+    }
+}
+
+/// Fountain decoder that may be limited to the UR types a flow expects, so
+/// a payload of another type is refused at the first part instead of
+/// reaching code written for a different one, e.g. a hot wallet that only
+/// takes `tron-signature`.
+///
+/// With an inactivity timeout, a half-scanned session nobody finishes is
+/// dropped instead of holding its parts for good. Time comes from the
+/// caller, in milliseconds of any monotonic clock.
+#[derive(Default)]
+pub struct URDecoder {
+    decoder: Decoder,
+    // None accepts every type
+    allowed_types: Option<Vec<String>>,
+    timeout_ms: Option<u64>,
+    // When the last part was accepted, None before the first
+    last_activity_ms: Option<u64>,
+    // Type of the parts accepted so far
+    ur_type: Option<String>,
+}
+
+impl URDecoder {
+    /// Empty decoder with this one's allowlist and inactivity timeout
+    pub fn with_same_limits(&self) -> Self {
+        URDecoder { allowed_types: self.allowed_types.clone(), timeout_ms: self.timeout_ms, ..Default::default() }
+    }
+
+    /// Accept only `types`, or any type for None
+    pub fn set_allowed_types(&mut self, types: Option<Vec<String>>) {
+        self.allowed_types = types.map(|types| types.iter().map(|t| t.trim().to_lowercase()).collect());
+    }
+
+    pub fn is_allowed(&self, ur_type: &str) -> bool {
+        self.allowed_types.as_ref().is_none_or(|types| types.iter().any(|t| t == ur_type))
+    }
+
+    /// Drop the collected parts once `timeout_ms` passes without a new one;
+    /// None keeps them indefinitely
+    pub fn set_inactivity_timeout(&mut self, timeout_ms: Option<u64>) {
+        self.timeout_ms = timeout_ms;
+    }
+
+    /// Clears the session if it has been idle past the timeout, reporting
+    /// `SessionExpired` when it did; idle hosts call it to free memory
+    pub fn check_timeout(&mut self, now_ms: u64) -> Result<(), ReceiveError> {
+        let expired = match (self.timeout_ms, self.last_activity_ms) {
+            (Some(timeout), Some(last)) => now_ms.saturating_sub(last) > timeout,
+            _ => false,
+        };
+        if expired {
+            self.decoder = Decoder::default();
+            self.last_activity_ms = None;
+            self.ur_type = None;
+            return Err(ReceiveError::SessionExpired);
+        }
+        Ok(())
+    }
+
+    /// `receive` for a part arriving at `now_ms`. A part after the timeout
+    /// isn't kept: the session restarts with the next one.
+    pub fn receive_at(&mut self, part: &str, now_ms: u64) -> Result<(), ReceiveError> {
+        self.check_timeout(now_ms)?;
+        self.receive(part)?;
+        self.last_activity_ms = Some(now_ms);
+        Ok(())
+    }
+
+    /// Feeds a lowercased part to the decoder
+    pub fn receive(&mut self, part: &str) -> Result<(), ReceiveError> {
+        // `ur:<type>/...`; malformed parts are left to the decoder to refuse
+        let ur_type = part.strip_prefix("ur:").and_then(|rest| rest.split('/').next()).unwrap_or_default();
+        if !self.is_allowed(ur_type) {
+            return Err(ReceiveError::TypeNotAllowed(ur_type.to_string()));
+        }
+        if let Some(expected) = self.ur_type.as_ref().filter(|expected| *expected != ur_type) {
+            return Err(ReceiveError::InvalidPart(format!("{} part in a {} message", ur_type, expected)));
+        }
+        self.decoder.receive(part).map_err(|e| ReceiveError::InvalidPart(e.to_string()))?;
+        self.ur_type.get_or_insert_with(|| ur_type.to_string());
+        Ok(())
+    }
+
+    /// UR type of the parts received, None before the first
+    pub fn get_ur_type(&self) -> Option<&str> {
+        self.ur_type.as_deref()
+    }
+
+    /// `Err` unless the message may be resolved as `target`: an allowed
+    /// type, and the one its parts carried
+    pub fn check_target(&self, target: &str) -> Result<(), String> {
+        if !self.is_allowed(target) {
+            return Err(ReceiveError::TypeNotAllowed(target.to_string()).to_string());
+        }
+        match self.get_ur_type() {
+            Some(ur_type) if ur_type != target => Err(format!("Message is a {}, not a {}", ur_type, target)),
+            _ => Ok(()),
+        }
+    }
+
+    /// `receive` for a frame of `ur_encoder_next_part_bytes`
+    pub fn receive_bytes(&mut self, frame: &[u8]) -> Result<(), ReceiveError> {
+        self.receive(&binary_part::decode_part(frame).map_err(ReceiveError::InvalidPart)?)
+    }
+
+    pub fn complete(&self) -> bool {
+        self.decoder.complete()
+    }
+
+    /// The complete message
+    pub fn message(&self) -> Result<Vec<u8>, String> {
+        get_result(self)
+    }
+
+    /// CRC-32 of the complete message, the checksum its fountain parts carry
+    pub fn message_checksum(&self) -> Result<u32, String> {
+        get_result(self).map(|message| Crc::<u32>::new(&CRC_32_ISO_HDLC).checksum(&message))
+    }
+
+    /// SHA-256 of the complete message's CBOR
+    pub fn message_sha256(&self) -> Result<[u8; 32], String> {
+        get_result(self).map(|message| Sha256::digest(message).into())
+    }
+}
+
+/// Outcome of `ur_decoder_receive_part_into`, written in place so scanning
+/// loops get no response, and no error string, per camera frame. The
+/// caller owns it, typically on the stack. The layout is part of the ABI
+/// and mirrors `ReceiveSlot` in `lib_ur_registry_ffi.h`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ReceiveSlot {
+    /// `SUCCESS` or `ERROR` for the last part
+    pub status_code: u32,
+    /// `RECEIVE_OK`, or why the last part was refused
+    pub error_code: u32,
+    /// The decoder holds the whole message
+    pub complete: bool,
+}
+
+impl ReceiveSlot {
+    fn record(&mut self, result: Result<(), ReceiveError>, complete: bool) {
+        self.error_code = result.map_or_else(|e| e.code(), |_| RECEIVE_OK);
+        self.status_code = if self.error_code == RECEIVE_OK { SUCCESS } else { ERROR };
+        self.complete = complete;
+    }
+}
+
+thread_local! {
+    // Lowercased copy of the part, reused so receiving doesn't allocate
+    // once it has grown to the part size
+    static PART: RefCell<String> = const { RefCell::new(String::new()) };
+}
+
 #[no_mangle]
 pub extern "C" fn ur_decoder_new() -> PtrResponse {
-    Response::success_object(Box::into_raw(Box::new(ur::Decoder::default())) as *mut c_void).c_ptr()
+    Response::success_object(Box::into_raw(Box::new(URDecoder::default())) as *mut c_void).c_ptr()
 }
 
 #[no_mangle]
-pub extern "C" fn ur_decoder_receive(decoder: &mut Decoder, ur: PtrString) -> PtrResponse {
+pub extern "C" fn ur_decoder_receive(decoder: &mut URDecoder, ur: PtrString) -> PtrResponse {
     let ur_str = match unsafe { CStr::from_ptr(ur) }.to_str() {
         Ok(value) => value.to_lowercase(),
         Err(error) => return Response::error(error.to_string()).c_ptr(),
     };
     match decoder.receive(ur_str.as_str()) {
-        Err(error) => Response::error(format!("No data received before get result")).c_ptr(),
+        Err(error) => Response::error(error.to_string()).c_ptr(),
         _ => Response::success_null().c_ptr(),
     }
 }
 
+/// `ur_decoder_receive` for a hex binary frame
+#[no_mangle]
+pub extern "C" fn ur_decoder_receive_bytes(decoder: &mut URDecoder, frame: PtrString) -> PtrResponse {
+    let frame = match parse_ptr_string_to_bytes(frame).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    match decoder.receive_bytes(&frame) {
+        Ok(()) => Response::success_null(),
+        Err(e) => Response::error(e.to_string()),
+    }
+    .c_ptr()
+}
+
+/// `ur_decoder_receive_part_into` for a binary frame of `len` bytes at
+/// `frame`, as `ur_encoder_next_part_bytes_into` writes it
 #[no_mangle]
-pub extern "C" fn ur_decoder_is_complete(decoder: &mut Decoder) -> PtrResponse {
+pub extern "C" fn ur_decoder_receive_bytes_into(decoder: &mut URDecoder, frame: *const u8, len: usize, slot: &mut ReceiveSlot) {
+    let result = match frame.is_null() {
+        true => Err(ReceiveError::InvalidPart("Null frame".to_string())),
+        false => decoder.receive_bytes(unsafe { std::slice::from_raw_parts(frame, len) }),
+    };
+    slot.record(result, decoder.complete());
+}
+
+/// `ur_decoder_receive` for a part arriving at `now_ms`; fails with a
+/// "session expired" error, dropping the collected parts, after the
+/// inactivity timeout
+#[no_mangle]
+pub extern "C" fn ur_decoder_receive_at(decoder: &mut URDecoder, ur: PtrString, now_ms: u64) -> PtrResponse {
+    let ur_str = match convert_ptr_string_to_string(ur).map_err(|e| Response::error(e)) {
+        Ok(v) => v.to_lowercase(),
+        Err(e) => return e.c_ptr(),
+    };
+    match decoder.receive_at(&ur_str, now_ms) {
+        Ok(()) => Response::success_null(),
+        Err(e) => Response::error(e.to_string()),
+    }
+    .c_ptr()
+}
+
+/// Inactivity timeout in ms for sessions fed through `ur_decoder_receive_at`,
+/// 0 for none
+#[no_mangle]
+pub extern "C" fn ur_decoder_set_inactivity_timeout(decoder: &mut URDecoder, timeout_ms: u64) -> PtrResponse {
+    decoder.set_inactivity_timeout(Some(timeout_ms).filter(|t| *t > 0));
+    Response::success_null().c_ptr()
+}
+
+/// True when the session was idle past the timeout and has been cleared
+#[no_mangle]
+pub extern "C" fn ur_decoder_check_timeout(decoder: &mut URDecoder, now_ms: u64) -> PtrResponse {
+    Response::success_boolean(decoder.check_timeout(now_ms).is_err()).c_ptr()
+}
+
+/// Limits the decoder to `types`, one UR type per line; empty accepts
+/// every type again. Parts of other types are refused with
+/// `RECEIVE_TYPE_NOT_ALLOWED`, and resolving to them fails.
+#[no_mangle]
+pub extern "C" fn ur_decoder_set_allowed_types(decoder: &mut URDecoder, types: PtrString) -> PtrResponse {
+    let types = match convert_ptr_string_to_string(types).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    let types: Vec<String> = types.lines().map(str::trim).filter(|t| !t.is_empty()).map(String::from).collect();
+    decoder.set_allowed_types(Some(types).filter(|t| !t.is_empty()));
+    Response::success_null().c_ptr()
+}
+
+/// Same as `ur_decoder_receive`, writing the outcome to `slot` instead of
+/// returning a response. The decoder's own work still allocates.
+#[no_mangle]
+pub extern "C" fn ur_decoder_receive_part_into(decoder: &mut URDecoder, ur: PtrString, slot: &mut ReceiveSlot) {
+    let result = match unsafe { CStr::from_ptr(ur) }.to_str() {
+        Ok(value) => PART.with(|part| {
+            let mut part = part.borrow_mut();
+            part.clear();
+            part.extend(value.chars().map(|c| c.to_ascii_lowercase()));
+            decoder.receive(&part)
+        }),
+        Err(_) => Err(ReceiveError::InvalidUtf8),
+    };
+    slot.record(result, decoder.complete());
+}
+
+#[no_mangle]
+pub extern "C" fn ur_decoder_is_complete(decoder: &URDecoder) -> PtrResponse {
     Response::success_boolean(decoder.complete()).c_ptr()
 }
 
-fn get_result(decoder: &mut Decoder) -> Result<Vec<u8>, String> {
-    match decoder.message() {
+fn get_result(decoder: &URDecoder) -> Result<Vec<u8>, String> {
+    match decoder.decoder.message() {
         Ok(m) => match m {
             Some(message) => Ok(message),
             None => Err(format!("No data received before get result")),
@@ -37,36 +323,229 @@ fn get_result(decoder: &mut Decoder) -> Result<Vec<u8>, String> {
 }
 
 #[no_mangle]
-pub extern "C" fn ur_decoder_result(decoder: &mut Decoder) -> PtrResponse {
+pub extern "C" fn ur_decoder_result(decoder: &URDecoder) -> PtrResponse {
     match get_result(decoder) {
-        Ok(message) => Response::success_string(encode(message)).c_ptr(),
+        Ok(message) => Response::success_string(hex_codec::encode(&message)).c_ptr(),
         Err(error) => Response::error(error).c_ptr(),
     }
 }
 
+/// Same CRC-32 as `ur_encoder_checksum`, once the message is complete
 #[no_mangle]
-pub extern "C" fn ur_decoder_resolve(decoder: &mut Decoder, target_type: PtrString) -> PtrResponse {
+pub extern "C" fn ur_decoder_checksum(decoder: &URDecoder) -> PtrResponse {
+    match decoder.message_checksum() {
+        Ok(checksum) => Response::success_uint32(checksum),
+        Err(error) => Response::error(error),
+    }
+    .c_ptr()
+}
+
+/// Hex SHA-256 of the decoded CBOR, once complete
+#[no_mangle]
+pub extern "C" fn ur_decoder_sha256(decoder: &URDecoder) -> PtrResponse {
+    match decoder.message_sha256() {
+        Ok(sha256) => Response::success_string(hex_codec::encode(&sha256)),
+        Err(error) => Response::error(error),
+    }
+    .c_ptr()
+}
+
+/// `ur_encoder_short_code` of the decoded message, to compare with the
+/// sending device's
+#[no_mangle]
+pub extern "C" fn ur_decoder_short_code(decoder: &URDecoder) -> PtrResponse {
+    match decoder.message_checksum() {
+        Ok(checksum) => Response::success_string(short_code(checksum)),
+        Err(error) => Response::error(error),
+    }
+    .c_ptr()
+}
+
+#[no_mangle]
+pub extern "C" fn ur_decoder_resolve(decoder: &URDecoder, target_type: PtrString) -> PtrResponse {
     let result = match get_result(decoder) {
         Ok(res) => res,
         Err(error) => return Response::error(error.to_string()).c_ptr(),
     };
     let target = unsafe { CStr::from_ptr(target_type) }.to_str().unwrap();
+    if let Err(error) = decoder.check_target(target) {
+        return Response::error(error).c_ptr();
+    }
+    resolve(target, result)
+}
+
+/// Like `ur_decoder_resolve`, but returns a `RegistryItem` that knows its
+/// type, for `registry_item_to_cbor`; free it with `registry_item_free`
+#[no_mangle]
+pub extern "C" fn ur_decoder_resolve_item(decoder: &URDecoder, target_type: PtrString) -> PtrResponse {
+    let result = match get_result(decoder) {
+        Ok(res) => res,
+        Err(error) => return Response::error(error).c_ptr(),
+    };
+    let target = match convert_ptr_string_to_string(target_type).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    if let Err(error) = decoder.check_target(&target) {
+        return Response::error(error).c_ptr();
+    }
+    match RegistryItem::decode(&target, result) {
+        Ok(item) => Response::success_object(Box::into_raw(Box::new(item)) as *mut c_void),
+        Err(error) => Response::error(error),
+    }
+    .c_ptr()
+}
+
+pub fn resolve(target: &str, result: Vec<u8>) -> PtrResponse {
     match target {
         "crypto-multi-accounts" => crate::extend::crypto_multi_accounts::resolve(result),
         "crypto-hdkey" => crate::crypto_hd_key::resolve(result),
         "crypto-account" => crate::crypto_account::resolve(result),
+        "crypto-address" => crate::crypto_address::resolve(result),
+        "crypto-eckey" => crate::crypto_ec_key::resolve(result),
         "crypto-output" => crate::crypto_output::resolve(result),
         "crypto-psbt" => crate::crypto_psbt::resolve(result),
+        "crypto-sskr" => crate::crypto_sskr::resolve(result),
         "sol-signature" => crate::solana::solana_signarure::resolve(result),
         "sol-sign-request" => crate::solana::solana_sign_request::resolve(result),
         "eth-signature" => crate::ethereum::eth_signarure::resolve(result),
         "eth-sign-request" => crate::ethereum::eth_sign_request::resolve(result),
+        #[cfg(feature = "json")]
         "cardano-signature" => crate::cardano::cardano_signature::resolve(result),
+        #[cfg(feature = "json")]
         "cardano-catalyst-signature" => crate::cardano::cardano_catalyst_signature::resolve(result),
+        #[cfg(feature = "json")]
         "cardano-sign-cip8-data-signature" => crate::cardano::cardano_sign_cip8_data_signature::resolve(result),
+        #[cfg(feature = "json")]
         "cardano-sign-data-signature" => crate::cardano::cardano_sign_data_signature::resolve(result),
         "tron-sign-request" => crate::tron::tron_sign_request::resolve(result),
         "tron-signature" => crate::tron::tron_signature::resolve(result),
+        "tron-encrypted-payload" => crate::tron::tron_encrypted_payload::resolve(result),
+        "tron-pairing" => crate::tron::tron_pairing::resolve(result),
+        "tron-wallet-bundle" => crate::tron::tron_wallet_bundle::resolve(result),
+        "tron-sign-rejection" => crate::tron::tron_sign_rejection::resolve(result),
+        "tron-address-book" => crate::tron::tron_address_book::resolve(result),
+        "tron-sign-session" => crate::tron::tron_sign_session::resolve(result),
         t => Response::error(format!("type {} is not supported yet", t)).c_ptr(),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CString;
+
+    #[test]
+    fn test_receive_part_into() {
+        let message = vec![7u8; 300];
+        let mut encoder = ur::Encoder::new(&message, 100, "bytes").unwrap();
+        let mut decoder = URDecoder::default();
+        let mut slot = ReceiveSlot::default();
+
+        #[cfg(debug_assertions)]
+        let before = crate::utils::string_tracker::live_strings();
+        let garbage = CString::new("UR:BYTES/NOPE").unwrap();
+        ur_decoder_receive_part_into(&mut decoder, garbage.as_ptr() as PtrString, &mut slot);
+        assert_eq!((slot.status_code, slot.error_code, slot.complete), (ERROR, RECEIVE_INVALID_PART, false));
+        // Parts of one message are the same size, so after the first the
+        // lowercase copy reuses its buffer instead of reallocating
+        let mut buffer = None;
+        while !slot.complete {
+            let part = CString::new(encoder.next_part().unwrap().to_uppercase()).unwrap();
+            ur_decoder_receive_part_into(&mut decoder, part.as_ptr() as PtrString, &mut slot);
+            assert_eq!((slot.status_code, slot.error_code), (SUCCESS, RECEIVE_OK));
+            let current = PART.with(|part| part.borrow().as_ptr());
+            assert_eq!(*buffer.get_or_insert(current), current, "the part buffer was reallocated");
+        }
+        #[cfg(debug_assertions)]
+        assert_eq!(crate::utils::string_tracker::live_strings(), before, "receiving returned response strings");
+        assert_eq!(get_result(&decoder).unwrap(), message);
+    }
+
+    #[test]
+    fn test_allowed_types() {
+        let mut decoder = URDecoder::default();
+        decoder.set_allowed_types(Some(vec!["Tron-Signature".to_string()]));
+        let mut encoder = ur::Encoder::new(&[7u8; 10], 100, "tron-sign-request").unwrap();
+        let part = encoder.next_part().unwrap();
+        assert_eq!(decoder.receive(&part), Err(ReceiveError::TypeNotAllowed("tron-sign-request".to_string())));
+
+        let mut slot = ReceiveSlot::default();
+        let part = CString::new(part.to_uppercase()).unwrap();
+        ur_decoder_receive_part_into(&mut decoder, part.as_ptr() as PtrString, &mut slot);
+        assert_eq!((slot.error_code, slot.complete), (RECEIVE_TYPE_NOT_ALLOWED, false));
+
+        decoder.set_allowed_types(None);
+        ur_decoder_receive_part_into(&mut decoder, part.as_ptr() as PtrString, &mut slot);
+        assert_eq!((slot.error_code, slot.complete), (RECEIVE_OK, true));
+    }
+
+    #[test]
+    fn test_receive_reports_part_error() {
+        let mut decoder = URDecoder::default();
+        // A well-formed frame whose CBOR is a bare integer, not a part
+        let part = format!("ur:bytes/1-3/{}", ur::bytewords::encode(&[0x01], &ur::bytewords::Style::Minimal));
+        let c_part = CString::new(part.clone()).unwrap();
+        let response = Response::from_ptr(ur_decoder_receive(&mut decoder, c_part.as_ptr() as PtrString));
+        assert_eq!(response.status_code, ERROR);
+        let message = crate::utils::convert_ptr_string_to_string(response.error_message).unwrap();
+        assert_eq!(message, decoder.receive(&part).unwrap_err().to_string());
+        assert_ne!(message, "No data received before get result");
+        crate::utils::ur_registry_free_string(response.error_message);
+        crate::utils::ur_registry_free_string(response.value_type);
+    }
+
+    #[test]
+    fn test_inactivity_timeout() {
+        let mut encoder = ur::Encoder::new(&[7u8; 300], 100, "bytes").unwrap();
+        let mut decoder = URDecoder::default();
+        decoder.set_inactivity_timeout(Some(5_000));
+        assert_eq!(decoder.check_timeout(1_000_000), Ok(()));
+
+        decoder.receive_at(&encoder.next_part().unwrap(), 1_000).unwrap();
+        decoder.receive_at(&encoder.next_part().unwrap(), 6_000).unwrap();
+        assert_eq!(decoder.check_timeout(11_000), Ok(()));
+        assert_eq!(decoder.receive_at(&encoder.next_part().unwrap(), 11_001), Err(ReceiveError::SessionExpired));
+        assert!(decoder.decoder.message().unwrap().is_none());
+
+        // A fresh session starts with the next part
+        while !decoder.complete() {
+            decoder.receive_at(&encoder.next_part().unwrap(), 12_000).unwrap();
+        }
+        assert_eq!(get_result(&decoder).unwrap(), vec![7u8; 300]);
+        decoder.check_timeout(20_000).unwrap_err();
+        assert!(!decoder.complete());
+    }
+
+    #[test]
+    fn test_resolve_checks_message_type() {
+        let mut encoder = ur::Encoder::new(&[7u8; 300], 100, "tron-sign-request").unwrap();
+        let mut other = ur::Encoder::new(&[8u8; 300], 100, "tron-signature").unwrap();
+        let mut decoder = URDecoder::default();
+        assert_eq!(decoder.get_ur_type(), None);
+        decoder.receive(&encoder.next_part().unwrap()).unwrap();
+        assert_eq!(decoder.get_ur_type(), Some("tron-sign-request"));
+        assert!(matches!(decoder.receive(&other.next_part().unwrap()), Err(ReceiveError::InvalidPart(_))));
+        while !decoder.complete() {
+            decoder.receive(&encoder.next_part().unwrap()).unwrap();
+        }
+        assert_eq!(decoder.check_target("tron-sign-request"), Ok(()));
+        assert_eq!(decoder.check_target("tron-signature").unwrap_err(), "Message is a tron-sign-request, not a tron-signature");
+
+        decoder.set_allowed_types(Some(vec!["tron-signature".to_string()]));
+        assert!(decoder.check_target("tron-sign-request").unwrap_err().contains("not allowed"));
+    }
+
+    #[test]
+    fn test_digests_match_encoder() {
+        let mut encoder = crate::ur_encoder::UREncoder::new(&[9; 300], 100, "bytes").unwrap();
+        let mut decoder = URDecoder::default();
+        assert!(decoder.message_checksum().is_err());
+        while !decoder.complete() {
+            decoder.receive(&encoder.next_part().unwrap()).unwrap();
+        }
+        assert_eq!(decoder.message_checksum().unwrap(), encoder.checksum());
+        assert_eq!(&decoder.message_sha256().unwrap(), encoder.sha256());
+        assert_eq!(short_code(0x1a2b3c4d), "1A2B-3C4D");
+    }
+}
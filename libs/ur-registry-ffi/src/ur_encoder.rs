@@ -1,15 +1,543 @@
+use crate::binary_part;
 use crate::response::{PtrResponse, Response};
+use crate::hex_codec;
+use crc::{Crc, CRC_32_ISO_HDLC};
+use sha2::{Digest, Sha256};
 use ur::Encoder;
 
-#[no_mangle]
-pub extern "C" fn ur_encoder_next_part(ur_encoder: &mut Encoder) -> PtrResponse {
-    if ur_encoder.fragment_count() == 1 {
-        return Response::success_string(ur_encoder.get_single_part().unwrap()).c_ptr();
+pub struct UREncoder {
+    encoder: Encoder,
+    checksum: u32,
+    sha256: [u8; 32],
+    emitted: usize,
+    uppercase: bool,
+    redundancy_percent: u32,
+    last_part_len: usize,
+    // Frame `ur_encoder_next_part_bytes_into` couldn't fit yet
+    pending_frame: Option<Vec<u8>>,
+}
+
+/// Parts planned per fragment, in percent: 100 sends each fragment once
+pub const MIN_REDUNDANCY_PERCENT: u32 = 100;
+// Past this the frames are mostly repeats of what the decoder already has
+pub const MAX_REDUNDANCY_PERCENT: u32 = 1000;
+
+/// The checksum as `1A2B-3C4D`, short enough for two devices' users to
+/// read out and compare
+pub fn short_code(checksum: u32) -> String {
+    format!("{:04X}-{:04X}", checksum >> 16, checksum & 0xffff)
+}
+
+impl UREncoder {
+    pub fn new(message: &[u8], max_fragment_length: usize, ur_type: impl Into<String>) -> Result<Self, String> {
+        let encoder = Encoder::new(message, max_fragment_length, ur_type).map_err(|e| e.to_string())?;
+        Ok(UREncoder {
+            encoder,
+            // Same CRC-32 the fountain encoder stamps into every multi-part frame
+            checksum: Crc::<u32>::new(&CRC_32_ISO_HDLC).checksum(message),
+            sha256: Sha256::digest(message).into(),
+            emitted: 0,
+            uppercase: false,
+            redundancy_percent: MIN_REDUNDANCY_PERCENT,
+            last_part_len: 0,
+            pending_frame: None,
+        })
+    }
+
+    /// Encoder whose parts are uppercased and at most `capacity` characters
+    /// long, so each fits an alphanumeric-mode QR segment of that capacity
+    #[cfg(feature = "qr")]
+    pub fn new_alphanumeric(message: &[u8], capacity: usize, ur_type: &str) -> Result<Self, String> {
+        let max_fragment_length = alphanumeric_fragment_length(message, capacity, ur_type)?;
+        let mut encoder = UREncoder::new(message, max_fragment_length, ur_type)?;
+        encoder.uppercase = true;
+        Ok(encoder)
+    }
+
+    /// The next part as text; a frame held back for a bigger buffer comes
+    /// first
+    pub fn next_part(&mut self) -> Result<String, String> {
+        if let Some(frame) = self.take_pending_frame() {
+            let part = binary_part::decode_part(&frame)?;
+            return Ok(if self.uppercase { part.to_uppercase() } else { part });
+        }
+        let part = if self.encoder.fragment_count() == 1 {
+            self.encoder.get_single_part()
+        } else {
+            self.encoder.next_part()
+        }
+        .map_err(|e| e.to_string())?;
+        self.emitted += 1;
+        self.last_part_len = part.len();
+        Ok(if self.uppercase { part.to_uppercase() } else { part })
+    }
+
+    /// `next_part` as a binary frame, for binary QR mode and BLE; a frame
+    /// held back for a bigger buffer comes first
+    pub fn next_part_bytes(&mut self) -> Result<Vec<u8>, String> {
+        match self.take_pending_frame() {
+            Some(frame) => Ok(frame),
+            None => binary_part::encode_part(&self.next_part()?),
+        }
+    }
+
+    // Keeps the frame just emitted for the next call, text or binary; it
+    // counts as emitted once that call hands it out
+    fn hold_back(&mut self, frame: Vec<u8>) {
+        self.emitted -= 1;
+        self.pending_frame = Some(frame);
+    }
+
+    fn take_pending_frame(&mut self) -> Option<Vec<u8>> {
+        let frame = self.pending_frame.take()?;
+        self.emitted += 1;
+        Some(frame)
+    }
+
+    /// Sequence number of the last emitted part, 0 before the first call to `next_part`
+    pub fn current_index(&self) -> usize {
+        self.emitted
+    }
+
+    pub fn fragment_count(&self) -> usize {
+        self.encoder.fragment_count()
+    }
+
+    pub fn checksum(&self) -> u32 {
+        self.checksum
+    }
+
+    /// SHA-256 of the encoded CBOR
+    pub fn sha256(&self) -> &[u8; 32] {
+        &self.sha256
+    }
+
+    /// True once every fragment has been emitted at least once
+    pub fn has_looped(&self) -> bool {
+        self.emitted >= self.fragment_count()
+    }
+
+    /// Display time recommended for the last emitted part
+    #[cfg(feature = "qr")]
+    pub fn last_part_duration_ms(&self, profile: ScanProfile) -> u32 {
+        recommended_duration_ms(self.last_part_len, profile)
+    }
+
+    /// The next part with the time to show it for
+    #[cfg(feature = "qr")]
+    pub fn next_paced_part(&mut self, profile: ScanProfile) -> Result<(String, u32), String> {
+        let part = self.next_part()?;
+        Ok((part, self.last_part_duration_ms(profile)))
+    }
+
+    /// Plans `percent` parts per 100 fragments, e.g. 150 to send half as
+    /// many mixed fountain parts again after the fragments, so a camera
+    /// that misses frames recovers without waiting for the sequence to loop
+    pub fn set_redundancy_percent(&mut self, percent: u32) -> Result<(), String> {
+        if !(MIN_REDUNDANCY_PERCENT..=MAX_REDUNDANCY_PERCENT).contains(&percent) {
+            return Err(format!(
+                "Redundancy must be {}..={} percent, got {}",
+                MIN_REDUNDANCY_PERCENT, MAX_REDUNDANCY_PERCENT, percent
+            ));
+        }
+        self.redundancy_percent = percent;
+        Ok(())
+    }
+
+    pub fn get_redundancy_percent(&self) -> u32 {
+        self.redundancy_percent
+    }
+
+    /// Parts to emit before starting over, the fragment count scaled by the
+    /// redundancy and rounded up
+    pub fn planned_part_count(&self) -> usize {
+        (self.fragment_count() * self.redundancy_percent as usize).div_ceil(MIN_REDUNDANCY_PERCENT as usize)
     }
 
+    /// True once the planned parts, extra ones included, have been emitted
+    pub fn has_emitted_planned(&self) -> bool {
+        self.emitted >= self.planned_part_count()
+    }
+
+    /// The planned parts not emitted yet: the fragments, then the redundant
+    /// fountain parts. Empty once `has_emitted_planned`.
+    pub fn planned_parts(&mut self) -> std::iter::Take<&mut Self> {
+        let count = self.planned_part_count().saturating_sub(self.emitted);
+        self.take(count)
+    }
+
+    /// The fragments not emitted yet, each once, the parts a static display
+    /// cycles through; from a fresh encoder, all of them. Draining the
+    /// encoder itself instead gives the endless frame sequence.
+    pub fn first_cycle(&mut self) -> std::iter::Take<&mut Self> {
+        let count = self.fragment_count().saturating_sub(self.emitted);
+        self.take(count)
+    }
+}
+
+/// Parts for animated QR frames, indefinitely: single-part messages repeat
+/// their part, fountain codes keep mixing fragments. Never ends; a part that
+/// fails to encode is yielded as its error.
+impl Iterator for UREncoder {
+    type Item = Result<String, String>;
+
+    fn next(&mut self) -> Option<Result<String, String>> {
+        Some(self.next_part())
+    }
+}
+
+/// How hard host apps should favour scan reliability over transfer time
+#[cfg(feature = "qr")]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ScanProfile {
+    /// Recent phone cameras at close range
+    Fast = 0,
+    Balanced = 1,
+    /// Fixed-focus or low-light cameras, such as hardware wallets'
+    Reliable = 2,
+}
+
+#[cfg(feature = "qr")]
+impl ScanProfile {
+    pub fn from_u32(value: u32) -> Result<Self, String> {
+        match value {
+            0 => Ok(ScanProfile::Fast),
+            1 => Ok(ScanProfile::Balanced),
+            2 => Ok(ScanProfile::Reliable),
+            _ => Err(format!("Invalid scan profile: {}", value)),
+        }
+    }
+
+    // Base time and extra time per 100 characters, in ms
+    fn timing(&self) -> (u32, u32) {
+        match self {
+            ScanProfile::Fast => (100, 10),
+            ScanProfile::Balanced => (150, 20),
+            ScanProfile::Reliable => (250, 35),
+        }
+    }
+}
+
+/// Longest a frame is recommended for; past it the next one helps more
+#[cfg(feature = "qr")]
+pub const MAX_FRAME_DURATION_MS: u32 = 1000;
+
+/// Time to show a part of `part_len` characters for: denser codes take
+/// cameras longer to focus on and decode
+#[cfg(feature = "qr")]
+pub fn recommended_duration_ms(part_len: usize, profile: ScanProfile) -> u32 {
+    let (base, per_100_chars) = profile.timing();
+    let extra = (part_len as u64 * per_100_chars as u64).div_ceil(100);
+    (base as u64 + extra).min(MAX_FRAME_DURATION_MS as u64) as u32
+}
+
+// Parts are sized for sequence numbers up to this; fountain parts past it
+// gain a digit and a CBOR byte and may overflow the capacity
+#[cfg(feature = "qr")]
+const ALPHANUMERIC_SEQUENCE_LIMIT: u32 = 65535;
+
+#[cfg(feature = "qr")]
+fn cbor_uint_len(value: u32) -> usize {
+    match value {
+        0..=23 => 1,
+        24..=0xff => 2,
+        0x100..=0xffff => 3,
+        _ => 5,
+    }
+}
+
+/// Length of the longest part an encoder with this fragment length emits
+/// for sequence numbers up to `ALPHANUMERIC_SEQUENCE_LIMIT`
+#[cfg(feature = "qr")]
+fn longest_part_len(message: &[u8], max_fragment_length: usize, ur_type: &str) -> Result<usize, String> {
+    let mut encoder = UREncoder::new(message, max_fragment_length, ur_type)?;
+    let first = encoder.next_part()?.len();
+    if encoder.fragment_count() == 1 {
+        return Ok(first);
+    }
+    let limit = ALPHANUMERIC_SEQUENCE_LIMIT.max(encoder.fragment_count() as u32);
+    // From sequence number 1 to `limit`: more digits in `seq-count`, and two
+    // bytewords characters per extra CBOR byte
+    let digits = limit.to_string().len() - 1;
+    Ok(first + digits + 2 * (cbor_uint_len(limit) - cbor_uint_len(1)))
+}
+
+/// Largest fragment length whose parts, `ur:` prefix included, fit
+/// `capacity` alphanumeric characters
+#[cfg(feature = "qr")]
+pub fn alphanumeric_fragment_length(message: &[u8], capacity: usize, ur_type: &str) -> Result<usize, String> {
+    let fits = |length| longest_part_len(message, length, ur_type).map(|len| len <= capacity);
+    // Anything longer than the message makes a single part
+    let single = message.len() + 1;
+    if fits(single)? {
+        return Ok(single);
+    }
+    if !fits(1)? {
+        return Err(format!("No {} part fits {} characters", ur_type, capacity));
+    }
+    // Part length grows with the fragment length: find the last that fits
+    let (mut low, mut high) = (1, single);
+    while high - low > 1 {
+        let middle = low + (high - low) / 2;
+        if fits(middle)? {
+            low = middle
+        } else {
+            high = middle
+        }
+    }
+    Ok(low)
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn ur_encoder_next_part(ur_encoder: &mut UREncoder) -> PtrResponse {
     match ur_encoder.next_part() {
         Ok(v) => Response::success_string(v),
-        Err(e) => Response::error(e.to_string()),
+        Err(e) => Response::error(e),
     }
     .c_ptr()
 }
+
+/// Hex of the next part as a binary frame
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn ur_encoder_next_part_bytes(ur_encoder: &mut UREncoder) -> PtrResponse {
+    match ur_encoder.next_part_bytes() {
+        Ok(frame) => Response::success_string(hex_codec::encode(&frame)),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+/// Writes the next binary frame to `buffer` when `capacity` bytes suffice,
+/// and returns its size, or 0 when the part can't be encoded. A frame that
+/// doesn't fit is kept for the next call, so a null buffer sizes it
+/// without skipping a part.
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn ur_encoder_next_part_bytes_into(ur_encoder: &mut UREncoder, buffer: *mut u8, capacity: usize) -> usize {
+    let frame = match ur_encoder.next_part_bytes() {
+        Ok(frame) => frame,
+        Err(_) => return 0,
+    };
+    let needed = frame.len();
+    if buffer.is_null() || capacity < needed {
+        ur_encoder.hold_back(frame);
+    } else {
+        write_frame(&frame, buffer);
+    }
+    needed
+}
+
+// `buffer` holds at least `frame.len()` bytes
+fn write_frame(frame: &[u8], buffer: *mut u8) {
+    unsafe { std::slice::from_raw_parts_mut(buffer, frame.len()) }.copy_from_slice(frame);
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn ur_encoder_current_index(ur_encoder: &UREncoder) -> PtrResponse {
+    Response::success_uint32(ur_encoder.current_index() as u32).c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn ur_encoder_fragment_count(ur_encoder: &UREncoder) -> PtrResponse {
+    Response::success_uint32(ur_encoder.fragment_count() as u32).c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn ur_encoder_checksum(ur_encoder: &UREncoder) -> PtrResponse {
+    Response::success_uint32(ur_encoder.checksum()).c_ptr()
+}
+
+/// Hex SHA-256 of the encoded CBOR, the same `ur_decoder_sha256` returns
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn ur_encoder_sha256(ur_encoder: &UREncoder) -> PtrResponse {
+    Response::success_string(hex_codec::encode(ur_encoder.sha256())).c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn ur_encoder_short_code(ur_encoder: &UREncoder) -> PtrResponse {
+    Response::success_string(short_code(ur_encoder.checksum())).c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn ur_encoder_has_looped(ur_encoder: &UREncoder) -> PtrResponse {
+    Response::success_boolean(ur_encoder.has_looped()).c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn ur_encoder_set_redundancy(ur_encoder: &mut UREncoder, percent: u32) -> PtrResponse {
+    match ur_encoder.set_redundancy_percent(percent) {
+        Ok(()) => Response::success_null(),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn ur_encoder_planned_part_count(ur_encoder: &UREncoder) -> PtrResponse {
+    Response::success_uint32(ur_encoder.planned_part_count() as u32).c_ptr()
+}
+
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn ur_encoder_has_emitted_planned(ur_encoder: &UREncoder) -> PtrResponse {
+    Response::success_boolean(ur_encoder.has_emitted_planned()).c_ptr()
+}
+
+/// Display time in ms for the last emitted part; `profile` is a
+/// `ScanProfile` value
+#[cfg(feature = "qr")]
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn ur_encoder_last_part_duration(ur_encoder: &UREncoder, profile: u32) -> PtrResponse {
+    match ScanProfile::from_u32(profile) {
+        Ok(profile) => Response::success_uint32(ur_encoder.last_part_duration_ms(profile)),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+/// JSON `{"part", "duration_ms"}` for the next part
+#[cfg(all(feature = "qr", feature = "json"))]
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub extern "C" fn ur_encoder_next_paced_part(ur_encoder: &mut UREncoder, profile: u32) -> PtrResponse {
+    match ScanProfile::from_u32(profile).and_then(|profile| ur_encoder.next_paced_part(profile)) {
+        Ok((part, duration_ms)) => Response::success_string(serde_json::json!({ "part": part, "duration_ms": duration_ms }).to_string()),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_part_getters() {
+        let message: Vec<u8> = (0..1000u32).map(|i| (i * 5) as u8).collect();
+        let mut encoder = UREncoder::new(&message, 100, "bytes").unwrap();
+        assert_eq!(encoder.current_index(), 0);
+        assert!(encoder.fragment_count() > 1);
+        assert_eq!(encoder.sha256()[..], Sha256::digest(&message)[..]);
+        assert_eq!(hex_codec::encode(encoder.sha256()), hex::encode(Sha256::digest(&message)));
+
+        // The checksum is the one every frame carries
+        let part = encoder.next_part().unwrap();
+        assert_eq!(encoder.current_index(), 1);
+        let body = part.rsplit('/').next().unwrap();
+        let cbor = ur::bytewords::decode(body, &ur::bytewords::Style::Minimal).unwrap();
+        let frame: Vec<serde_cbor::Value> = serde_cbor::from_slice(&cbor).unwrap();
+        assert_eq!(frame[3], serde_cbor::Value::Integer(encoder.checksum().into()));
+        assert_eq!(short_code(0x1a2b3c4d), "1A2B-3C4D");
+        assert_eq!(short_code(encoder.checksum()).replace('-', ""), format!("{:08X}", encoder.checksum()));
+
+        for _ in 1..encoder.fragment_count() {
+            assert!(!encoder.has_looped());
+            encoder.next_part().unwrap();
+        }
+        assert!(encoder.has_looped());
+        assert_eq!(encoder.current_index(), encoder.fragment_count());
+
+        let single = UREncoder::new(&message[..10], 100, "bytes").unwrap();
+        assert_eq!(single.fragment_count(), 1);
+        assert_eq!(single.checksum(), Crc::<u32>::new(&CRC_32_ISO_HDLC).checksum(&message[..10]));
+    }
+
+    #[cfg(feature = "qr")]
+    #[test]
+    fn test_alphanumeric_parts() {
+        let message: Vec<u8> = (0..2000u32).map(|i| (i * 7) as u8).collect();
+        let mut encoder = UREncoder::new_alphanumeric(&message, 1000, "tron-sign-request").unwrap();
+        assert!(encoder.fragment_count() > 1);
+        for _ in 0..encoder.fragment_count() * 2 {
+            let part = encoder.next_part().unwrap();
+            assert!(part.len() <= 1000);
+            assert!(part.starts_with("UR:TRON-SIGN-REQUEST/"));
+            assert!(part.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || "/:-".contains(c)));
+        }
+
+        // The largest fragment that fits, leaving no room for one byte more
+        let length = alphanumeric_fragment_length(&message, 1000, "tron-sign-request").unwrap();
+        assert!(longest_part_len(&message, length + 1, "tron-sign-request").unwrap() > 1000);
+
+        let single = UREncoder::new_alphanumeric(&message[..10], 1000, "bytes").unwrap().next_part().unwrap();
+        assert_eq!(single.to_lowercase(), UREncoder::new(&message[..10], 400, "bytes").unwrap().next_part().unwrap());
+        assert!(UREncoder::new_alphanumeric(&message, 20, "bytes").is_err());
+    }
+
+    #[cfg(feature = "qr")]
+    #[test]
+    fn test_paced_parts() {
+        let message: Vec<u8> = (0..2000u32).map(|i| (i * 7) as u8).collect();
+        let mut encoder = UREncoder::new_alphanumeric(&message, 1000, "tron-sign-request").unwrap();
+        let (part, duration) = encoder.next_paced_part(ScanProfile::Balanced).unwrap();
+        assert_eq!(duration, recommended_duration_ms(part.len(), ScanProfile::Balanced));
+        assert!(duration > recommended_duration_ms(100, ScanProfile::Balanced));
+        assert!(encoder.last_part_duration_ms(ScanProfile::Reliable) > duration);
+        assert_eq!(recommended_duration_ms(100_000, ScanProfile::Fast), MAX_FRAME_DURATION_MS);
+    }
+
+    #[cfg(feature = "ffi")]
+    #[test]
+    fn test_parts_after_held_back_frame() {
+        let message: Vec<u8> = (0..1000u32).map(|i| (i * 3) as u8).collect();
+        let mut reference = UREncoder::new(&message, 100, "bytes").unwrap();
+        let cycle: Vec<String> = reference.first_cycle().collect::<Result<_, _>>().unwrap();
+
+        let mut encoder = UREncoder::new(&message, 100, "bytes").unwrap();
+        assert!(ur_encoder_next_part_bytes_into(&mut encoder, std::ptr::null_mut(), 0) > 0);
+        assert_eq!(encoder.current_index(), 0);
+        assert_eq!(encoder.next_part().unwrap(), cycle[0]);
+        assert_eq!(encoder.current_index(), 1);
+        assert!(ur_encoder_next_part_bytes_into(&mut encoder, std::ptr::null_mut(), 0) > 0);
+        assert_eq!(binary_part::decode_part(&encoder.next_part_bytes().unwrap()).unwrap(), cycle[1]);
+        assert!(ur_encoder_next_part_bytes_into(&mut encoder, std::ptr::null_mut(), 0) > 0);
+        assert_eq!(encoder.first_cycle().collect::<Result<Vec<_>, _>>().unwrap(), cycle[2..]);
+        assert!(encoder.has_looped());
+    }
+
+    #[test]
+    fn test_iterator() {
+        let message: Vec<u8> = (0..1000u32).map(|i| i as u8).collect();
+        let mut encoder = UREncoder::new(&message, 100, "bytes").unwrap();
+        let cycle: Vec<String> = encoder.first_cycle().collect::<Result<_, _>>().unwrap();
+        assert_eq!(cycle.len(), encoder.fragment_count());
+        assert!(encoder.has_looped());
+        assert!(cycle[0].starts_with(&format!("ur:bytes/1-{}/", cycle.len())));
+        let next = encoder.next().unwrap().unwrap();
+        assert!(next.starts_with(&format!("ur:bytes/{}-", cycle.len() + 1)));
+
+        let mut redundant = UREncoder::new(&message, 100, "bytes").unwrap();
+        assert!(redundant.set_redundancy_percent(99).is_err());
+        redundant.set_redundancy_percent(150).unwrap();
+        assert_eq!(redundant.planned_part_count(), (redundant.fragment_count() * 3).div_ceil(2));
+        let planned: Vec<String> = redundant.planned_parts().collect::<Result<_, _>>().unwrap();
+        assert_eq!(planned.len(), redundant.planned_part_count());
+        assert!(redundant.has_emitted_planned());
+        assert_eq!(planned[..cycle.len()], cycle[..]);
+
+        // Started part way, both stop where they would have from the start
+        let mut started = UREncoder::new(&message, 100, "bytes").unwrap();
+        started.next_part().unwrap();
+        assert_eq!(started.first_cycle().collect::<Result<Vec<_>, _>>().unwrap(), cycle[1..]);
+        assert!(started.has_looped());
+        assert!(started.first_cycle().next().is_none());
+        started.set_redundancy_percent(150).unwrap();
+        assert_eq!(started.planned_parts().count(), planned.len() - cycle.len());
+        assert!(started.has_emitted_planned());
+
+        let mut single = UREncoder::new(&message[..10], 100, "bytes").unwrap();
+        let parts: Vec<String> = single.by_ref().take(3).collect::<Result<_, _>>().unwrap();
+        assert!(parts.iter().all(|p| *p == parts[0]));
+    }
+}
@@ -0,0 +1,139 @@
+//! `.ur` files for moving payloads between air-gapped machines on SD cards
+//! or USB sticks. A file is UTF-8 text holding either one single-part UR or
+//! the parts of a multi-part UR, one per line, in any order; blank lines
+//! and lines starting with `#` are ignored. Writers emit a single part,
+//! since files have no QR size limit.
+
+use crate::response::{PtrResponse, Response};
+use crate::types::{PtrString, PtrVoid};
+use crate::ur_decoder::resolve;
+use crate::ur_encoder::UREncoder;
+use crate::utils::{convert_ptr_string_to_string, parse_ptr_string_to_bytes};
+use ur::bytewords::{self, Style};
+use ur::Decoder;
+
+pub struct URFile {
+    pub ur_type: String,
+    pub cbor: Vec<u8>,
+}
+
+impl URFile {
+    pub fn new(ur_type: String, cbor: Vec<u8>) -> Self {
+        URFile { ur_type, cbor }
+    }
+
+    pub fn write(&self) -> Result<String, String> {
+        let part = UREncoder::new(&self.cbor, self.cbor.len() + 1, self.ur_type.as_str())?.next_part()?;
+        Ok(format!("{}\n", part))
+    }
+
+    pub fn read(contents: &str) -> Result<Self, String> {
+        let parts: Vec<String> = contents
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(str::to_lowercase)
+            .collect();
+        let mut ur_type = None;
+        for part in parts.iter() {
+            let (part_type, _) = part.strip_prefix("ur:").and_then(|v| v.split_once('/')).ok_or("Not a UR file")?;
+            match ur_type {
+                None => ur_type = Some(part_type),
+                Some(t) if t != part_type => return Err(format!("Mixed UR types {} and {}", t, part_type)),
+                Some(_) => {}
+            }
+        }
+        let ur_type = ur_type.ok_or("Empty UR file")?.to_string();
+
+        let cbor = match parts.as_slice() {
+            [single] if single.matches('/').count() == 1 => {
+                let (_, body) = single.rsplit_once('/').unwrap();
+                bytewords::decode(body, &Style::Minimal).map_err(|e| e.to_string())?
+            }
+            _ => {
+                let mut decoder = Decoder::default();
+                for part in parts.iter() {
+                    decoder.receive(part).map_err(|e| e.to_string())?;
+                }
+                decoder
+                    .message()
+                    .map_err(|e| e.to_string())?
+                    .ok_or("UR file is missing parts")?
+            }
+        };
+        Ok(URFile { ur_type, cbor })
+    }
+}
+
+// ========== FFI Functions ==========
+
+/// Contents of a `.ur` file holding hex `cbor` as `ur_type`
+#[no_mangle]
+pub extern "C" fn ur_file_write(ur_type: PtrString, cbor: PtrString) -> PtrResponse {
+    let ur_type = match convert_ptr_string_to_string(ur_type).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    let cbor = match parse_ptr_string_to_bytes(cbor).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    match URFile::new(ur_type, cbor).write() {
+        Ok(contents) => Response::success_string(contents),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[no_mangle]
+pub extern "C" fn ur_file_read(contents: PtrString) -> PtrResponse {
+    let contents = match convert_ptr_string_to_string(contents).map_err(|e| Response::error(e)) {
+        Ok(v) => v,
+        Err(e) => return e.c_ptr(),
+    };
+    match URFile::read(&contents) {
+        Ok(file) => Response::success_object(Box::into_raw(Box::new(file)) as PtrVoid),
+        Err(e) => Response::error(e),
+    }
+    .c_ptr()
+}
+
+#[no_mangle]
+pub extern "C" fn ur_file_get_type(ur_file: &URFile) -> PtrResponse {
+    Response::success_string(ur_file.ur_type.clone()).c_ptr()
+}
+
+#[no_mangle]
+pub extern "C" fn ur_file_get_cbor(ur_file: &URFile) -> PtrResponse {
+    Response::success_string(hex::encode(&ur_file.cbor)).c_ptr()
+}
+
+/// The registry object the file holds, as `ur_decoder_resolve` returns it
+#[no_mangle]
+pub extern "C" fn ur_file_resolve(ur_file: &URFile) -> PtrResponse {
+    resolve(&ur_file.ur_type, ur_file.cbor.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ur_file_round_trip() {
+        let cbor: Vec<u8> = (0..600u32).map(|i| i as u8).collect();
+        let contents = URFile::new("tron-sign-request".to_string(), cbor.clone()).write().unwrap();
+        assert_eq!(contents.lines().count(), 1);
+        let file = URFile::read(&format!("# exported request\n\n{}", contents.to_uppercase())).unwrap();
+        assert_eq!((file.ur_type.as_str(), &file.cbor), ("tron-sign-request", &cbor));
+
+        let mut encoder = UREncoder::new(&cbor, 100, "tron-sign-request").unwrap();
+        let mut parts: Vec<String> = (0..encoder.fragment_count()).map(|_| encoder.next_part().unwrap()).collect();
+        parts.reverse();
+        assert_eq!(URFile::read(&parts.join("\n")).unwrap().cbor, cbor);
+
+        assert_eq!(URFile::read(&parts[1..].join("\n")).err(), Some("UR file is missing parts".to_string()));
+        let mixed = format!("{}\nur:bytes/1-2/lpadaobkcywkwmhfwnfeghihjtcxiansvomopr", parts[0]);
+        assert!(URFile::read(&mixed).err().unwrap().starts_with("Mixed UR types"));
+        assert!(URFile::read("# nothing\n").is_err());
+    }
+}
@@ -1,5 +1,5 @@
+use crate::{alloc, arena};
 use crate::types::PtrString;
-use hex::FromHex;
 use std::ffi::{c_void, CStr, CString};
 
 pub fn remove_prefix<'a>(s: &'a str, prefix: &str) -> &'a str {
@@ -13,6 +13,7 @@ pub fn remove_prefix_0x(s: &str) -> &str {
     remove_prefix(s, "0x")
 }
 
+#[cfg(feature = "ffi")]
 #[no_mangle]
 pub extern "C" fn utils_free(any_ptr: *mut c_void) {
     if any_ptr.is_null() {
@@ -23,8 +24,55 @@ pub extern "C" fn utils_free(any_ptr: *mut c_void) {
     }
 }
 
-pub fn str_to_ptr_c_char(str: String) -> PtrString {
-    CString::new(str).unwrap().into_raw()
+/// Frees a string this library returned: a `STRING` value, an error
+/// message or a value type. Each string must be freed exactly once, and
+/// only with this function; null is ignored.
+// Exported even without `ffi`: arenas free what they collected with it
+#[no_mangle]
+pub extern "C" fn ur_registry_free_string(str: PtrString) {
+    if str.is_null() {
+        return;
+    }
+    string_tracker::released();
+    alloc::free_string(str);
+}
+
+/// Hands `str` to the caller as a C string. Text from payloads may hold NUL
+/// bytes, which would cut a C string short, so those fail instead.
+pub fn str_to_ptr_c_char(str: String) -> Result<PtrString, String> {
+    let c_str = CString::new(str).map_err(|e| format!("String has a NUL byte at {}", e.nul_position()))?;
+    string_tracker::allocated();
+    let ptr = alloc::alloc_string(c_str);
+    arena::adopt_string(ptr);
+    Ok(ptr)
+}
+
+/// Debug builds count the strings handed out and freed on each thread, so
+/// tests can catch leaks; release builds track nothing
+pub mod string_tracker {
+    #[cfg(debug_assertions)]
+    use std::cell::Cell;
+
+    #[cfg(debug_assertions)]
+    thread_local! {
+        static LIVE: Cell<isize> = const { Cell::new(0) };
+    }
+
+    pub fn allocated() {
+        #[cfg(debug_assertions)]
+        LIVE.with(|live| live.set(live.get() + 1));
+    }
+
+    pub fn released() {
+        #[cfg(debug_assertions)]
+        LIVE.with(|live| live.set(live.get() - 1));
+    }
+
+    /// Strings returned on this thread and not freed yet
+    #[cfg(all(debug_assertions, test))]
+    pub fn live_strings() -> isize {
+        LIVE.with(|live| live.get())
+    }
 }
 
 pub fn parse_ptr_string_to_bytes(str: PtrString) -> Result<Vec<u8>, String> {
@@ -32,7 +80,7 @@ pub fn parse_ptr_string_to_bytes(str: PtrString) -> Result<Vec<u8>, String> {
         CStr::from_ptr(str)
             .to_str()
             .map_err(|e| e.to_string())
-            .and_then(|v| Vec::from_hex(v.to_string()).map_err(|e| e.to_string()))
+            .and_then(crate::hex_codec::decode)
     }
 }
 
@@ -44,3 +92,16 @@ pub fn convert_ptr_string_to_string(str: PtrString) -> Result<String, String> {
             .map(|v| v.to_string())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_str_to_ptr_c_char_rejects_nul() {
+        let ptr = str_to_ptr_c_char("plain".to_string()).unwrap();
+        assert_eq!(convert_ptr_string_to_string(ptr).unwrap(), "plain");
+        ur_registry_free_string(ptr);
+        assert_eq!(str_to_ptr_c_char("memo\0text".to_string()).unwrap_err(), "String has a NUL byte at 4");
+    }
+}